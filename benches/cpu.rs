@@ -0,0 +1,118 @@
+use std::{fs, hint::black_box, path::Path};
+
+use chip8::cpu::CPU;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+const TICKS: u64 = 10_000;
+
+// register arithmetic, skips and jumps, no memory traffic besides fetching
+const ALU_LOOP: &[u8] = &[
+    0x60, 0x00, // 200: LD V0, #00
+    0x61, 0x01, // 202: LD V1, #01
+    0x80, 0x14, // 204: ADD V0, V1
+    0x82, 0x03, // 206: XOR V2, V0
+    0x83, 0x26, // 208: SHR V3, V2
+    0x30, 0xFF, // 20A: SE V0, #FF
+    0x12, 0x04, // 20C: JP #204
+    0x12, 0x00, // 20E: JP #200
+];
+
+// draws a full height sprite over and over, moving it across the screen
+const DRAW_LOOP: &[u8] = &[
+    0xA2, 0x0C, // 200: LD I, #20C
+    0xD0, 0x1F, // 202: DRW V0, V1, 15
+    0x70, 0x08, // 204: ADD V0, #08
+    0x71, 0x01, // 206: ADD V1, #01
+    0x12, 0x02, // 208: JP #202
+    0x00, 0x00, // 20A: padding
+    0xFF, 0x81, 0xBD, 0xA5, 0xA5, 0xBD, 0x81, 0xFF, // 20C: sprite
+    0xFF, 0x81, 0xBD, 0xA5, 0xA5, 0xBD, 0x81,
+];
+
+// stores over one of its own instructions every time round, so it's decoded again each time
+const SELF_MODIFYING_LOOP: &[u8] = &[
+    0xA2, 0x08, // 200: LD I, #208
+    0x60, 0x71, // 202: LD V0, #71
+    0x61, 0x01, // 204: LD V1, #01
+    0xF1, 0x55, // 206: LD [I], V1
+    0x71, 0x01, // 208: ADD V1, #01, written again by 206
+    0x12, 0x00, // 20A: JP #200
+];
+
+fn setup(rom: &[u8]) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.seed(0);
+    cpu.load(rom);
+    cpu
+}
+
+fn run(mut cpu: CPU) -> CPU {
+    for _ in 0..TICKS {
+        cpu.tick().unwrap();
+    }
+    cpu
+}
+
+fn bench_tick(c: &mut Criterion) {
+    let particles = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("roms")
+        .join("Particle Demo [zeroZshadow, 2008].ch8");
+    let particles = fs::read(particles).expect("unable to read ROM");
+
+    let mut group = c.benchmark_group("tick");
+    group.throughput(Throughput::Elements(TICKS));
+
+    for (name, rom) in [
+        ("alu", ALU_LOOP),
+        ("draw", DRAW_LOOP),
+        ("self_modifying", SELF_MODIFYING_LOOP),
+        ("particle_demo", &particles[..]),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_batched(|| setup(black_box(rom)), run, BatchSize::SmallInput)
+        });
+    }
+
+    group.finish();
+}
+
+// the same programs with register instructions compiled to native code, the blocks are compiled
+// on the first run and reused after
+#[cfg(feature = "jit")]
+fn bench_jit(c: &mut Criterion) {
+    use chip8::jit::Jit;
+
+    let particles = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("roms")
+        .join("Particle Demo [zeroZshadow, 2008].ch8");
+    let particles = fs::read(particles).expect("unable to read ROM");
+
+    let mut group = c.benchmark_group("jit");
+    group.throughput(Throughput::Elements(TICKS));
+
+    for (name, rom) in [
+        ("alu", ALU_LOOP),
+        ("draw", DRAW_LOOP),
+        ("particle_demo", &particles[..]),
+    ] {
+        let mut jit = Jit::new().expect("unable to start the JIT");
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || setup(black_box(rom)),
+                |mut cpu| {
+                    jit.run_cycles(&mut cpu, TICKS as u32);
+                    cpu
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(feature = "jit"))]
+fn bench_jit(_: &mut Criterion) {}
+
+criterion_group!(benches, bench_tick, bench_jit);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+// runs a ROM the way firmware for a microcontroller with a 128x64 SSD1306 would. `firmware` is
+// everything the device needs and, like the crate root, is no_std, using only core, alloc and the
+// core built without std. `hosted` stands in for the board so the example runs here: its Panel
+// takes the place of the display driver, which implements the same DrawTarget, and it prints the
+// screen as text after a couple of seconds
+//   cargo run --example embedded --features embedded -- roms/Airplane.ch8
+#![no_std]
+
+extern crate alloc;
+
+mod firmware {
+    use chip8::cpu::{CpuError, CPU, NUM_KEYS};
+    use chip8::embedded::Display;
+    use chip8::error::RomError;
+    use embedded_graphics_core::{draw_target::DrawTarget, pixelcolor::BinaryColor};
+
+    pub enum Fault<E> {
+        Cpu(CpuError),
+        Panel(E),
+    }
+
+    pub struct Firmware {
+        cpu: CPU,
+        display: Display<BinaryColor>,
+    }
+
+    impl Firmware {
+        pub fn new(rom: &[u8]) -> Result<Firmware, RomError> {
+            let mut cpu = CPU::new();
+            cpu.try_load(rom)?;
+            Ok(Firmware {
+                cpu,
+                display: Display::new(BinaryColor::On, BinaryColor::Off).with_scale(2),
+            })
+        }
+
+        // called from a 60Hz timer interrupt with the keypad as it is now
+        pub fn frame<D: DrawTarget<Color = BinaryColor>>(
+            &mut self,
+            keys: &[bool; NUM_KEYS],
+            panel: &mut D,
+        ) -> Result<(), Fault<D::Error>> {
+            self.cpu.set_keys(keys);
+            self.cpu.run_frame().map_err(Fault::Cpu)?;
+            self.cpu.tick_timers();
+            self.display.draw(&self.cpu, panel).map_err(Fault::Panel)
+        }
+    }
+}
+
+mod hosted {
+    extern crate std;
+
+    use std::{convert::Infallible, env, fs, println, string::String};
+
+    use chip8::cpu::NUM_KEYS;
+    use embedded_graphics_core::{
+        draw_target::DrawTarget,
+        geometry::{OriginDimensions, Point, Size},
+        pixelcolor::BinaryColor,
+        Pixel,
+    };
+
+    use crate::firmware::{Fault, Firmware};
+
+    const PANEL_WIDTH: usize = 128;
+    const PANEL_HEIGHT: usize = 64;
+    const FRAMES: u32 = 120;
+
+    // a stand-in for the panel's frame buffer
+    struct Panel {
+        pixels: [bool; PANEL_WIDTH * PANEL_HEIGHT],
+    }
+
+    impl OriginDimensions for Panel {
+        fn size(&self) -> Size {
+            Size::new(PANEL_WIDTH as u32, PANEL_HEIGHT as u32)
+        }
+    }
+
+    impl DrawTarget for Panel {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I: IntoIterator<Item = Pixel<BinaryColor>>>(
+            &mut self,
+            pixels: I,
+        ) -> Result<(), Infallible> {
+            for Pixel(Point { x, y }, color) in pixels {
+                if (0..PANEL_WIDTH as i32).contains(&x) && (0..PANEL_HEIGHT as i32).contains(&y) {
+                    self.pixels[y as usize * PANEL_WIDTH + x as usize] = color.is_on();
+                }
+            }
+            Ok(())
+        }
+    }
+
+    pub fn main() {
+        let path = env::args().nth(1).expect("usage: embedded /path/to/game");
+        let rom = fs::read(path).expect("unable to read ROM file");
+        let mut firmware = Firmware::new(&rom).expect("unable to load ROM");
+
+        let mut panel = Panel {
+            pixels: [false; PANEL_WIDTH * PANEL_HEIGHT],
+        };
+        for _ in 0..FRAMES {
+            match firmware.frame(&[false; NUM_KEYS], &mut panel) {
+                Ok(()) => {}
+                Err(Fault::Cpu(error)) => panic!("program stopped: {}", error),
+                Err(Fault::Panel(error)) => match error {},
+            }
+        }
+
+        // two panel rows per line keeps the aspect ratio close in a terminal
+        for rows in panel.pixels.chunks(PANEL_WIDTH * 2) {
+            let (top, bottom) = rows.split_at(PANEL_WIDTH);
+            let line: String = top
+                .iter()
+                .zip(bottom)
+                .map(|pixels| match pixels {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect();
+            println!("{}", line);
+        }
+    }
+}
+
+fn main() {
+    hosted::main();
+}
@@ -0,0 +1,32 @@
+// runs arbitrary bytes as a ROM, any panic is a bug since bad programs should only ever
+// produce a CpuError. run with: cargo +nightly fuzz run rom
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chip8::cpu::{CPU, MEMORY_SIZE, START_ADDRESS};
+
+// enough for the usual loops to go around a few times
+const MAX_TICKS: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    // the first two bytes choose which keys are held, the rest is the program
+    let (keys, rom) = data.split_at(data.len().min(2));
+    let rom = &rom[..rom.len().min(MEMORY_SIZE - START_ADDRESS as usize)];
+
+    let mut cpu = CPU::new();
+    cpu.seed(0);
+    cpu.load(rom);
+    for key in 0..16 {
+        let held = keys
+            .get(key / 8)
+            .is_some_and(|byte| byte & (1 << (key % 8)) != 0);
+        cpu.keypress(key, held).unwrap();
+    }
+
+    for _ in 0..MAX_TICKS {
+        if cpu.tick().is_err() {
+            break;
+        }
+    }
+});
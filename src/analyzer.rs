@@ -0,0 +1,197 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::cpu::{CPU, MEMORY_SIZE, START_ADDRESS};
+use crate::dumps;
+use crate::flowgraph::FlowGraph;
+use crate::instruction::Instruction;
+
+const MAX_ROM_SIZE: usize = MEMORY_SIZE - START_ADDRESS as usize;
+// anything smaller can't do much more than loop forever
+const MIN_ROM_SIZE: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub address: Option<u16>,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.address {
+            Some(address) => write!(f, "#{:03X}: {}", address, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn warning(address: Option<u16>, message: String) -> Warning {
+    Warning { address, message }
+}
+
+// looks for problems without running the ROM, only reachable code is checked so data
+// sitting in the ROM isn't reported as bad instructions
+pub fn check(rom: &[u8]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    if rom.is_empty() {
+        warnings.push(warning(None, "ROM is empty".to_string()));
+        return warnings;
+    }
+    if let Some(message) = dumps::check(rom) {
+        warnings.push(warning(None, message));
+    }
+    if rom.len() > MAX_ROM_SIZE {
+        warnings.push(warning(
+            None,
+            format!(
+                "ROM is {} bytes but only {} fit in memory",
+                rom.len(),
+                MAX_ROM_SIZE
+            ),
+        ));
+        return warnings;
+    }
+    if rom.len() < MIN_ROM_SIZE {
+        warnings.push(warning(
+            None,
+            format!("ROM is suspiciously small ({} bytes)", rom.len()),
+        ));
+    }
+    if rom.len() & 1 != 0 {
+        warnings.push(warning(
+            None,
+            format!(
+                "ROM has an odd size ({} bytes), it may be truncated or padded",
+                rom.len()
+            ),
+        ));
+    }
+
+    let mut cpu = CPU::new();
+    cpu.load(rom);
+    let graph = FlowGraph::analyze(cpu.memory(), START_ADDRESS, &BTreeSet::new());
+
+    let rom_end = START_ADDRESS as usize + rom.len();
+    let in_rom = |address: u16| (START_ADDRESS as usize..rom_end).contains(&(address as usize));
+
+    // blocks outside the ROM were already reported by whatever jumped there
+    for (_, block) in graph.blocks.iter().filter(|(start, _)| in_rom(**start)) {
+        // I is only tracked within a block, it's unknown on entry
+        let mut index_register = None;
+
+        for (address, instruction) in block.instructions.iter() {
+            let address = *address;
+
+            if !in_rom(address) {
+                warnings.push(warning(
+                    Some(address),
+                    "execution can run past the end of the ROM".to_string(),
+                ));
+                break;
+            }
+
+            match *instruction {
+                Instruction::Unknown(op) => warnings.push(warning(
+                    Some(address),
+                    format!("invalid opcode #{:04X}", op),
+                )),
+                Instruction::Jump(target) | Instruction::Call(target) => {
+                    check_target(&mut warnings, address, target, in_rom(target))
+                }
+                Instruction::JumpV0(base) => {
+                    // V0 can add anything up to 255 to the base
+                    let reachable = in_rom(base) || in_rom(base + 0xFF);
+                    check_target(&mut warnings, address, base, reachable)
+                }
+                Instruction::LoadIndex(nnn) => index_register = Some(nnn),
+                Instruction::AddIndex { .. } | Instruction::LoadFont { .. } => {
+                    index_register = None
+                }
+                Instruction::Bcd { .. } | Instruction::StoreRegs { .. } => {
+                    if let Some(i) = index_register.filter(|i| *i < START_ADDRESS) {
+                        warnings.push(warning(
+                            Some(address),
+                            format!(
+                                "writes to #{:03X}, below #{:03X} where the font lives",
+                                i, START_ADDRESS
+                            ),
+                        ));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    warnings
+}
+
+fn check_target(warnings: &mut Vec<Warning>, address: u16, target: u16, in_rom: bool) {
+    if !in_rom {
+        warnings.push(warning(
+            Some(address),
+            format!("jumps to #{:03X}, outside the ROM", target),
+        ));
+    }
+    if target & 1 != 0 {
+        warnings.push(warning(
+            Some(address),
+            format!("jumps to odd-aligned address #{:03X}", target),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(rom: &[u8]) -> Vec<String> {
+        check(rom)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_rom() {
+        // LD I, #206; DRW V0, V0, 1; JP #204; sprite data that isn't valid code
+        assert!(check(&[0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xFF, 0xFF]).is_empty());
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(messages(&[]), ["ROM is empty"]);
+        assert_eq!(
+            messages(&[0; MAX_ROM_SIZE + 1]),
+            ["ROM is 3585 bytes but only 3584 fit in memory"]
+        );
+        // a dump of all of memory is too big, and says where the program is
+        assert_eq!(messages(&[0; MEMORY_SIZE]).len(), 2);
+        assert!(messages(&[0x12, 0x00, 0x00])
+            .contains(&"ROM has an odd size (3 bytes), it may be truncated or padded".to_string()));
+    }
+
+    #[test]
+    fn test_bad_code() {
+        assert_eq!(
+            messages(&[0xFF, 0xFF, 0x12, 0x00]),
+            ["#200: invalid opcode #FFFF"]
+        );
+        assert_eq!(
+            messages(&[0x13, 0x01, 0x00, 0x00]),
+            [
+                "#200: jumps to #301, outside the ROM",
+                "#200: jumps to odd-aligned address #301"
+            ]
+        );
+        assert_eq!(
+            messages(&[0x60, 0x00, 0x70, 0x01]),
+            ["#204: execution can run past the end of the ROM"]
+        );
+        assert_eq!(
+            messages(&[0xA0, 0x10, 0xF2, 0x55, 0x12, 0x04]),
+            ["#202: writes to #010, below #200 where the font lives"]
+        );
+    }
+}
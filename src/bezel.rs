@@ -0,0 +1,75 @@
+// an image drawn around the screen, like the plastic around a console's display or the art on
+// an arcade cabinet. the window takes the image's size and the screen is letterboxed into the
+// middle of it, so the image should leave room there
+use sdl2::{
+    rect::Rect,
+    render::{Canvas, Texture, TextureCreator},
+    surface::Surface,
+    video::{Window, WindowContext},
+};
+
+// the part of the image's width or height the screen covers, whichever runs out first
+const SCREEN_SHARE: f64 = 0.8;
+
+pub struct Bezel<'a> {
+    texture: Texture<'a>,
+    screen: Rect,
+}
+
+impl<'a> Bezel<'a> {
+    pub fn new(
+        image: &Surface,
+        creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<Bezel<'a>, String> {
+        let texture = creator
+            .create_texture_from_surface(image)
+            .map_err(|error| error.to_string())?;
+        Ok(Bezel {
+            texture,
+            screen: screen_area(image.width(), image.height()),
+        })
+    }
+
+    pub fn screen(&self) -> Rect {
+        self.screen
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        canvas.copy(&self.texture, None, None).unwrap();
+    }
+}
+
+// BMP is the only format SDL reads without SDL_image
+pub fn load(path: &str) -> Result<Surface<'static>, String> {
+    Surface::load_bmp(path).map_err(|error| format!("unable to read bezel {}: {}", path, error))
+}
+
+// the biggest 2:1 area that fits in the middle of the image with its share to spare
+fn screen_area(width: u32, height: u32) -> Rect {
+    let most_width = (width as f64 * SCREEN_SHARE) as u32;
+    let most_height = (height as f64 * SCREEN_SHARE) as u32;
+    let (screen_width, screen_height) = match most_width / 2 <= most_height {
+        true => (most_width, most_width / 2),
+        false => (most_height * 2, most_height),
+    };
+    Rect::new(
+        ((width - screen_width) / 2) as i32,
+        ((height - screen_height) / 2) as i32,
+        screen_width,
+        screen_height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_area() {
+        // images wider than the screen are limited by their height, the rest by their width
+        assert_eq!(screen_area(1920, 1080), Rect::new(192, 156, 1536, 768));
+        assert_eq!(screen_area(3000, 500), Rect::new(1100, 50, 800, 400));
+        assert_eq!(screen_area(800, 800), Rect::new(80, 240, 640, 320));
+        assert_eq!(screen_area(1000, 500), Rect::new(100, 50, 800, 400));
+    }
+}
@@ -0,0 +1,227 @@
+// a frontend without any C libraries, for when SDL2 can't be installed. it only plays games,
+// the debugging tools and sound are in the SDL frontend. build it with
+//   cargo run --no-default-features --features winit --bin chip8-winit -- game.ch8
+use std::{
+    env, fs,
+    io::{self, Read},
+    num::NonZeroU32,
+    process,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use softbuffer::{Context, Surface};
+use tracing::error;
+use winit::{
+    application::ApplicationHandler,
+    dpi::LogicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowId},
+};
+
+use chip8::cpu::{CPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::headless::TICKS_PER_FRAME;
+use chip8::input;
+use chip8::options::{self, Command};
+use chip8::patch;
+
+const SCALE: u32 = 15;
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+const PIXEL_ON: u32 = 0xFFFFFF;
+const PIXEL_OFF: u32 = 0x000000;
+
+struct App {
+    cpu: CPU,
+    window: Option<Rc<Window>>,
+    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
+    next_frame: Instant,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let attributes = Window::default_attributes()
+            .with_title("Rusty Chip8")
+            .with_inner_size(LogicalSize::new(
+                SCREEN_WIDTH as u32 * SCALE,
+                SCREEN_HEIGHT as u32 * SCALE,
+            ));
+        let window = Rc::new(
+            event_loop
+                .create_window(attributes)
+                .expect("unable to create window"),
+        );
+        let context = Context::new(window.clone()).expect("unable to create graphics context");
+        let surface = Surface::new(&context, window.clone()).expect("unable to create surface");
+
+        self.window = Some(window);
+        self.surface = Some(surface);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                if key == KeyCode::Escape && state == ElementState::Released {
+                    event_loop.exit();
+                }
+
+                if let Some(k) = convert_key_to_button(key) {
+                    input::press(&mut self.cpu, k, state == ElementState::Pressed);
+                }
+            }
+            WindowEvent::RedrawRequested => self.draw_screen(),
+            _ => (),
+        }
+    }
+
+    // runs the CPU at 60 frames a second, drawing after each frame
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if Instant::now() < self.next_frame {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_frame));
+            return;
+        }
+        self.next_frame += FRAME_TIME;
+
+        for _ in 0..TICKS_PER_FRAME {
+            let pc = self.cpu.pc();
+            if let Err(error) = self.cpu.tick() {
+                error!("program stopped at #{:03X}: {}", pc, error);
+                event_loop.exit();
+                return;
+            }
+        }
+        self.cpu.tick_timers();
+
+        if let Some(window) = self.window.as_ref() {
+            window.request_redraw();
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_frame));
+    }
+}
+
+impl App {
+    fn draw_screen(&mut self) {
+        let (Some(window), Some(surface)) = (self.window.as_ref(), self.surface.as_mut()) else {
+            return;
+        };
+
+        let size = window.inner_size();
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        surface
+            .resize(width, height)
+            .expect("unable to resize surface");
+
+        // scale to whatever size the window has been given
+        let mut buffer = surface.buffer_mut().expect("unable to get frame buffer");
+        for y in 0..size.height as usize {
+            let row = y * SCREEN_HEIGHT / size.height as usize;
+            for x in 0..size.width as usize {
+                let column = x * SCREEN_WIDTH / size.width as usize;
+                buffer[y * size.width as usize + x] =
+                    match self.cpu.screen[row * SCREEN_WIDTH + column] {
+                        true => PIXEL_ON,
+                        false => PIXEL_OFF,
+                    };
+            }
+        }
+        buffer.present().expect("unable to present frame");
+    }
+}
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+    let options = match options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, options::USAGE);
+            return;
+        }
+    };
+    // as the SDL frontend logs, without the file
+    tracing_subscriber::fmt()
+        .with_max_level(options.log_level)
+        .with_target(false)
+        .without_time()
+        .init();
+
+    if options.command != Command::Run {
+        eprintln!("only run is available here, the other commands are in the SDL frontend");
+        return;
+    }
+
+    let mut rom = if let Some(demo) = options.demo {
+        demo.rom.to_vec()
+    } else if options.rom_path == "-" {
+        let mut rom = Vec::new();
+        io::stdin()
+            .read_to_end(&mut rom)
+            .expect("unable to read ROM from stdin");
+        rom
+    } else {
+        fs::read(&options.rom_path).expect("unable to read ROM file")
+    };
+    if let Some(path) = &options.patch_path {
+        let patch = fs::read(path).expect("unable to read patch file");
+        rom = match patch::apply(&rom, &patch) {
+            Ok(patched) => patched,
+            Err(message) => {
+                error!("unable to apply {}: {}", path, message);
+                process::exit(1);
+            }
+        };
+    }
+
+    let mut cpu = CPU::new();
+    if let Err(error) = cpu.try_load(&rom) {
+        error!("unable to load the ROM: {}", error);
+        process::exit(1);
+    }
+
+    let mut app = App {
+        cpu,
+        window: None,
+        surface: None,
+        next_frame: Instant::now(),
+    };
+    let event_loop = EventLoop::new().expect("unable to create event loop");
+    event_loop
+        .run_app(&mut app)
+        .expect("unable to run event loop");
+}
+
+fn convert_key_to_button(key: KeyCode) -> Option<usize> {
+    match key {
+        KeyCode::Digit1 => Some(0x1),
+        KeyCode::Digit2 => Some(0x2),
+        KeyCode::Digit3 => Some(0x3),
+        KeyCode::Digit4 => Some(0xC),
+        KeyCode::KeyQ => Some(0x4),
+        KeyCode::KeyW => Some(0x5),
+        KeyCode::KeyE => Some(0x6),
+        KeyCode::KeyR => Some(0xD),
+        KeyCode::KeyA => Some(0x7),
+        KeyCode::KeyS => Some(0x8),
+        KeyCode::KeyD => Some(0x9),
+        KeyCode::KeyF => Some(0xE),
+        KeyCode::KeyZ => Some(0xA),
+        KeyCode::KeyX => Some(0x0),
+        KeyCode::KeyC => Some(0xB),
+        KeyCode::KeyV => Some(0xF),
+        _ => None,
+    }
+}
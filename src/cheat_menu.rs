@@ -0,0 +1,191 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+use tracing::error;
+
+use crate::memory_viewer::convert_key_to_nibble;
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::cheats::{Cheat, CheatKind, Cheats};
+
+const MENU_PADDING: i32 = 6;
+// three address digits followed by two value digits
+const ENTRY_DIGITS: usize = 5;
+
+const MENU_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const NOTE_COLOR: Color = Color::RGB(0, 255, 128);
+const DISABLED_COLOR: Color = Color::RGB(128, 128, 128);
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+
+pub struct CheatMenu {
+    visible: bool,
+    selected: usize,
+    // hex digits typed for a new cheat
+    entry: Vec<u8>,
+}
+
+impl CheatMenu {
+    pub fn new() -> CheatMenu {
+        CheatMenu {
+            visible: false,
+            selected: 0,
+            entry: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.entry.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // returns true if the menu consumed the key, cheats are saved whenever they change
+    pub fn handle_key(&mut self, key: Keycode, cheats: &mut Cheats) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let count = cheats.cheats.len();
+        let changed = match key {
+            Keycode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                false
+            }
+            Keycode::Down => {
+                self.selected = (self.selected + 1).min(count.saturating_sub(1));
+                false
+            }
+            Keycode::Return | Keycode::KpEnter if self.entry.len() == ENTRY_DIGITS => {
+                let digit = |index: usize| self.entry[index] as u16;
+                let address = digit(0) << 8 | digit(1) << 4 | digit(2);
+                let value = (digit(3) << 4 | digit(4)) as u8;
+                cheats.add(Cheat::new(address, value, CheatKind::Freeze));
+                self.entry.clear();
+                self.selected = count;
+                true
+            }
+            Keycode::Return | Keycode::KpEnter | Keycode::Space if self.entry.is_empty() => {
+                cheats.toggle(self.selected);
+                true
+            }
+            Keycode::Tab => {
+                cheats.toggle_kind(self.selected);
+                true
+            }
+            Keycode::Backspace if !self.entry.is_empty() => {
+                self.entry.pop();
+                false
+            }
+            Keycode::Delete | Keycode::Backspace => {
+                cheats.remove(self.selected);
+                self.selected = self.selected.min(cheats.cheats.len().saturating_sub(1));
+                true
+            }
+            _ => {
+                if let Some(nibble) = convert_key_to_nibble(key) {
+                    if self.entry.len() < ENTRY_DIGITS {
+                        self.entry.push(nibble);
+                    }
+                }
+                false
+            }
+        };
+
+        if changed {
+            // the cheats still apply this session, they just won't be there next time
+            if let Err(error) = cheats.save() {
+                error!("unable to save cheats: {}", error);
+            }
+        }
+
+        true
+    }
+
+    pub fn draw(&self, cheats: &Cheats, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(MENU_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = MENU_PADDING;
+        let mut y = MENU_PADDING;
+        draw_text(
+            canvas,
+            "CHEATS  UP/DOWN SELECT, RETURN TOGGLE, TAB FREEZE/POKE, DEL REMOVE",
+            x,
+            y,
+            TITLE_COLOR,
+        );
+
+        y += LINE_HEIGHT;
+        let typed: String = self
+            .entry
+            .iter()
+            .map(|digit| format!("{:X}", digit))
+            .collect();
+        let mut entry = format!("{:_<width$}", typed, width = ENTRY_DIGITS);
+        entry.insert_str(3, " = ");
+        draw_text(
+            canvas,
+            &format!("NEW  {}  TYPE ADDRESS AND VALUE, RETURN ADDS", entry),
+            x,
+            y,
+            TITLE_COLOR,
+        );
+
+        for note in cheats.notes.iter() {
+            y += LINE_HEIGHT;
+            draw_text(canvas, note, x, y, NOTE_COLOR);
+        }
+
+        y += LINE_HEIGHT;
+        if cheats.cheats.is_empty() {
+            draw_text(canvas, "NO CHEATS", x, y + LINE_HEIGHT, TEXT_COLOR);
+        }
+
+        for (index, cheat) in cheats.cheats.iter().enumerate() {
+            y += LINE_HEIGHT;
+
+            if index == self.selected {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y - 2, window_width, LINE_HEIGHT as u32))
+                    .unwrap();
+            }
+
+            let kind = match cheat.kind {
+                CheatKind::Freeze => "FREEZE",
+                CheatKind::Poke => "POKE",
+            };
+            let line = format!(
+                "[{}] #{:03X} = #{:02X}  {}",
+                if cheat.enabled { "X" } else { " " },
+                cheat.address,
+                cheat.value,
+                kind
+            );
+            let color = if cheat.enabled {
+                TEXT_COLOR
+            } else {
+                DISABLED_COLOR
+            };
+            draw_text(canvas, &line, x, y, color);
+        }
+    }
+}
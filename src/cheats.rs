@@ -0,0 +1,272 @@
+use std::{fmt::Write, fs, io};
+
+use crate::cpu::CPU;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheatKind {
+    // written every frame so the program can't change it
+    Freeze,
+    // written once when the cheat is switched on
+    Poke,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    pub kind: CheatKind,
+    pub enabled: bool,
+    // a poke only happens once per switch on
+    applied: bool,
+}
+
+impl Cheat {
+    pub fn new(address: u16, value: u8, kind: CheatKind) -> Cheat {
+        Cheat {
+            address,
+            value,
+            kind,
+            enabled: true,
+            applied: false,
+        }
+    }
+}
+
+// the cheats and notes for one ROM, kept in a file next to it so they travel with the game
+pub struct Cheats {
+    path: String,
+    pub cheats: Vec<Cheat>,
+    pub notes: Vec<String>,
+    // named addresses, written back in place of the number when saving
+    constants: Vec<(String, u16)>,
+}
+
+impl Cheats {
+    // a missing file just means no cheats yet
+    pub fn load(path: &str) -> io::Result<Cheats> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        parse(path, &text).map_err(|message| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, message))
+        })
+    }
+
+    // no cheats, saved to path like any others
+    pub fn empty(path: &str) -> Cheats {
+        Cheats {
+            path: path.to_string(),
+            cheats: Vec::new(),
+            notes: Vec::new(),
+            constants: Vec::new(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::write(&self.path, format(self))
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.cheats.len() {
+            self.cheats.remove(index);
+        }
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = !cheat.enabled;
+            cheat.applied = false;
+        }
+    }
+
+    pub fn toggle_kind(&mut self, index: usize) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.kind = match cheat.kind {
+                CheatKind::Freeze => CheatKind::Poke,
+                CheatKind::Poke => CheatKind::Freeze,
+            };
+            cheat.applied = false;
+        }
+    }
+
+    // called at the start of every frame, before the CPU runs
+    pub fn apply(&mut self, cpu: &mut CPU) {
+        let size = cpu.memory().len();
+        for cheat in self.cheats.iter_mut() {
+            if !cheat.enabled || cheat.applied || cheat.address as usize >= size {
+                continue;
+            }
+
+            cpu.poke(cheat.address, cheat.value);
+            cheat.applied = cheat.kind == CheatKind::Poke;
+        }
+    }
+
+    // a constant's address, or the token as a number
+    fn resolve(&self, token: &str) -> Option<u16> {
+        self.constants
+            .iter()
+            .find(|(name, _)| name == token)
+            .map(|(_, address)| *address)
+            .or_else(|| parse_number(token))
+    }
+}
+
+// the file reads like Octo source: # starts a comment and numbers are decimal, 0x hex or
+// 0b binary. each line is one directive:
+//   :note <text>                                a note about the game
+//   :const <name> <address>                     a name usable in place of an address
+//   :cheat <address> <value> [freeze|poke] [on|off]
+fn parse(path: &str, text: &str) -> Result<Cheats, String> {
+    let mut cheats = Cheats::empty(path);
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let error = || format!("line {}: invalid directive {}", number + 1, line);
+
+        if let Some(note) = line.strip_prefix(":note") {
+            cheats.notes.push(note.trim().to_string());
+            continue;
+        }
+
+        let code = line.split('#').next().unwrap_or("");
+        let tokens: Vec<_> = code.split_whitespace().collect();
+        match tokens[..] {
+            [] => (),
+            [":const", name, value] => {
+                let address = parse_number(value).ok_or_else(error)?;
+                cheats.constants.push((name.to_string(), address));
+            }
+            [":cheat", address, value, ref options @ ..] if options.len() <= 2 => {
+                let address = cheats.resolve(address).ok_or_else(error)?;
+                let value = parse_number(value)
+                    .and_then(|value| u8::try_from(value).ok())
+                    .ok_or_else(error)?;
+
+                let mut cheat = Cheat::new(address, value, CheatKind::Freeze);
+                for option in options {
+                    match *option {
+                        "freeze" => cheat.kind = CheatKind::Freeze,
+                        "poke" => cheat.kind = CheatKind::Poke,
+                        "on" => cheat.enabled = true,
+                        "off" => cheat.enabled = false,
+                        _ => return Err(error()),
+                    }
+                }
+                cheats.cheats.push(cheat);
+            }
+            _ => return Err(error()),
+        }
+    }
+
+    Ok(cheats)
+}
+
+fn parse_number(token: &str) -> Option<u16> {
+    if let Some(digits) = token.strip_prefix("0x") {
+        u16::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = token.strip_prefix("0b") {
+        u16::from_str_radix(digits, 2).ok()
+    } else {
+        token.parse().ok()
+    }
+}
+
+fn format(cheats: &Cheats) -> String {
+    let mut text = String::new();
+    for note in cheats.notes.iter() {
+        writeln!(text, ":note {}", note).unwrap();
+    }
+    for (name, address) in cheats.constants.iter() {
+        writeln!(text, ":const {} 0x{:03X}", name, address).unwrap();
+    }
+
+    for cheat in cheats.cheats.iter() {
+        let address = match cheats.constants.iter().find(|(_, a)| *a == cheat.address) {
+            Some((name, _)) => name.clone(),
+            None => format!("0x{:03X}", cheat.address),
+        };
+        let kind = match cheat.kind {
+            CheatKind::Freeze => "freeze",
+            CheatKind::Poke => "poke",
+        };
+        let state = if cheat.enabled { "on" } else { "off" };
+        writeln!(
+            text,
+            ":cheat {} 0x{:02X} {} {}",
+            address, cheat.value, kind, state
+        )
+        .unwrap();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let text = "\
+# shared by a friend
+:note lives are at 0x2F0
+:const lives 0x2F0
+
+:cheat lives 3 # infinite lives
+:cheat 0x300 0b1001 poke off
+";
+        let cheats = parse("game.ch8.cheats", text).unwrap();
+        assert_eq!(cheats.notes, ["lives are at 0x2F0"]);
+        assert_eq!(cheats.cheats.len(), 2);
+        assert_eq!(cheats.cheats[0], Cheat::new(0x2F0, 3, CheatKind::Freeze));
+        assert_eq!(cheats.cheats[1].value, 9);
+        assert!(!cheats.cheats[1].enabled);
+        assert_eq!(
+            format(&cheats),
+            "\
+:note lives are at 0x2F0
+:const lives 0x2F0
+:cheat lives 0x03 freeze on
+:cheat 0x300 0x09 poke off
+"
+        );
+
+        assert!(parse("", ":cheat 0x2F0").is_err());
+        assert!(parse("", ":cheat 0x2F0 256").is_err());
+        assert!(parse("", ":cheat 0x2F0 3 sometimes").is_err());
+        assert!(parse("", ":cheat lives 3").is_err());
+        assert!(parse("", ":bogus").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut cpu = CPU::new();
+        let mut cheats = parse("", "").unwrap();
+        cheats.cheats = vec![
+            Cheat::new(0x300, 0x11, CheatKind::Freeze),
+            Cheat::new(0x301, 0x22, CheatKind::Poke),
+        ];
+
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x300..0x302], [0x11, 0x22]);
+
+        // only the frozen byte comes back
+        cpu.poke(0x300, 0);
+        cpu.poke(0x301, 0);
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x300..0x302], [0x11, 0x00]);
+
+        // switching a poke back on applies it again
+        cheats.toggle(1);
+        cheats.toggle(1);
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x301], 0x22);
+    }
+}
@@ -0,0 +1,100 @@
+// copies the screen to the system clipboard as an image, for pasting into bug reports and posts
+use std::borrow::Cow;
+
+use arboard::{Clipboard, ImageData};
+
+use chip8::cpu::CPU;
+use chip8::settings::Palette;
+
+// the same 2:1 shape as the window whatever the display, the two-page one stretched as it is there
+const IMAGE_WIDTH: usize = 1024;
+const IMAGE_HEIGHT: usize = 512;
+
+pub struct ScreenClipboard {
+    // opened on the first copy and kept, on X11 what was copied goes when the clipboard is dropped
+    clipboard: Option<Clipboard>,
+}
+
+impl ScreenClipboard {
+    pub fn new() -> ScreenClipboard {
+        ScreenClipboard { clipboard: None }
+    }
+
+    pub fn copy(&mut self, cpu: &CPU, palette: &Palette) -> Result<(), String> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self
+                .clipboard
+                .insert(Clipboard::new().map_err(|error| error.to_string())?),
+        };
+        clipboard
+            .set_image(ImageData {
+                width: IMAGE_WIDTH,
+                height: IMAGE_HEIGHT,
+                bytes: Cow::Owned(image(cpu, palette)),
+            })
+            .map_err(|error| error.to_string())
+    }
+}
+
+// the screen in the palette's colours as RGBA rows
+fn image(cpu: &CPU, palette: &Palette) -> Vec<u8> {
+    let (width, height) = cpu.resolution();
+    let color = |(red, green, blue)| [red, green, blue, 255];
+
+    let mut bytes = Vec::with_capacity(IMAGE_WIDTH * IMAGE_HEIGHT * 4);
+    for y in 0..IMAGE_HEIGHT {
+        let row = y * height / IMAGE_HEIGHT * width;
+        for x in 0..IMAGE_WIDTH {
+            let index = row + x * width / IMAGE_WIDTH;
+            bytes.extend_from_slice(&match (cpu.screen[index], cpu.second_plane[index]) {
+                (true, false) => color(palette.foreground),
+                (false, true) => color(palette.second),
+                (true, true) => color(palette.both),
+                (false, false) => color(palette.background),
+            });
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8::cpu::DisplayMode;
+    use chip8::settings::PALETTES;
+
+    fn pixel(bytes: &[u8], x: usize, y: usize) -> &[u8] {
+        let at = (y * IMAGE_WIDTH + x) * 4;
+        &bytes[at..at + 4]
+    }
+
+    #[test]
+    fn test_image() {
+        let amber = &PALETTES[1];
+        let mut cpu = CPU::new();
+        cpu.screen.set(64 + 1, true);
+
+        // each of the 64x32 pixels is 16 wide and tall
+        let bytes = image(&cpu, amber);
+        assert_eq!(bytes.len(), IMAGE_WIDTH * IMAGE_HEIGHT * 4);
+        assert_eq!(pixel(&bytes, 16, 16), [255, 176, 0, 255]);
+        assert_eq!(pixel(&bytes, 31, 31), [255, 176, 0, 255]);
+        assert_eq!(pixel(&bytes, 32, 16), [24, 12, 0, 255]);
+        assert_eq!(pixel(&bytes, 16, 32), [24, 12, 0, 255]);
+
+        // the two-page display's pixels come out half as tall
+        cpu.set_display_mode(DisplayMode::TwoPage);
+        cpu.screen.set(64 + 1, true);
+        let bytes = image(&cpu, amber);
+        assert_eq!(pixel(&bytes, 16, 8), [255, 176, 0, 255]);
+        assert_eq!(pixel(&bytes, 16, 16), [24, 12, 0, 255]);
+
+        // XO-CHIP's second plane has colours of its own
+        cpu.second_plane.set(64 + 1, true);
+        cpu.second_plane.set(2 * 64 + 1, true);
+        let bytes = image(&cpu, amber);
+        assert_eq!(pixel(&bytes, 16, 8), [100, 60, 0, 255]);
+        assert_eq!(pixel(&bytes, 16, 16), [170, 110, 0, 255]);
+    }
+}
@@ -0,0 +1,60 @@
+// turns a clock speed into instructions for each frame from how long the frame really took, so a
+// game runs at the same speed whatever rate the display refreshes at
+use std::time::{Duration, Instant};
+
+// a frame longer than this, because the window was being dragged or the machine was asleep,
+// isn't made up for all at once
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+pub struct Clock {
+    hz: u32,
+    last_frame: Instant,
+    // part of an instruction owed from earlier frames
+    owed: f64,
+}
+
+impl Clock {
+    pub fn new(hz: u32, now: Instant) -> Clock {
+        Clock {
+            hz,
+            last_frame: now,
+            owed: 0.0,
+        }
+    }
+
+    // call once per frame, paused or not, for the instructions to run in it
+    pub fn frame(&mut self, now: Instant) -> u32 {
+        let elapsed = now.saturating_duration_since(self.last_frame);
+        self.last_frame = now;
+
+        let owed = self.owed + self.hz as f64 * elapsed.min(MAX_FRAME_TIME).as_secs_f64();
+        self.owed = owed.fract();
+        owed as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        let start = Instant::now();
+        let mut clock = Clock::new(700, start);
+
+        // 700 instructions a second comes out as 11 or 12 a frame at 60 frames a second
+        let frame = Duration::from_secs(1) / 60;
+        let total: u32 = (1..=60).map(|n| clock.frame(start + frame * n)).sum();
+        assert!((699..=700).contains(&total));
+
+        // and as 5 or 6 at 120, adding up the same
+        let mut clock = Clock::new(700, start);
+        let frame = Duration::from_secs(1) / 120;
+        let counts: Vec<u32> = (1..=120).map(|n| clock.frame(start + frame * n)).collect();
+        assert!(counts.iter().all(|&count| count == 5 || count == 6));
+        assert!((699..=700).contains(&counts.iter().sum::<u32>()));
+
+        // a long stall only counts for a tenth of a second
+        assert_eq!(clock.frame(start + Duration::from_secs(10)), 70);
+    }
+}
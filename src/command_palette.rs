@@ -0,0 +1,277 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, text_width, LINE_HEIGHT};
+
+const MENU_PADDING: i32 = 6;
+const VISIBLE_MATCHES: usize = 12;
+
+const MENU_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const HOTKEY_COLOR: Color = Color::RGB(128, 128, 128);
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Debugger,
+    Registers,
+    MemoryViewer,
+    SpriteViewer,
+    Cheats,
+    RamSearch,
+    SpeedrunTimer,
+    SpeedrunSplit,
+    SpeedrunReset,
+    Keypad,
+    TasEditor,
+    Heatmap,
+    OpenRom,
+    Settings,
+    DumpMemory,
+    TextScreenshot,
+    CopyScreenshot,
+    SaveState,
+    LoadState,
+    Faster,
+    Slower,
+    NextPalette,
+    NextQuirks,
+    AntiFlicker,
+    Fullscreen,
+    Quit,
+}
+
+// every action with what it's called here and the key that does the same, if there is one
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Debugger, "toggle debugger", "F1"),
+    (Action::Registers, "toggle registers", "F2"),
+    (Action::MemoryViewer, "toggle memory viewer", "F3"),
+    (Action::SpriteViewer, "toggle sprite viewer", "F4"),
+    (Action::Cheats, "cheats", "F5"),
+    (Action::RamSearch, "ram search", "F6"),
+    (Action::SpeedrunTimer, "toggle speedrun timer", "F7"),
+    (Action::SpeedrunSplit, "split speedrun timer", "F8"),
+    (Action::SpeedrunReset, "reset speedrun timer", "F9"),
+    (Action::Keypad, "toggle keypad", "F10"),
+    (Action::TasEditor, "tas editor", "F11"),
+    (Action::Heatmap, "toggle heatmap", "F12"),
+    (Action::OpenRom, "open rom", ""),
+    (Action::Settings, "settings", "CTRL+,"),
+    (Action::DumpMemory, "dump memory", "CTRL+D"),
+    (Action::TextScreenshot, "screenshot as text", "CTRL+T"),
+    (Action::CopyScreenshot, "copy screenshot", "CTRL+C"),
+    (Action::SaveState, "save state", "CTRL+S"),
+    (Action::LoadState, "load state", "CTRL+L"),
+    (Action::Faster, "faster", ""),
+    (Action::Slower, "slower", ""),
+    (Action::NextPalette, "next palette", ""),
+    (Action::NextQuirks, "next quirk preset", ""),
+    (Action::AntiFlicker, "toggle anti-flicker", ""),
+    (Action::Fullscreen, "toggle fullscreen", "ALT+RETURN"),
+    (Action::Quit, "quit", ""),
+];
+
+pub struct CommandPalette {
+    visible: bool,
+    query: String,
+    selected: usize,
+    // picked with return, taken by the main loop
+    chosen: Option<Action>,
+}
+
+impl CommandPalette {
+    pub fn new() -> CommandPalette {
+        CommandPalette {
+            visible: false,
+            query: String::new(),
+            selected: 0,
+            chosen: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // returns true if the palette consumed the key
+    pub fn handle_key(&mut self, key: Keycode) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let count = matches(&self.query).len();
+        match key {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            Keycode::Return | Keycode::KpEnter => {
+                if let Some(&(action, _, _)) = matches(&self.query).get(self.selected) {
+                    self.chosen = Some(action);
+                    self.toggle();
+                }
+            }
+            Keycode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            Keycode::Space => {
+                self.query.push(' ');
+                self.selected = 0;
+            }
+            _ => {
+                // SDL's keycodes for letters and digits are the characters themselves
+                if let Some(c) = char::from_u32(key as u32).filter(char::is_ascii_alphanumeric) {
+                    self.query.push(c);
+                    self.selected = 0;
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn take_action(&mut self) -> Option<Action> {
+        self.chosen.take()
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(MENU_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = MENU_PADDING;
+        let mut y = MENU_PADDING;
+        draw_text(
+            canvas,
+            "COMMANDS  TYPE TO SEARCH, RETURN RUNS, CTRL+P CLOSES",
+            x,
+            y,
+            TITLE_COLOR,
+        );
+
+        y += LINE_HEIGHT;
+        draw_text(canvas, &format!("> {}_", self.query), x, y, TITLE_COLOR);
+
+        y += LINE_HEIGHT;
+        let matches = matches(&self.query);
+        if matches.is_empty() {
+            draw_text(canvas, "NO MATCHES", x, y + LINE_HEIGHT, TEXT_COLOR);
+        }
+
+        for (index, (_, name, hotkey)) in matches.iter().enumerate().take(VISIBLE_MATCHES) {
+            y += LINE_HEIGHT;
+
+            if index == self.selected {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y - 2, window_width, LINE_HEIGHT as u32))
+                    .unwrap();
+            }
+            draw_text(canvas, name, x, y, TEXT_COLOR);
+            let hotkey_x = window_width as i32 - MENU_PADDING - text_width(hotkey);
+            draw_text(canvas, hotkey, hotkey_x, y, HOTKEY_COLOR);
+        }
+    }
+}
+
+// the actions the query picks out, best first
+fn matches(query: &str) -> Vec<(Action, &'static str, &'static str)> {
+    let mut scored: Vec<_> = ACTIONS
+        .iter()
+        .filter_map(|&entry| score(query, entry.1).map(|score| (score, entry)))
+        .collect();
+    // stable, so equal scores keep the order above
+    scored.sort_by_key(|&(score, _)| -score);
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// None unless every character of the query appears in the name in order, spaces aside. a
+// character scores more for starting a word or following the one matched before it
+fn score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+
+    for c in query.chars().filter(|c| !c.is_whitespace()) {
+        let found = position + name[position..].iter().position(|&n| n == c)?;
+        score += 1;
+        if found == 0 || name[found - 1] == ' ' {
+            score += 3;
+        }
+        if previous == Some(found.wrapping_sub(1)) {
+            score += 2;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(query: &str) -> Vec<&'static str> {
+        matches(query)
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(score("", "cheats"), Some(0));
+        assert_eq!(score("xyz", "cheats"), None);
+        assert_eq!(score("tsaehc", "cheats"), None);
+        assert!(score("tm", "toggle memory viewer") > score("tm", "toggle heatmap"));
+        assert!(score("che", "cheats") > score("che", "toggle speedrun timer"));
+    }
+
+    #[test]
+    fn test_matches() {
+        assert_eq!(names("").len(), ACTIONS.len());
+        assert_eq!(names("heat")[0], "toggle heatmap");
+        assert_eq!(names("heatm"), ["toggle heatmap"]);
+        assert_eq!(names("mem")[..2], ["toggle memory viewer", "dump memory"]);
+        assert_eq!(names("speed")[0], "toggle speedrun timer");
+        assert!(names("qqq").is_empty());
+    }
+
+    #[test]
+    fn test_choose() {
+        let mut palette = CommandPalette::new();
+        assert!(!palette.handle_key(Keycode::H));
+
+        palette.toggle();
+        for key in [Keycode::H, Keycode::E, Keycode::A, Keycode::T] {
+            assert!(palette.handle_key(key));
+        }
+        assert_eq!(palette.query, "heat");
+        palette.handle_key(Keycode::Return);
+        assert_eq!(palette.take_action(), Some(Action::Heatmap));
+        assert_eq!(palette.take_action(), None);
+        assert!(!palette.visible);
+    }
+}
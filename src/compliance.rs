@@ -0,0 +1,305 @@
+use std::{fmt::Write, fs, path::Path};
+
+use crate::cpu::CpuError;
+use crate::error::Error;
+use crate::headless::{self, KeyEvent};
+use crate::quirks::Quirks;
+
+// CXNN is seeded so runs are repeatable
+const SEED: u64 = 0xC8;
+
+pub struct Preset {
+    pub name: &'static str,
+    pub quirks: Quirks,
+    // the key that picks this platform in the quirks test's menu
+    pub platform_key: usize,
+}
+
+pub fn presets() -> Vec<Preset> {
+    vec![
+        Preset {
+            name: "default",
+            quirks: Quirks::default(),
+            platform_key: 0x1,
+        },
+        Preset {
+            name: "vip",
+            quirks: Quirks::vip(),
+            platform_key: 0x1,
+        },
+        Preset {
+            name: "schip",
+            quirks: Quirks::schip(),
+            platform_key: 0x2,
+        },
+        Preset {
+            name: "schip-legacy",
+            quirks: Quirks::schip_legacy(),
+            platform_key: 0x2,
+        },
+        Preset {
+            name: "chip8e",
+            quirks: Quirks::chip8e(),
+            platform_key: 0x1,
+        },
+        Preset {
+            name: "hires",
+            quirks: Quirks::two_page(),
+            platform_key: 0x1,
+        },
+        Preset {
+            name: "xochip",
+            quirks: Quirks::xochip(),
+            platform_key: 0x3,
+        },
+    ]
+}
+
+// one of Timendus' test ROMs (https://github.com/Timendus/chip8-test-suite)
+pub struct SuiteTest {
+    pub name: &'static str,
+    pub frames: u32,
+    // the test starts with a menu asking which platform to check
+    pub platform_menu: bool,
+}
+
+pub const SUITE: &[SuiteTest] = &[
+    SuiteTest {
+        name: "1-chip8-logo",
+        frames: 40,
+        platform_menu: false,
+    },
+    SuiteTest {
+        name: "2-ibm-logo",
+        frames: 40,
+        platform_menu: false,
+    },
+    SuiteTest {
+        name: "3-corax+",
+        frames: 120,
+        platform_menu: false,
+    },
+    SuiteTest {
+        name: "4-flags",
+        frames: 120,
+        platform_menu: false,
+    },
+    SuiteTest {
+        name: "5-quirks",
+        frames: 600,
+        platform_menu: true,
+    },
+];
+
+impl SuiteTest {
+    pub fn keys(&self, preset: &Preset) -> Vec<KeyEvent> {
+        match self.platform_menu {
+            true => vec![
+                (30, preset.platform_key, true),
+                (40, preset.platform_key, false),
+            ],
+            false => Vec::new(),
+        }
+    }
+
+    // the known-good screen for this test, a preset specific one wins if there is one
+    pub fn reference(&self, directory: &Path, preset: &Preset) -> Option<String> {
+        let specific = directory.join(format!("{}-{}.txt", self.name, preset.name));
+        let general = directory.join(format!("{}.txt", self.name));
+        fs::read_to_string(specific)
+            .or_else(|_| fs::read_to_string(general))
+            .ok()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Crash(CpuError),
+    // empty or too big to load
+    BadRom,
+    MissingRom,
+    MissingReference,
+}
+
+pub fn check(directory: &Path, test: &SuiteTest, preset: &Preset) -> Outcome {
+    let Ok(rom) = fs::read(directory.join(format!("{}.ch8", test.name))) else {
+        return Outcome::MissingRom;
+    };
+    let Some(reference) = test.reference(directory, preset) else {
+        return Outcome::MissingReference;
+    };
+
+    match headless::run(&rom, preset.quirks, SEED, test.frames, &test.keys(preset)) {
+        Ok(cpu) if headless::screen_text(&cpu) == reference => Outcome::Pass,
+        Ok(_) => Outcome::Fail,
+        Err(Error::Cpu(error)) => Outcome::Crash(error),
+        Err(_) => Outcome::BadRom,
+    }
+}
+
+// a matrix of every test under every preset
+pub fn report(directory: &Path, suite: &[SuiteTest]) -> String {
+    let presets = presets();
+    let mut report = String::new();
+
+    let names: Vec<_> = presets.iter().map(|preset| preset.name).collect();
+    write_row(&mut report, "test", &names);
+
+    for test in suite {
+        let cells: Vec<_> = presets
+            .iter()
+            .map(|preset| match check(directory, test, preset) {
+                Outcome::Pass => "pass",
+                Outcome::Fail => "FAIL",
+                Outcome::Crash(_) => "CRASH",
+                Outcome::BadRom => "BAD-ROM",
+                Outcome::MissingRom => "-",
+                Outcome::MissingReference => "?",
+            })
+            .collect();
+        write_row(&mut report, test.name, &cells);
+    }
+
+    writeln!(
+        report,
+        "\n- ROM not found in {}, ? no known-good screen to compare with, \
+         BAD-ROM empty or too big to load",
+        directory.display()
+    )
+    .unwrap();
+    report
+}
+
+fn write_row(report: &mut String, name: &str, cells: &[&str]) {
+    let mut row = format!("{:<14}", name);
+    for cell in cells {
+        write!(row, " {:<8}", cell).unwrap();
+    }
+    writeln!(report, "{}", row.trim_end()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // hand-made stand-ins for the suite in tests/compliance/, with references of their own
+    const FIXTURES: &[SuiteTest] = &[
+        // 8XY6 shifting VY or VX in place, shift-schip.txt is missing so schip is held to the VIP
+        SuiteTest {
+            name: "shift",
+            frames: 10,
+            platform_menu: false,
+        },
+        // draws the key picked from the menu, menu-schip.txt and menu-xochip.txt cover the others
+        SuiteTest {
+            name: "menu",
+            frames: 60,
+            platform_menu: true,
+        },
+        SuiteTest {
+            name: "crash",
+            frames: 10,
+            platform_menu: false,
+        },
+        SuiteTest {
+            name: "unchecked",
+            frames: 10,
+            platform_menu: false,
+        },
+        SuiteTest {
+            name: "empty",
+            frames: 10,
+            platform_menu: false,
+        },
+        SuiteTest {
+            name: "missing",
+            frames: 10,
+            platform_menu: false,
+        },
+    ];
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compliance")
+    }
+
+    fn preset(name: &str) -> Preset {
+        presets()
+            .into_iter()
+            .find(|preset| preset.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        let directory = fixtures();
+        let [shift, menu, crash, unchecked, empty, missing] = FIXTURES else {
+            unreachable!()
+        };
+
+        assert_eq!(check(&directory, shift, &preset("vip")), Outcome::Pass);
+        assert_eq!(check(&directory, shift, &preset("default")), Outcome::Fail);
+        assert_eq!(check(&directory, menu, &preset("vip")), Outcome::Pass);
+        assert_eq!(check(&directory, menu, &preset("schip")), Outcome::Pass);
+        assert_eq!(check(&directory, menu, &preset("xochip")), Outcome::Pass);
+        // picks 2 in the menu, so the plain CHIP-8 screen doesn't match
+        assert_eq!(
+            check(&directory, menu, &preset("schip-legacy")),
+            Outcome::Fail
+        );
+        assert_eq!(
+            check(&directory, crash, &preset("default")),
+            Outcome::Crash(CpuError::StackUnderflow)
+        );
+        assert_eq!(
+            check(&directory, unchecked, &preset("default")),
+            Outcome::MissingReference
+        );
+        assert_eq!(
+            check(&directory, empty, &preset("default")),
+            Outcome::BadRom
+        );
+        assert_eq!(
+            check(&directory, missing, &preset("default")),
+            Outcome::MissingRom
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let report = report(&fixtures(), FIXTURES);
+        let rows: Vec<Vec<_>> = report
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+
+        assert_eq!(
+            rows[..7],
+            [
+                vec![
+                    "test",
+                    "default",
+                    "vip",
+                    "schip",
+                    "schip-legacy",
+                    "chip8e",
+                    "hires",
+                    "xochip"
+                ],
+                vec!["shift", "FAIL", "pass", "FAIL", "FAIL", "pass", "pass", "pass"],
+                vec!["menu", "pass", "pass", "pass", "FAIL", "pass", "pass", "pass"],
+                vec!["crash", "CRASH", "CRASH", "CRASH", "CRASH", "CRASH", "CRASH", "CRASH"],
+                vec!["unchecked", "?", "?", "?", "?", "?", "?", "?"],
+                vec![
+                    "empty", "BAD-ROM", "BAD-ROM", "BAD-ROM", "BAD-ROM", "BAD-ROM", "BAD-ROM",
+                    "BAD-ROM"
+                ],
+                vec!["missing", "-", "-", "-", "-", "-", "-", "-"],
+            ]
+        );
+        assert_eq!(rows.len(), FIXTURES.len() + 3);
+    }
+}
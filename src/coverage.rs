@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+use crate::cpu::{AccessKind, MemoryAccess};
+
+const EXECUTED: u8 = 0b001;
+const READ: u8 = 0b010;
+const WRITTEN: u8 = 0b100;
+
+const MAP_ROW_BYTES: usize = 64;
+
+// remembers how every address has been used over the whole run
+pub struct Coverage {
+    usage: Vec<u8>,
+}
+
+impl Coverage {
+    pub fn new(memory_size: usize) -> Coverage {
+        Coverage {
+            usage: vec![0; memory_size],
+        }
+    }
+
+    // called after every tick with the accesses the instruction made
+    pub fn record(&mut self, accesses: &[MemoryAccess]) {
+        for access in accesses {
+            self.usage[access.address as usize] |= match access.kind {
+                AccessKind::Execute => EXECUTED,
+                AccessKind::Read => READ,
+                AccessKind::Write => WRITTEN,
+            };
+        }
+    }
+
+    // a text map with one character per byte, code wins over data when both apply
+    pub fn map(&self) -> String {
+        let count = |flag: u8| {
+            self.usage
+                .iter()
+                .filter(|usage| **usage & flag != 0)
+                .count()
+        };
+        let untouched = self.usage.iter().filter(|usage| **usage == 0).count();
+
+        let mut map = String::new();
+        writeln!(
+            map,
+            "# {} bytes: {} executed, {} read, {} written, {} untouched",
+            self.usage.len(),
+            count(EXECUTED),
+            count(READ),
+            count(WRITTEN),
+            untouched
+        )
+        .unwrap();
+        writeln!(
+            map,
+            "# X executed, R read, W written, B read and written, . untouched"
+        )
+        .unwrap();
+
+        for (row, chunk) in self.usage.chunks(MAP_ROW_BYTES).enumerate() {
+            let cells: String = chunk
+                .iter()
+                .map(|usage| {
+                    if usage & EXECUTED != 0 {
+                        'X'
+                    } else if usage & (READ | WRITTEN) == READ | WRITTEN {
+                        'B'
+                    } else if usage & READ != 0 {
+                        'R'
+                    } else if usage & WRITTEN != 0 {
+                        'W'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            writeln!(map, "{:04X} {}", row * MAP_ROW_BYTES, cells).unwrap();
+        }
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CPU;
+
+    #[test]
+    fn test_coverage_map() {
+        let mut cpu = CPU::new();
+        let mut coverage = Coverage::new(cpu.memory().len());
+
+        // I = 0x240, draw a 1 row sprite, store V0 at I
+        cpu.load(&[0xA2, 0x40, 0xD0, 0x01, 0xF0, 0x55]);
+        for _ in 0..3 {
+            cpu.tick().unwrap();
+            coverage.record(cpu.memory_accesses());
+        }
+
+        let map = coverage.map();
+        assert!(map.starts_with("# 4096 bytes: 6 executed, 1 read, 1 written, 4089 untouched"));
+
+        let code = map.lines().find(|line| line.starts_with("0200")).unwrap();
+        assert_eq!(&code[5..12], "XXXXXX.");
+        let data = map.lines().find(|line| line.starts_with("0240")).unwrap();
+        assert_eq!(&data[5..7], "B.");
+    }
+}
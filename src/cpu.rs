@@ -1,18 +1,50 @@
-use rand::random;
+use alloc::{collections::BTreeSet, format, sync::Arc, vec, vec::Vec};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use thiserror::Error;
+
+#[cfg(feature = "std")]
+use crate::compliance;
+use crate::error::{ConfigError, RomError};
+use crate::events::Event;
+use crate::framebuffer::Framebuffer;
+use crate::instruction::Instruction;
+use crate::patch::crc32;
+use crate::quirks::Quirks;
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
-
-const MEMORY_SIZE: usize = 4096;
+// the hires CHIP-8 interpreter's two-page display
+pub const TWO_PAGE_HEIGHT: usize = 64;
+// SUPER-CHIP's high resolution display
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+// the screen holds the largest display, rows are as wide as the one in use
+pub const SCREEN_PIXELS: usize = HIRES_WIDTH * HIRES_HEIGHT;
+// a hires CHIP-8 program starts by jumping over the interpreter's setup code to here
+const TWO_PAGE_START: u16 = 0x2C0;
+
+pub const MEMORY_SIZE: usize = 4096;
+// instructions a frame, the speed the SDL frontend starts at
+pub const TICKS_PER_FRAME: u32 = 10;
+// events queued for a frontend that never takes them stop here rather than piling up
+const MAX_PENDING_EVENTS: usize = 256;
 const NUM_V_REGISTERS: usize = 16;
-// stack size is not in the Chip8 specification
-const STACK_SIZE: usize = 16;
-const NUM_KEYS: usize = 16;
+// stack size is not in the Chip8 specification. 16 is the usual, Octo programs that recurse can
+// need more
+pub const DEFAULT_STACK_SIZE: usize = 16;
+// a program calling deeper than this has run away rather than recursed
+pub const MAX_STACK_SIZE: usize = 256;
+pub const NUM_KEYS: usize = 16;
 // the first 512 bytes were originally for the interpreter, no program should use them
-const START_ADDRESS: u16 = 0x200;
-const FONTSET_SIZE: usize = 80;
-
-const FONTSET: [u8; FONTSET_SIZE] = [
+pub const START_ADDRESS: u16 = 0x200;
+pub const FONTSET_SIZE: usize = 80;
+// XO-CHIP's 64K, all a 16-bit address reaches. the PC wraps round to 0 past the end
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+// the pitch an XO-CHIP audio pattern plays at until FX3A sets another, 4000 samples a second
+pub const DEFAULT_PITCH: u8 = 64;
+
+pub const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -31,62 +63,796 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+    Execute,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub address: u16,
+    pub kind: AccessKind,
+}
+
+// the displays a program can switch between
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayMode {
+    Standard,
+    TwoPage,
+    Hires,
+}
+
+impl DisplayMode {
+    // (width, height) in pixels
+    pub fn size(self) -> (usize, usize) {
+        match self {
+            DisplayMode::Standard => (SCREEN_WIDTH, SCREEN_HEIGHT),
+            DisplayMode::TwoPage => (SCREEN_WIDTH, TWO_PAGE_HEIGHT),
+            DisplayMode::Hires => (HIRES_WIDTH, HIRES_HEIGHT),
+        }
+    }
+}
+
+// things a program does that the interpreters it may have been written for disagree on
+#[derive(Clone, Copy)]
+enum QuirkWarning {
+    Shift,
+    Jump,
+    EmptySprite,
+    Edge,
+}
+
+// what a broken or hostile program can do to stop the CPU, none of these are recoverable
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum CpuError {
+    #[error("unknown opcode {0:#06x}")]
+    UnknownOpcode(u16),
+    #[error("stack overflow")]
+    StackOverflow,
+    #[error("return with an empty stack")]
+    StackUnderflow,
+    // an access past the end of memory, including fetching from it
+    #[error("address {0:#x} is out of range")]
+    AddressOutOfRange(usize),
+    #[error("key {0:#x} does not exist")]
+    InvalidKey(u8),
+    // a write below the start address on a strict machine
+    #[error("write to {0:#05x}, where the interpreter and font are")]
+    ProtectedWrite(usize),
+}
+
+// how far run_cycles got with its budget
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CycleReport {
+    // instructions run, an FX0A that's still waiting counts but one that failed doesn't
+    pub ran: u32,
+    // the program is waiting for a key, nothing more happens until one is pressed
+    pub waiting_for_key: bool,
+    pub stopped: Option<CpuError>,
+}
+
+type Handler = fn(&mut CPU, u16) -> Result<(), CpuError>;
+
+// one handler per top nibble, indexing this beats matching on all four nibbles at once. an
+// instruction that has run before skips it, see CPU::run
+const HANDLERS: [Handler; 16] = [
+    CPU::execute_system,
+    CPU::execute_jump,
+    CPU::execute_call,
+    CPU::execute_skip_eq_imm,
+    CPU::execute_skip_ne_imm,
+    CPU::execute_skip_eq_reg,
+    CPU::execute_load_imm,
+    CPU::execute_add_imm,
+    CPU::execute_alu,
+    CPU::execute_skip_ne_reg,
+    CPU::execute_load_index,
+    CPU::execute_jump_v0,
+    CPU::execute_random,
+    CPU::execute_draw,
+    CPU::execute_key,
+    CPU::execute_misc,
+];
+
+// opcode fields, named as in the usual XYN notation
+fn x(op: u16) -> usize {
+    ((op & 0x0F00) >> 8) as usize
+}
+
+fn y(op: u16) -> usize {
+    ((op & 0x00F0) >> 4) as usize
+}
+
+fn n(op: u16) -> usize {
+    (op & 0x000F) as usize
+}
+
+fn nn(op: u16) -> u8 {
+    (op & 0x00FF) as u8
+}
+
+fn nnn(op: u16) -> u16 {
+    op & 0x0FFF
+}
+
+// the register `offset` along from VX towards VY, for the CHIP-8E ranges that count down when X
+// is past Y
+fn register_towards(vx: usize, vy: usize, offset: usize) -> usize {
+    if vx <= vy {
+        vx + offset
+    } else {
+        vx - offset
+    }
+}
+
+// seeded from the OS, which a microcontroller doesn't have, there it's the same every time until
+// CPU::seed is given something, like a timer read when a button is first pressed
+#[cfg(feature = "std")]
+fn new_rng() -> StdRng {
+    StdRng::from_entropy()
+}
+
+#[cfg(not(feature = "std"))]
+fn new_rng() -> StdRng {
+    StdRng::seed_from_u64(0)
+}
+
+// a stack size a machine can be given, for checking one from the user before building it
+pub fn check_stack_size(size: usize) -> Result<(), ConfigError> {
+    if !(1..=MAX_STACK_SIZE).contains(&size) {
+        return Err(ConfigError::InvalidStackSize { size, min: 1 });
+    }
+    Ok(())
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct CPU {
     pc: u16,
-    memory: [u8; MEMORY_SIZE],
+    memory: Vec<u8>,
+    // the instruction at each address, decoded the first time it runs or when a ROM loads. a
+    // write to memory forgets the two entries it could change, and memory_mut all of them. it
+    // starts empty, and is filled out to the size of memory at the first miss
+    decoded: Vec<Option<Instruction>>,
+    // copied to the start of memory whenever it's cleared
+    fontset: [u8; FONTSET_SIZE],
     // pixels don't have colours, they are either on or off
-    pub screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    pub screen: Framebuffer,
+    // XO-CHIP's second plane, a pixel lit on either or both showing in one of three colours
+    pub second_plane: Framebuffer,
+    // the planes 00E0, scrolling and DXYN work on, the first in bit 0. only FN01 changes it
+    planes: u8,
+    display_mode: DisplayMode,
     v_registers: [u8; NUM_V_REGISTERS],
     index_register: u16,
-    stack: [u16; STACK_SIZE],
-    stack_pointer: u16,
+    // return addresses, oldest first, and how many there can be
+    stack: Vec<u16>,
+    stack_size: usize,
     keys: [bool; NUM_KEYS],
+    // the key that went down most recently, held or not
+    last_key: Option<u8>,
+    // frames run_frame has run, and (frame, key, pressed) for it to apply at the start of one,
+    // in frame order
+    frame: u64,
+    scheduled_keys: Vec<(u64, usize, bool)>,
     delay_timer: u8,
     sound_timer: u8,
+    // every memory access made by the last instruction, for the debugging tools
+    accesses: Vec<MemoryAccess>,
+    rng: StdRng,
+    // the seed given, if any, and how many numbers have been drawn since, which together say
+    // where the generator is for state_hash
+    rng_seed: Option<u64>,
+    random_draws: u64,
+    // see CpuBuilder::deterministic
+    deterministic: bool,
+    // see CpuBuilder::strict
+    strict: bool,
+    quirks: Quirks,
+    // instructions run_frame runs before the timers
+    ticks_per_frame: u32,
+    // a CHIP-8E FX4F has set the delay timer and is waiting for it to run out
+    delay_wait: bool,
+    // an FX0A is waiting for a key, so WaitingForKey is only sent when the wait starts
+    key_wait: bool,
+    // XO-CHIP's 128 one-bit samples from F002, played in a loop while the sound timer runs
+    // instead of the buzzer, and the pitch from FX3A
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    // happenings not yet taken by the frontend, oldest first
+    events: Vec<Event>,
+    // a bit for each QuirkWarning already logged since the last reset
+    quirk_warnings: u8,
+    breakpoints: BTreeSet<u16>,
+    // given the opcodes the CPU doesn't know before they're an error, returns true for one it ran
+    extra_opcodes: Option<OpcodeExtension>,
+}
+
+pub struct CpuBuilder {
+    deterministic: bool,
+    strict: bool,
+    quirks: Quirks,
+    ticks_per_frame: u32,
+    fontset: [u8; FONTSET_SIZE],
+    seed: Option<u64>,
+    memory_size: usize,
+    stack_size: usize,
+}
+
+impl CpuBuilder {
+    pub fn quirks(mut self, quirks: Quirks) -> CpuBuilder {
+        self.quirks = quirks;
+        self
+    }
+
+    // the quirks of one of compliance::presets() by name, like "vip" or "schip"
+    #[cfg(feature = "std")]
+    pub fn variant(self, name: &str) -> Result<CpuBuilder, ConfigError> {
+        let preset = compliance::presets()
+            .into_iter()
+            .find(|preset| preset.name == name)
+            .ok_or_else(|| ConfigError::UnknownVariant(name.to_string()))?;
+        Ok(self.quirks(preset.quirks))
+    }
+
+    // instructions a second, which run_frame spreads over 60 frames
+    pub fn clock_speed(mut self, hz: u32) -> CpuBuilder {
+        self.ticks_per_frame = (hz / 60).max(1);
+        self
+    }
+
+    // the 0 to F digits FX29 points at, five bytes each
+    pub fn fontset(mut self, fontset: [u8; FONTSET_SIZE]) -> CpuBuilder {
+        self.fontset = fontset;
+        self
+    }
+
+    // makes CXNN repeatable, otherwise it's seeded from the system
+    pub fn seed(mut self, seed: u64) -> CpuBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    // a machine that does exactly the same again given the same keys on the same frames, for
+    // replays and netplay. CXNN is seeded with `seed`, and time only passes in run_frame: the
+    // timers count down once a frame there and keys pressed between frames wait for the next one
+    pub fn deterministic(mut self, seed: u64) -> CpuBuilder {
+        self.deterministic = true;
+        self.seed = Some(seed);
+        self
+    }
+
+    // a machine that stops with ProtectedWrite when the program writes below the start address,
+    // where the interpreter and font were. nothing a program should do, but something a buggy one
+    // does without noticing until the font it overwrote is drawn
+    pub fn strict(mut self) -> CpuBuilder {
+        self.strict = true;
+        self
+    }
+
+    // bytes of memory, 4K unless a program needs more
+    pub fn memory_size(mut self, size: usize) -> CpuBuilder {
+        self.memory_size = size;
+        self
+    }
+
+    // how many calls can be nested, DEFAULT_STACK_SIZE unless a program recurses deeper
+    pub fn stack_size(mut self, size: usize) -> CpuBuilder {
+        self.stack_size = size;
+        self
+    }
+
+    pub fn build(self) -> Result<CPU, ConfigError> {
+        if !(START_ADDRESS as usize + 2..=MAX_MEMORY_SIZE).contains(&self.memory_size) {
+            return Err(ConfigError::Invalid(format!(
+                "memory must be from {} to {} bytes, not {}",
+                START_ADDRESS + 2,
+                MAX_MEMORY_SIZE,
+                self.memory_size
+            )));
+        }
+
+        let mut cpu = CPU::new();
+        cpu.memory = vec![0; self.memory_size];
+        cpu.fontset = self.fontset;
+        cpu.memory[..FONTSET_SIZE].copy_from_slice(&cpu.fontset);
+        cpu.quirks = self.quirks;
+        cpu.ticks_per_frame = self.ticks_per_frame;
+        if let Some(seed) = self.seed {
+            cpu.seed(seed);
+        }
+        cpu.deterministic = self.deterministic;
+        cpu.strict = self.strict;
+        cpu.set_stack_size(self.stack_size)?;
+        Ok(cpu)
+    }
+}
+
+pub type OpcodeExtension = Arc<dyn Fn(&mut CPU, u16) -> bool + Send + Sync>;
+
+// cloning is how savestates are taken. clone_from reuses the buffers of the machine it's cloned
+// into, so a frontend that keeps taking them, as rewind does every frame, isn't allocating for each
+impl Clone for CPU {
+    fn clone(&self) -> CPU {
+        // with room for the whole stack, as the machine it's cloned from has
+        let mut stack = Vec::with_capacity(self.stack_size);
+        stack.extend_from_slice(&self.stack);
+        CPU {
+            pc: self.pc,
+            memory: self.memory.clone(),
+            // decoded again as the clone runs, so savestates don't carry it
+            decoded: Vec::new(),
+            fontset: self.fontset,
+            screen: self.screen,
+            second_plane: self.second_plane,
+            planes: self.planes,
+            display_mode: self.display_mode,
+            v_registers: self.v_registers,
+            index_register: self.index_register,
+            stack,
+            stack_size: self.stack_size,
+            keys: self.keys,
+            last_key: self.last_key,
+            frame: self.frame,
+            scheduled_keys: self.scheduled_keys.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            accesses: self.accesses.clone(),
+            rng: self.rng.clone(),
+            rng_seed: self.rng_seed,
+            random_draws: self.random_draws,
+            deterministic: self.deterministic,
+            strict: self.strict,
+            quirks: self.quirks,
+            ticks_per_frame: self.ticks_per_frame,
+            delay_wait: self.delay_wait,
+            key_wait: self.key_wait,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            events: self.events.clone(),
+            quirk_warnings: self.quirk_warnings,
+            breakpoints: self.breakpoints.clone(),
+            extra_opcodes: self.extra_opcodes.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &CPU) {
+        // every field by name, so one added later can't be missed
+        let CPU {
+            pc,
+            memory,
+            decoded: _,
+            fontset,
+            screen,
+            second_plane,
+            planes,
+            display_mode,
+            v_registers,
+            index_register,
+            stack,
+            stack_size,
+            keys,
+            last_key,
+            frame,
+            scheduled_keys,
+            delay_timer,
+            sound_timer,
+            accesses,
+            rng,
+            rng_seed,
+            random_draws,
+            deterministic,
+            strict,
+            quirks,
+            ticks_per_frame,
+            delay_wait,
+            key_wait,
+            audio_pattern,
+            pitch,
+            events,
+            quirk_warnings,
+            breakpoints,
+            extra_opcodes,
+        } = source;
+        self.pc = *pc;
+        self.memory.clone_from(memory);
+        self.decoded.clear();
+        self.fontset = *fontset;
+        self.screen = *screen;
+        self.second_plane = *second_plane;
+        self.planes = *planes;
+        self.display_mode = *display_mode;
+        self.v_registers = *v_registers;
+        self.index_register = *index_register;
+        self.stack.clone_from(stack);
+        self.stack_size = *stack_size;
+        self.keys = *keys;
+        self.last_key = *last_key;
+        self.frame = *frame;
+        self.scheduled_keys.clone_from(scheduled_keys);
+        self.delay_timer = *delay_timer;
+        self.sound_timer = *sound_timer;
+        self.accesses.clone_from(accesses);
+        self.rng.clone_from(rng);
+        self.rng_seed = *rng_seed;
+        self.random_draws = *random_draws;
+        self.deterministic = *deterministic;
+        self.strict = *strict;
+        self.quirks = *quirks;
+        self.ticks_per_frame = *ticks_per_frame;
+        self.delay_wait = *delay_wait;
+        self.key_wait = *key_wait;
+        self.audio_pattern = *audio_pattern;
+        self.pitch = *pitch;
+        self.events.clone_from(events);
+        self.quirk_warnings = *quirk_warnings;
+        self.breakpoints.clone_from(breakpoints);
+        self.extra_opcodes.clone_from(extra_opcodes);
+    }
+}
+
+impl Default for CPU {
+    fn default() -> CPU {
+        CPU::new()
+    }
 }
 
 impl CPU {
+    // a machine set up some other way than the defaults new gives
+    pub fn builder() -> CpuBuilder {
+        CpuBuilder {
+            deterministic: false,
+            strict: false,
+            quirks: Quirks::default(),
+            ticks_per_frame: TICKS_PER_FRAME,
+            fontset: FONTSET,
+            seed: None,
+            memory_size: MEMORY_SIZE,
+            stack_size: DEFAULT_STACK_SIZE,
+        }
+    }
+
     pub fn new() -> CPU {
         let mut cpu = CPU {
             pc: START_ADDRESS,
-            memory: [0; MEMORY_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            memory: vec![0; MEMORY_SIZE],
+            decoded: Vec::new(),
+            fontset: FONTSET,
+            screen: Framebuffer::new(SCREEN_WIDTH),
+            second_plane: Framebuffer::new(SCREEN_WIDTH),
+            planes: 1,
+            display_mode: DisplayMode::Standard,
             v_registers: [0; NUM_V_REGISTERS],
             index_register: 0,
-            stack: [0; STACK_SIZE],
-            stack_pointer: 0,
+            stack: Vec::with_capacity(DEFAULT_STACK_SIZE),
+            stack_size: DEFAULT_STACK_SIZE,
             keys: [false; NUM_KEYS],
+            last_key: None,
+            frame: 0,
+            scheduled_keys: Vec::new(),
             delay_timer: 0,
             sound_timer: 0,
+            accesses: Vec::new(),
+            rng: new_rng(),
+            rng_seed: None,
+            random_draws: 0,
+            deterministic: false,
+            strict: false,
+            quirks: Quirks::default(),
+            ticks_per_frame: TICKS_PER_FRAME,
+            delay_wait: false,
+            key_wait: false,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            events: Vec::new(),
+            quirk_warnings: 0,
+            breakpoints: BTreeSet::new(),
+            extra_opcodes: None,
         };
 
-        cpu.memory[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        cpu.memory[..FONTSET_SIZE].copy_from_slice(&cpu.fontset);
 
         cpu
     }
 
     pub fn reset(&mut self) {
         self.pc = START_ADDRESS;
-        self.memory = [0; MEMORY_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.memory.fill(0);
+        self.decoded.clear();
+        self.screen = Framebuffer::new(SCREEN_WIDTH);
+        self.second_plane = Framebuffer::new(SCREEN_WIDTH);
+        self.planes = 1;
+        self.display_mode = DisplayMode::Standard;
         self.v_registers = [0; NUM_V_REGISTERS];
         self.index_register = 0;
-        self.stack_pointer = 0;
-        self.stack = [0; STACK_SIZE];
+        self.stack.clear();
         self.keys = [false; NUM_KEYS];
+        self.last_key = None;
+        self.frame = 0;
+        self.scheduled_keys.clear();
         self.delay_timer = 0;
         self.sound_timer = 0;
+        self.delay_wait = false;
+        self.key_wait = false;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.accesses.clear();
+        self.quirk_warnings = 0;
+
+        self.memory[..FONTSET_SIZE].copy_from_slice(&self.fontset);
+    }
+
+    pub fn tick(&mut self) -> Result<(), CpuError> {
+        self.accesses.clear();
+        let sounding = self.sound_timer > 0;
+        let result = self.fetch_decoded().and_then(|instruction| {
+            tracing::trace!(
+                "{:03X} {:04X}",
+                self.pc.wrapping_sub(2),
+                instruction.encode()
+            );
+            self.run(instruction)
+        });
+
+        match result {
+            Err(error) => self.emit(Event::Halted(error)),
+            Ok(()) => {
+                match (sounding, self.sound_timer > 0) {
+                    (false, true) => self.emit(Event::SoundStarted),
+                    (true, false) => self.emit(Event::SoundStopped),
+                    _ => (),
+                }
+                if self.breakpoints.contains(&self.pc) {
+                    self.emit(Event::Breakpoint(self.pc));
+                }
+            }
+        }
+        result
+    }
+
+    // tick, then what it and anything since the last step or take_events did, so a frontend can
+    // redraw or start the buzzer only when something changed rather than looking every tick. if
+    // the program stops the error is returned and the events wait for the next take_events
+    pub fn step(&mut self) -> Result<Vec<Event>, CpuError> {
+        self.tick()?;
+        Ok(self.take_events())
+    }
+
+    // runs up to `budget` instructions, for frontends whose frames aren't all the same length.
+    // stops early when the program waits for a key or hits an error, so there's no spinning on an
+    // FX0A for the rest of the budget
+    pub fn run_cycles(&mut self, budget: u32) -> CycleReport {
+        let mut report = CycleReport {
+            ran: 0,
+            waiting_for_key: false,
+            stopped: None,
+        };
+        while report.ran < budget {
+            if let Err(error) = self.tick() {
+                report.stopped = Some(error);
+                break;
+            }
+            report.ran += 1;
+            if self.key_wait {
+                report.waiting_for_key = true;
+                break;
+            }
+        }
+        report
+    }
+
+    // a 60Hz frame: the instructions in it then the timers once, in that order, giving the events
+    // the frame sent. if the program stops part way the timers aren't ticked
+    pub fn run_frame(&mut self) -> Result<Vec<Event>, CpuError> {
+        let due = self
+            .scheduled_keys
+            .partition_point(|&(frame, _, _)| frame <= self.frame);
+        for (_, key, pressed) in self.scheduled_keys.drain(..due).collect::<Vec<_>>() {
+            self.set_key(key, pressed);
+        }
+        self.frame += 1;
+
+        for _ in 0..self.ticks_per_frame {
+            self.tick()?;
+        }
+        self.count_down_timers();
+        Ok(self.take_events())
+    }
+
+    // the delay and sound timers count down at 60Hz whatever speed the program runs at, so
+    // frontends call this 60 times a second of real time, or once a frame when they need to be
+    // repeatable. a deterministic machine ignores it, run_frame does it there
+    pub fn tick_timers(&mut self) {
+        if !self.deterministic {
+            self.count_down_timers();
+        }
+    }
+
+    fn count_down_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            if self.sound_timer == 1 {
+                self.emit(Event::SoundStopped);
+            }
+
+            self.sound_timer -= 1;
+        }
+    }
+
+    // everything that's happened since the last call, for an events::EventBus to hand out
+    pub fn take_events(&mut self) -> Vec<Event> {
+        core::mem::take(&mut self.events)
+    }
 
-        self.memory[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+    // take_events keeping hold of the buffer, for a frontend taking them every instruction
+    pub fn drain_events(&mut self) -> vec::Drain<'_, Event> {
+        self.events.drain(..)
     }
 
-    pub fn tick(&mut self) {
-        let op = self.fetch();
-        self.execute(op);
-        self.tick_timers();
+    fn emit(&mut self, event: Event) {
+        if self.events.len() >= MAX_PENDING_EVENTS {
+            // nothing else says the program has stopped, so a halt takes the oldest event's
+            // place, once
+            if !matches!(event, Event::Halted(_)) || self.events.last() == Some(&event) {
+                return;
+            }
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    // sets a breakpoint at the address, or clears the one there
+    pub fn toggle_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    // instructions of a plugin's own, tried for any opcode the CPU doesn't know
+    pub fn set_opcode_extension(&mut self, extension: Option<OpcodeExtension>) {
+        self.extra_opcodes = extension;
+    }
+
+    pub fn ticks_per_frame(&self) -> u32 {
+        self.ticks_per_frame
+    }
+
+    pub fn set_ticks_per_frame(&mut self, ticks: u32) {
+        self.ticks_per_frame = ticks;
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
     }
 
-    pub fn keypress(&mut self, index: usize, pressed: bool) {
+    // makes CXNN repeatable, for tests and recordings
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.rng_seed = Some(seed);
+        self.random_draws = 0;
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    // see CpuBuilder::strict
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    // everything that decides what the machine does next, keys, waits, quirks and the random
+    // number generator included, so two runs can be checked to be the same frame by frame. only
+    // a seeded generator can be told apart, one seeded from the system hashes as if it were
+    // unseeded
+    pub fn state_hash(&self) -> u32 {
+        let mut bytes = Vec::with_capacity(self.memory.len() + SCREEN_PIXELS + 96);
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        bytes.extend_from_slice(&self.index_register.to_le_bytes());
+        bytes.extend_from_slice(&self.v_registers);
+        bytes.push(self.delay_timer);
+        bytes.push(self.sound_timer);
+        for address in self.stack() {
+            bytes.extend_from_slice(&address.to_le_bytes());
+        }
+        bytes.extend(self.keys.iter().map(|&pressed| pressed as u8));
+        bytes.push(self.last_key.map_or(0xFF, |key| key));
+        bytes.push(self.display_mode as u8);
+        bytes.extend_from_slice(&self.frame.to_le_bytes());
+        for &(frame, key, pressed) in self.scheduled_keys.iter() {
+            bytes.extend_from_slice(&frame.to_le_bytes());
+            bytes.extend([key as u8, pressed as u8]);
+        }
+        bytes.extend([self.delay_wait as u8, self.key_wait as u8]);
+        // every quirk by name, so one added later can't be missed
+        let Quirks {
+            vf_reset,
+            memory_increment,
+            shift_uses_vy,
+            jump_uses_vx,
+            clipping,
+            increment_by_x,
+            tall_sprites,
+            chip8e,
+            two_page,
+            schip_hires,
+            xochip,
+        } = self.quirks;
+        bytes.extend(
+            [
+                vf_reset,
+                memory_increment,
+                shift_uses_vy,
+                jump_uses_vx,
+                clipping,
+                increment_by_x,
+                tall_sprites,
+                chip8e,
+                two_page,
+                schip_hires,
+                xochip,
+            ]
+            .map(u8::from),
+        );
+        bytes.extend_from_slice(&self.rng_seed.unwrap_or(0).to_le_bytes());
+        bytes.extend_from_slice(&self.random_draws.to_le_bytes());
+        bytes.extend_from_slice(&self.memory);
+        // as the array of every pixel the screen used to be, so hashes stay the same
+        let (_, height) = self.resolution();
+        let pixels = self.screen.pixels(height);
+        let unused = SCREEN_PIXELS - pixels.len();
+        bytes.extend(pixels.map(|pixel| pixel as u8));
+        bytes.resize(bytes.len() + unused, 0);
+        // and what only XO-CHIP has after it
+        if xochip {
+            bytes.extend(self.second_plane.pixels(height).map(|pixel| pixel as u8));
+            bytes.push(self.planes);
+            bytes.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+            bytes.extend([self.audio_pattern.is_some() as u8, self.pitch]);
+        }
+        crc32(&bytes)
+    }
+
+    // on a deterministic machine the key changes at the start of the next run_frame
+    pub fn keypress(&mut self, index: usize, pressed: bool) -> Result<(), CpuError> {
+        if index >= NUM_KEYS {
+            return Err(CpuError::InvalidKey(index as u8));
+        }
+        self.press(index, pressed);
+        Ok(())
+    }
+
+    // the whole keypad at once, each key as keypress would set it
+    pub fn set_keys(&mut self, keys: &[bool; NUM_KEYS]) {
+        for (index, &pressed) in keys.iter().enumerate() {
+            self.press(index, pressed);
+        }
+    }
+
+    fn press(&mut self, index: usize, pressed: bool) {
+        if self.deterministic {
+            self.schedule(self.frame, index, pressed);
+        } else {
+            self.set_key(index, pressed);
+        }
+    }
+
+    fn set_key(&mut self, index: usize, pressed: bool) {
+        if pressed && !self.keys[index] {
+            self.last_key = Some(index as u8);
+        }
         self.keys[index] = pressed;
     }
 
@@ -94,120 +860,577 @@ impl CPU {
         let start = START_ADDRESS as usize;
         let end = START_ADDRESS as usize + data.len();
 
-        self.memory[start..end].copy_from_slice(&data);
+        self.memory[start..end].copy_from_slice(data);
+        // the ROM decoded up front, each instruction where it would be if none are skipped
+        self.decoded.clear();
+        self.decoded.resize(self.memory.len(), None);
+        for address in (start..end.saturating_sub(1)).step_by(2) {
+            let op = u16::from_be_bytes([self.memory[address], self.memory[address + 1]]);
+            self.decoded[address] = Some(Instruction::decode(op));
+        }
+        self.emit(Event::RomLoaded(data.len()));
+    }
+
+    // load for a ROM that came from outside and may not fit
+    pub fn try_load(&mut self, data: &[u8]) -> Result<(), RomError> {
+        self.check_rom(data)?;
+        self.load(data);
+        Ok(())
+    }
+
+    // whether try_load would take a ROM, without touching the machine
+    pub fn check_rom(&self, data: &[u8]) -> Result<(), RomError> {
+        let room = self.memory.len() - START_ADDRESS as usize;
+        match data.len() {
+            0 => Err(RomError::Empty),
+            size if size > room => Err(RomError::TooLarge { size, room }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn v_registers(&self) -> &[u8] {
+        &self.v_registers
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // for frontends that hand the RAM to someone else, writes through it aren't tracked
+    // anything could be written, so everything decoded is forgotten
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        self.memory_changed();
+        &mut self.memory
+    }
+
+    // for memory written through a pointer memory_mut gave out earlier, before the next tick
+    pub fn memory_changed(&mut self) {
+        self.decoded.clear();
+    }
+
+    pub fn keys(&self) -> &[bool] {
+        &self.keys
+    }
+
+    // presses or releases a key at the start of a run_frame frame, counting from 0, for playing
+    // a game without a keyboard. a frame already run gets it at the start of the next
+    pub fn schedule_key(&mut self, frame: u64, key: usize, pressed: bool) -> Result<(), CpuError> {
+        if key >= NUM_KEYS {
+            return Err(CpuError::InvalidKey(key as u8));
+        }
+        self.schedule(frame, key, pressed);
+        Ok(())
+    }
+
+    fn schedule(&mut self, frame: u64, key: usize, pressed: bool) {
+        let at = self
+            .scheduled_keys
+            .partition_point(|&(scheduled, _, _)| scheduled <= frame);
+        self.scheduled_keys.insert(at, (frame, key, pressed));
+    }
+
+    // how many frames run_frame has run
+    pub fn frame(&self) -> u64 {
+        self.frame
     }
 
-    fn fetch(&mut self) -> u16 {
-        let higher_byte = self.memory[self.pc as usize] as u16;
-        let lower_byte = self.memory[(self.pc + 1) as usize] as u16;
-        self.pc += 2;
+    pub fn last_key_pressed(&self) -> Option<u8> {
+        self.last_key
+    }
+
+    // an FX0A is holding the program up until a key goes down
+    pub fn waiting_for_key(&self) -> bool {
+        self.key_wait
+    }
+
+    // return addresses currently on the stack, oldest first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn stack_size(&self) -> usize {
+        self.stack_size
+    }
+
+    // how many calls can be nested before a stack overflow, fewer than are on the stack now is an
+    // error
+    pub fn set_stack_size(&mut self, size: usize) -> Result<(), ConfigError> {
+        check_stack_size(size)?;
+        if size < self.stack.len() {
+            return Err(ConfigError::InvalidStackSize {
+                size,
+                min: self.stack.len(),
+            });
+        }
+        self.stack_size = size;
+        // room for all of it now, so a call never has to grow it
+        self.stack.reserve_exact(size - self.stack.len());
+        Ok(())
+    }
+
+    // the raw opcode stored at an address, without executing or tracking it
+    pub fn opcode_at(&self, address: u16) -> u16 {
+        let address = address as usize;
+        let higher_byte = *self.memory.get(address).unwrap_or(&0) as u16;
+        let lower_byte = *self.memory.get(address + 1).unwrap_or(&0) as u16;
         (higher_byte << 8) | lower_byte
     }
 
-    fn execute(&mut self, op: u16) {
-        let digit_one = (op & 0xF000) >> 12;
-        let digit_two = (op & 0x0F00) >> 8;
-        let digit_three = (op & 0x00F0) >> 4;
-        let digit_four = (op & 0x000F);
+    pub fn memory_accesses(&self) -> &[MemoryAccess] {
+        &self.accesses
+    }
+
+    // writes a byte on behalf of the user rather than the program, so it isn't tracked
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+        self.forget_decoded(address as usize);
+    }
+
+    pub fn set_v_register(&mut self, index: usize, value: u8) {
+        self.v_registers[index] = value;
+    }
+
+    pub fn set_index_register(&mut self, value: u16) {
+        self.index_register = value;
+    }
+
+    pub fn set_pc(&mut self, value: u16) {
+        self.pc = value;
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    // replaces the return addresses on the stack, oldest first
+    pub fn set_stack(&mut self, stack: &[u16]) -> Result<(), CpuError> {
+        if stack.len() > self.stack_size {
+            return Err(CpuError::StackOverflow);
+        }
+        self.stack.clear();
+        self.stack.extend_from_slice(stack);
+        Ok(())
+    }
+
+    // the planes as FN01 would pick them, only the low two bits count
+    pub fn set_planes(&mut self, planes: u8) {
+        self.planes = planes & 0b11;
+    }
+
+    pub fn set_audio_pattern(&mut self, pattern: Option<[u8; 16]>) {
+        self.audio_pattern = pattern;
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    // the display in use as (width, height)
+    pub fn resolution(&self) -> (usize, usize) {
+        self.display_mode.size()
+    }
+
+    // the pixels of the display in use, row by row, lit on either plane
+    pub fn pixels(&self) -> impl ExactSizeIterator<Item = bool> + '_ {
+        let (_, height) = self.resolution();
+        let second = self.second_plane.pixels(height);
+        self.screen
+            .pixels(height)
+            .zip(second)
+            .map(|(first, second)| first || second)
+    }
+
+    // switches to another display, clearing both planes
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+        self.screen = Framebuffer::new(mode.size().0);
+        self.second_plane = Framebuffer::new(mode.size().0);
+        self.emit(Event::DisplayChanged);
+    }
+
+    // the planes FN01 picked, the first in bit 0
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    // the pattern F002 loaded, None until it has and the buzzer is heard instead
+    pub fn audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.audio_pattern.as_ref()
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // the pattern's samples a second at the pitch FX3A set
+    #[cfg(feature = "std")]
+    pub fn playback_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((self.pitch as f64 - DEFAULT_PITCH as f64) / 48.0)
+    }
+
+    // calls `apply` with each plane FN01 picked, the first plane before the second
+    fn for_each_plane(&mut self, mut apply: impl FnMut(&mut Framebuffer)) {
+        if self.planes & 1 != 0 {
+            apply(&mut self.screen);
+        }
+        if self.planes & 2 != 0 {
+            apply(&mut self.second_plane);
+        }
+    }
+
+    // steps over the next instruction, all four bytes of an XO-CHIP F000 NNNN
+    fn skip(&mut self) {
+        let length = if self.quirks.xochip && self.opcode_at(self.pc) == 0xF000 {
+            4
+        } else {
+            2
+        };
+        self.pc = self.pc.wrapping_add(length);
+    }
+
+    fn read_memory(&mut self, address: usize) -> Result<u8, CpuError> {
+        let byte = *self
+            .memory
+            .get(address)
+            .ok_or(CpuError::AddressOutOfRange(address))?;
+        self.track(address, AccessKind::Read);
+        Ok(byte)
+    }
+
+    fn write_memory(&mut self, address: usize, value: u8) -> Result<(), CpuError> {
+        if self.strict && address < START_ADDRESS as usize {
+            return Err(CpuError::ProtectedWrite(address));
+        }
+        let byte = self
+            .memory
+            .get_mut(address)
+            .ok_or(CpuError::AddressOutOfRange(address))?;
+        *byte = value;
+        self.forget_decoded(address);
+        self.track(address, AccessKind::Write);
+        Ok(())
+    }
+
+    // the instructions a write to `address` changes, the one starting there and the one before
+    fn forget_decoded(&mut self, address: usize) {
+        if let Some(entries) = self.decoded.get_mut(address.saturating_sub(1)..=address) {
+            entries.fill(None);
+        }
+    }
+
+    fn key(&self, key: u8) -> Result<bool, CpuError> {
+        self.keys
+            .get(key as usize)
+            .copied()
+            .ok_or(CpuError::InvalidKey(key))
+    }
+
+    fn track(&mut self, address: usize, kind: AccessKind) {
+        self.accesses.push(MemoryAccess {
+            address: address as u16,
+            kind,
+        });
+    }
+
+    fn fetch(&mut self) -> Result<u16, CpuError> {
+        let address = self.pc as usize;
+        if address + 1 >= self.memory.len() {
+            return Err(CpuError::AddressOutOfRange(address + 1));
+        }
+
+        self.track(address, AccessKind::Execute);
+        self.track(address + 1, AccessKind::Execute);
+
+        let higher_byte = self.memory[address] as u16;
+        let lower_byte = self.memory[address + 1] as u16;
+        self.pc = self.pc.wrapping_add(2);
+        Ok((higher_byte << 8) | lower_byte)
+    }
+
+    // fetch, with the instruction decoded already if it has run before
+    fn fetch_decoded(&mut self) -> Result<Instruction, CpuError> {
+        let address = self.pc as usize;
+        if let Some(&Some(instruction)) = self.decoded.get(address) {
+            self.track(address, AccessKind::Execute);
+            self.track(address + 1, AccessKind::Execute);
+            self.pc = self.pc.wrapping_add(2);
+            return Ok(instruction);
+        }
+
+        let instruction = Instruction::decode(self.fetch()?);
+        if self.decoded.len() != self.memory.len() {
+            self.decoded.clear();
+            self.decoded.resize(self.memory.len(), None);
+        }
+        self.decoded[address] = Some(instruction);
+        Ok(instruction)
+    }
+
+    // the most common instructions straight from their decoded operands, none of them changed by
+    // a quirk. everything else goes back through the handlers as its opcode
+    fn run(&mut self, instruction: Instruction) -> Result<(), CpuError> {
+        let v = &mut self.v_registers;
+        match instruction {
+            Instruction::Jump(nnn) if !self.quirks.two_page => self.pc = nnn,
+            Instruction::Call(nnn) => {
+                self.push(self.pc)?;
+                self.pc = nnn;
+            }
+            Instruction::Ret => self.pc = self.pop()?,
+            Instruction::SkipEqImm { x, nn } => {
+                if v[x as usize] == nn {
+                    self.skip();
+                }
+            }
+            Instruction::SkipNeImm { x, nn } => {
+                if v[x as usize] != nn {
+                    self.skip();
+                }
+            }
+            Instruction::SkipEqReg { x, y } => {
+                if v[x as usize] == v[y as usize] {
+                    self.skip();
+                }
+            }
+            Instruction::SkipNeReg { x, y } => {
+                if v[x as usize] != v[y as usize] {
+                    self.skip();
+                }
+            }
+            Instruction::LoadImm { x, nn } => v[x as usize] = nn,
+            Instruction::AddImm { x, nn } => v[x as usize] = v[x as usize].wrapping_add(nn),
+            Instruction::LoadReg { x, y } => v[x as usize] = v[y as usize],
+            Instruction::AddReg { x, y } => {
+                let (result, overflow) = v[x as usize].overflowing_add(v[y as usize]);
+                v[0xF] = overflow as u8;
+                v[x as usize] = result;
+            }
+            Instruction::SubReg { x, y } => {
+                let (result, underflow) = v[x as usize].overflowing_sub(v[y as usize]);
+                v[0xF] = !underflow as u8;
+                v[x as usize] = result;
+            }
+            Instruction::LoadIndex(nnn) => self.index_register = nnn,
+            instruction => return self.execute(instruction.encode()),
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, op: u16) -> Result<(), CpuError> {
+        match HANDLERS[(op >> 12) as usize](self, op) {
+            Err(CpuError::UnknownOpcode(op)) => match self.extra_opcodes.clone() {
+                Some(extension) if extension(self, op) => Ok(()),
+                _ => Err(CpuError::UnknownOpcode(op)),
+            },
+            result => result,
+        }
+    }
+
+    fn execute_system(&mut self, op: u16) -> Result<(), CpuError> {
+        match op {
+            // NOP - no operation
+            0x0000 => (),
+            // CLS - clear screen
+            0x00E0 => {
+                self.for_each_plane(Framebuffer::clear);
+                self.emit(Event::DisplayCleared);
+            }
+            // hires CHIP-8 CLS
+            0x0230 if self.quirks.two_page => {
+                self.for_each_plane(Framebuffer::clear);
+                self.emit(Event::DisplayCleared);
+            }
+            // SUPER-CHIP SCD N - scroll down N rows
+            0x00C0..=0x00CF if self.quirks.schip_hires => {
+                let (_, height) = self.resolution();
+                self.for_each_plane(|plane| plane.scroll_down(n(op), height));
+                self.emit(Event::DisplayChanged);
+            }
+            // XO-CHIP SCU N - scroll up N rows
+            0x00D0..=0x00DF if self.quirks.xochip => {
+                let (_, height) = self.resolution();
+                self.for_each_plane(|plane| plane.scroll_up(n(op), height));
+                self.emit(Event::DisplayChanged);
+            }
+            // SUPER-CHIP SCR - scroll right 4 pixels
+            0x00FB if self.quirks.schip_hires => {
+                self.for_each_plane(|plane| plane.scroll_right(4));
+                self.emit(Event::DisplayChanged);
+            }
+            // SUPER-CHIP SCL - scroll left 4 pixels
+            0x00FC if self.quirks.schip_hires => {
+                self.for_each_plane(|plane| plane.scroll_left(4));
+                self.emit(Event::DisplayChanged);
+            }
+            // SUPER-CHIP LOW - back to the standard display
+            0x00FE if self.quirks.schip_hires => self.set_display_mode(DisplayMode::Standard),
+            // SUPER-CHIP HIGH - 128x64
+            0x00FF if self.quirks.schip_hires => self.set_display_mode(DisplayMode::Hires),
+            // RET - return from subroutine
+            0x00EE => {
+                let return_address = self.pop()?;
+                self.pc = return_address;
+            }
+            // CHIP-8E STOP - stays on this instruction for good
+            0x00ED if self.quirks.chip8e => self.pc = self.pc.wrapping_sub(2),
+            // CHIP-8E WAIT FOR DT == 0
+            0x0151 if self.quirks.chip8e => {
+                if self.delay_timer != 0 {
+                    self.pc = self.pc.wrapping_sub(2);
+                }
+            }
+            // CHIP-8E SKIP - always skip next
+            0x0188 if self.quirks.chip8e => self.pc += 2,
+            _ => return Err(CpuError::UnknownOpcode(op)),
+        }
+
+        Ok(())
+    }
+
+    // JMP nnn - jump
+    fn execute_jump(&mut self, op: u16) -> Result<(), CpuError> {
+        // a hires CHIP-8 program's first instruction, which ran the interpreter's setup code for
+        // the two-page display
+        if self.quirks.two_page && op == 0x1260 && self.pc == START_ADDRESS + 2 {
+            self.set_display_mode(DisplayMode::TwoPage);
+            self.pc = TWO_PAGE_START;
+            return Ok(());
+        }
+
+        self.pc = nnn(op);
+        Ok(())
+    }
+
+    // CALL nnn - call subroutine
+    fn execute_call(&mut self, op: u16) -> Result<(), CpuError> {
+        self.push(self.pc)?;
+        self.pc = nnn(op);
+        Ok(())
+    }
+
+    // SKIP VX == NN - skip next if VX == VN
+    fn execute_skip_eq_imm(&mut self, op: u16) -> Result<(), CpuError> {
+        if self.v_registers[x(op)] == nn(op) {
+            self.skip();
+        }
+        Ok(())
+    }
+
+    // SKIP VX != NN - skip next if VX != VN
+    fn execute_skip_ne_imm(&mut self, op: u16) -> Result<(), CpuError> {
+        if self.v_registers[x(op)] != nn(op) {
+            self.skip();
+        }
+        Ok(())
+    }
+
+    // SKIP VX == VY - skip next if VX == VY
+    fn execute_skip_eq_reg(&mut self, op: u16) -> Result<(), CpuError> {
+        let vx = x(op);
+        let vy = y(op);
 
-        match (digit_one, digit_two, digit_three, digit_four) {
-            // NOP - no operation
-            (0, 0, 0, 0) => return,
-            // CLS - clear screen
-            (0, 0, 0xE, 0) => {
-                self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
-            }
-            // RET - return from subroutine
-            (0, 0, 0xE, 0xE) => {
-                let return_address = self.pop();
-                self.pc = return_address;
+        match n(op) {
+            0 => {
+                if self.v_registers[vx] == self.v_registers[vy] {
+                    self.skip();
+                }
             }
-            // JMP nnn - jump
-            (1, _, _, _) => {
-                let address = op & 0x0FFF;
-                self.pc = address;
-            }
-            // CALL nnn - call subroutine
-            (2, _, _, _) => {
-                let address = op & 0x0FFF;
-                self.push(self.pc);
-                self.pc = address;
-            }
-            // SKIP VX == NN - skip next if VX == VN
-            (3, _, _, _) => {
-                let vx = digit_two as usize;
-                let nn = (op & 0x00FF) as u8;
-
-                if self.v_registers[vx] == nn {
-                    // instruction length is 2 bytes
-                    self.pc += 2;
+            // CHIP-8E SKIP VX > VY
+            1 if self.quirks.chip8e => {
+                if self.v_registers[vx] > self.v_registers[vy] {
+                    self.skip();
                 }
             }
-            // SKIP VX != NN - skip next if VX != VN
-            (4, _, _, _) => {
-                let vx = digit_two as usize;
-                let nn = (op & 0x00FF) as u8;
+            // CHIP-8E and XO-CHIP STORE VX - VY, I is left alone
+            2 if self.quirks.chip8e || self.quirks.xochip => {
+                let memory_start = self.index_register as usize;
 
-                if self.v_registers[vx] != nn {
-                    self.pc += 2;
+                for offset in 0..=vx.abs_diff(vy) {
+                    let register = self.v_registers[register_towards(vx, vy, offset)];
+                    self.write_memory(memory_start + offset, register)?;
                 }
             }
-            // SKIP VX == VY - skip next if VX == VY
-            (5, _, _, 0) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
+            // CHIP-8E and XO-CHIP LOAD VX - VY
+            3 if self.quirks.chip8e || self.quirks.xochip => {
+                let memory_start = self.index_register as usize;
 
-                if self.v_registers[vx] == self.v_registers[vy] {
-                    self.pc += 2;
+                for offset in 0..=vx.abs_diff(vy) {
+                    self.v_registers[register_towards(vx, vy, offset)] =
+                        self.read_memory(memory_start + offset)?;
                 }
             }
-            // VX = VN - set VX -> NN
-            (6, _, _, _) => {
-                let vx = digit_two as usize;
-                let nn = (op & 0x00FF) as u8;
+            _ => return Err(CpuError::UnknownOpcode(op)),
+        }
+        Ok(())
+    }
 
-                self.v_registers[vx] = nn;
-            }
-            // VX += NN - set VX -> VX + NN
-            (7, _, _, _) => {
-                let vx = digit_two as usize;
-                let nn = (op & 0x00FF) as u8;
+    // VX = VN - set VX -> NN
+    fn execute_load_imm(&mut self, op: u16) -> Result<(), CpuError> {
+        self.v_registers[x(op)] = nn(op);
+        Ok(())
+    }
 
-                self.v_registers[vx] = self.v_registers[vx].wrapping_add(nn);
-            }
-            // VX = VY - set VX -> VY
-            (8, _, _, 0) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
+    // VX += NN - set VX -> VX + NN
+    fn execute_add_imm(&mut self, op: u16) -> Result<(), CpuError> {
+        let vx = x(op);
+        self.v_registers[vx] = self.v_registers[vx].wrapping_add(nn(op));
+        Ok(())
+    }
+
+    fn execute_alu(&mut self, op: u16) -> Result<(), CpuError> {
+        let vx = x(op);
+        let vy = y(op);
 
+        match n(op) {
+            // VX = VY - set VX -> VY
+            0 => {
                 self.v_registers[vx] = self.v_registers[vy];
             }
             // VX |= VY
-            (8, _, _, 1) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
-
+            1 => {
                 self.v_registers[vx] |= self.v_registers[vy];
+                self.reset_flag();
             }
             // VX &= VY
-            (8, _, _, 2) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
-
+            2 => {
                 self.v_registers[vx] &= self.v_registers[vy];
+                self.reset_flag();
             }
             // VX ^= VY
-            (8, _, _, 3) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
-
+            3 => {
                 self.v_registers[vx] ^= self.v_registers[vy];
+                self.reset_flag();
             }
             // VX += VY - VX -> VX + VY
-            (8, _, _, 4) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
-
+            4 => {
                 let (result, overflow) = self.v_registers[vx].overflowing_add(self.v_registers[vy]);
 
                 // set carry flag
@@ -215,10 +1438,7 @@ impl CPU {
                 self.v_registers[vx] = result;
             }
             // VX -= VY - VX -> VX - VY
-            (8, _, _, 5) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
-
+            5 => {
                 let (result, underflow) =
                     self.v_registers[vx].overflowing_sub(self.v_registers[vy]);
 
@@ -226,8 +1446,8 @@ impl CPU {
                 self.v_registers[vx] = result;
             }
             // VX >> 1
-            (8, _, _, 6) => {
-                let vx = digit_two as usize;
+            6 => {
+                self.shift_source(vx, vy);
                 // the flag register is set to the LSB
                 let rightmost_bit = self.v_registers[vx] & 1;
 
@@ -235,10 +1455,7 @@ impl CPU {
                 self.v_registers[0xF] = rightmost_bit;
             }
             // VX = VY - VX
-            (8, _, _, 7) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
-
+            7 => {
                 let (result, underflow) =
                     self.v_registers[vy].overflowing_sub(self.v_registers[vx]);
 
@@ -246,203 +1463,342 @@ impl CPU {
                 self.v_registers[vx] = result;
             }
             // VX << 1
-            (8, _, _, 0xE) => {
-                let vx = digit_two as usize;
+            0xE => {
+                self.shift_source(vx, vy);
                 // NOTE: may need & 1
                 let leftmost_bit = self.v_registers[vx] >> 7;
 
                 self.v_registers[vx] <<= 1;
                 self.v_registers[0xF] = leftmost_bit;
             }
-            // SKIP VX != VY
-            (9, _, _, 0) => {
-                let vx = digit_two as usize;
-                let vy = digit_three as usize;
+            _ => return Err(CpuError::UnknownOpcode(op)),
+        }
 
-                if self.v_registers[vx] != self.v_registers[vy] {
-                    self.pc += 2;
-                }
-            }
-            // I = NNN
-            (0xA, _, _, _) => {
-                let nnn = op & 0x0FFF;
+        Ok(())
+    }
 
-                self.index_register = nnn;
-            }
-            // JUMP V0 + NNN
-            (0xB, _, _, _) => {
-                let nnn = op & 0x0FFF;
+    // logs the first time since a reset that the program does something interpreters disagree
+    // on, as a hint when a game misbehaves that it wants other quirks
+    fn warn_quirk(&mut self, warning: QuirkWarning) {
+        let bit = 1 << warning as u8;
+        if self.quirk_warnings & bit != 0 {
+            return;
+        }
+        self.quirk_warnings |= bit;
 
-                self.pc = self.v_registers[0] as u16 + nnn;
+        let message = match warning {
+            QuirkWarning::Shift => {
+                "8XY6 or 8XYE with VX and VY different, the shift quirk picks which is shifted"
+            }
+            QuirkWarning::Jump => {
+                "BXNN with VX and V0 different, the jump quirk picks which is added"
             }
-            // VX = RAND() & NN
-            (0xC, _, _, _) => {
-                let vx = digit_two as usize;
-                let nn = (op & 0x00FF) as u8;
-                let rng: u8 = random();
+            QuirkWarning::EmptySprite => {
+                "DXY0 draws nothing without the tall sprite or SUPER-CHIP quirks"
+            }
+            QuirkWarning::Edge => {
+                "a sprite goes past the screen's edge, the clipping quirk picks whether it wraps"
+            }
+        };
+        tracing::warn!("{} at #{:03X}", message, self.pc.wrapping_sub(2));
+    }
+
+    fn reset_flag(&mut self) {
+        if self.quirks.vf_reset {
+            self.v_registers[0xF] = 0;
+        }
+    }
+
+    fn shift_source(&mut self, vx: usize, vy: usize) {
+        if self.v_registers[vx] != self.v_registers[vy] {
+            self.warn_quirk(QuirkWarning::Shift);
+        }
+        if self.quirks.shift_uses_vy {
+            self.v_registers[vx] = self.v_registers[vy];
+        }
+    }
+
+    // SKIP VX != VY
+    fn execute_skip_ne_reg(&mut self, op: u16) -> Result<(), CpuError> {
+        if n(op) != 0 {
+            return Err(CpuError::UnknownOpcode(op));
+        }
+
+        if self.v_registers[x(op)] != self.v_registers[y(op)] {
+            self.skip();
+        }
+        Ok(())
+    }
 
-                self.v_registers[vx] = rng & nn;
+    // I = NNN
+    fn execute_load_index(&mut self, op: u16) -> Result<(), CpuError> {
+        self.index_register = nnn(op);
+        Ok(())
+    }
+
+    // JUMP V0 + NNN
+    fn execute_jump_v0(&mut self, op: u16) -> Result<(), CpuError> {
+        // CHIP-8E takes BBNN and BFNN as branches NN bytes back or forward from the next
+        // instruction, leaving only B0NN to BANN and BCNN to BENN as the usual jump
+        if self.quirks.chip8e {
+            match x(op) {
+                0xB => {
+                    self.pc = self.pc.wrapping_sub(nn(op) as u16);
+                    return Ok(());
+                }
+                0xF => {
+                    self.pc = self.pc.wrapping_add(nn(op) as u16);
+                    return Ok(());
+                }
+                _ => (),
             }
-            // DRAW
-            (0xD, _, _, _) => {
-                let draw_x = self.v_registers[digit_two as usize] as u16;
-                let draw_y = self.v_registers[digit_three as usize] as u16;
-                let height = digit_four;
+        }
+
+        if self.v_registers[x(op)] != self.v_registers[0] {
+            self.warn_quirk(QuirkWarning::Jump);
+        }
+        let register = if self.quirks.jump_uses_vx { x(op) } else { 0 };
+        self.pc = self.v_registers[register] as u16 + nnn(op);
+        Ok(())
+    }
 
-                let mut pixels_flipped = false;
+    // VX = RAND() & NN
+    fn execute_random(&mut self, op: u16) -> Result<(), CpuError> {
+        let rng: u8 = self.rng.gen();
+        self.random_draws += 1;
 
-                for current_y in 0..height {
-                    let address = self.index_register + current_y as u16;
-                    let row_pixels = self.memory[address as usize];
+        self.v_registers[x(op)] = rng & nn(op);
+        Ok(())
+    }
 
-                    for current_x in 0..8 {
-                        if (row_pixels & (0b1000_0000 >> current_x)) != 0 {
-                            let x = (draw_x + current_x) as usize % SCREEN_WIDTH;
-                            let y = (draw_y + current_y) as usize % SCREEN_HEIGHT;
+    // DRAW
+    fn execute_draw(&mut self, op: u16) -> Result<(), CpuError> {
+        // the starting position always wraps, only the sprite itself may be clipped
+        let (width, height) = self.resolution();
+        let draw_x = self.v_registers[x(op)] as usize % width;
+        let draw_y = self.v_registers[y(op)] as usize % height;
+        let lores = self.display_mode != DisplayMode::Hires;
+        // (width, rows), SUPER-CHIP 1.0 only drew the tall half of a big sprite in low resolution
+        let (sprite_width, rows) = match n(op) {
+            0 if self.quirks.schip_hires && !(self.quirks.tall_sprites && lores) => (16, 16),
+            0 if self.quirks.tall_sprites => (8, 16),
+            n => (8, n),
+        };
+        let row_bytes = sprite_width / 8;
+        if rows == 0 {
+            self.warn_quirk(QuirkWarning::EmptySprite);
+        } else if draw_x + sprite_width > width || draw_y + rows > height {
+            self.warn_quirk(QuirkWarning::Edge);
+        }
 
-                            let index = x + SCREEN_WIDTH * y;
+        // rows with a pixel that was already lit, and rows cut off at the bottom of the screen
+        let mut rows_collided = 0;
+        let mut rows_clipped = 0;
+        let mut drawn = false;
 
-                            pixels_flipped |= self.screen[index];
-                            self.screen[index] ^= true;
-                        }
-                    }
+        // each plane picked gets the sprite's next rows, only XO-CHIP picks any but the first
+        let mut address = self.index_register as usize;
+        for plane in 0..2 {
+            if self.planes & (1 << plane) == 0 {
+                continue;
+            }
+            for current_y in 0..rows {
+                let mut row_pixels = 0u16;
+                for byte in 0..row_bytes {
+                    row_pixels |= (self.read_memory(address + byte)? as u16) << (8 - byte * 8);
+                }
+                address += row_bytes;
+
+                if self.quirks.clipping && draw_y + current_y >= height {
+                    rows_clipped += 1;
+                    continue;
                 }
 
-                self.v_registers[0xF] = if pixels_flipped { 1 } else { 0 };
+                let target = match plane {
+                    0 => &mut self.screen,
+                    _ => &mut self.second_plane,
+                };
+                let pixels = target.place(row_pixels, draw_x, !self.quirks.clipping);
+                if target.xor_row((draw_y + current_y) % height, pixels) {
+                    rows_collided += 1;
+                }
+                drawn |= pixels != 0;
             }
-            // SKIP IF KEY PRESSED
-            (0xE, _, 9, 0xE) => {
-                let vx = digit_two as usize;
-                let key_pressed = self.keys[self.v_registers[vx] as usize];
+        }
+        if drawn {
+            self.emit(Event::DisplayChanged);
+        }
+
+        // high resolution SUPER-CHIP counts the rows that collided or were cut off, everything
+        // else, XO-CHIP included, only says whether any pixel collided
+        self.v_registers[0xF] = if self.quirks.schip_hires && !self.quirks.xochip && !lores {
+            rows_collided + rows_clipped
+        } else {
+            (rows_collided > 0) as u8
+        };
+        Ok(())
+    }
 
-                if key_pressed {
-                    self.pc += 2;
+    fn execute_key(&mut self, op: u16) -> Result<(), CpuError> {
+        let key = self.v_registers[x(op)];
+
+        match nn(op) {
+            // SKIP IF KEY PRESSED
+            0x9E => {
+                if self.key(key)? {
+                    self.skip();
                 }
             }
             // SKIP IF KEY NOT PRESSED
-            (0xE, _, 0xA, 1) => {
-                let vx = digit_two as usize;
-                let key_pressed = self.keys[self.v_registers[vx] as usize];
-
-                if !key_pressed {
-                    self.pc += 2;
+            0xA1 => {
+                if !self.key(key)? {
+                    self.skip();
                 }
             }
-            // VX = DT
-            (0xF, _, 0, 7) => {
-                let vx = digit_two as usize;
+            _ => return Err(CpuError::UnknownOpcode(op)),
+        }
+
+        Ok(())
+    }
+
+    fn execute_misc(&mut self, op: u16) -> Result<(), CpuError> {
+        let vx = x(op);
 
+        match nn(op) {
+            // VX = DT
+            0x07 => {
                 self.v_registers[vx] = self.delay_timer;
             }
             // WAIT FOR KEY PRESS
-            (0xF, _, 0, 0xA) => {
-                let vx = digit_two as usize;
-                let mut pressed = false;
-
-                for i in 0..self.keys.len() {
-                    if self.keys[i] {
-                        self.v_registers[vx] = i as u8;
-                        pressed = true;
-                        break;
-                    }
+            0x0A => match self.keys.iter().position(|pressed| *pressed) {
+                Some(key) => {
+                    self.v_registers[vx] = key as u8;
+                    self.key_wait = false;
                 }
-
-                if !pressed {
-                    self.pc -= 2;
+                None => {
+                    self.pc = self.pc.wrapping_sub(2);
+                    if !self.key_wait {
+                        self.key_wait = true;
+                        self.emit(Event::WaitingForKey);
+                    }
                 }
-            }
+            },
             // DT = VX
-            (0xF, _, 1, 5) => {
-                let vx = digit_two as usize;
-
+            0x15 => {
                 self.delay_timer = self.v_registers[vx];
             }
             // ST = VX
-            (0xF, _, 1, 8) => {
-                let vx = digit_two as usize;
-
+            0x18 => {
                 self.sound_timer = self.v_registers[vx];
             }
+            // CHIP-8E SKIP VX BYTES
+            0x1B if self.quirks.chip8e => {
+                self.pc = self.pc.wrapping_add(self.v_registers[vx] as u16);
+            }
+            // CHIP-8E DT = VX, then wait for DT == 0
+            0x4F if self.quirks.chip8e => {
+                if !self.delay_wait {
+                    self.delay_timer = self.v_registers[vx];
+                    self.delay_wait = true;
+                }
+                if self.delay_timer != 0 {
+                    self.pc = self.pc.wrapping_sub(2);
+                } else {
+                    self.delay_wait = false;
+                }
+            }
+            // XO-CHIP I = NNNN, the address being the two bytes after the instruction
+            0x00 if vx == 0 && self.quirks.xochip => {
+                self.index_register = self.fetch()?;
+            }
+            // XO-CHIP PLANE N - the planes drawing, clearing and scrolling work on
+            0x01 if vx <= 3 && self.quirks.xochip => {
+                self.planes = vx as u8;
+            }
+            // XO-CHIP AUDIO - the 16 bytes at I are the pattern played while the sound timer runs
+            0x02 if vx == 0 && self.quirks.xochip => {
+                let mut pattern = [0; 16];
+                for (offset, sample) in pattern.iter_mut().enumerate() {
+                    *sample = self.read_memory(self.index_register as usize + offset)?;
+                }
+                self.audio_pattern = Some(pattern);
+            }
+            // XO-CHIP PITCH = VX
+            0x3A if self.quirks.xochip => {
+                self.pitch = self.v_registers[vx];
+            }
+            // CHIP-8E output VX to port 3, nothing is plugged in so it goes nowhere
+            0x03 if self.quirks.chip8e => (),
+            // CHIP-8E read VX from port 3, after waiting for a strobe for E3. nothing is plugged
+            // in, so there's no strobe to wait for and the port reads 0
+            0xE3 | 0xE7 if self.quirks.chip8e => {
+                self.v_registers[vx] = 0;
+            }
             // I += VX
-            (0xF, _, 1, 0xE) => {
-                let vx = digit_two as usize;
-
+            0x1E => {
                 self.index_register = self
                     .index_register
                     .wrapping_add(self.v_registers[vx] as u16);
             }
             // I = FONT
-            (0xF, _, 2, 9) => {
-                let vx = digit_two as usize;
+            0x29 => {
                 let char = self.v_registers[vx] as u16;
 
                 self.index_register = char * 5;
             }
             // BCD
-            (0xF, _, 3, 3) => {
-                let vx = digit_two as usize;
-                let mut vx_value = self.v_registers[vx] as f32;
+            0x33 => {
+                let value = self.v_registers[vx];
+                let address = self.index_register as usize;
 
-                let hundreds = (vx_value / 100.0).floor() as u8;
-                vx_value %= 100.0;
-                let tens = (vx_value / 10.0).floor() as u8;
-                vx_value %= 10.0;
-                let ones = vx_value.floor() as u8;
-
-                self.memory[self.index_register as usize] = hundreds;
-                self.memory[(self.index_register + 1) as usize] = tens;
-                self.memory[(self.index_register + 2) as usize] = ones;
+                self.write_memory(address, value / 100)?;
+                self.write_memory(address + 1, value / 10 % 10)?;
+                self.write_memory(address + 2, value % 10)?;
             }
             // STORE V0 - VX
-            (0xF, _, 5, 5) => {
-                let vx = digit_two as usize;
+            0x55 => {
                 let memory_start = self.index_register as usize;
 
-                for i in 0..=vx as usize {
-                    self.memory[memory_start + i] = self.v_registers[i];
+                for i in 0..=vx {
+                    self.write_memory(memory_start + i, self.v_registers[i])?;
                 }
+                self.increment_index(vx);
             }
             // LOAD V0 - VX
-            (0xF, _, 6, 5) => {
-                let vx = digit_two as usize;
+            0x65 => {
                 let memory_start = self.index_register as usize;
 
-                for i in 0..=vx as usize {
-                    self.v_registers[i] = self.memory[memory_start + i];
+                for i in 0..=vx {
+                    self.v_registers[i] = self.read_memory(memory_start + i)?;
                 }
+                self.increment_index(vx);
             }
-            (_, _, _, _) => panic!("unknown opcode: {:#x}", op),
-        }
-    }
-
-    fn tick_timers(&mut self) {
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+            _ => return Err(CpuError::UnknownOpcode(op)),
         }
 
-        if self.sound_timer > 0 {
-            if self.sound_timer == 1 {
-                // BEEP
-            }
+        Ok(())
+    }
 
-            self.sound_timer -= 1;
+    fn increment_index(&mut self, vx: usize) {
+        if self.quirks.memory_increment {
+            self.index_register = self.index_register.wrapping_add(vx as u16 + 1);
+        } else if self.quirks.increment_by_x {
+            self.index_register = self.index_register.wrapping_add(vx as u16);
         }
     }
 
     // Stack Operations
 
-    fn push(&mut self, val: u16) {
-        self.stack[self.stack_pointer as usize] = val;
-        self.stack_pointer += 1;
-
-        if self.stack_pointer > STACK_SIZE as u16 {
-            panic!("stack overflow... HE SAID THE THING");
+    fn push(&mut self, val: u16) -> Result<(), CpuError> {
+        if self.stack.len() == self.stack_size {
+            return Err(CpuError::StackOverflow);
         }
+        self.stack.push(val);
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
-        self.stack_pointer -= 1;
-        self.stack[self.stack_pointer as usize]
+    fn pop(&mut self) -> Result<u16, CpuError> {
+        self.stack.pop().ok_or(CpuError::StackUnderflow)
     }
 }
 
@@ -454,37 +1810,95 @@ mod tests {
     fn test_stack_operations() {
         let mut cpu = CPU::new();
 
-        cpu.push(1);
+        cpu.push(1).unwrap();
         assert_eq!(cpu.stack[0], 1);
-        assert_eq!(cpu.pop(), 1);
+        assert_eq!(cpu.pop().unwrap(), 1);
 
         for i in 0..10 {
-            cpu.push(i);
+            cpu.push(i).unwrap();
         }
         assert_eq!(cpu.stack[5], 5);
         for _ in 0..9 {
-            cpu.pop();
+            cpu.pop().unwrap();
         }
         assert_eq!(cpu.stack[0], 0);
     }
 
+    #[test]
+    fn test_stack_size() {
+        let mut cpu = CPU::builder().stack_size(64).build().unwrap();
+        for _ in 0..64 {
+            cpu.execute(0x2200).unwrap();
+        }
+        assert_eq!(cpu.execute(0x2200), Err(CpuError::StackOverflow));
+        assert!(cpu.set_stack_size(32).is_err());
+        assert_eq!(cpu.clone().stack_size(), 64);
+
+        cpu.reset();
+        cpu.set_stack_size(2).unwrap();
+        assert!(cpu.set_stack(&[0x200; 3]).is_err());
+        assert!(CPU::builder().stack_size(0).build().is_err());
+        assert!(CPU::builder()
+            .stack_size(MAX_STACK_SIZE + 1)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_stack_size_errors() {
+        assert_eq!(
+            check_stack_size(0),
+            Err(ConfigError::InvalidStackSize { size: 0, min: 1 })
+        );
+        assert_eq!(
+            CPU::builder().stack_size(MAX_STACK_SIZE + 1).build().err(),
+            Some(ConfigError::InvalidStackSize {
+                size: MAX_STACK_SIZE + 1,
+                min: 1
+            })
+        );
+
+        // fewer than the return addresses already on the stack
+        let mut cpu = CPU::new();
+        cpu.execute(0x2200).unwrap();
+        cpu.execute(0x2200).unwrap();
+        let error = cpu.set_stack_size(1).unwrap_err();
+        assert_eq!(error, ConfigError::InvalidStackSize { size: 1, min: 2 });
+        assert_eq!(
+            error.to_string(),
+            "the stack must hold from 2 to 256 return addresses, not 1"
+        );
+    }
+
+    #[test]
+    fn test_stack_view() {
+        let mut cpu = CPU::new();
+
+        assert!(cpu.stack().is_empty());
+        cpu.execute(0x2300).unwrap();
+        cpu.execute(0x2400).unwrap();
+        assert_eq!(cpu.stack(), [START_ADDRESS, 0x300]);
+        cpu.execute(0x00EE).unwrap();
+        assert_eq!(cpu.stack(), [START_ADDRESS]);
+    }
+
     // operations
 
     #[test]
     fn test_cls() {
         let mut cpu = CPU::new();
 
-        cpu.screen = [true; SCREEN_WIDTH * SCREEN_HEIGHT];
-        cpu.execute(0x00E0);
-        assert_eq!(cpu.screen, [false; SCREEN_WIDTH * SCREEN_HEIGHT]);
+        cpu.screen.fill();
+        cpu.execute(0x00E0).unwrap();
+        assert!(cpu.screen.is_blank());
     }
 
     #[test]
     fn test_ret() {
         let mut cpu = CPU::new();
 
-        cpu.push(0x69);
-        cpu.execute(0x00EE);
+        cpu.push(0x69).unwrap();
+        cpu.execute(0x00EE).unwrap();
         assert_eq!(cpu.pc, 0x69);
     }
 
@@ -492,7 +1906,7 @@ mod tests {
     fn test_jmp() {
         let mut cpu = CPU::new();
 
-        cpu.execute(0x1420);
+        cpu.execute(0x1420).unwrap();
         assert_eq!(cpu.pc, 0x420);
     }
 
@@ -501,8 +1915,8 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.pc = 0x69;
-        cpu.execute(0x2420);
-        assert_eq!(cpu.pop(), 0x69);
+        cpu.execute(0x2420).unwrap();
+        assert_eq!(cpu.pop().unwrap(), 0x69);
         assert_eq!(cpu.pc, 0x420);
     }
 
@@ -511,9 +1925,9 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[5] = 0x69;
-        cpu.execute(0x3569);
+        cpu.execute(0x3569).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
-        cpu.execute(0x3570);
+        cpu.execute(0x3570).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
     }
 
@@ -522,9 +1936,9 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[5] = 0x69;
-        cpu.execute(0x3570);
+        cpu.execute(0x3570).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS);
-        cpu.execute(0x3569);
+        cpu.execute(0x3569).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
     }
 
@@ -534,9 +1948,9 @@ mod tests {
 
         cpu.v_registers[0] = 0x69;
         cpu.v_registers[15] = 0x69;
-        cpu.execute(0x50F0);
+        cpu.execute(0x50F0).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
-        cpu.execute(0x5010);
+        cpu.execute(0x5010).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
     }
 
@@ -544,7 +1958,7 @@ mod tests {
     fn test_set_vx_to_nn() {
         let mut cpu = CPU::new();
 
-        cpu.execute(0x6769);
+        cpu.execute(0x6769).unwrap();
         assert_eq!(cpu.v_registers[7], 0x69);
     }
 
@@ -553,7 +1967,7 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[3] = 255;
-        cpu.execute(0x7302);
+        cpu.execute(0x7302).unwrap();
         assert_eq!(cpu.v_registers[3], 1);
     }
 
@@ -563,7 +1977,7 @@ mod tests {
 
         cpu.v_registers[5] = 0b1010_1010;
         cpu.v_registers[0xA] = 0b0101_0101;
-        cpu.execute(0x85A1);
+        cpu.execute(0x85A1).unwrap();
         assert_eq!(cpu.v_registers[5], 0xFF);
     }
 
@@ -573,7 +1987,7 @@ mod tests {
 
         cpu.v_registers[8] = 0b1010_1010;
         cpu.v_registers[2] = 0b0101_0101;
-        cpu.execute(0x8822);
+        cpu.execute(0x8822).unwrap();
         assert_eq!(cpu.v_registers[8], 0x00);
     }
 
@@ -583,7 +1997,7 @@ mod tests {
 
         cpu.v_registers[0xF] = 0b1110_1110;
         cpu.v_registers[0] = 0b0111_0111;
-        cpu.execute(0x8F03);
+        cpu.execute(0x8F03).unwrap();
         assert_eq!(cpu.v_registers[0xF], 0b1001_1001);
     }
 
@@ -593,13 +2007,13 @@ mod tests {
 
         cpu.v_registers[0] = 255;
         cpu.v_registers[1] = 1;
-        cpu.execute(0x8014);
+        cpu.execute(0x8014).unwrap();
         assert_eq!(cpu.v_registers[0], 0);
         assert_eq!(cpu.v_registers[0xF], 1);
 
         cpu.v_registers[6] = 10;
         cpu.v_registers[0xA] = 10;
-        cpu.execute(0x86A4);
+        cpu.execute(0x86A4).unwrap();
         assert_eq!(cpu.v_registers[6], 20);
         assert_eq!(cpu.v_registers[0xF], 0);
     }
@@ -610,13 +2024,13 @@ mod tests {
 
         cpu.v_registers[0] = 0;
         cpu.v_registers[1] = 1;
-        cpu.execute(0x8015);
+        cpu.execute(0x8015).unwrap();
         assert_eq!(cpu.v_registers[0], 255);
         assert_eq!(cpu.v_registers[0xF], 0);
 
         cpu.v_registers[6] = 10;
         cpu.v_registers[0xA] = 10;
-        cpu.execute(0x86A5);
+        cpu.execute(0x86A5).unwrap();
         assert_eq!(cpu.v_registers[6], 0);
         assert_eq!(cpu.v_registers[0xF], 1);
     }
@@ -626,12 +2040,12 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0] = 0b0101_0101;
-        cpu.execute(0x8006);
+        cpu.execute(0x8006).unwrap();
         assert_eq!(cpu.v_registers[0], 0b0010_1010);
         assert_eq!(cpu.v_registers[0xF], 1);
 
         cpu.v_registers[0xB] = 0b1010_1010;
-        cpu.execute(0x8B06);
+        cpu.execute(0x8B06).unwrap();
         assert_eq!(cpu.v_registers[0xB], 0b0101_0101);
         assert_eq!(cpu.v_registers[0xF], 0);
     }
@@ -641,13 +2055,13 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0] = 1;
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.v_registers[0], 255);
         assert_eq!(cpu.v_registers[0xF], 0);
 
         cpu.v_registers[0] = 0;
         cpu.v_registers[1] = 1;
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.v_registers[0], 1);
         assert_eq!(cpu.v_registers[0xF], 1);
     }
@@ -657,12 +2071,12 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0] = 0b1010_1010;
-        cpu.execute(0x800E);
+        cpu.execute(0x800E).unwrap();
         assert_eq!(cpu.v_registers[0], 0b0101_0100);
         assert_eq!(cpu.v_registers[0xF], 1);
 
         cpu.v_registers[0] = 0b0101_0101;
-        cpu.execute(0x800E);
+        cpu.execute(0x800E).unwrap();
         assert_eq!(cpu.v_registers[0], 0b1010_1010);
         assert_eq!(cpu.v_registers[0xF], 0);
     }
@@ -672,11 +2086,11 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0] = 1;
-        cpu.execute(0x9010);
+        cpu.execute(0x9010).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
 
         cpu.v_registers[0] = 0;
-        cpu.execute(0x9010);
+        cpu.execute(0x9010).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2)
     }
 
@@ -684,7 +2098,7 @@ mod tests {
     fn test_set_i_nnn() {
         let mut cpu = CPU::new();
 
-        cpu.execute(0xA420);
+        cpu.execute(0xA420).unwrap();
         assert_eq!(cpu.index_register, 0x420);
     }
 
@@ -693,13 +2107,686 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0] = 69;
-        cpu.execute(0xB420);
+        cpu.execute(0xB420).unwrap();
         assert_eq!(cpu.pc, 69 + 0x420);
     }
 
-    // NOTE: test for the random opcode is omitted on account of the fact it's random
+    #[test]
+    fn test_quirk_warnings() {
+        let mut cpu = CPU::new();
+
+        // the same either way, nothing to warn about
+        cpu.execute(0x8016).unwrap();
+        cpu.execute(0xD005).unwrap();
+        assert_eq!(cpu.quirk_warnings, 0);
+
+        cpu.v_registers[1] = 2;
+        cpu.execute(0x8016).unwrap();
+        cpu.execute(0xD000).unwrap();
+        let shift_and_sprite =
+            1 << QuirkWarning::Shift as u8 | 1 << QuirkWarning::EmptySprite as u8;
+        assert_eq!(cpu.quirk_warnings, shift_and_sprite);
+
+        cpu.v_registers[2] = 62;
+        cpu.execute(0xD225).unwrap();
+        assert_ne!(cpu.quirk_warnings & 1 << QuirkWarning::Edge as u8, 0);
+
+        cpu.reset();
+        assert_eq!(cpu.quirk_warnings, 0);
+    }
+
+    #[test]
+    fn test_opcode_extension() {
+        let mut cpu = CPU::new();
+        // 5XY1 adds VY to VX, the others stay unknown
+        cpu.set_opcode_extension(Some(Arc::new(|cpu: &mut CPU, op: u16| {
+            let (x, y) = (x(op), y(op));
+            op & 0xF00F == 0x5001 && {
+                cpu.v_registers[x] = cpu.v_registers[x].wrapping_add(cpu.v_registers[y]);
+                true
+            }
+        })));
+        cpu.v_registers[1] = 3;
+        cpu.execute(0x5011).unwrap();
+        assert_eq!(cpu.v_registers[0], 3);
+        assert_eq!(cpu.execute(0x5002), Err(CpuError::UnknownOpcode(0x5002)));
+        // known opcodes never reach it
+        cpu.execute(0x5010).unwrap();
+    }
+
+    #[test]
+    fn test_step() {
+        // draw the 0 glyph, draw nothing, then wait for a key twice over
+        let mut cpu = CPU::new();
+        cpu.load(&[0xD0, 0x05, 0xD0, 0x00, 0xF0, 0x0A, 0xF1, 0x0A]);
+        assert_eq!(
+            cpu.step(),
+            Ok(vec![Event::RomLoaded(8), Event::DisplayChanged])
+        );
+        assert_eq!(cpu.step(), Ok(vec![]));
+        assert_eq!(cpu.step(), Ok(vec![Event::WaitingForKey]));
+        assert_eq!(cpu.step(), Ok(vec![]));
+
+        cpu.keypress(3, true).unwrap();
+        assert_eq!(cpu.step(), Ok(vec![]));
+        assert_eq!(cpu.v_registers[0], 3);
+        cpu.keypress(3, false).unwrap();
+        assert_eq!(cpu.step(), Ok(vec![Event::WaitingForKey]));
+
+        cpu.set_display_mode(DisplayMode::Hires);
+        assert_eq!(cpu.take_events(), [Event::DisplayChanged]);
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        // two instructions, then wait for a key and jump off the end of memory
+        let mut cpu = CPU::new();
+        cpu.load(&[0x60, 0x01, 0x70, 0x01, 0xF1, 0x0A, 0x1F, 0xFF]);
+        let report = cpu.run_cycles(1);
+        assert_eq!(report.ran, 1);
+        assert!(!report.waiting_for_key && report.stopped.is_none());
+
+        let report = cpu.run_cycles(100);
+        assert_eq!(report.ran, 2);
+        assert!(report.waiting_for_key);
+        assert_eq!(cpu.run_cycles(100).ran, 1);
+
+        cpu.keypress(2, true).unwrap();
+        assert_eq!(
+            cpu.run_cycles(100),
+            CycleReport {
+                ran: 2,
+                waiting_for_key: false,
+                stopped: Some(CpuError::AddressOutOfRange(0x1000)),
+            }
+        );
+        assert_eq!(cpu.run_cycles(0).ran, 0);
+    }
+
+    #[test]
+    fn test_run_frame() {
+        // ST = 2 then count in V1
+        let mut cpu = CPU::new();
+        cpu.load(&[0x60, 0x02, 0xF0, 0x18, 0x71, 0x01, 0x12, 0x04]);
+        cpu.set_ticks_per_frame(4);
+        assert_eq!(
+            cpu.run_frame(),
+            Ok(vec![Event::RomLoaded(8), Event::SoundStarted])
+        );
+        assert_eq!(cpu.v_registers[1], 1);
+        assert_eq!(cpu.sound_timer(), 1);
+        assert_eq!(cpu.run_frame(), Ok(vec![Event::SoundStopped]));
+        assert_eq!(cpu.v_registers[1], 3);
+
+        cpu.load(&[0x1F, 0xFF]);
+        cpu.pc = START_ADDRESS;
+        assert_eq!(cpu.run_frame(), Err(CpuError::AddressOutOfRange(0x1000)));
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut font = FONTSET;
+        font[0] = 0xFF;
+        let cpu = CPU::builder()
+            .variant("schip")
+            .unwrap()
+            .clock_speed(600)
+            .fontset(font)
+            .seed(1)
+            .memory_size(0x2000)
+            .build()
+            .unwrap();
+        assert_eq!(cpu.quirks(), Quirks::schip());
+        assert_eq!(cpu.ticks_per_frame(), 10);
+        assert_eq!(cpu.memory().len(), 0x2000);
+        assert_eq!(cpu.memory()[0], 0xFF);
+
+        // CXNN gives the same numbers for the same seed
+        let random = |cpu: &mut CPU| {
+            cpu.execute(0xC0FF).unwrap();
+            cpu.v_registers[0]
+        };
+        let mut other = CPU::builder().seed(1).build().unwrap();
+        let mut cpu = cpu;
+        assert_eq!(random(&mut cpu), random(&mut other));
+
+        // the font comes back after a reset, and the bigger memory can be run from
+        cpu.reset();
+        assert_eq!(cpu.memory()[0], 0xFF);
+        cpu.set_pc(0x1FFE);
+        cpu.memory_mut()[0x1FFE..].copy_from_slice(&[0x12, 0x00]);
+        cpu.tick().unwrap();
+
+        assert_eq!(
+            CPU::builder().variant("nonsense").err(),
+            Some(ConfigError::UnknownVariant("nonsense".to_string()))
+        );
+        assert!(CPU::builder().memory_size(0x100).build().is_err());
+        assert!(CPU::builder().memory_size(0x10001).build().is_err());
+    }
+
+    #[test]
+    fn test_input_state() {
+        let mut cpu = CPU::new();
+        cpu.load(&[0xF0, 0x0A]);
+        assert_eq!(cpu.last_key_pressed(), None);
+        cpu.tick().unwrap();
+        assert!(cpu.waiting_for_key());
+
+        cpu.keypress(4, true).unwrap();
+        cpu.keypress(9, true).unwrap();
+        // held keys being pressed again each frame don't count as new presses
+        cpu.keypress(4, true).unwrap();
+        assert_eq!(cpu.last_key_pressed(), Some(9));
+        cpu.tick().unwrap();
+        assert!(!cpu.waiting_for_key());
+        assert_eq!(cpu.keys()[4..10], [true, false, false, false, false, true]);
+
+        cpu.keypress(9, false).unwrap();
+        assert_eq!(cpu.last_key_pressed(), Some(9));
+        cpu.reset();
+        assert_eq!(cpu.last_key_pressed(), None);
+    }
+
+    #[test]
+    fn test_scheduled_keys() {
+        // V0 = the key pressed, again and again
+        let mut cpu = CPU::new();
+        cpu.load(&[0xF0, 0x0A, 0x12, 0x00]);
+        cpu.set_ticks_per_frame(2);
+        cpu.schedule_key(2, 7, false).unwrap();
+        cpu.schedule_key(1, 7, true).unwrap();
+        cpu.schedule_key(3, 3, true).unwrap();
+        assert_eq!(cpu.schedule_key(0, 16, true), Err(CpuError::InvalidKey(16)));
+
+        cpu.run_frame().unwrap();
+        assert!(cpu.waiting_for_key());
+        cpu.run_frame().unwrap();
+        assert_eq!(cpu.v_registers[0], 7);
+        cpu.run_frame().unwrap();
+        assert!(!cpu.keys()[7]);
+        cpu.run_frame().unwrap();
+        assert_eq!(cpu.v_registers[0], 3);
+        assert_eq!(cpu.frame(), 4);
+    }
+
+    #[test]
+    fn test_strict() {
+        // I = #1FE, then FX33 of V0 over the top of the interpreter's area
+        let rom = [0xA1, 0xFE, 0xF0, 0x33];
+        let mut cpu = CPU::builder().strict().build().unwrap();
+        cpu.load(&rom);
+        cpu.tick().unwrap();
+        assert_eq!(cpu.tick(), Err(CpuError::ProtectedWrite(0x1FE)));
+        assert_eq!(cpu.memory()[0x1FE], 0);
+
+        let mut cpu = CPU::new();
+        assert!(!cpu.is_strict());
+        cpu.load(&rom);
+        cpu.tick().unwrap();
+        cpu.tick().unwrap();
+        cpu.set_strict(true);
+        assert!(cpu.clone().is_strict());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // V0 = random, then draw it as a digit while waiting for a key in between
+        let rom = [0xC0, 0x0F, 0xF0, 0x29, 0xD1, 0x15, 0xF2, 0x0A, 0x12, 0x00];
+        let run = |seed: u64| {
+            let mut cpu = CPU::builder().deterministic(seed).build().unwrap();
+            cpu.load(&rom);
+            let mut hashes = Vec::new();
+            for frame in 0..20 {
+                // pressed between frames, so it lands at the start of the next
+                cpu.keypress(frame % 16, frame % 3 == 0).unwrap();
+                cpu.tick_timers();
+                cpu.run_frame().unwrap();
+                hashes.push(cpu.state_hash());
+            }
+            hashes
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+
+        let mut cpu = CPU::builder().deterministic(7).build().unwrap();
+        assert!(cpu.is_deterministic());
+        cpu.keypress(1, true).unwrap();
+        assert!(!cpu.keys()[1]);
+        cpu.set_sound_timer(2);
+        cpu.tick_timers();
+        assert_eq!(cpu.sound_timer(), 2);
+        cpu.load(&[0x12, 0x00]);
+        cpu.run_frame().unwrap();
+        assert!(cpu.keys()[1]);
+        assert_eq!(cpu.sound_timer(), 1);
+    }
+
+    #[test]
+    fn test_decoded_matches_handlers() {
+        for quirks in [Quirks::vip(), Quirks::schip(), Quirks::xochip()] {
+            let mut base = CPU::builder()
+                .quirks(quirks)
+                .deterministic(1)
+                .build()
+                .unwrap();
+            // pairs of equal registers so the skips go both ways, and a return address
+            base.v_registers = [
+                0, 0, 5, 5, 0xFF, 1, 0x80, 0x80, 9, 3, 2, 0xF0, 7, 7, 0x10, 1,
+            ];
+            base.index_register = 0x400;
+            base.pc = 0x300;
+            base.push(0x250).unwrap();
+            for op in 0..=u16::MAX {
+                let mut handlers = base.clone();
+                let mut decoded = base.clone();
+                let state = |cpu: &CPU| {
+                    (
+                        cpu.pc,
+                        cpu.v_registers,
+                        cpu.index_register,
+                        cpu.stack.clone(),
+                    )
+                };
+                let result = handlers.execute(op);
+                assert_eq!(
+                    (decoded.run(Instruction::decode(op)), state(&decoded)),
+                    (result, state(&handlers)),
+                    "{:04X}",
+                    op
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        let mut cpu = CPU::new();
+        cpu.load(&[
+            0x12, 0x06, // 200: JP 206
+            0x6B, 0x01, // 202: LD VB, 01
+            0x00, 0xEE, // 204: RET
+            0x22, 0x02, // 206: CALL 202
+            0xA2, 0x02, // 208: LD I, 202
+            0x60, 0x6B, // 20A: LD V0, 6B
+            0x61, 0x07, // 20C: LD V1, 07
+            0xF1, 0x55, // 20E: LD [I], V1, so 202 is LD VB, 07
+            0x22, 0x02, // 210: CALL 202
+        ]);
+        for _ in 0..4 {
+            cpu.tick().unwrap();
+        }
+        assert_eq!(cpu.v_registers[0xB], 1);
+        for _ in 0..6 {
+            cpu.tick().unwrap();
+        }
+        assert_eq!(cpu.v_registers[0xB], 7);
+
+        // and from outside, a byte at a time or through the whole of memory
+        cpu.poke(0x203, 0x09);
+        cpu.pc = 0x202;
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v_registers[0xB], 9);
+        cpu.memory_mut()[0x203] = 0x0A;
+        cpu.pc = 0x202;
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v_registers[0xB], 0x0A);
+    }
+
+    #[test]
+    fn test_state_hash_waits() {
+        let mut waiting = CPU::new();
+        waiting.load(&[0xF0, 0x0A]);
+        let before = waiting.clone();
+        waiting.tick().unwrap();
+        assert!(waiting.waiting_for_key());
+        assert_eq!(waiting.pc(), before.pc());
+        assert_ne!(waiting.state_hash(), before.state_hash());
+
+        let mut vip = before.clone();
+        vip.set_quirks(Quirks::vip());
+        assert_ne!(vip.state_hash(), before.state_hash());
+
+        let mut scheduled = before.clone();
+        scheduled.schedule_key(3, 1, true).unwrap();
+        assert_ne!(scheduled.state_hash(), before.state_hash());
+    }
+
+    #[test]
+    fn test_halted_event_kept() {
+        // draws until the queue is full, then an opcode that doesn't exist
+        let mut cpu = CPU::new();
+        cpu.load(&[0xD0, 0x01, 0xFF, 0xFF]);
+        for _ in 0..MAX_PENDING_EVENTS {
+            cpu.set_pc(START_ADDRESS);
+            cpu.tick().unwrap();
+        }
+        for _ in 0..2 {
+            cpu.set_pc(0x202);
+            assert!(cpu.tick().is_err());
+        }
+        let events: Vec<Event> = cpu.drain_events().collect();
+        assert_eq!(events.len(), MAX_PENDING_EVENTS);
+        assert!(matches!(events.last(), Some(Event::Halted(_))));
+        assert!(!matches!(events[events.len() - 2], Event::Halted(_)));
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        for mut cpu in [CPU::new(), CPU::builder().deterministic(7).build().unwrap()] {
+            assert_eq!(
+                cpu.keypress(NUM_KEYS, true),
+                Err(CpuError::InvalidKey(NUM_KEYS as u8))
+            );
+            let mut keys = [false; NUM_KEYS];
+            keys[0xF] = true;
+            cpu.set_keys(&keys);
+            cpu.run_frame().unwrap();
+            assert_eq!(cpu.keys(), keys);
+        }
+    }
+
+    #[test]
+    fn test_clone_from() {
+        let mut cpu = CPU::builder().seed(7).memory_size(8192).build().unwrap();
+        cpu.load(&[0xC0, 0xFF, 0xA2, 0x00, 0xD0, 0x15, 0x12, 0x00]);
+        cpu.toggle_breakpoint(0x204);
+        cpu.run_cycles(10);
+
+        // into a machine with different sized memory and nothing in common
+        let mut copy = CPU::new();
+        copy.keypress(3, true).unwrap();
+        copy.clone_from(&cpu);
+        assert_eq!(copy.state_hash(), cpu.state_hash());
+        assert_eq!(copy.memory(), cpu.memory());
+        assert_eq!(copy.breakpoints(), cpu.breakpoints());
+        // and the generator carries on the same
+        copy.run_cycles(10);
+        cpu.run_cycles(10);
+        assert_eq!(copy.v_registers(), cpu.v_registers());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.execute(0x5001), Err(CpuError::UnknownOpcode(0x5001)));
+        assert_eq!(cpu.execute(0x00EE), Err(CpuError::StackUnderflow));
+
+        for _ in 0..DEFAULT_STACK_SIZE {
+            cpu.execute(0x2200).unwrap();
+        }
+        assert_eq!(cpu.execute(0x2200), Err(CpuError::StackOverflow));
+        // the call that overflowed left the stack as it was
+        assert_eq!(cpu.stack().len(), DEFAULT_STACK_SIZE);
+
+        cpu.index_register = 0xFFE;
+        assert_eq!(
+            cpu.execute(0xF255),
+            Err(CpuError::AddressOutOfRange(0x1000))
+        );
+        cpu.index_register = 0xFFFF;
+        assert!(cpu.execute(0xD001).is_err());
+
+        cpu.v_registers[0] = 0x10;
+        assert_eq!(cpu.execute(0xE09E), Err(CpuError::InvalidKey(0x10)));
+
+        cpu.pc = 0xFFF;
+        assert_eq!(cpu.tick(), Err(CpuError::AddressOutOfRange(0x1000)));
+    }
+
+    #[test]
+    fn test_quirks() {
+        let mut cpu = CPU::new();
+        cpu.set_quirks(Quirks::vip());
+
+        cpu.v_registers[0xF] = 1;
+        cpu.execute(0x8011).unwrap();
+        assert_eq!(cpu.v_registers[0xF], 0);
+
+        cpu.v_registers[1] = 0b110;
+        cpu.execute(0x8016).unwrap();
+        assert_eq!(cpu.v_registers[0], 0b011);
+
+        cpu.index_register = 0x300;
+        cpu.execute(0xF255).unwrap();
+        assert_eq!(cpu.index_register, 0x303);
+
+        // the right half of the sprite is off the screen
+        cpu.memory[0x300] = 0xFF;
+        cpu.index_register = 0x300;
+        cpu.v_registers[0] = 60;
+        cpu.v_registers[1] = 0;
+        cpu.execute(0xD011).unwrap();
+        assert!(cpu.screen[63]);
+        assert!(!cpu.screen[0]);
+
+        cpu.set_quirks(Quirks::schip());
+        cpu.v_registers[2] = 0x10;
+        cpu.execute(0xB220).unwrap();
+        assert_eq!(cpu.pc, 0x230);
+
+        cpu.set_quirks(Quirks::schip_legacy());
+        cpu.index_register = 0x300;
+        cpu.execute(0xF255).unwrap();
+        assert_eq!(cpu.index_register, 0x302);
+
+        // a 16 row sprite, the last row a single pixel
+        cpu.memory[0x30F] = 0x80;
+        cpu.execute(0x00E0).unwrap();
+        cpu.index_register = 0x300;
+        cpu.v_registers[0] = 0;
+        cpu.v_registers[1] = 0;
+        cpu.execute(0xD010).unwrap();
+        assert!(cpu.screen[15 * SCREEN_WIDTH]);
+        assert!(!cpu.screen[16 * SCREEN_WIDTH]);
+    }
+
+    #[test]
+    fn test_chip8e() {
+        let mut cpu = CPU::new();
+        // none of these exist without the quirk
+        assert!(cpu.execute(0x5011).is_err());
+        assert!(cpu.execute(0x0188).is_err());
+
+        cpu.set_quirks(Quirks::chip8e());
+        cpu.v_registers[0] = 5;
+        cpu.v_registers[1] = 3;
+        cpu.pc = 0x300;
+        cpu.execute(0x5011).unwrap();
+        assert_eq!(cpu.pc, 0x302);
+        cpu.execute(0x5101).unwrap();
+        assert_eq!(cpu.pc, 0x302);
+
+        // V1 down to V0 into memory, then back in the other order
+        cpu.index_register = 0x400;
+        cpu.execute(0x5102).unwrap();
+        assert_eq!(cpu.memory[0x400..0x402], [3, 5]);
+        assert_eq!(cpu.index_register, 0x400);
+        cpu.execute(0x5013).unwrap();
+        assert_eq!(cpu.v_registers[..2], [3, 5]);
+
+        cpu.execute(0xBB10).unwrap();
+        assert_eq!(cpu.pc, 0x2F2);
+        cpu.execute(0xBF20).unwrap();
+        assert_eq!(cpu.pc, 0x312);
+        cpu.execute(0x0188).unwrap();
+        assert_eq!(cpu.pc, 0x314);
+        cpu.execute(0xF01B).unwrap();
+        assert_eq!(cpu.pc, 0x317);
+        cpu.execute(0x00ED).unwrap();
+        assert_eq!(cpu.pc, 0x315);
+
+        // FX4F sets the delay timer once and holds the program there until it runs out, with a
+        // frame going by after each instruction
+        cpu.load(&[0x61, 0x02, 0xF1, 0x4F]);
+        cpu.pc = START_ADDRESS;
+        let mut pcs = Vec::new();
+        for _ in 0..5 {
+            cpu.tick().unwrap();
+            cpu.tick_timers();
+            pcs.push(cpu.pc);
+        }
+        assert_eq!(pcs, [0x202, 0x202, 0x202, 0x204, 0x206]);
+    }
+
+    #[test]
+    fn test_two_page() {
+        let mut cpu = CPU::new();
+        cpu.set_quirks(Quirks::two_page());
+        cpu.load(&[0x12, 0x60]);
+        // the sprite at 2C0 is the font's 0, drawn at the bottom of the 64x64 display
+        cpu.memory[0x2C0..0x2C8].copy_from_slice(&[0x60, 0x00, 0x61, 0x3B, 0xA0, 0x00, 0xD0, 0x15]);
+
+        cpu.tick().unwrap();
+        assert_eq!(cpu.pc, 0x2C0);
+        assert_eq!(cpu.resolution(), (64, 64));
+        for _ in 0..4 {
+            cpu.tick().unwrap();
+        }
+        assert!(cpu.screen[59 * 64]);
+        assert_eq!(cpu.pixels().len(), 64 * 64);
+
+        cpu.execute(0x0230).unwrap();
+        assert!(cpu.screen.is_blank());
+
+        // the same program on the standard interpreter just jumps
+        let mut cpu = CPU::new();
+        cpu.load(&[0x12, 0x60]);
+        cpu.tick().unwrap();
+        assert_eq!(cpu.pc, 0x260);
+        assert_eq!(cpu.resolution(), (64, 32));
+        assert!(cpu.execute(0x0230).is_err());
+    }
+
+    #[test]
+    fn test_schip_hires() {
+        let mut cpu = CPU::new();
+        cpu.set_quirks(Quirks::schip());
+        cpu.execute(0x00FF).unwrap();
+        assert_eq!(cpu.resolution(), (128, 64));
+
+        // a solid 16x16 sprite at 120,56, cut off to the right and for its last 8 rows
+        cpu.memory[0x300..0x320].fill(0xFF);
+        cpu.index_register = 0x300;
+        cpu.v_registers[0] = 120;
+        cpu.v_registers[1] = 56;
+        cpu.execute(0xD010).unwrap();
+        assert!(cpu.screen[56 * 128 + 127]);
+        assert!(!cpu.screen[56 * 128]);
+        assert_eq!(cpu.v_registers[0xF], 8);
+
+        // drawn again every row that's still there collides as well
+        cpu.execute(0xD010).unwrap();
+        assert_eq!(cpu.v_registers[0xF], 16);
+        assert!(cpu.screen.is_blank());
+
+        // scrolling moves what's drawn, in pixels of the display in use
+        cpu.v_registers[0] = 0;
+        cpu.v_registers[1] = 0;
+        cpu.execute(0xD011).unwrap();
+        cpu.execute(0x00C2).unwrap();
+        cpu.execute(0x00FB).unwrap();
+        assert!(cpu.screen[2 * 128 + 4] && !cpu.screen[0]);
+        cpu.execute(0x00FC).unwrap();
+        assert!(cpu.screen[2 * 128]);
+        cpu.execute(0x00E0).unwrap();
+
+        // in low resolution it's just whether anything collided
+        cpu.execute(0x00FE).unwrap();
+        cpu.v_registers[0] = 0;
+        cpu.v_registers[1] = 28;
+        cpu.execute(0xD010).unwrap();
+        assert_eq!(cpu.v_registers[0xF], 0);
+        cpu.execute(0xD010).unwrap();
+        assert_eq!(cpu.v_registers[0xF], 1);
+
+        // only SUPER-CHIP has the big display
+        let mut cpu = CPU::new();
+        assert!(cpu.execute(0x00FF).is_err());
+    }
+
+    #[test]
+    fn test_xochip() {
+        let mut cpu = CPU::builder()
+            .quirks(Quirks::xochip())
+            .memory_size(MAX_MEMORY_SIZE)
+            .build()
+            .unwrap();
+        // F000 NNNN reaches all 64K, and a skip steps over all four of its bytes
+        cpu.load(&[
+            0xF0, 0x00, 0xFF, 0xF0, 0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x60, 0x01,
+        ]);
+        cpu.tick().unwrap();
+        assert_eq!((cpu.index_register, cpu.pc), (0xFFF0, 0x204));
+        cpu.tick().unwrap();
+        assert_eq!(cpu.pc, 0x20A);
+
+        // V1 down to V3 out and back in, however far apart they are
+        cpu.v_registers[1..4].copy_from_slice(&[1, 2, 3]);
+        cpu.execute(0x5132).unwrap();
+        assert_eq!(cpu.memory[0xFFF0..0xFFF3], [1, 2, 3]);
+        cpu.execute(0x5312).unwrap();
+        assert_eq!(cpu.memory[0xFFF0..0xFFF3], [3, 2, 1]);
+        cpu.v_registers[1..4].fill(0);
+        cpu.execute(0x5133).unwrap();
+        assert_eq!(cpu.v_registers[1..4], [3, 2, 1]);
+
+        // with both planes a sprite's first rows go to the first plane and the rest to the second
+        cpu.memory[0x300..0x302].copy_from_slice(&[0x80, 0x40]);
+        cpu.index_register = 0x300;
+        cpu.v_registers[0] = 0;
+        cpu.execute(0xF301).unwrap();
+        cpu.execute(0xD001).unwrap();
+        assert!(cpu.screen[0] && !cpu.screen[1]);
+        assert!(cpu.second_plane[1] && !cpu.second_plane[0]);
+        assert_eq!(cpu.pixels().filter(|&on| on).count(), 2);
+        // scrolling and clearing only touch the planes picked
+        cpu.execute(0xF201).unwrap();
+        cpu.execute(0x00C1).unwrap();
+        cpu.execute(0x00D1).unwrap();
+        assert!(cpu.second_plane[1] && cpu.screen[0]);
+        cpu.execute(0x00E0).unwrap();
+        assert!(cpu.second_plane.is_blank() && !cpu.screen.is_blank());
+
+        // a pattern from memory, played faster the higher the pitch
+        cpu.index_register = 0x300;
+        cpu.execute(0xF002).unwrap();
+        assert_eq!(cpu.audio_pattern().unwrap()[..2], [0x80, 0x40]);
+        assert_eq!(cpu.playback_rate(), 4000.0);
+        cpu.v_registers[0] = DEFAULT_PITCH + 48;
+        cpu.execute(0xF03A).unwrap();
+        assert_eq!(cpu.playback_rate(), 8000.0);
+
+        // the PC wraps round to the start of memory
+        cpu.pc = 0xFFFE;
+        cpu.memory[0xFFFE..].copy_from_slice(&[0x60, 0x07]);
+        cpu.tick().unwrap();
+        assert_eq!((cpu.pc, cpu.v_registers[0]), (0, 7));
+
+        // none of it is there without the quirk
+        let mut cpu = CPU::new();
+        assert!(cpu.execute(0xF001).is_err());
+        assert!(cpu.execute(0x00D1).is_err());
+        assert!(cpu.execute(0x5012).is_err());
+    }
+
+    #[test]
+    fn test_random_seeded() {
+        let mut cpu = CPU::new();
+        let mut other = CPU::new();
+        cpu.seed(8);
+        other.seed(8);
+
+        for _ in 0..8 {
+            cpu.execute(0xC00F).unwrap();
+            other.execute(0xC00F).unwrap();
+            assert_eq!(cpu.v_registers[0], other.v_registers[0]);
+            assert_eq!(cpu.v_registers[0] & 0xF0, 0);
+        }
+    }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_draw() {
         let mut cpu = CPU::new();
 
@@ -713,7 +2800,7 @@ mod tests {
         cpu.v_registers[0] = 10;
         cpu.v_registers[1] = 10;
         cpu.index_register = START_ADDRESS + 4;
-        cpu.execute(0xD013);
+        cpu.execute(0xD013).unwrap();
 
         assert_eq!(cpu.screen[650], false);
         assert_eq!(cpu.screen[651], true);
@@ -728,17 +2815,35 @@ mod tests {
         assert_eq!(cpu.screen[780], false);
     }
 
+    #[test]
+    fn test_accessors() {
+        let mut cpu = CPU::new();
+        cpu.load(&[0xA2, 0x34]);
+        cpu.pc = START_ADDRESS + 2;
+        cpu.index_register = 0x234;
+        cpu.v_registers[0xB] = 0x56;
+        cpu.delay_timer = 7;
+        cpu.sound_timer = 8;
+
+        assert_eq!(cpu.pc(), START_ADDRESS + 2);
+        assert_eq!(cpu.index_register(), 0x234);
+        assert_eq!(cpu.v_registers()[0xB], 0x56);
+        assert_eq!(cpu.delay_timer(), 7);
+        assert_eq!(cpu.sound_timer(), 8);
+        assert_eq!(cpu.memory()[START_ADDRESS as usize..][..2], [0xA2, 0x34]);
+    }
+
     #[test]
     fn test_skip_key_pressed() {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0xA] = 2;
         cpu.keys[2] = true;
-        cpu.execute(0xEA9E);
+        cpu.execute(0xEA9E).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
 
         cpu.keys[2] = false;
-        cpu.execute(0xEA9E);
+        cpu.execute(0xEA9E).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
     }
 
@@ -748,11 +2853,11 @@ mod tests {
 
         cpu.v_registers[0xA] = 2;
         cpu.keys[2] = false;
-        cpu.execute(0xEA9E);
+        cpu.execute(0xEA9E).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS);
 
         cpu.keys[2] = true;
-        cpu.execute(0xEA9E);
+        cpu.execute(0xEA9E).unwrap();
         assert_eq!(cpu.pc, START_ADDRESS + 2);
     }
 
@@ -761,7 +2866,7 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.delay_timer = 69;
-        cpu.execute(0xF407);
+        cpu.execute(0xF407).unwrap();
         assert_eq!(cpu.v_registers[4], 69);
     }
 
@@ -770,7 +2875,7 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.keys[0xD] = true;
-        cpu.execute(0xF80A);
+        cpu.execute(0xF80A).unwrap();
         assert_eq!(cpu.v_registers[8], 0xD);
 
         // TODO: can't test the waiting functionality in this way, requires multiple cycles - change
@@ -781,16 +2886,34 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0xE] = 42;
-        cpu.execute(0xFE15);
+        cpu.execute(0xFE15).unwrap();
         assert_eq!(cpu.delay_timer, 42);
     }
 
+    #[test]
+    fn test_timers() {
+        let mut cpu = CPU::new();
+        // DT = ST = 2, then jump to itself
+        cpu.load(&[0x60, 0x02, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06]);
+
+        // running instructions leaves the timers alone, only the 60Hz ticks count them down
+        for _ in 0..20 {
+            cpu.tick().unwrap();
+        }
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (2, 2));
+        cpu.tick_timers();
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (1, 1));
+        cpu.tick_timers();
+        cpu.tick_timers();
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (0, 0));
+    }
+
     #[test]
     fn test_set_st_vx() {
         let mut cpu = CPU::new();
 
         cpu.v_registers[0xE] = 42;
-        cpu.execute(0xFE18);
+        cpu.execute(0xFE18).unwrap();
         assert_eq!(cpu.sound_timer, 42);
     }
 
@@ -800,7 +2923,7 @@ mod tests {
 
         cpu.v_registers[0xB] = 9;
         cpu.index_register = 10;
-        cpu.execute(0xFB1E);
+        cpu.execute(0xFB1E).unwrap();
         assert_eq!(cpu.index_register, 19);
     }
 
@@ -809,7 +2932,7 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.v_registers[2] = 7;
-        cpu.execute(0xF229);
+        cpu.execute(0xF229).unwrap();
         assert_eq!(cpu.index_register, 7 * 5);
     }
 
@@ -819,7 +2942,7 @@ mod tests {
 
         cpu.v_registers[0] = 123;
         cpu.index_register = 69;
-        cpu.execute(0xF033);
+        cpu.execute(0xF033).unwrap();
         assert_eq!(cpu.memory[69], 1);
         assert_eq!(cpu.memory[70], 2);
         assert_eq!(cpu.memory[71], 3);
@@ -833,12 +2956,42 @@ mod tests {
         cpu.v_registers[1] = 2;
         cpu.v_registers[2] = 3;
         cpu.index_register = START_ADDRESS + 10;
-        cpu.execute(0xF255);
+        cpu.execute(0xF255).unwrap();
         assert_eq!(cpu.memory[(START_ADDRESS + 10) as usize], 1);
         assert_eq!(cpu.memory[(START_ADDRESS + 11) as usize], 2);
         assert_eq!(cpu.memory[(START_ADDRESS + 12) as usize], 3);
     }
 
+    #[test]
+    fn test_memory_accesses() {
+        let mut cpu = CPU::new();
+
+        cpu.load(&[0xF1, 0x55]);
+        cpu.index_register = 0x300;
+        cpu.tick().unwrap();
+        assert_eq!(
+            cpu.memory_accesses(),
+            [
+                MemoryAccess {
+                    address: START_ADDRESS,
+                    kind: AccessKind::Execute
+                },
+                MemoryAccess {
+                    address: START_ADDRESS + 1,
+                    kind: AccessKind::Execute
+                },
+                MemoryAccess {
+                    address: 0x300,
+                    kind: AccessKind::Write
+                },
+                MemoryAccess {
+                    address: 0x301,
+                    kind: AccessKind::Write
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_load_v0_vx() {
         let mut cpu = CPU::new();
@@ -847,7 +3000,7 @@ mod tests {
         cpu.memory[(START_ADDRESS + 11) as usize] = 2;
         cpu.memory[(START_ADDRESS + 12) as usize] = 3;
         cpu.index_register = START_ADDRESS + 10;
-        cpu.execute(0xF265);
+        cpu.execute(0xF265).unwrap();
         assert_eq!(cpu.v_registers[0], 1);
         assert_eq!(cpu.v_registers[1], 2);
         assert_eq!(cpu.v_registers[2], 3);
@@ -0,0 +1,167 @@
+// when a program stops the CPU, everything needed to work out why goes into a text file: the
+// error, the ROM's checksum, the registers, stack, screen and memory, and the instructions that
+// led up to it
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cpu::{CpuError, CPU};
+use crate::headless::screen_text;
+use crate::instruction::Instruction;
+use crate::patch::crc32;
+
+pub const HISTORY_LENGTH: usize = 64;
+
+const MEMORY_ROW_BYTES: usize = 16;
+
+// the last instructions the CPU ran, as (address, opcode)
+pub struct History {
+    instructions: VecDeque<(u16, u16)>,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::new()
+    }
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            instructions: VecDeque::with_capacity(HISTORY_LENGTH),
+        }
+    }
+
+    // call before each instruction runs, so the one that failed is included
+    pub fn record(&mut self, cpu: &CPU) {
+        if self.instructions.len() == HISTORY_LENGTH {
+            self.instructions.pop_front();
+        }
+        self.instructions
+            .push_back((cpu.pc(), cpu.opcode_at(cpu.pc())));
+    }
+
+    pub fn report(&self, rom: &[u8], cpu: &CPU, error: &CpuError) -> String {
+        let mut report = String::new();
+        writeln!(report, "# rusty_chip8 crash report").unwrap();
+        writeln!(report, "error: {}", error).unwrap();
+        writeln!(report, "rom: {} bytes, crc32 {:08x}", rom.len(), crc32(rom)).unwrap();
+
+        writeln!(report, "\n# registers").unwrap();
+        writeln!(
+            report,
+            "PC #{:03X}  I #{:03X}  DT {:02X}  ST {:02X}",
+            cpu.pc(),
+            cpu.index_register(),
+            cpu.delay_timer(),
+            cpu.sound_timer()
+        )
+        .unwrap();
+        for (index, values) in cpu.v_registers().chunks(4).enumerate() {
+            let line = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("V{:X} {:02X}", index * 4 + i, value))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(report, "{}", line).unwrap();
+        }
+        let held: Vec<String> = (0..cpu.keys().len())
+            .filter(|&key| cpu.keys()[key])
+            .map(|key| format!("{:X}", key))
+            .collect();
+        writeln!(report, "keys held: {}", held.join(" ")).unwrap();
+
+        writeln!(report, "\n# stack, innermost last").unwrap();
+        for return_address in cpu.stack() {
+            writeln!(report, "#{:03X}", return_address).unwrap();
+        }
+
+        writeln!(
+            report,
+            "\n# last {} instructions, oldest first",
+            self.instructions.len()
+        )
+        .unwrap();
+        for (address, op) in self.instructions.iter() {
+            writeln!(
+                report,
+                "#{:03X} {:04X} {}",
+                address,
+                op,
+                Instruction::decode(*op)
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\n# screen").unwrap();
+        report.push_str(&screen_text(cpu));
+
+        writeln!(report, "\n# memory").unwrap();
+        for (row, chunk) in cpu.memory().chunks(MEMORY_ROW_BYTES).enumerate() {
+            let bytes = chunk
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(report, "{:03X} {}", row * MEMORY_ROW_BYTES, bytes).unwrap();
+        }
+
+        report
+    }
+
+    // writes the report to a new file in the working directory and returns its path
+    pub fn write_report(&self, rom: &[u8], cpu: &CPU, error: &CpuError) -> io::Result<PathBuf> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = PathBuf::from(format!("chip8-crash-{}.txt", seconds));
+        fs::write(&path, self.report(rom, cpu, error))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        // V0 = 5, then an opcode that doesn't exist
+        let rom = [0x60, 0x05, 0xFF, 0xFF];
+        let mut cpu = CPU::new();
+        cpu.load(&rom);
+        let mut history = History::new();
+
+        let error = loop {
+            history.record(&cpu);
+            if let Err(error) = cpu.tick() {
+                break error;
+            }
+        };
+
+        let report = history.report(&rom, &cpu, &error);
+        assert!(report.contains("error: unknown opcode 0xffff"));
+        assert!(report.contains(&format!("crc32 {:08x}", crc32(&rom))));
+        assert!(report.contains("V0 05"));
+        assert!(report.contains("# last 2 instructions, oldest first\n#200 6005"));
+        assert!(report.contains("\n#202 FFFF"));
+        assert!(report.contains("\n200 60 05 FF FF 00"));
+    }
+
+    #[test]
+    fn test_history_length() {
+        let mut cpu = CPU::new();
+        // jump to itself
+        cpu.load(&[0x12, 0x00]);
+        let mut history = History::new();
+        for _ in 0..HISTORY_LENGTH * 2 {
+            history.record(&cpu);
+            cpu.tick().unwrap();
+        }
+        assert_eq!(history.instructions.len(), HISTORY_LENGTH);
+    }
+}
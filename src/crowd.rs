@@ -0,0 +1,244 @@
+// lets a chat room play the game. every message in an IRC channel (Twitch chat speaks IRC too)
+// is a vote for a key, e.g. "5", "c" or "up". at the end of each vote window the key with the
+// most votes is pressed for a moment, ties going to whichever got its first vote earliest
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+// frames the winning key stays down, long enough for games that only check the keys now and then
+const HOLD_FRAMES: u32 = 6;
+pub const DEFAULT_WINDOW: u32 = 30;
+
+pub struct Crowd {
+    votes: Receiver<usize>,
+    tally: Tally,
+}
+
+impl Crowd {
+    // joins the channel anonymously, twitch accepts any justinfan nick without a password
+    pub fn connect(server: &str, channel: &str, window: u32) -> io::Result<Crowd> {
+        let mut stream = TcpStream::connect(server)?;
+        let channel = format!("#{}", channel.trim_start_matches('#').to_lowercase());
+        let nick = format!("justinfan{}", rand::random::<u16>());
+        write!(
+            stream,
+            "NICK {}\r\nUSER {} 0 * :rusty_chip8\r\nJOIN {}\r\n",
+            nick, nick, channel
+        )?;
+
+        let (sender, votes) = mpsc::channel();
+        thread::spawn(move || read_chat(stream, channel, sender));
+
+        Ok(Crowd {
+            votes,
+            tally: Tally::new(window),
+        })
+    }
+
+    // call once per frame, returns the keys to press or release
+    pub fn update(&mut self) -> Vec<(usize, bool)> {
+        let votes: Vec<_> = self.votes.try_iter().collect();
+        self.tally.frame(&votes)
+    }
+}
+
+fn read_chat(stream: TcpStream, channel: String, votes: Sender<usize>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+
+        // the server drops us if pings go unanswered
+        if let Some(token) = line.strip_prefix("PING") {
+            if write!(writer, "PONG{}\r\n", token).is_err() {
+                return;
+            }
+            continue;
+        }
+
+        if let Some(key) = chat_message(&line, &channel).and_then(parse_vote) {
+            if votes.send(key).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// the text of a PRIVMSG to the channel, twitch puts @tags in front of the usual prefix
+fn chat_message<'a>(line: &'a str, channel: &str) -> Option<&'a str> {
+    let line = match line.strip_prefix('@') {
+        Some(tagged) => tagged.split_once(' ')?.1,
+        None => line,
+    };
+    let line = line.strip_prefix(':')?.split_once(' ')?.1;
+    let (command, rest) = line.split_once(' ')?;
+    let (target, text) = rest.split_once(' ')?;
+    (command == "PRIVMSG" && target.eq_ignore_ascii_case(channel))
+        .then(|| text.trim_start_matches(':'))
+}
+
+// a keypad key by name or by the direction it usually means, only the first word counts
+fn parse_vote(text: &str) -> Option<usize> {
+    let word = text.split_whitespace().next()?.to_lowercase();
+    match word.as_str() {
+        "up" => Some(0x2),
+        "left" => Some(0x4),
+        "fire" => Some(0x5),
+        "right" => Some(0x6),
+        "down" => Some(0x8),
+        _ if word.len() == 1 => usize::from_str_radix(&word, 16).ok(),
+        _ => None,
+    }
+}
+
+struct Tally {
+    window: u32,
+    frame: u32,
+    // votes per key and the order keys first got a vote in this window
+    counts: [u32; 16],
+    order: Vec<usize>,
+    held: Option<(usize, u32)>,
+}
+
+impl Tally {
+    fn new(window: u32) -> Tally {
+        Tally {
+            window: window.max(1),
+            frame: 0,
+            counts: [0; 16],
+            order: Vec::new(),
+            held: None,
+        }
+    }
+
+    fn frame(&mut self, votes: &[usize]) -> Vec<(usize, bool)> {
+        let mut events = Vec::new();
+
+        if let Some((key, frames)) = self.held.as_mut() {
+            *frames -= 1;
+            if *frames == 0 {
+                events.push((*key, false));
+                self.held = None;
+            }
+        }
+
+        for &key in votes {
+            if self.counts[key] == 0 {
+                self.order.push(key);
+            }
+            self.counts[key] += 1;
+        }
+
+        self.frame += 1;
+        if self.frame < self.window {
+            return events;
+        }
+        self.frame = 0;
+
+        // max_by_key keeps the last of equal keys, so go through them latest first
+        let winner = self
+            .order
+            .iter()
+            .rev()
+            .copied()
+            .max_by_key(|&key| self.counts[key]);
+        self.counts = [0; 16];
+        self.order.clear();
+
+        if let Some(winner) = winner {
+            if let Some((key, _)) = self.held.take() {
+                events.push((key, false));
+            }
+            events.push((winner, true));
+            self.held = Some((winner, HOLD_FRAMES));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse() {
+        let line = "@badge-info=;color=#FF0000 :viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #game :up up up";
+        assert_eq!(chat_message(line, "#game"), Some("up up up"));
+        assert_eq!(
+            chat_message(":a!a@host PRIVMSG #Game :4", "#game"),
+            Some("4")
+        );
+        assert_eq!(chat_message(":a!a@host PRIVMSG #other :4", "#game"), None);
+        assert_eq!(chat_message(":server 001 nick :welcome", "#game"), None);
+
+        assert_eq!(parse_vote("up up up"), Some(0x2));
+        assert_eq!(parse_vote("C"), Some(0xC));
+        assert_eq!(parse_vote("4 please"), Some(0x4));
+        assert_eq!(parse_vote("hello"), None);
+        assert_eq!(parse_vote("g"), None);
+        assert_eq!(parse_vote(""), None);
+    }
+
+    #[test]
+    fn test_tally() {
+        // votes count across the whole window
+        let mut tally = Tally::new(2);
+        assert!(tally.frame(&[0x4, 0x6]).is_empty());
+        assert_eq!(tally.frame(&[0x6]), vec![(0x6, true)]);
+
+        // 4 and 6 tie, 4 was voted for first
+        let mut tally = Tally::new(2);
+        tally.frame(&[0x4, 0x6]);
+        assert_eq!(tally.frame(&[]), vec![(0x4, true)]);
+
+        // nobody voted
+        let mut tally = Tally::new(1);
+        assert!(tally.frame(&[]).is_empty());
+
+        // a new winner releases the old one, then it's released after HOLD_FRAMES
+        let mut tally = Tally::new(1);
+        assert_eq!(tally.frame(&[0x1]), vec![(0x1, true)]);
+        assert_eq!(tally.frame(&[0x2]), vec![(0x1, false), (0x2, true)]);
+        for _ in 1..HOLD_FRAMES {
+            assert!(tally.frame(&[]).is_empty());
+        }
+        assert_eq!(tally.frame(&[]), vec![(0x2, false)]);
+    }
+
+    #[test]
+    fn test_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut crowd = Crowd::connect(&address, "Game", 1).unwrap();
+
+        let (mut server, _) = listener.accept().unwrap();
+        let mut lines = BufReader::new(server.try_clone().unwrap()).lines();
+        assert!(lines.next().unwrap().unwrap().starts_with("NICK justinfan"));
+        lines.next();
+        assert_eq!(lines.next().unwrap().unwrap(), "JOIN #game");
+
+        write!(
+            server,
+            "PING :tmi.twitch.tv\r\n:a!a@host PRIVMSG #game :right\r\n"
+        )
+        .unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "PONG :tmi.twitch.tv");
+
+        let events = loop {
+            let events = crowd.update();
+            if !events.is_empty() {
+                break events;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(events, vec![(0x6, true)]);
+    }
+}
@@ -0,0 +1,340 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, CHAR_WIDTH, LINE_HEIGHT};
+use chip8::cpu::{DisplayMode, CPU};
+use chip8::instruction::Instruction;
+
+// panels are sized to fit the widest line of the memory dump
+const PANEL_COLUMNS: i32 = 28;
+const PANEL_PADDING: i32 = 6;
+const PANEL_WIDTH: i32 = PANEL_COLUMNS * CHAR_WIDTH + 2 * PANEL_PADDING;
+
+const PANEL_BACKGROUND: Color = Color::RGBA(0, 0, 0, 200);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const HIGHLIGHT_COLOR: Color = Color::RGB(0, 255, 128);
+
+// instructions shown before the PC in the disassembly
+const DISASSEMBLY_CONTEXT: u16 = 4;
+const DISASSEMBLY_LINES: u16 = 12;
+const MEMORY_ROWS: usize = 8;
+const MEMORY_ROW_BYTES: usize = 8;
+// the display plane is shrunk to this many characters across, each one lit if any pixel under it is
+const PLANE_COLUMNS: usize = 16;
+// the COSMAC VIP's hex keypad
+const KEYPAD: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dock {
+    Hidden,
+    Right,
+    Left,
+}
+
+struct Panel {
+    title: &'static str,
+    lines: Vec<(String, Color)>,
+}
+
+pub struct Debugger {
+    dock: Dock,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger { dock: Dock::Hidden }
+    }
+
+    // cycles between docked on the right, docked on the left and hidden
+    pub fn toggle(&mut self) {
+        self.dock = match self.dock {
+            Dock::Hidden => Dock::Right,
+            Dock::Right => Dock::Left,
+            Dock::Left => Dock::Hidden,
+        };
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.dock != Dock::Hidden
+    }
+
+    // B sets or clears a breakpoint where the program is, while the debugger is showing
+    pub fn handle_key(&mut self, key: Keycode, cpu: &mut CPU) -> bool {
+        if self.dock == Dock::Hidden || key != Keycode::B {
+            return false;
+        }
+        cpu.toggle_breakpoint(cpu.pc());
+        true
+    }
+
+    pub fn draw(&self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        let (window_width, window_height) = canvas.logical_size();
+
+        // panels that don't fit in the column wrap into a new one further from the edge
+        let (mut x, column_step) = match self.dock {
+            Dock::Hidden => return,
+            Dock::Left => (0, PANEL_WIDTH),
+            Dock::Right => (window_width as i32 - PANEL_WIDTH, -PANEL_WIDTH),
+        };
+
+        let panels = [
+            registers_panel(cpu),
+            disassembly_panel(cpu),
+            memory_panel(cpu),
+            stack_panel(cpu),
+            keys_panel(cpu),
+            display_panel(cpu),
+        ];
+
+        canvas.set_blend_mode(BlendMode::Blend);
+
+        let mut y = 0;
+        for panel in panels.iter() {
+            let height = (panel.lines.len() as i32 + 1) * LINE_HEIGHT + 2 * PANEL_PADDING;
+            if y > 0 && y + height > window_height as i32 {
+                x += column_step;
+                y = 0;
+            }
+
+            canvas.set_draw_color(PANEL_BACKGROUND);
+            canvas
+                .fill_rect(Rect::new(x, y, PANEL_WIDTH as u32, height as u32))
+                .unwrap();
+
+            let text_x = x + PANEL_PADDING;
+            let mut text_y = y + PANEL_PADDING;
+            draw_text(canvas, panel.title, text_x, text_y, TITLE_COLOR);
+
+            for (line, color) in panel.lines.iter() {
+                text_y += LINE_HEIGHT;
+                draw_text(canvas, line, text_x, text_y, *color);
+            }
+
+            y += height;
+        }
+
+        canvas.set_blend_mode(BlendMode::None);
+    }
+}
+
+fn registers_panel(cpu: &CPU) -> Panel {
+    let mut lines = vec![
+        (
+            format!("PC #{:03X}  I #{:03X}", cpu.pc(), cpu.index_register()),
+            TEXT_COLOR,
+        ),
+        (
+            format!("DT {:02X}  ST {:02X}", cpu.delay_timer(), cpu.sound_timer()),
+            TEXT_COLOR,
+        ),
+    ];
+
+    for (index, values) in cpu.v_registers().chunks(4).enumerate() {
+        let line = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("V{:X} {:02X}", index * 4 + i, value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push((line, TEXT_COLOR));
+    }
+
+    Panel {
+        title: "REGISTERS",
+        lines,
+    }
+}
+
+fn disassembly_panel(cpu: &CPU) -> Panel {
+    let memory = cpu.memory();
+    let pc = cpu.pc();
+    let start = pc.saturating_sub(DISASSEMBLY_CONTEXT * 2);
+
+    let mut lines = Vec::new();
+    for address in (start..).step_by(2).take(DISASSEMBLY_LINES as usize) {
+        let (Some(high), Some(low)) = (
+            memory.get(address as usize),
+            memory.get(address as usize + 1),
+        ) else {
+            break;
+        };
+
+        let op = (*high as u16) << 8 | *low as u16;
+        let (marker, color) = if address == pc {
+            ('>', HIGHLIGHT_COLOR)
+        } else if cpu.breakpoints().contains(&address) {
+            ('*', TEXT_COLOR)
+        } else {
+            (' ', TEXT_COLOR)
+        };
+
+        lines.push((
+            format!(
+                "{}{:03X} {:04X} {}",
+                marker,
+                address,
+                op,
+                Instruction::decode(op)
+            ),
+            color,
+        ));
+    }
+
+    Panel {
+        title: "DISASSEMBLY  B BREAKS",
+        lines,
+    }
+}
+
+// memory around I, which is where sprites and BCD/register dumps end up
+fn memory_panel(cpu: &CPU) -> Panel {
+    let memory = cpu.memory();
+    let start = cpu.index_register() as usize & !(MEMORY_ROW_BYTES - 1);
+
+    let mut lines = Vec::new();
+    for row_start in (start..memory.len())
+        .step_by(MEMORY_ROW_BYTES)
+        .take(MEMORY_ROWS)
+    {
+        let row_end = (row_start + MEMORY_ROW_BYTES).min(memory.len());
+        let bytes = memory[row_start..row_end]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        lines.push((format!("{:03X} {}", row_start, bytes), TEXT_COLOR));
+    }
+
+    Panel {
+        title: "MEMORY (I)",
+        lines,
+    }
+}
+
+// innermost call first, each entry names the subroutine that was called and where it returns to
+fn stack_panel(cpu: &CPU) -> Panel {
+    let memory = cpu.memory();
+    let stack = cpu.stack();
+
+    let mut lines = Vec::new();
+    for (depth, return_address) in stack.iter().enumerate().rev() {
+        let call_address = return_address.wrapping_sub(2) as usize;
+        let call = match (memory.get(call_address), memory.get(call_address + 1)) {
+            (Some(high), Some(low)) => Instruction::decode((*high as u16) << 8 | *low as u16),
+            _ => Instruction::Unknown(0),
+        };
+
+        let line = match call {
+            Instruction::Call(target) => {
+                format!(
+                    "{:X} SUB #{:03X} RET #{:03X}",
+                    depth, target, return_address
+                )
+            }
+            _ => format!("{:X} RET #{:03X}", depth, return_address),
+        };
+        lines.push((line, TEXT_COLOR));
+    }
+
+    if lines.is_empty() {
+        lines.push(("EMPTY".to_string(), TEXT_COLOR));
+    }
+
+    Panel {
+        title: "CALL STACK",
+        lines,
+    }
+}
+
+// the keys held down as the keypad is laid out, the program waiting on FX0A, and the last key
+// that went down
+fn keys_panel(cpu: &CPU) -> Panel {
+    let keys = cpu.keys();
+    let mut lines: Vec<(String, Color)> = KEYPAD
+        .iter()
+        .map(|row| {
+            let line = row
+                .iter()
+                .map(|&key| match keys[key] {
+                    true => format!("{:X}", key),
+                    false => ".".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            (line, TEXT_COLOR)
+        })
+        .collect();
+
+    let last = cpu
+        .last_key_pressed()
+        .map_or("-".to_string(), |key| format!("{:X}", key));
+    lines.push((format!("LAST {}", last), TEXT_COLOR));
+    if cpu.waiting_for_key() {
+        lines.push(("WAITING FOR A KEY".to_string(), HIGHLIGHT_COLOR));
+    }
+
+    Panel {
+        title: "KEYS",
+        lines,
+    }
+}
+
+// the one plane the display has, its size, how much of it is lit and where, and a shrunk copy
+fn display_panel(cpu: &CPU) -> Panel {
+    let (width, height) = cpu.resolution();
+    let mode = match cpu.display_mode() {
+        DisplayMode::Standard => "STANDARD",
+        DisplayMode::TwoPage => "TWO PAGE",
+        DisplayMode::Hires => "HIRES",
+    };
+
+    let scale = width / PLANE_COLUMNS;
+    let mut preview = vec![vec!['.'; PLANE_COLUMNS]; height.div_ceil(scale)];
+    let mut lit = 0;
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (index, pixel) in cpu.pixels().enumerate() {
+        if !pixel {
+            continue;
+        }
+        let (x, y) = (index % width, index / width);
+        lit += 1;
+        preview[y / scale][x / scale] = '#';
+        bounds = Some(match bounds {
+            Some((left, top, right, bottom)) => {
+                (left.min(x), top.min(y), right.max(x), bottom.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    let mut lines = vec![
+        (format!("PLANE 1 {}X{} {}", width, height, mode), TEXT_COLOR),
+        (format!("LIT {}/{}", lit, width * height), TEXT_COLOR),
+    ];
+    if let Some((left, top, right, bottom)) = bounds {
+        lines.push((
+            format!("X {}-{} Y {}-{}", left, right, top, bottom),
+            TEXT_COLOR,
+        ));
+    }
+    for row in preview {
+        lines.push((row.into_iter().collect(), HIGHLIGHT_COLOR));
+    }
+
+    Panel {
+        title: "DISPLAY PLANES",
+        lines,
+    }
+}
@@ -0,0 +1,68 @@
+// a few freely distributed programs built into the emulator, so there's something to run
+// straight away and screenshots and tests don't depend on files lying around
+use crate::error::ConfigError;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Demo {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub rom: &'static [u8],
+}
+
+pub const DEMOS: &[Demo] = &[
+    Demo {
+        name: "maze",
+        title: "Maze (alt) by David Winter",
+        rom: include_bytes!("../roms/Maze (alt) [David Winter, 199x].ch8"),
+    },
+    Demo {
+        name: "particles",
+        title: "Particle Demo by zeroZshadow, 2008",
+        rom: include_bytes!("../roms/Particle Demo [zeroZshadow, 2008].ch8"),
+    },
+    Demo {
+        name: "airplane",
+        title: "Airplane, drop packages with 8",
+        rom: include_bytes!("../roms/Airplane.ch8"),
+    },
+];
+
+pub fn find(name: &str) -> Result<&'static Demo, ConfigError> {
+    DEMOS.iter().find(|demo| demo.name == name).ok_or_else(|| {
+        let names: Vec<_> = DEMOS.iter().map(|demo| demo.name).collect();
+        ConfigError::Invalid(format!(
+            "unknown demo {}, expected {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+    use crate::quirks::Quirks;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("maze").unwrap().name, "maze");
+        assert_eq!(
+            find("pong").unwrap_err().to_string(),
+            "unknown demo pong, expected maze, particles, airplane"
+        );
+    }
+
+    #[test]
+    fn test_demos_run() {
+        for demo in DEMOS {
+            let cpu = headless::run(demo.rom, Quirks::default(), 0xC8, 60, &[]);
+            assert!(cpu.is_ok(), "{} stopped: {:?}", demo.name, cpu.err());
+            assert!(
+                cpu.unwrap().pixels().any(|on| on),
+                "{} drew nothing",
+                demo.name
+            );
+        }
+    }
+}
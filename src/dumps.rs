@@ -0,0 +1,123 @@
+// spotting bad dumps of a ROM, since a game that glitches is often just a bad file. a dump that
+// starts with a known good one and carries on is an overdump, and one that's padded out to a round
+// size or holds the whole of memory was probably read off something bigger than the program
+use crate::cpu::{MEMORY_SIZE, START_ADDRESS};
+use crate::patch::crc32;
+
+// trailing 00 or FF bytes past this many look like padding rather than a program's data
+const MIN_PADDING: usize = 256;
+
+pub struct GoodDump {
+    pub name: &'static str,
+    pub size: usize,
+    pub crc32: u32,
+}
+
+// the ROMs that come with the emulator
+pub const GOOD_DUMPS: &[GoodDump] = &[
+    GoodDump {
+        name: "Addition Problems [Paul C. Moews]",
+        size: 168,
+        crc32: 0xC20DC1AB,
+    },
+    GoodDump {
+        name: "Airplane",
+        size: 356,
+        crc32: 0x6FD89B3D,
+    },
+    GoodDump {
+        name: "Maze (alt) [David Winter, 199x]",
+        size: 38,
+        crc32: 0x39199FE2,
+    },
+    GoodDump {
+        name: "Particle Demo [zeroZshadow, 2008]",
+        size: 353,
+        crc32: 0x53B431FC,
+    },
+];
+
+// what looks wrong with the dump and how to get a good one, None if nothing does
+pub fn check(rom: &[u8]) -> Option<String> {
+    if GOOD_DUMPS.iter().any(|good| is_dump(rom, good)) {
+        return None;
+    }
+
+    if let Some(good) = GOOD_DUMPS
+        .iter()
+        .find(|good| good.size < rom.len() && is_dump(&rom[..good.size], good))
+    {
+        return Some(format!(
+            "this is {} with {} extra bytes on the end, which can make it glitch. \
+             the good dump is its first {} bytes, CRC-32 {:08X}",
+            good.name,
+            rom.len() - good.size,
+            good.size,
+            good.crc32
+        ));
+    }
+
+    if rom.len() == MEMORY_SIZE {
+        return Some(format!(
+            "this looks like a dump of all of memory, the program is the {} bytes from #{:03X}",
+            MEMORY_SIZE - START_ADDRESS as usize,
+            START_ADDRESS
+        ));
+    }
+
+    let padding = trailing_padding(rom);
+    if padding >= MIN_PADDING && rom.len().is_power_of_two() {
+        return Some(format!(
+            "the ROM is padded out to {} bytes with {} bytes of #{:02X} on the end, it may \
+             be an overdump. the good dump is likely the first {} bytes",
+            rom.len(),
+            padding,
+            rom[rom.len() - 1],
+            rom.len() - padding
+        ));
+    }
+    None
+}
+
+fn is_dump(rom: &[u8], good: &GoodDump) -> bool {
+    rom.len() == good.size && crc32(rom) == good.crc32
+}
+
+// how many bytes at the end are all 00 or all FF
+fn trailing_padding(rom: &[u8]) -> usize {
+    match rom.last() {
+        Some(&last) if last == 0x00 || last == 0xFF => {
+            rom.iter().rev().take_while(|&&byte| byte == last).count()
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demos;
+
+    #[test]
+    fn test_check() {
+        let airplane = demos::find("airplane").unwrap().rom;
+        assert_eq!(check(airplane), None);
+
+        let overdump = [airplane, &[0x12, 0x00, 0x00, 0x00]].concat();
+        assert_eq!(
+            check(&overdump).unwrap(),
+            "this is Airplane with 4 extra bytes on the end, which can make it glitch. \
+             the good dump is its first 356 bytes, CRC-32 6FD89B3D"
+        );
+
+        let mut memory = vec![0; MEMORY_SIZE];
+        memory[START_ADDRESS as usize] = 0x12;
+        assert!(check(&memory).unwrap().contains("all of memory"));
+
+        let mut padded = vec![0xFF; 1024];
+        padded[..2].copy_from_slice(&[0x12, 0x00]);
+        assert!(check(&padded).unwrap().ends_with("the first 2 bytes"));
+        // the same bytes at the end of a program that isn't a round size are just its data
+        assert_eq!(check(&padded[..1000]), None);
+    }
+}
@@ -0,0 +1,225 @@
+// draws the screen onto anything implementing embedded-graphics' DrawTarget, e.g. an SSD1306 or
+// ILI9341 driver. it only needs core, and remembers the rows it last drew so a frame sends just
+// the ones that changed, which is most of the time none of them over a slow SPI or I2C bus
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::PixelColor,
+    primitives::Rectangle,
+};
+
+use crate::cpu::{CPU, HIRES_HEIGHT, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub struct Display<C> {
+    on: C,
+    off: C,
+    // XO-CHIP pixels lit on only the second plane and on both, `on` unless the panel has colours
+    second: C,
+    both: C,
+    scale: u32,
+    offset: Point,
+    // the resolution last drawn at, None until the first frame, and both planes' rows then
+    resolution: Option<(usize, usize)>,
+    rows: [(u128, u128); HIRES_HEIGHT],
+}
+
+impl<C: PixelColor> Display<C> {
+    pub fn new(on: C, off: C) -> Display<C> {
+        Display {
+            on,
+            off,
+            second: on,
+            both: on,
+            scale: 1,
+            offset: Point::zero(),
+            resolution: None,
+            rows: [(0, 0); HIRES_HEIGHT],
+        }
+    }
+
+    // the low resolution display becomes 64*scale by 32*scale pixels, the high resolution one
+    // fits the same area. a 128x64 SSD1306 fits scale 2
+    pub fn with_scale(mut self, scale: u32) -> Display<C> {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Point) -> Display<C> {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_plane_colors(mut self, second: C, both: C) -> Display<C> {
+        self.second = second;
+        self.both = both;
+        self
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32) * self.scale
+    }
+
+    // draws the rows that changed since the last call, or all of them the first time and after
+    // the program switches resolution
+    pub fn draw<D: DrawTarget<Color = C>>(
+        &mut self,
+        cpu: &CPU,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let resolution = cpu.resolution();
+        let (width, height) = resolution;
+        let everything = self.resolution != Some(resolution);
+
+        let size = self.size();
+        let column = |x: usize| (x * size.width as usize / width) as i32;
+        let row = |y: usize| (y * size.height as usize / height) as i32;
+        for (y, drawn) in self.rows.iter_mut().enumerate().take(height) {
+            let planes = (cpu.screen.row(y), cpu.second_plane.row(y));
+            if !everything && planes == *drawn {
+                continue;
+            }
+
+            let top = row(y);
+            let bottom = row(y + 1);
+            for x in 0..width {
+                let lit = |plane: u128| plane << x >> 127 != 0;
+                let color = match (lit(planes.0), lit(planes.1)) {
+                    (false, false) => self.off,
+                    (true, false) => self.on,
+                    (false, true) => self.second,
+                    (true, true) => self.both,
+                };
+                let left = column(x);
+                let area = Rectangle::new(
+                    self.offset + Point::new(left, top),
+                    Size::new((column(x + 1) - left) as u32, (bottom - top) as u32),
+                );
+                target.fill_solid(&area, color)?;
+            }
+            *drawn = planes;
+        }
+
+        self.resolution = Some(resolution);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quirks::Quirks;
+    use embedded_graphics_core::{
+        geometry::OriginDimensions,
+        pixelcolor::{BinaryColor, Gray8},
+        Pixel,
+    };
+    use std::convert::Infallible;
+
+    struct Buffer<C> {
+        pixels: Vec<C>,
+        width: usize,
+        // pixels drawn, to see what a frame sent
+        drawn: usize,
+    }
+
+    impl<C: PixelColor> Buffer<C> {
+        fn new(width: usize, height: usize, color: C) -> Buffer<C> {
+            Buffer {
+                pixels: vec![color; width * height],
+                width,
+                drawn: 0,
+            }
+        }
+
+        fn find(&self, color: C) -> Vec<usize> {
+            (0..self.pixels.len())
+                .filter(|&index| self.pixels[index] == color)
+                .collect()
+        }
+    }
+
+    impl<C: PixelColor> OriginDimensions for Buffer<C> {
+        fn size(&self) -> Size {
+            Size::new(self.width as u32, (self.pixels.len() / self.width) as u32)
+        }
+    }
+
+    impl<C: PixelColor> DrawTarget for Buffer<C> {
+        type Color = C;
+        type Error = Infallible;
+
+        fn draw_iter<I: IntoIterator<Item = Pixel<C>>>(
+            &mut self,
+            pixels: I,
+        ) -> Result<(), Infallible> {
+            for Pixel(point, color) in pixels {
+                self.pixels[point.y as usize * self.width + point.x as usize] = color;
+                self.drawn += 1;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut cpu = CPU::new();
+        cpu.screen.set(1, true);
+
+        let mut display = Display::new(BinaryColor::On, BinaryColor::Off)
+            .with_scale(2)
+            .with_offset(Point::new(0, 1));
+        let mut buffer = Buffer::new(128, 66, BinaryColor::Off);
+        display.draw(&cpu, &mut buffer).unwrap();
+
+        assert_eq!(display.size(), Size::new(128, 64));
+        assert_eq!(
+            buffer.find(BinaryColor::On),
+            [128 + 2, 128 + 3, 256 + 2, 256 + 3]
+        );
+        assert_eq!(buffer.drawn, 128 * 64);
+
+        // nothing changed, nothing is sent
+        buffer.drawn = 0;
+        display.draw(&cpu, &mut buffer).unwrap();
+        assert_eq!(buffer.drawn, 0);
+
+        // clearing the pixel redraws only its row
+        cpu.screen.set(1, false);
+        display.draw(&cpu, &mut buffer).unwrap();
+        assert!(buffer.find(BinaryColor::On).is_empty());
+        assert_eq!(buffer.drawn, 128 * 2);
+    }
+
+    #[test]
+    fn test_hires() {
+        let mut cpu = CPU::new();
+        cpu.set_quirks(Quirks::schip());
+        // 00FF, then a pixel lit at (127, 63)
+        cpu.load(&[0x00, 0xFF]);
+        cpu.tick().unwrap();
+        cpu.screen.set(128 * 64 - 1, true);
+
+        let mut display = Display::new(BinaryColor::On, BinaryColor::Off).with_scale(2);
+        let mut buffer = Buffer::new(128, 64, BinaryColor::Off);
+        display.draw(&cpu, &mut buffer).unwrap();
+        assert_eq!(buffer.find(BinaryColor::On), [128 * 64 - 1]);
+    }
+
+    #[test]
+    fn test_planes() {
+        let mut cpu = CPU::new();
+        cpu.screen.set(0, true);
+        cpu.second_plane.set(1, true);
+        cpu.screen.set(2, true);
+        cpu.second_plane.set(2, true);
+
+        let mut display = Display::new(Gray8::new(1), Gray8::new(0))
+            .with_plane_colors(Gray8::new(2), Gray8::new(3));
+        let mut buffer = Buffer::new(64, 32, Gray8::new(9));
+        display.draw(&cpu, &mut buffer).unwrap();
+        assert_eq!(
+            buffer.pixels[..4],
+            [Gray8::new(1), Gray8::new(2), Gray8::new(3), Gray8::new(0)]
+        );
+    }
+}
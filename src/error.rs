@@ -0,0 +1,111 @@
+// what can go wrong using the core, one type for each part so callers can match on the cause
+// rather than a message. Error holds any of them, for code that just passes failures up
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::io;
+
+use thiserror::Error;
+
+#[cfg(feature = "std")]
+use crate::cpu::CpuError;
+use crate::cpu::MAX_STACK_SIZE;
+
+#[cfg(feature = "std")]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Cpu(#[from] CpuError),
+    #[error(transparent)]
+    Rom(#[from] RomError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    State(#[from] StateError),
+    #[error(transparent)]
+    Metadata(#[from] MetadataError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum RomError {
+    #[error("the ROM is empty")]
+    Empty,
+    // the program and the room after the start address, in bytes
+    #[error("a {size} byte ROM doesn't fit in the {room} bytes of memory for it")]
+    TooLarge { size: usize, room: usize },
+}
+
+// the command line, the settings file and the machine they set up
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ConfigError {
+    #[error("unknown option {0}")]
+    UnknownOption(String),
+    #[error("unexpected argument {0}")]
+    UnexpectedArgument(String),
+    #[error("{0} needs a value")]
+    MissingValue(String),
+    #[error("no ROM given")]
+    NoRom,
+    // a name that isn't one of compliance::presets()
+    #[error("unknown variant {0}")]
+    UnknownVariant(String),
+    // a line of the settings file that can't be read, counting from 1
+    #[error("line {line}: {message}")]
+    Line { line: usize, message: String },
+    // a call stack smaller than min, which is 1 or the return addresses already on it, or bigger
+    // than any machine's
+    #[error(
+        "the stack must hold from {min} to {max} return addresses, not {size}",
+        max = MAX_STACK_SIZE
+    )]
+    InvalidStackSize { size: usize, min: usize },
+    // a value that doesn't make sense, or options that don't go together
+    #[error("{0}")]
+    Invalid(String),
+}
+
+// a saved state
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum StateError {
+    #[error("the state isn't valid JSON: {0}")]
+    Syntax(String),
+    // JSON, but not a state this can load
+    #[error("{0}")]
+    Invalid(String),
+}
+
+// a ROM's sidecar metadata
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum MetadataError {
+    #[error("the metadata isn't valid JSON: {0}")]
+    Syntax(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors() {
+        let error: Error = CpuError::StackOverflow.into();
+        assert!(matches!(error, Error::Cpu(CpuError::StackOverflow)));
+        assert_eq!(error.to_string(), "stack overflow");
+
+        let error: Error = RomError::TooLarge {
+            size: 5000,
+            room: 3584,
+        }
+        .into();
+        assert_eq!(
+            error.to_string(),
+            "a 5000 byte ROM doesn't fit in the 3584 bytes of memory for it"
+        );
+
+        let error: Error = ConfigError::Invalid("invalid speed 0".to_string()).into();
+        assert!(matches!(error, Error::Config(ConfigError::Invalid(_))));
+        assert_eq!(error.to_string(), "invalid speed 0");
+    }
+}
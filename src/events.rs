@@ -0,0 +1,118 @@
+// things that happen inside the machine that a frontend, script or plugin may want to react to.
+// the CPU queues them as it runs and the bus hands them out to whoever subscribed, so nothing
+// has to keep looking at the timers or screen to notice a change
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::cpu::{CpuError, CPU};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    DisplayCleared,
+    // a sprite drawn turned pixels on or off, or the display switched resolution
+    DisplayChanged,
+    // an FX0A found no key down and is waiting for one, sent once when the wait starts
+    WaitingForKey,
+    // the sound timer went from zero to something else, and back again
+    SoundStarted,
+    SoundStopped,
+    // the size of the program loaded
+    RomLoaded(usize),
+    // execution reached an address with a breakpoint on it, before running the instruction there
+    Breakpoint(u16),
+    // the program stopped with an error and can't go on
+    Halted(CpuError),
+}
+
+type Subscriber = Box<dyn FnMut(&Event)>;
+
+pub struct EventBus {
+    subscribers: Vec<Subscriber>,
+}
+
+impl Default for EventBus {
+    fn default() -> EventBus {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus {
+            subscribers: Vec::new(),
+        }
+    }
+
+    // subscribers are called in the order they subscribed, with every event
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&Event) + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    pub fn publish(&mut self, event: &Event) {
+        for subscriber in self.subscribers.iter_mut() {
+            subscriber(event);
+        }
+    }
+
+    // passes on everything the CPU has queued since the last time
+    pub fn dispatch(&mut self, cpu: &mut CPU) {
+        for event in cpu.drain_events() {
+            self.publish(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_dispatch() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = EventBus::new();
+        let log = seen.clone();
+        bus.subscribe(move |event| log.borrow_mut().push(*event));
+
+        // CLS, ST = V0 with V0 = 2, then a breakpoint on the jump to nowhere after it
+        let mut cpu = CPU::new();
+        cpu.load(&[0x00, 0xE0, 0x60, 0x02, 0xF0, 0x18, 0x1F, 0xFF]);
+        cpu.toggle_breakpoint(0x206);
+        for _ in 0..3 {
+            cpu.tick().unwrap();
+        }
+        cpu.tick_timers();
+        cpu.tick_timers();
+        bus.dispatch(&mut cpu);
+        assert_eq!(
+            *seen.borrow(),
+            [
+                Event::RomLoaded(8),
+                Event::DisplayCleared,
+                Event::SoundStarted,
+                Event::Breakpoint(0x206),
+                Event::SoundStopped,
+            ]
+        );
+
+        seen.borrow_mut().clear();
+        cpu.tick().unwrap();
+        assert!(cpu.tick().is_err());
+        bus.dispatch(&mut cpu);
+        assert_eq!(
+            *seen.borrow(),
+            [Event::Halted(CpuError::AddressOutOfRange(0x1000))]
+        );
+    }
+
+    #[test]
+    fn test_pending_limit() {
+        // a CPU nobody takes events from keeps only the first ones
+        let mut cpu = CPU::new();
+        cpu.load(&[0x00, 0xE0, 0x12, 0x00]);
+        for _ in 0..10_000 {
+            cpu.tick().unwrap();
+        }
+        assert!(cpu.take_events().len() < 1000);
+        assert!(cpu.take_events().is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+// what an instruction just did, in plain English with the values it used, for people learning
+// how CHIP-8 works: "8014: add V1 (0x30) to V0 (0xF0), giving 0x20, carry set"
+use crate::cpu::CPU;
+use crate::instruction::Instruction;
+
+// takes the machine as it was before and after running `op`
+pub fn explain(op: u16, before: &CPU, after: &CPU) -> String {
+    let v = |x: u8| before.v_registers()[x as usize];
+    let result = |x: u8| after.v_registers()[x as usize];
+    let flag = after.v_registers()[0xF];
+    let skipped = if after.pc() == before.pc().wrapping_add(4) {
+        "skipped"
+    } else {
+        "not skipped"
+    };
+
+    let description = match Instruction::decode(op) {
+        Instruction::Nop => "do nothing".to_string(),
+        Instruction::Cls => "clear the screen".to_string(),
+        Instruction::Ret => format!("return from a subroutine to #{:03X}", after.pc()),
+        Instruction::Jump(nnn) => format!("jump to #{:03X}", nnn),
+        Instruction::Call(nnn) => format!(
+            "call the subroutine at #{:03X}, coming back to #{:03X} when it returns",
+            nnn,
+            before.pc().wrapping_add(2)
+        ),
+        Instruction::SkipEqImm { x, nn } => format!(
+            "skip the next instruction if V{:X} (0x{:02X}) is 0x{:02X}: {}",
+            x,
+            v(x),
+            nn,
+            skipped
+        ),
+        Instruction::SkipNeImm { x, nn } => format!(
+            "skip the next instruction if V{:X} (0x{:02X}) is not 0x{:02X}: {}",
+            x,
+            v(x),
+            nn,
+            skipped
+        ),
+        Instruction::SkipEqReg { x, y } => format!(
+            "skip the next instruction if V{:X} (0x{:02X}) equals V{:X} (0x{:02X}): {}",
+            x,
+            v(x),
+            y,
+            v(y),
+            skipped
+        ),
+        Instruction::SkipNeReg { x, y } => format!(
+            "skip the next instruction if V{:X} (0x{:02X}) differs from V{:X} (0x{:02X}): {}",
+            x,
+            v(x),
+            y,
+            v(y),
+            skipped
+        ),
+        Instruction::LoadImm { x, nn } => format!("set V{:X} to 0x{:02X}", x, nn),
+        Instruction::AddImm { x, nn } => format!(
+            "add 0x{:02X} to V{:X} (0x{:02X}), giving 0x{:02X}, VF is left alone",
+            nn,
+            x,
+            v(x),
+            result(x)
+        ),
+        Instruction::LoadReg { x, y } => format!("copy V{:X} (0x{:02X}) into V{:X}", y, v(y), x),
+        Instruction::Or { x, y } => bitwise("OR", x, y, v(x), v(y), result(x)),
+        Instruction::And { x, y } => bitwise("AND", x, y, v(x), v(y), result(x)),
+        Instruction::Xor { x, y } => bitwise("XOR", x, y, v(x), v(y), result(x)),
+        Instruction::AddReg { x, y } => format!(
+            "add V{:X} (0x{:02X}) to V{:X} (0x{:02X}), giving 0x{:02X}, {}",
+            y,
+            v(y),
+            x,
+            v(x),
+            result(x),
+            if flag == 1 { "carry set" } else { "no carry" }
+        ),
+        Instruction::SubReg { x, y } => format!(
+            "subtract V{:X} (0x{:02X}) from V{:X} (0x{:02X}), giving 0x{:02X}, {}",
+            y,
+            v(y),
+            x,
+            v(x),
+            result(x),
+            if flag == 1 { "no borrow" } else { "borrow" }
+        ),
+        Instruction::SubnReg { x, y } => format!(
+            "subtract V{:X} (0x{:02X}) from V{:X} (0x{:02X}) into V{:X}, giving 0x{:02X}, {}",
+            x,
+            v(x),
+            y,
+            v(y),
+            x,
+            result(x),
+            if flag == 1 { "no borrow" } else { "borrow" }
+        ),
+        Instruction::ShiftRight { x, .. } => format!(
+            "shift right by one bit into V{:X}, giving 0x{:02X}, VF gets the bit shifted out ({})",
+            x,
+            result(x),
+            flag
+        ),
+        Instruction::ShiftLeft { x, .. } => format!(
+            "shift left by one bit into V{:X}, giving 0x{:02X}, VF gets the bit shifted out ({})",
+            x,
+            result(x),
+            flag
+        ),
+        Instruction::LoadIndex(nnn) => format!("point I at #{:03X}", nnn),
+        Instruction::JumpV0(nnn) => format!(
+            "jump to #{:03X} plus a register, landing on #{:03X}",
+            nnn,
+            after.pc()
+        ),
+        Instruction::Random { x, nn } => format!(
+            "set V{:X} to a random number masked with 0x{:02X}, giving 0x{:02X}",
+            x,
+            nn,
+            result(x)
+        ),
+        Instruction::Draw { x, y, n } => format!(
+            "draw the {} byte sprite at I (#{:03X}) at V{:X}, V{:X} ({}, {}), {}",
+            n,
+            before.index_register(),
+            x,
+            y,
+            v(x),
+            v(y),
+            if flag == 1 {
+                "pixels were turned off so VF is 1"
+            } else {
+                "no pixels were turned off so VF is 0"
+            }
+        ),
+        Instruction::SkipKey { x } => format!(
+            "skip the next instruction if key {:X} (from V{:X}) is held: {}",
+            v(x) & 0xF,
+            x,
+            skipped
+        ),
+        Instruction::SkipNotKey { x } => format!(
+            "skip the next instruction if key {:X} (from V{:X}) is not held: {}",
+            v(x) & 0xF,
+            x,
+            skipped
+        ),
+        Instruction::LoadDelay { x } => format!(
+            "copy the delay timer (0x{:02X}) into V{:X}",
+            before.delay_timer(),
+            x
+        ),
+        Instruction::WaitKey { x } => {
+            if after.pc() == before.pc() {
+                format!("wait for a key press to put in V{:X}, none yet", x)
+            } else {
+                format!("wait for a key press, key {:X} goes in V{:X}", result(x), x)
+            }
+        }
+        Instruction::SetDelay { x } => {
+            format!("set the delay timer to V{:X} (0x{:02X})", x, v(x))
+        }
+        Instruction::SetSound { x } => {
+            format!(
+                "set the sound timer to V{:X} (0x{:02X}), beeping until it runs out",
+                x,
+                v(x)
+            )
+        }
+        Instruction::AddIndex { x } => format!(
+            "add V{:X} (0x{:02X}) to I (#{:03X}), giving #{:03X}",
+            x,
+            v(x),
+            before.index_register(),
+            after.index_register()
+        ),
+        Instruction::LoadFont { x } => format!(
+            "point I at the font sprite for digit {:X} (from V{:X}), #{:03X}",
+            v(x) & 0xF,
+            x,
+            after.index_register()
+        ),
+        Instruction::Bcd { x } => format!(
+            "store the decimal digits of V{:X} ({}) at I (#{:03X}): {}, {}, {}",
+            x,
+            v(x),
+            before.index_register(),
+            v(x) / 100,
+            v(x) / 10 % 10,
+            v(x) % 10
+        ),
+        Instruction::StoreRegs { x } => format!(
+            "store V0 to V{:X} in memory starting at I (#{:03X})",
+            x,
+            before.index_register()
+        ),
+        Instruction::LoadRegs { x } => format!(
+            "load V0 to V{:X} from memory starting at I (#{:03X})",
+            x,
+            before.index_register()
+        ),
+        Instruction::Unknown(_) => "not an instruction CHIP-8 knows".to_string(),
+    };
+
+    format!("{:04X}: {}", op, description)
+}
+
+fn bitwise(name: &str, x: u8, y: u8, vx: u8, vy: u8, result: u8) -> String {
+    format!(
+        "V{:X} (0x{:02X}) {} V{:X} (0x{:02X}) gives 0x{:02X} in V{:X}",
+        x, vx, name, y, vy, result, x
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // runs the instructions, explaining the last one
+    fn explain_last(rom: &[u8]) -> String {
+        let mut cpu = CPU::new();
+        cpu.load(rom);
+        for _ in 1..rom.len() / 2 {
+            cpu.tick().unwrap();
+        }
+        let before = cpu.clone();
+        let op = cpu.opcode_at(cpu.pc());
+        cpu.tick().unwrap();
+        explain(op, &before, &cpu)
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(
+            explain_last(&[0x60, 0xF0, 0x61, 0x30, 0x80, 0x14]),
+            "8014: add V1 (0x30) to V0 (0xF0), giving 0x20, carry set"
+        );
+        assert_eq!(
+            explain_last(&[0x60, 0x05, 0x30, 0x05]),
+            "3005: skip the next instruction if V0 (0x05) is 0x05: skipped"
+        );
+        assert_eq!(
+            explain_last(&[0x6A, 0x7B, 0xA3, 0x00, 0xFA, 0x33]),
+            "FA33: store the decimal digits of VA (123) at I (#300): 1, 2, 3"
+        );
+        assert_eq!(
+            explain_last(&[0x22, 0x04]),
+            "2204: call the subroutine at #204, coming back to #202 when it returns"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+// downloads ROMs given as http(s) URLs, so games can be played straight from an archive. each
+// download is kept in the user's cache directory and used instead of the network next time
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::cpu::{MEMORY_SIZE, START_ADDRESS};
+use crate::library::cache_dir;
+use crate::patch::crc32;
+
+// anything bigger couldn't be loaded anyway
+const MAX_ROM_SIZE: usize = MEMORY_SIZE - START_ADDRESS as usize;
+
+pub fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    fetch_cached(url, cache_dir().as_deref())
+}
+
+fn fetch_cached(url: &str, cache: Option<&Path>) -> Result<Vec<u8>, String> {
+    let path = cache.map(|dir| cache_path(dir, url));
+    if let Some(rom) = path.as_ref().and_then(|path| fs::read(path).ok()) {
+        return Ok(rom);
+    }
+
+    let rom = download(url)?;
+    // a cache that can't be written is only slower, not an error
+    if let Some(path) = path {
+        let _ = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(path, &rom));
+    }
+    Ok(rom)
+}
+
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url).call().map_err(|error| error.to_string())?;
+
+    let mut rom = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_ROM_SIZE as u64 + 1)
+        .read_to_end(&mut rom)
+        .map_err(|error| error.to_string())?;
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(format!(
+            "more than {} bytes, too big for a ROM",
+            MAX_ROM_SIZE
+        ));
+    }
+    Ok(rom)
+}
+
+// the checksum keeps URLs with the same file name apart, the name is for people looking in there
+fn cache_path(dir: &Path, url: &str) -> PathBuf {
+    let name = url
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("rom");
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    dir.join(format!("{:08x}-{}", crc32(url.as_bytes()), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    // answers one request with the body, returning the URL to ask for
+    fn serve(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/games/pong.ch8", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            // the client may hang up early on a body that's too big
+            let _ = stream.write_all(&body);
+        });
+        url
+    }
+
+    #[test]
+    fn test_cache_path() {
+        let dir = Path::new("cache");
+        let path = cache_path(dir, "https://example.com/games/Space Invaders.ch8");
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.ends_with("-Space_Invaders.ch8"));
+        assert_ne!(
+            path,
+            cache_path(dir, "https://example.org/games/Space Invaders.ch8")
+        );
+        let path = cache_path(dir, "https://example.com/");
+        assert!(path.to_str().unwrap().ends_with("-rom"));
+    }
+
+    #[test]
+    fn test_fetch() {
+        let cache = env::temp_dir().join(format!("rusty_chip8-fetch-{}", std::process::id()));
+        let url = serve(vec![0x12, 0x00]);
+        assert_eq!(fetch_cached(&url, Some(&cache)).unwrap(), vec![0x12, 0x00]);
+        // the server only answers once, so this comes from the cache
+        assert_eq!(fetch_cached(&url, Some(&cache)).unwrap(), vec![0x12, 0x00]);
+        fs::remove_dir_all(&cache).unwrap();
+
+        let url = serve(vec![0; MAX_ROM_SIZE + 1]);
+        assert!(fetch_cached(&url, None).is_err());
+    }
+}
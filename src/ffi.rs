@@ -0,0 +1,256 @@
+// the core behind a plain C API, for embedding it in C, C# or a game engine. build it with
+//   cargo build --release --no-default-features --features ffi
+// and link against target/release/libchip8.so (chip8.dll, libchip8.dylib) with these declarations:
+//
+//   typedef struct Chip8 Chip8;
+//   Chip8 *chip8_create(void);
+//   void chip8_destroy(Chip8 *machine);
+//   bool chip8_load(Chip8 *machine, const uint8_t *rom, size_t length);
+//   bool chip8_tick(Chip8 *machine);     false once the program has stopped with an error
+//   bool chip8_frame(Chip8 *machine);    a 60Hz frame of ticks, then the timers
+//   void chip8_get_resolution(const Chip8 *machine, size_t *width, size_t *height);
+//   size_t chip8_get_framebuffer(const Chip8 *machine, uint8_t *buffer, size_t length);
+//   void chip8_set_key(Chip8 *machine, uint8_t key, bool pressed);
+//   bool chip8_beeping(const Chip8 *machine);
+//   typedef void (*Chip8SoundCallback)(bool on, void *user_data);
+//   void chip8_set_sound_callback(Chip8 *machine, Chip8SoundCallback callback, void *user_data);
+//
+// the sound callback is called from chip8_tick and chip8_frame as the sound timer starts and
+// stops, so an audio backend can start and stop its tone on the instruction rather than whenever
+// it next asks chip8_beeping. a beep too short to last until the next frame is still heard
+//
+// a machine belongs to whoever created it and is used from one thread at a time
+#![allow(clippy::missing_safety_doc)]
+
+use std::{ffi::c_void, slice};
+
+use crate::cpu::{CPU, START_ADDRESS};
+use crate::events::Event;
+
+pub type SoundCallback = extern "C" fn(on: bool, user_data: *mut c_void);
+
+pub struct Chip8 {
+    cpu: CPU,
+    // the program hit an error, ticking does nothing more
+    stopped: bool,
+    // told when the sound starts and stops, with the pointer it was set with
+    sound_callback: Option<(SoundCallback, *mut c_void)>,
+}
+
+#[no_mangle]
+pub extern "C" fn chip8_create() -> *mut Chip8 {
+    Box::into_raw(Box::new(Chip8 {
+        cpu: CPU::new(),
+        stopped: false,
+        sound_callback: None,
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(machine: *mut Chip8) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+// false if the ROM is empty or doesn't fit, the machine is left as it was
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load(machine: *mut Chip8, rom: *const u8, length: usize) -> bool {
+    let Some(machine) = machine.as_mut() else {
+        return false;
+    };
+    // a length that can't fit is turned away before it's trusted to be the buffer's
+    if rom.is_null() || length > machine.cpu.memory().len() - START_ADDRESS as usize {
+        return false;
+    }
+    if machine
+        .cpu
+        .try_load(slice::from_raw_parts(rom, length))
+        .is_err()
+    {
+        return false;
+    }
+
+    machine.stopped = false;
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick(machine: *mut Chip8) -> bool {
+    let Some(machine) = machine.as_mut() else {
+        return false;
+    };
+    tick(machine)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chip8_frame(machine: *mut Chip8) -> bool {
+    let Some(machine) = machine.as_mut() else {
+        return false;
+    };
+    if !machine.stopped {
+        match machine.cpu.run_frame() {
+            Ok(events) => report_sound(machine.sound_callback, events),
+            Err(_) => machine.stopped = true,
+        }
+    }
+    // whatever the frame got through before the program stopped
+    report_sound(machine.sound_callback, machine.cpu.drain_events());
+    !machine.stopped
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chip8_get_resolution(
+    machine: *const Chip8,
+    width: *mut usize,
+    height: *mut usize,
+) {
+    let Some(machine) = machine.as_ref() else {
+        return;
+    };
+    let (screen_width, screen_height) = machine.cpu.resolution();
+    if let Some(width) = width.as_mut() {
+        *width = screen_width;
+    }
+    if let Some(height) = height.as_mut() {
+        *height = screen_height;
+    }
+}
+
+// copies up to `length` pixels into `buffer` a row at a time, 1 for lit, and returns how many the
+// screen has. a null buffer just asks for the size
+#[no_mangle]
+pub unsafe extern "C" fn chip8_get_framebuffer(
+    machine: *const Chip8,
+    buffer: *mut u8,
+    length: usize,
+) -> usize {
+    let Some(machine) = machine.as_ref() else {
+        return 0;
+    };
+    let pixels = machine.cpu.pixels();
+    let size = pixels.len();
+    if !buffer.is_null() {
+        let buffer = slice::from_raw_parts_mut(buffer, length.min(size));
+        for (byte, on) in buffer.iter_mut().zip(pixels) {
+            *byte = on as u8;
+        }
+    }
+    size
+}
+
+// keys past F are ignored
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(machine: *mut Chip8, key: u8, pressed: bool) {
+    if let Some(machine) = machine.as_mut() {
+        let _ = machine.cpu.keypress(key as usize, pressed);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chip8_beeping(machine: *const Chip8) -> bool {
+    machine
+        .as_ref()
+        .is_some_and(|machine| machine.cpu.sound_timer() > 0)
+}
+
+// a null callback stops the calls
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_sound_callback(
+    machine: *mut Chip8,
+    callback: Option<SoundCallback>,
+    user_data: *mut c_void,
+) {
+    if let Some(machine) = machine.as_mut() {
+        machine.sound_callback = callback.map(|callback| (callback, user_data));
+    }
+}
+
+fn tick(machine: &mut Chip8) -> bool {
+    if !machine.stopped && machine.cpu.tick().is_err() {
+        machine.stopped = true;
+    }
+    report_sound(machine.sound_callback, machine.cpu.drain_events());
+    !machine.stopped
+}
+
+// the other events are taken too, so they don't pile up with nobody to take them
+fn report_sound(
+    sound_callback: Option<(SoundCallback, *mut c_void)>,
+    events: impl IntoIterator<Item = Event>,
+) {
+    for event in events {
+        match (event, sound_callback) {
+            (Event::SoundStarted, Some((callback, user_data))) => callback(true, user_data),
+            (Event::SoundStopped, Some((callback, user_data))) => callback(false, user_data),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::MEMORY_SIZE;
+    use std::ptr;
+
+    extern "C" fn record_edge(on: bool, user_data: *mut c_void) {
+        unsafe { (*(user_data as *mut Vec<bool>)).push(on) }
+    }
+
+    #[test]
+    fn test_machine() {
+        unsafe {
+            // set the sound timer, then draw the 0 glyph at 0,0
+            let rom = [0x60, 0x20, 0xF0, 0x18, 0xD1, 0x15, 0x12, 0x06];
+            let machine = chip8_create();
+            assert!(chip8_load(machine, rom.as_ptr(), rom.len()));
+            chip8_set_key(machine, 0x10, true);
+            chip8_set_key(machine, 0x5, true);
+            assert!(chip8_frame(machine));
+            assert!(chip8_beeping(machine));
+
+            let (mut width, mut height) = (0, 0);
+            chip8_get_resolution(machine, &mut width, &mut height);
+            assert_eq!((width, height), (64, 32));
+            let size = chip8_get_framebuffer(machine, ptr::null_mut(), 0);
+            assert_eq!(size, width * height);
+            let mut framebuffer = vec![0; size];
+            chip8_get_framebuffer(machine, framebuffer.as_mut_ptr(), size);
+            assert_eq!(framebuffer[..5], [1, 1, 1, 1, 0]);
+
+            // jumping off the end of memory stops the program for good
+            let rom = [0x1F, 0xFF];
+            assert!(chip8_load(machine, rom.as_ptr(), rom.len()));
+            assert!(!chip8_load(machine, rom.as_ptr(), MEMORY_SIZE));
+            (*machine).cpu.set_pc(START_ADDRESS);
+            assert!(chip8_tick(machine));
+            assert!(!chip8_tick(machine));
+            assert!(!chip8_tick(machine));
+            chip8_destroy(machine);
+
+            // the sound's edges come as they happen, a ticks-long beep included
+            let rom = [0x60, 0x01, 0xF0, 0x18, 0x12, 0x04];
+            let machine = chip8_create();
+            let mut edges: Vec<bool> = Vec::new();
+            chip8_set_sound_callback(
+                machine,
+                Some(record_edge),
+                &mut edges as *mut _ as *mut c_void,
+            );
+            assert!(chip8_load(machine, rom.as_ptr(), rom.len()));
+            assert!(chip8_frame(machine));
+            assert!(!chip8_beeping(machine));
+            assert_eq!(edges, [true, false]);
+            chip8_set_sound_callback(machine, None, ptr::null_mut());
+            (*machine).cpu.set_pc(START_ADDRESS);
+            assert!(chip8_tick(machine) && chip8_tick(machine));
+            assert_eq!(edges, [true, false]);
+            chip8_destroy(machine);
+
+            // a null machine is ignored rather than crashing
+            assert!(!chip8_tick(ptr::null_mut()));
+            chip8_destroy(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::cpu::CPU;
+use crate::instruction::Instruction;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    // the instruction after a skip is jumped over
+    Skip,
+    Call,
+    // only seen while the program was running, e.g. a JP V0 target
+    Dynamic,
+}
+
+pub struct BasicBlock {
+    pub instructions: Vec<(u16, Instruction)>,
+}
+
+// basic blocks and the edges between them, keyed by start address
+pub struct FlowGraph {
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub edges: BTreeSet<(u16, u16, EdgeKind)>,
+}
+
+fn decode_at(memory: &[u8], address: u16) -> Option<Instruction> {
+    let high = *memory.get(address as usize)?;
+    let low = *memory.get(address as usize + 1)?;
+    Some(Instruction::decode((high as u16) << 8 | low as u16))
+}
+
+fn is_skip(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::SkipEqImm { .. }
+            | Instruction::SkipNeImm { .. }
+            | Instruction::SkipEqReg { .. }
+            | Instruction::SkipNeReg { .. }
+            | Instruction::SkipKey { .. }
+            | Instruction::SkipNotKey { .. }
+    )
+}
+
+// where control can go after an instruction, None if the block ends there
+fn successors(address: u16, instruction: Instruction) -> Option<Vec<(u16, EdgeKind)>> {
+    let next = address.wrapping_add(2);
+    match instruction {
+        Instruction::Jump(target) => Some(vec![(target, EdgeKind::Jump)]),
+        Instruction::Call(target) => Some(vec![
+            (target, EdgeKind::Call),
+            (next, EdgeKind::Fallthrough),
+        ]),
+        Instruction::Ret | Instruction::JumpV0(_) | Instruction::Unknown(_) => Some(Vec::new()),
+        _ if is_skip(instruction) => Some(vec![
+            (next, EdgeKind::Fallthrough),
+            (next.wrapping_add(2), EdgeKind::Skip),
+        ]),
+        _ => None,
+    }
+}
+
+impl FlowGraph {
+    // follows every statically known path from the entry point, plus any transfers
+    // (from instruction, to address) recorded while the program ran
+    pub fn analyze(memory: &[u8], entry: u16, dynamic: &BTreeSet<(u16, u16)>) -> FlowGraph {
+        let mut reachable = BTreeSet::new();
+        let mut leaders = BTreeSet::from([entry]);
+        let mut worklist = vec![entry];
+        worklist.extend(dynamic.iter().map(|(_, to)| *to));
+        leaders.extend(dynamic.iter().map(|(_, to)| *to));
+
+        while let Some(mut address) = worklist.pop() {
+            while !reachable.contains(&address) {
+                let Some(instruction) = decode_at(memory, address) else {
+                    break;
+                };
+                reachable.insert(address);
+
+                match successors(address, instruction) {
+                    None => address += 2,
+                    Some(targets) => {
+                        for (target, _) in targets {
+                            leaders.insert(target);
+                            worklist.push(target);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+
+        for &start in leaders.iter().filter(|leader| reachable.contains(leader)) {
+            let mut instructions = Vec::new();
+            let mut address = start;
+
+            loop {
+                let instruction = decode_at(memory, address).unwrap();
+                instructions.push((address, instruction));
+
+                if let Some(targets) = successors(address, instruction) {
+                    for (target, kind) in targets {
+                        if reachable.contains(&target) {
+                            edges.insert((start, target, kind));
+                        }
+                    }
+                    break;
+                }
+
+                address += 2;
+                if !reachable.contains(&address) {
+                    break;
+                }
+                if leaders.contains(&address) {
+                    edges.insert((start, address, EdgeKind::Fallthrough));
+                    break;
+                }
+            }
+
+            blocks.insert(start, BasicBlock { instructions });
+        }
+
+        for (from, to) in dynamic {
+            let block = blocks.range(..=from).next_back().map(|(start, _)| *start);
+            if let Some(start) = block {
+                edges.insert((start, *to, EdgeKind::Dynamic));
+            }
+        }
+
+        FlowGraph { blocks, edges }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph chip8 {{").unwrap();
+        writeln!(dot, "    node [shape=box fontname=\"monospace\"];").unwrap();
+
+        for (start, block) in self.blocks.iter() {
+            let label: String = block
+                .instructions
+                .iter()
+                .map(|(address, instruction)| format!("{:03X}: {}\\l", address, instruction))
+                .collect();
+            writeln!(dot, "    \"{:03X}\" [label=\"{}\"];", start, label).unwrap();
+        }
+
+        for (from, to, kind) in self.edges.iter() {
+            let style = match kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [color=blue]",
+                EdgeKind::Skip => " [color=orange label=\"skip\"]",
+                EdgeKind::Call => " [style=dashed label=\"call\"]",
+                EdgeKind::Dynamic => " [style=dotted color=red label=\"dynamic\"]",
+            };
+            writeln!(dot, "    \"{:03X}\" -> \"{:03X}\"{};", from, to, style).unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+// records computed jumps while the program runs so they can be added to the graph
+#[derive(Default)]
+pub struct DynamicEdges {
+    pub edges: BTreeSet<(u16, u16)>,
+    pending: Option<u16>,
+}
+
+impl DynamicEdges {
+    pub fn new() -> DynamicEdges {
+        DynamicEdges {
+            edges: BTreeSet::new(),
+            pending: None,
+        }
+    }
+
+    // call right before the CPU ticks
+    pub fn before(&mut self, cpu: &CPU) {
+        let pc = cpu.pc();
+        self.pending = match Instruction::decode(cpu.opcode_at(pc)) {
+            Instruction::JumpV0(_) => Some(pc),
+            _ => None,
+        };
+    }
+
+    // call right after the CPU ticks
+    pub fn after(&mut self, cpu: &CPU) {
+        if let Some(from) = self.pending.take() {
+            self.edges.insert((from, cpu.pc()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(program: &[u8]) -> Vec<u8> {
+        let mut cpu = CPU::new();
+        cpu.load(program);
+        cpu.memory().to_vec()
+    }
+
+    #[test]
+    fn test_blocks_and_edges() {
+        let memory = memory(&[
+            0x60, 0x00, // 200: LD V0, #00
+            0x30, 0x05, // 202: SE V0, #05
+            0x22, 0x0A, // 204: CALL #20A
+            0x12, 0x02, // 206: JP #202
+            0x00, 0x00, // 208: NOP (never reached)
+            0x70, 0x01, // 20A: ADD V0, #01
+            0x00, 0xEE, // 20C: RET
+        ]);
+        let graph = FlowGraph::analyze(&memory, 0x200, &BTreeSet::new());
+
+        assert_eq!(
+            graph.blocks.keys().copied().collect::<Vec<_>>(),
+            [0x200, 0x202, 0x204, 0x206, 0x20A]
+        );
+        assert_eq!(graph.blocks[&0x20A].instructions.len(), 2);
+        assert!(graph.edges.contains(&(0x200, 0x202, EdgeKind::Fallthrough)));
+        assert!(graph.edges.contains(&(0x202, 0x206, EdgeKind::Skip)));
+        assert!(graph.edges.contains(&(0x204, 0x20A, EdgeKind::Call)));
+        assert!(graph.edges.contains(&(0x206, 0x202, EdgeKind::Jump)));
+    }
+
+    #[test]
+    fn test_dynamic_edges() {
+        let memory = memory(&[
+            0xB2, 0x04, // 200: JP V0, #204
+            0x00, 0x00, // 202: NOP
+            0x12, 0x04, // 204: JP #204
+        ]);
+
+        let graph = FlowGraph::analyze(&memory, 0x200, &BTreeSet::new());
+        assert_eq!(graph.blocks.len(), 1);
+
+        let graph = FlowGraph::analyze(&memory, 0x200, &BTreeSet::from([(0x200, 0x204)]));
+        assert_eq!(graph.blocks.len(), 2);
+        assert!(graph.edges.contains(&(0x200, 0x204, EdgeKind::Dynamic)));
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"200\" -> \"204\" [style=dotted color=red label=\"dynamic\"];"));
+        assert!(dot.contains("\"204\" [label=\"204: JP #204\\l\"];"));
+    }
+}
@@ -0,0 +1,208 @@
+// the screen packed a bit per pixel, a row to a u128 with the leftmost pixel in the top bit. that
+// lines a sprite's bytes up with the row as they are, so drawing one is a shift and an XOR per row
+// and clearing or scrolling moves whole rows, two machine words at a time, rather than a bool per
+// pixel. rows are as wide as the display in use, the pixels past its right edge stay unlit
+use core::ops::Index;
+
+use crate::cpu::{HIRES_HEIGHT, HIRES_WIDTH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    rows: [u128; HIRES_HEIGHT],
+    // how a pixel's index splits into its row and column
+    width: usize,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize) -> Framebuffer {
+        assert!(
+            width <= HIRES_WIDTH,
+            "rows are at most {} pixels",
+            HIRES_WIDTH
+        );
+        Framebuffer {
+            rows: [0; HIRES_HEIGHT],
+            width,
+        }
+    }
+
+    // the pixel `index` along, counting row by row
+    pub fn get(&self, index: usize) -> bool {
+        let (x, y) = (index % self.width, index / self.width);
+        self.rows[y] & column(x) != 0
+    }
+
+    pub fn set(&mut self, index: usize, on: bool) {
+        let (x, y) = (index % self.width, index / self.width);
+        if on {
+            self.rows[y] |= column(x);
+        } else {
+            self.rows[y] &= !column(x);
+        }
+    }
+
+    // the first `height` rows, a pixel at a time
+    pub fn pixels(&self, height: usize) -> impl ExactSizeIterator<Item = bool> + '_ {
+        (0..self.width * height).map(|index| self.get(index))
+    }
+
+    // row `y` laid out as they all are, the leftmost pixel in the top bit
+    pub fn row(&self, y: usize) -> u128 {
+        self.rows[y]
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+
+    pub fn clear(&mut self) {
+        self.rows = [0; HIRES_HEIGHT];
+    }
+
+    pub fn fill(&mut self) {
+        self.rows = [self.mask(); HIRES_HEIGHT];
+    }
+
+    // flips the lit bits of `pixels` in row `y`, a u128 laid out like the rows, returning whether
+    // any of them were already lit
+    pub fn xor_row(&mut self, y: usize, pixels: u128) -> bool {
+        let pixels = pixels & self.mask();
+        let collided = self.rows[y] & pixels != 0;
+        self.rows[y] ^= pixels;
+        collided
+    }
+
+    // a sprite row whose leftmost pixel is the top bit of `pixels` placed at column `x`, anything
+    // past the right edge cut off or wrapped round to the left
+    pub fn place(&self, pixels: u16, x: usize, wrap: bool) -> u128 {
+        let line = (pixels as u128) << (HIRES_WIDTH - 16);
+        let mut placed = line >> x;
+        if wrap && x + 16 > self.width {
+            placed |= line << (self.width - x);
+        }
+        placed & self.mask()
+    }
+
+    // the first `height` rows move down `amount`, leaving blank rows at the top
+    pub fn scroll_down(&mut self, amount: usize, height: usize) {
+        let amount = amount.min(height);
+        self.rows.copy_within(..height - amount, amount);
+        self.rows[..amount].fill(0);
+    }
+
+    // the first `height` rows move up `amount`, leaving blank rows at the bottom
+    pub fn scroll_up(&mut self, amount: usize, height: usize) {
+        let amount = amount.min(height);
+        self.rows.copy_within(amount..height, 0);
+        self.rows[height - amount..height].fill(0);
+    }
+
+    pub fn scroll_right(&mut self, amount: usize) {
+        let mask = self.mask();
+        for row in &mut self.rows {
+            *row = row.checked_shr(amount as u32).unwrap_or(0) & mask;
+        }
+    }
+
+    pub fn scroll_left(&mut self, amount: usize) {
+        for row in &mut self.rows {
+            *row = row.checked_shl(amount as u32).unwrap_or(0);
+        }
+    }
+
+    // the bits of a row that are on the display
+    fn mask(&self) -> u128 {
+        u128::MAX
+            .checked_shl((HIRES_WIDTH - self.width) as u32)
+            .unwrap_or(0)
+    }
+}
+
+// reading a pixel as screen[index], like the array of bools the screen used to be
+impl Index<usize> for Framebuffer {
+    type Output = bool;
+
+    fn index(&self, index: usize) -> &bool {
+        if self.get(index) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+fn column(x: usize) -> u128 {
+    1 << (HIRES_WIDTH - 1 - x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixels() {
+        let mut framebuffer = Framebuffer::new(64);
+        framebuffer.set(1, true);
+        framebuffer.set(64 + 63, true);
+        assert!(framebuffer[1] && framebuffer[127] && !framebuffer[0]);
+        assert_eq!(framebuffer.pixels(2).filter(|&on| on).count(), 2);
+        framebuffer.set(1, false);
+        assert!(!framebuffer[1]);
+
+        framebuffer.fill();
+        assert_eq!(framebuffer.pixels(32).filter(|&on| on).count(), 64 * 32);
+        // the columns past the edge of a narrow display stay unlit
+        assert_eq!(framebuffer.rows[0], u128::MAX << 64);
+        framebuffer.clear();
+        assert!(framebuffer.is_blank());
+    }
+
+    #[test]
+    fn test_place() {
+        let framebuffer = Framebuffer::new(64);
+        let sprite = 0xF100;
+        assert_eq!(framebuffer.place(sprite, 0, true), 0xF1 << 120);
+        assert_eq!(framebuffer.place(sprite, 60, false), 0xF << 64);
+        assert_eq!(framebuffer.place(sprite, 60, true), 0xF << 64 | 0x1 << 124);
+
+        let hires = Framebuffer::new(128);
+        assert_eq!(hires.place(0xFFFF, 120, false), 0xFF);
+        assert_eq!(hires.place(0xFFFF, 120, true), 0xFF | 0xFF << 120);
+    }
+
+    #[test]
+    fn test_xor_row() {
+        let mut framebuffer = Framebuffer::new(64);
+        assert!(!framebuffer.xor_row(3, 0b11 << 126));
+        assert!(framebuffer.xor_row(3, 0b10 << 126));
+        assert!(!framebuffer[3 * 64] && framebuffer[3 * 64 + 1]);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut framebuffer = Framebuffer::new(64);
+        framebuffer.set(0, true);
+        framebuffer.set(31 * 64 + 63, true);
+
+        framebuffer.scroll_down(2, 32);
+        assert!(framebuffer[2 * 64]);
+        // the bottom row scrolls off rather than into the rows below the display
+        assert!(framebuffer.rows[32..].iter().all(|&row| row == 0));
+        framebuffer.scroll_up(1, 32);
+        assert!(framebuffer[64] && !framebuffer[2 * 64]);
+        framebuffer.scroll_up(1, 32);
+        framebuffer.scroll_down(1, 32);
+        assert!(framebuffer[64]);
+
+        framebuffer.scroll_right(4);
+        assert!(framebuffer[64 + 4]);
+        framebuffer.scroll_right(60);
+        assert!(framebuffer.is_blank());
+
+        framebuffer.set(5, true);
+        framebuffer.scroll_left(4);
+        assert!(framebuffer[1]);
+        framebuffer.scroll_left(4);
+        assert!(framebuffer.is_blank());
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+use crate::cpu::{CpuError, CPU};
+use crate::error::{Error, RomError};
+use crate::library;
+use crate::quirks::Quirks;
+
+pub use crate::cpu::TICKS_PER_FRAME;
+
+// how often the delay and sound timers count down
+pub const TIMER_HZ: u32 = 60;
+
+// (frame, key, pressed), applied before the frame runs
+pub type KeyEvent = (u32, usize, bool);
+
+// runs a ROM without a window for a number of frames, pressing keys along the way
+pub fn run(
+    rom: &[u8],
+    quirks: Quirks,
+    seed: u64,
+    frames: u32,
+    keys: &[KeyEvent],
+) -> Result<CPU, Error> {
+    let mut cpu = CPU::new();
+    cpu.set_quirks(quirks);
+    cpu.seed(seed);
+    cpu.try_load(rom)?;
+
+    for &(frame, key, pressed) in keys {
+        cpu.schedule_key(frame as u64, key, pressed)?;
+    }
+    for _ in 0..frames {
+        cpu.run_frame()?;
+    }
+
+    Ok(cpu)
+}
+
+// the framebuffer as text, one line per row with # for lit pixels
+pub fn screen_text(cpu: &CPU) -> String {
+    let (width, _) = cpu.resolution();
+    let mut text = String::with_capacity(cpu.pixels().len() + cpu.pixels().len() / width);
+    for (index, on) in cpu.pixels().enumerate() {
+        text.push(if on { '#' } else { '.' });
+        if (index + 1) % width == 0 {
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// the framebuffer in block characters, two rows of pixels to a line, for pasting into a
+// terminal or a chat
+pub fn screen_blocks(cpu: &CPU) -> String {
+    library::render(&screen_text(cpu))
+}
+
+pub struct BenchReport {
+    pub instructions: u64,
+    pub elapsed: Duration,
+    // the program hit an error before the time was up
+    pub stopped: Option<CpuError>,
+}
+
+impl BenchReport {
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        self.instructions_per_second() / TICKS_PER_FRAME as f64
+    }
+}
+
+// frames run between looks at the clock, reading it costs about as much as a few instructions
+const BENCH_FRAMES_PER_CHECK: u32 = 100;
+
+// runs a ROM as fast as possible for a while, without drawing anything. the timers still count
+// down in real time, as a game waiting on the delay timer expects
+pub fn benchmark(rom: &[u8], quirks: Quirks, duration: Duration) -> Result<BenchReport, RomError> {
+    let mut cpu = CPU::new();
+    cpu.set_quirks(quirks);
+    cpu.try_load(rom)?;
+
+    let start = Instant::now();
+    let mut timers = Clock::new(TIMER_HZ, start);
+    let mut instructions = 0;
+
+    while start.elapsed() < duration {
+        for _ in 0..timers.frame(Instant::now()) {
+            cpu.tick_timers();
+        }
+        for _ in 0..BENCH_FRAMES_PER_CHECK * TICKS_PER_FRAME {
+            if let Err(error) = cpu.tick() {
+                return Ok(BenchReport {
+                    instructions,
+                    elapsed: start.elapsed(),
+                    stopped: Some(error),
+                });
+            }
+            instructions += 1;
+        }
+    }
+
+    Ok(BenchReport {
+        instructions,
+        elapsed: start.elapsed(),
+        stopped: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark() {
+        // jumps to itself forever
+        let report =
+            benchmark(&[0x12, 0x00], Quirks::default(), Duration::from_millis(10)).unwrap();
+        assert!(report.stopped.is_none());
+        assert!(report.instructions > 0);
+        let ratio = report.instructions_per_second() / report.frames_per_second();
+        assert!((ratio - TICKS_PER_FRAME as f64).abs() < 1e-9);
+
+        let report = benchmark(&[0x00, 0xEE], Quirks::default(), Duration::from_secs(10)).unwrap();
+        assert_eq!(report.instructions, 0);
+        assert_eq!(report.stopped, Some(CpuError::StackUnderflow));
+
+        assert_eq!(
+            benchmark(&[], Quirks::default(), Duration::from_secs(10)).err(),
+            Some(RomError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_screen_blocks() {
+        let mut cpu = CPU::new();
+        cpu.screen.set(0, true);
+        cpu.screen.set(64, true);
+        cpu.screen.set(65, true);
+
+        let blocks = screen_blocks(&cpu);
+        assert_eq!(blocks.lines().count(), 16);
+        assert!(blocks.starts_with("█▄ "));
+        assert_eq!(blocks.lines().next().unwrap().chars().count(), 64);
+    }
+}
@@ -0,0 +1,157 @@
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::cpu::{AccessKind, MemoryAccess};
+
+// 4K of memory as a 64x64 square, one cell per byte
+const MAP_COLUMNS: usize = 64;
+const CELL_SIZE: i32 = 6;
+const MAP_PADDING: i32 = 6;
+
+const MAP_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+// so a byte touched once still stands out from one never touched
+const MIN_BRIGHTNESS: f64 = 60.0;
+
+// how often every byte of memory has been read, written and executed over the run. writes show
+// as red, executes as green and reads as blue, brighter the more they happen, so a ROM's code and
+// data sit in visibly different colors
+pub struct Heatmap {
+    visible: bool,
+    reads: Vec<u64>,
+    writes: Vec<u64>,
+    executes: Vec<u64>,
+}
+
+impl Heatmap {
+    pub fn new(memory_size: usize) -> Heatmap {
+        Heatmap {
+            visible: false,
+            reads: vec![0; memory_size],
+            writes: vec![0; memory_size],
+            executes: vec![0; memory_size],
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // called after every tick, like the memory viewer
+    pub fn record(&mut self, accesses: &[MemoryAccess]) {
+        for access in accesses {
+            let counts = match access.kind {
+                AccessKind::Read => &mut self.reads,
+                AccessKind::Write => &mut self.writes,
+                AccessKind::Execute => &mut self.executes,
+            };
+            counts[access.address as usize] += 1;
+        }
+    }
+
+    fn colors(&self) -> Vec<Color> {
+        let scale = |counts: &[u64]| {
+            let most = counts.iter().copied().max().unwrap_or(0);
+            move |count: u64| brightness(count, most)
+        };
+        let (red, green, blue) = (
+            scale(&self.writes),
+            scale(&self.executes),
+            scale(&self.reads),
+        );
+
+        (0..self.reads.len())
+            .map(|address| {
+                Color::RGB(
+                    red(self.writes[address]),
+                    green(self.executes[address]),
+                    blue(self.reads[address]),
+                )
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let rows = self.reads.len().div_ceil(MAP_COLUMNS) as i32;
+        let width = MAP_COLUMNS as i32 * CELL_SIZE + 2 * MAP_PADDING;
+        let height = LINE_HEIGHT + rows * CELL_SIZE + 2 * MAP_PADDING;
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(MAP_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, width as u32, height as u32))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        draw_text(
+            canvas,
+            "MEMORY HEATMAP  RED WRITE, GREEN EXECUTE, BLUE READ",
+            MAP_PADDING,
+            MAP_PADDING,
+            TITLE_COLOR,
+        );
+
+        let top = MAP_PADDING + LINE_HEIGHT;
+        for (address, color) in self.colors().into_iter().enumerate() {
+            if color == Color::BLACK {
+                continue;
+            }
+            let (row, column) = (address / MAP_COLUMNS, address % MAP_COLUMNS);
+            canvas.set_draw_color(color);
+            canvas
+                .fill_rect(Rect::new(
+                    MAP_PADDING + column as i32 * CELL_SIZE,
+                    top + row as i32 * CELL_SIZE,
+                    CELL_SIZE as u32,
+                    CELL_SIZE as u32,
+                ))
+                .unwrap();
+        }
+    }
+}
+
+// on a log scale, a loop running millions of times shouldn't make everything else look untouched
+fn brightness(count: u64, most: u64) -> u8 {
+    if count == 0 {
+        return 0;
+    }
+    let fraction = ((count + 1) as f64).ln() / ((most + 1) as f64).ln();
+    (MIN_BRIGHTNESS + fraction * (255.0 - MIN_BRIGHTNESS)) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors() {
+        let mut heatmap = Heatmap::new(4);
+        let access = |address, kind| MemoryAccess { address, kind };
+        heatmap.record(&[
+            access(0, AccessKind::Execute),
+            access(1, AccessKind::Execute),
+        ]);
+        heatmap.record(&[access(0, AccessKind::Execute), access(2, AccessKind::Write)]);
+        heatmap.record(&[access(2, AccessKind::Read)]);
+
+        let colors = heatmap.colors();
+        assert_eq!(colors[0], Color::RGB(0, 255, 0));
+        assert_eq!(colors[1].g, brightness(1, 2));
+        assert!(colors[1].g > MIN_BRIGHTNESS as u8 && colors[1].g < 255);
+        assert_eq!(colors[2], Color::RGB(255, 0, 255));
+        assert_eq!(colors[3], Color::BLACK);
+    }
+}
@@ -0,0 +1,216 @@
+// key presses and releases from the keyboard, played into the CPU at the instruction matching
+// when they happened instead of all at once between frames. otherwise a tap shorter than a frame
+// goes down and comes back up before the program gets to look at it
+use std::collections::VecDeque;
+
+use crate::cpu::{CPU, NUM_KEYS};
+
+pub struct KeyQueue {
+    // in milliseconds, the same clock the transitions are timed with
+    last_frame: u32,
+    // (time, key, pressed) waiting for the next frame
+    pending: Vec<(u32, usize, bool)>,
+    // (tick, key, pressed) for the frame being run, in order
+    scheduled: VecDeque<(u32, usize, bool)>,
+    // the transitions start_frame is placing, kept to save allocating every frame
+    incoming: Vec<(u32, usize, bool)>,
+}
+
+impl KeyQueue {
+    pub fn new(now: u32) -> KeyQueue {
+        KeyQueue {
+            last_frame: now,
+            pending: Vec::new(),
+            scheduled: VecDeque::new(),
+            incoming: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, at: u32, key: usize, pressed: bool) {
+        self.pending.push((at, key, pressed));
+    }
+
+    // call before running a frame. the transitions since the last one are spread over its ticks
+    // by when they happened, a key always stays down for at least one tick, and anything that
+    // doesn't fit waits for the next frame
+    pub fn start_frame(&mut self, now: u32, ticks: u32) {
+        let span = now.saturating_sub(self.last_frame).max(1);
+        let last_tick = ticks.saturating_sub(1);
+        // whatever was left over goes first
+        let last_frame = self.last_frame;
+        self.incoming.clear();
+        self.incoming.extend(
+            self.scheduled
+                .drain(..)
+                .map(|(_, key, pressed)| (0, key, pressed)),
+        );
+        self.incoming
+            .extend(self.pending.drain(..).map(|(at, key, pressed)| {
+                let elapsed = at.saturating_sub(last_frame).min(span);
+                let tick = (elapsed as u64 * ticks as u64 / span as u64) as u32;
+                (tick.min(last_tick), key, pressed)
+            }));
+
+        for &(mut tick, key, pressed) in &self.incoming {
+            // in order, and the same key changing again waits for a tick after its last change
+            if let Some(&(previous, _, _)) = self.scheduled.back() {
+                tick = tick.max(previous);
+            }
+            if let Some(&(last, _, _)) = self.scheduled.iter().rev().find(|(_, k, _)| *k == key) {
+                tick = tick.max(last + 1);
+            }
+            self.scheduled.push_back((tick, key, pressed));
+        }
+
+        self.last_frame = now;
+    }
+
+    // call before each tick of the frame
+    pub fn apply(&mut self, tick: u32, cpu: &mut CPU) {
+        while let Some(&(at, key, pressed)) = self.scheduled.front() {
+            if at > tick {
+                break;
+            }
+            press(cpu, key, pressed);
+            self.scheduled.pop_front();
+        }
+    }
+
+    // applies everything queued right away, for when input has to change only between frames,
+    // like when it's being recorded or sent to another player
+    pub fn flush(&mut self, cpu: &mut CPU) {
+        for (_, key, pressed) in self.scheduled.drain(..) {
+            press(cpu, key, pressed);
+        }
+        for (_, key, pressed) in self.pending.drain(..) {
+            press(cpu, key, pressed);
+        }
+    }
+}
+
+// a key from a frontend's keymap, which shouldn't have any the CPU doesn't
+pub fn press(cpu: &mut CPU, key: usize, pressed: bool) {
+    if let Err(error) = cpu.keypress(key, pressed) {
+        tracing::warn!("ignoring a key: {}", error);
+    }
+}
+
+// input forgiveness for keyboards that drop keys: a press stays held for at least a few frames
+// even if the key comes up sooner, so a quick tap still registers with games that only check
+// every so often
+pub struct StickyKeys {
+    min_frames: u64,
+    pressed_at: [u64; NUM_KEYS],
+    // released by the player but still held down for the game
+    releasing: [bool; NUM_KEYS],
+}
+
+impl StickyKeys {
+    pub fn new(min_frames: u64) -> StickyKeys {
+        StickyKeys {
+            min_frames,
+            pressed_at: [0; NUM_KEYS],
+            releasing: [false; NUM_KEYS],
+        }
+    }
+
+    pub fn press(&mut self, key: usize, frame: u64) {
+        self.pressed_at[key] = frame;
+        self.releasing[key] = false;
+    }
+
+    // true if the key can come up now, otherwise `due` hands it back once it's been held long
+    // enough
+    pub fn release(&mut self, key: usize, frame: u64) -> bool {
+        let ready = frame >= self.pressed_at[key] + self.min_frames;
+        self.releasing[key] = !ready;
+        ready
+    }
+
+    // keys whose release was held back and can now come up, call once per frame
+    pub fn due(&mut self, frame: u64) -> Vec<usize> {
+        let due: Vec<usize> = (0..NUM_KEYS)
+            .filter(|&key| self.releasing[key] && frame >= self.pressed_at[key] + self.min_frames)
+            .collect();
+        for &key in due.iter() {
+            self.releasing[key] = false;
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the key's state before each of the frame's ticks
+    fn run(queue: &mut KeyQueue, now: u32, ticks: u32, cpu: &mut CPU, key: usize) -> Vec<bool> {
+        queue.start_frame(now, ticks);
+        (0..ticks)
+            .map(|tick| {
+                queue.apply(tick, cpu);
+                cpu.keys()[key]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_spread() {
+        let mut cpu = CPU::new();
+        let mut queue = KeyQueue::new(0);
+
+        // pressed halfway through a 10ms frame
+        queue.push(5, 3, true);
+        assert_eq!(
+            run(&mut queue, 10, 10, &mut cpu, 3),
+            [false, false, false, false, false, true, true, true, true, true]
+        );
+
+        // a tap inside a single millisecond still shows for a tick
+        queue.push(12, 3, false);
+        queue.push(14, 7, true);
+        queue.push(14, 7, false);
+        let seen = run(&mut queue, 20, 10, &mut cpu, 7);
+        assert_eq!(seen.iter().filter(|&&held| held).count(), 1);
+        assert!(!cpu.keys()[3]);
+    }
+
+    #[test]
+    fn test_carry_over() {
+        let mut cpu = CPU::new();
+        let mut queue = KeyQueue::new(0);
+
+        // pressed and released at the very end of a frame, the release waits for the next one
+        queue.push(10, 1, true);
+        queue.push(10, 1, false);
+        assert_eq!(run(&mut queue, 10, 2, &mut cpu, 1), [false, true]);
+        assert_eq!(run(&mut queue, 20, 2, &mut cpu, 1), [false, false]);
+
+        // nothing waits when flushed
+        queue.push(25, 2, true);
+        queue.flush(&mut cpu);
+        assert!(cpu.keys()[2]);
+    }
+
+    #[test]
+    fn test_sticky_keys() {
+        let mut sticky = StickyKeys::new(3);
+
+        // let go the frame after it went down, it stays down until frame 13
+        sticky.press(4, 10);
+        assert!(!sticky.release(4, 11));
+        assert!(sticky.due(12).is_empty());
+        assert_eq!(sticky.due(13), [4]);
+        assert!(sticky.due(14).is_empty());
+
+        // held long enough already
+        sticky.press(5, 20);
+        assert!(sticky.release(5, 25));
+
+        // pressed again before the held back release, that release is forgotten
+        sticky.press(6, 30);
+        assert!(!sticky.release(6, 31));
+        sticky.press(6, 32);
+        assert!(sticky.due(40).is_empty());
+    }
+}
@@ -0,0 +1,309 @@
+use core::fmt;
+
+// decoded form of a raw opcode, shared by the debugger and anything else that needs to read programs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,
+    Cls,
+    Ret,
+    Jump(u16),
+    Call(u16),
+    SkipEqImm { x: u8, nn: u8 },
+    SkipNeImm { x: u8, nn: u8 },
+    SkipEqReg { x: u8, y: u8 },
+    LoadImm { x: u8, nn: u8 },
+    AddImm { x: u8, nn: u8 },
+    LoadReg { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    AddReg { x: u8, y: u8 },
+    SubReg { x: u8, y: u8 },
+    ShiftRight { x: u8, y: u8 },
+    SubnReg { x: u8, y: u8 },
+    ShiftLeft { x: u8, y: u8 },
+    SkipNeReg { x: u8, y: u8 },
+    LoadIndex(u16),
+    JumpV0(u16),
+    Random { x: u8, nn: u8 },
+    Draw { x: u8, y: u8, n: u8 },
+    SkipKey { x: u8 },
+    SkipNotKey { x: u8 },
+    LoadDelay { x: u8 },
+    WaitKey { x: u8 },
+    SetDelay { x: u8 },
+    SetSound { x: u8 },
+    AddIndex { x: u8 },
+    LoadFont { x: u8 },
+    Bcd { x: u8 },
+    StoreRegs { x: u8 },
+    LoadRegs { x: u8 },
+    Unknown(u16),
+}
+
+impl Instruction {
+    pub fn decode(op: u16) -> Instruction {
+        let digit_one = (op & 0xF000) >> 12;
+        let x = ((op & 0x0F00) >> 8) as u8;
+        let y = ((op & 0x00F0) >> 4) as u8;
+        let n = (op & 0x000F) as u8;
+        let nn = (op & 0x00FF) as u8;
+        let nnn = op & 0x0FFF;
+
+        match (digit_one, x, y, n) {
+            (0, 0, 0, 0) => Instruction::Nop,
+            (0, 0, 0xE, 0) => Instruction::Cls,
+            (0, 0, 0xE, 0xE) => Instruction::Ret,
+            (1, _, _, _) => Instruction::Jump(nnn),
+            (2, _, _, _) => Instruction::Call(nnn),
+            (3, _, _, _) => Instruction::SkipEqImm { x, nn },
+            (4, _, _, _) => Instruction::SkipNeImm { x, nn },
+            (5, _, _, 0) => Instruction::SkipEqReg { x, y },
+            (6, _, _, _) => Instruction::LoadImm { x, nn },
+            (7, _, _, _) => Instruction::AddImm { x, nn },
+            (8, _, _, 0) => Instruction::LoadReg { x, y },
+            (8, _, _, 1) => Instruction::Or { x, y },
+            (8, _, _, 2) => Instruction::And { x, y },
+            (8, _, _, 3) => Instruction::Xor { x, y },
+            (8, _, _, 4) => Instruction::AddReg { x, y },
+            (8, _, _, 5) => Instruction::SubReg { x, y },
+            (8, _, _, 6) => Instruction::ShiftRight { x, y },
+            (8, _, _, 7) => Instruction::SubnReg { x, y },
+            (8, _, _, 0xE) => Instruction::ShiftLeft { x, y },
+            (9, _, _, 0) => Instruction::SkipNeReg { x, y },
+            (0xA, _, _, _) => Instruction::LoadIndex(nnn),
+            (0xB, _, _, _) => Instruction::JumpV0(nnn),
+            (0xC, _, _, _) => Instruction::Random { x, nn },
+            (0xD, _, _, _) => Instruction::Draw { x, y, n },
+            (0xE, _, 9, 0xE) => Instruction::SkipKey { x },
+            (0xE, _, 0xA, 1) => Instruction::SkipNotKey { x },
+            (0xF, _, 0, 7) => Instruction::LoadDelay { x },
+            (0xF, _, 0, 0xA) => Instruction::WaitKey { x },
+            (0xF, _, 1, 5) => Instruction::SetDelay { x },
+            (0xF, _, 1, 8) => Instruction::SetSound { x },
+            (0xF, _, 1, 0xE) => Instruction::AddIndex { x },
+            (0xF, _, 2, 9) => Instruction::LoadFont { x },
+            (0xF, _, 3, 3) => Instruction::Bcd { x },
+            (0xF, _, 5, 5) => Instruction::StoreRegs { x },
+            (0xF, _, 6, 5) => Instruction::LoadRegs { x },
+            (_, _, _, _) => Instruction::Unknown(op),
+        }
+    }
+
+    // the inverse of decode, so encode(decode(op)) == op for every opcode
+    pub fn encode(&self) -> u16 {
+        let xy = |family: u16, x: u8, y: u8, n: u16| {
+            family << 12 | (x as u16) << 8 | (y as u16) << 4 | n
+        };
+        let xnn = |family: u16, x: u8, nn: u8| family << 12 | (x as u16) << 8 | nn as u16;
+
+        match *self {
+            Instruction::Nop => 0x0000,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Jump(nnn) => 0x1000 | nnn,
+            Instruction::Call(nnn) => 0x2000 | nnn,
+            Instruction::SkipEqImm { x, nn } => xnn(3, x, nn),
+            Instruction::SkipNeImm { x, nn } => xnn(4, x, nn),
+            Instruction::SkipEqReg { x, y } => xy(5, x, y, 0),
+            Instruction::LoadImm { x, nn } => xnn(6, x, nn),
+            Instruction::AddImm { x, nn } => xnn(7, x, nn),
+            Instruction::LoadReg { x, y } => xy(8, x, y, 0),
+            Instruction::Or { x, y } => xy(8, x, y, 1),
+            Instruction::And { x, y } => xy(8, x, y, 2),
+            Instruction::Xor { x, y } => xy(8, x, y, 3),
+            Instruction::AddReg { x, y } => xy(8, x, y, 4),
+            Instruction::SubReg { x, y } => xy(8, x, y, 5),
+            Instruction::ShiftRight { x, y } => xy(8, x, y, 6),
+            Instruction::SubnReg { x, y } => xy(8, x, y, 7),
+            Instruction::ShiftLeft { x, y } => xy(8, x, y, 0xE),
+            Instruction::SkipNeReg { x, y } => xy(9, x, y, 0),
+            Instruction::LoadIndex(nnn) => 0xA000 | nnn,
+            Instruction::JumpV0(nnn) => 0xB000 | nnn,
+            Instruction::Random { x, nn } => xnn(0xC, x, nn),
+            Instruction::Draw { x, y, n } => xy(0xD, x, y, n as u16),
+            Instruction::SkipKey { x } => xnn(0xE, x, 0x9E),
+            Instruction::SkipNotKey { x } => xnn(0xE, x, 0xA1),
+            Instruction::LoadDelay { x } => xnn(0xF, x, 0x07),
+            Instruction::WaitKey { x } => xnn(0xF, x, 0x0A),
+            Instruction::SetDelay { x } => xnn(0xF, x, 0x15),
+            Instruction::SetSound { x } => xnn(0xF, x, 0x18),
+            Instruction::AddIndex { x } => xnn(0xF, x, 0x1E),
+            Instruction::LoadFont { x } => xnn(0xF, x, 0x29),
+            Instruction::Bcd { x } => xnn(0xF, x, 0x33),
+            Instruction::StoreRegs { x } => xnn(0xF, x, 0x55),
+            Instruction::LoadRegs { x } => xnn(0xF, x, 0x65),
+            Instruction::Unknown(op) => op,
+        }
+    }
+
+    // the opcode family this instruction belongs to, in the usual XNN notation
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "0000",
+            Instruction::Cls => "00E0",
+            Instruction::Ret => "00EE",
+            Instruction::Jump(_) => "1NNN",
+            Instruction::Call(_) => "2NNN",
+            Instruction::SkipEqImm { .. } => "3XNN",
+            Instruction::SkipNeImm { .. } => "4XNN",
+            Instruction::SkipEqReg { .. } => "5XY0",
+            Instruction::LoadImm { .. } => "6XNN",
+            Instruction::AddImm { .. } => "7XNN",
+            Instruction::LoadReg { .. } => "8XY0",
+            Instruction::Or { .. } => "8XY1",
+            Instruction::And { .. } => "8XY2",
+            Instruction::Xor { .. } => "8XY3",
+            Instruction::AddReg { .. } => "8XY4",
+            Instruction::SubReg { .. } => "8XY5",
+            Instruction::ShiftRight { .. } => "8XY6",
+            Instruction::SubnReg { .. } => "8XY7",
+            Instruction::ShiftLeft { .. } => "8XYE",
+            Instruction::SkipNeReg { .. } => "9XY0",
+            Instruction::LoadIndex(_) => "ANNN",
+            Instruction::JumpV0(_) => "BNNN",
+            Instruction::Random { .. } => "CXNN",
+            Instruction::Draw { .. } => "DXYN",
+            Instruction::SkipKey { .. } => "EX9E",
+            Instruction::SkipNotKey { .. } => "EXA1",
+            Instruction::LoadDelay { .. } => "FX07",
+            Instruction::WaitKey { .. } => "FX0A",
+            Instruction::SetDelay { .. } => "FX15",
+            Instruction::SetSound { .. } => "FX18",
+            Instruction::AddIndex { .. } => "FX1E",
+            Instruction::LoadFont { .. } => "FX29",
+            Instruction::Bcd { .. } => "FX33",
+            Instruction::StoreRegs { .. } => "FX55",
+            Instruction::LoadRegs { .. } => "FX65",
+            Instruction::Unknown(_) => "????",
+        }
+    }
+
+    // roughly how long the COSMAC VIP interpreter takes over this instruction, in microseconds.
+    // these are averages, drawing and the memory instructions really depend on the data, and
+    // FX0A doesn't count the time spent waiting for the key
+    pub fn vip_micros(&self) -> u32 {
+        match self {
+            Instruction::Nop | Instruction::Unknown(_) => 0,
+            Instruction::Cls => 109,
+            Instruction::Ret | Instruction::Jump(_) | Instruction::Call(_) => 105,
+            Instruction::SkipEqImm { .. } | Instruction::SkipNeImm { .. } => 55,
+            Instruction::SkipEqReg { .. } | Instruction::SkipNeReg { .. } => 73,
+            Instruction::LoadImm { .. } => 27,
+            Instruction::AddImm { .. } => 45,
+            Instruction::LoadReg { .. }
+            | Instruction::Or { .. }
+            | Instruction::And { .. }
+            | Instruction::Xor { .. }
+            | Instruction::AddReg { .. }
+            | Instruction::SubReg { .. }
+            | Instruction::ShiftRight { .. }
+            | Instruction::SubnReg { .. }
+            | Instruction::ShiftLeft { .. } => 200,
+            Instruction::LoadIndex(_) => 55,
+            Instruction::JumpV0(_) => 105,
+            Instruction::Random { .. } => 164,
+            Instruction::Draw { .. } => 22734,
+            Instruction::SkipKey { .. } | Instruction::SkipNotKey { .. } => 73,
+            Instruction::LoadDelay { .. }
+            | Instruction::SetDelay { .. }
+            | Instruction::SetSound { .. } => 45,
+            Instruction::WaitKey { .. } => 0,
+            Instruction::AddIndex { .. } => 86,
+            Instruction::LoadFont { .. } => 91,
+            Instruction::Bcd { .. } => 927,
+            Instruction::StoreRegs { .. } | Instruction::LoadRegs { .. } => 605,
+        }
+    }
+}
+
+// mnemonics follow Cowgod's reference, immediates are written in hex with a # prefix
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Nop => write!(f, "NOP"),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jump(nnn) => write!(f, "JP #{:03X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL #{:03X}", nnn),
+            Instruction::SkipEqImm { x, nn } => write!(f, "SE V{:X}, #{:02X}", x, nn),
+            Instruction::SkipNeImm { x, nn } => write!(f, "SNE V{:X}, #{:02X}", x, nn),
+            Instruction::SkipEqReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LoadImm { x, nn } => write!(f, "LD V{:X}, #{:02X}", x, nn),
+            Instruction::AddImm { x, nn } => write!(f, "ADD V{:X}, #{:02X}", x, nn),
+            Instruction::LoadReg { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SubReg { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubnReg { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNeReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadIndex(nnn) => write!(f, "LD I, #{:03X}", nnn),
+            Instruction::JumpV0(nnn) => write!(f, "JP V0, #{:03X}", nnn),
+            Instruction::Random { x, nn } => write!(f, "RND V{:X}, #{:02X}", x, nn),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKey { x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadDelay { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIndex { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::Bcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::StoreRegs { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegs { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::Unknown(op) => write!(f, "DW #{:04X}", op),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Instruction::decode(0x00E0), Instruction::Cls);
+        assert_eq!(Instruction::decode(0x1420), Instruction::Jump(0x420));
+        assert_eq!(
+            Instruction::decode(0x8AB4),
+            Instruction::AddReg { x: 0xA, y: 0xB }
+        );
+        assert_eq!(
+            Instruction::decode(0xD013),
+            Instruction::Draw { x: 0, y: 1, n: 3 }
+        );
+        assert_eq!(Instruction::decode(0xF265), Instruction::LoadRegs { x: 2 });
+    }
+
+    #[test]
+    fn test_decode_unknown() {
+        assert_eq!(Instruction::decode(0x5001), Instruction::Unknown(0x5001));
+        assert_eq!(Instruction::decode(0xE000), Instruction::Unknown(0xE000));
+        assert_eq!(Instruction::decode(0xFFFF), Instruction::Unknown(0xFFFF));
+    }
+
+    #[test]
+    fn test_mnemonics() {
+        assert_eq!(Instruction::decode(0x2A0C).to_string(), "CALL #A0C");
+        assert_eq!(Instruction::decode(0x6F0A).to_string(), "LD VF, #0A");
+        assert_eq!(Instruction::decode(0xD12F).to_string(), "DRW V1, V2, 15");
+        assert_eq!(Instruction::decode(0xF555).to_string(), "LD [I], V5");
+        assert_eq!(Instruction::decode(0x0123).to_string(), "DW #0123");
+    }
+
+    #[test]
+    fn test_vip_micros() {
+        assert_eq!(Instruction::decode(0x6005).vip_micros(), 27);
+        assert_eq!(Instruction::decode(0x8014).vip_micros(), 200);
+        // nothing costs more than drawing
+        let slowest = (0..=0xFFFF)
+            .max_by_key(|&op| Instruction::decode(op).vip_micros())
+            .unwrap();
+        assert_eq!(Instruction::decode(slowest).pattern(), "DXYN");
+    }
+}
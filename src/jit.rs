@@ -0,0 +1,507 @@
+// an experimental compiler from CHIP-8 to native code with Cranelift, mostly for learning how a
+// JIT fits together. a block is the straight run of register instructions from an address, up
+// to and including a jump or skip, compiled once into a function over the registers. anything
+// else (drawing, memory, timers, keys, calls) and any block whose bytes have changed since it
+// was compiled run in the interpreter, so the machine always behaves as if it were interpreted.
+// the memory accesses of a tick aren't recorded for the instructions in a block. build with
+//   cargo build --features jit
+use std::mem;
+
+use cranelift_codegen::{
+    ir::{condcodes::IntCC, types, AbiParam, InstBuilder, MemFlags, Value},
+    settings::{self, Configurable},
+    Context,
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+
+use crate::cpu::{CycleReport, CPU, START_ADDRESS};
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+
+// what a compiled block reads and writes, copied out of the CPU and back around each call
+#[repr(C)]
+#[derive(Default)]
+struct Registers {
+    v: [u8; 16],
+    index: u16,
+    pc: u16,
+}
+
+const INDEX_OFFSET: i32 = 16;
+const PC_OFFSET: i32 = 18;
+
+struct Block {
+    function: extern "C" fn(*mut Registers),
+    // the bytes it was compiled from, it's only run while memory still has them
+    bytes: Vec<u8>,
+    // instructions it runs, each counts as a tick
+    length: u32,
+}
+
+enum Slot {
+    Unseen,
+    Compiled(Block),
+    // nothing to compile there, or the program has changed the code since it was compiled, so
+    // it's left to the interpreter for good
+    Interpreted,
+}
+
+pub struct Jit {
+    module: JITModule,
+    context: Context,
+    builder_context: FunctionBuilderContext,
+    // what's at each address of memory, looked up by the program counter
+    slots: Vec<Slot>,
+    // blocks have the quirks in force when they were compiled built in
+    quirks: Quirks,
+}
+
+impl Jit {
+    pub fn new() -> Result<Jit, String> {
+        Ok(Jit {
+            module: module()?,
+            context: Context::new(),
+            builder_context: FunctionBuilderContext::new(),
+            slots: Vec::new(),
+            quirks: Quirks::default(),
+        })
+    }
+
+    // CPU::run_cycles with the compiled blocks run in place of the instructions in them. a block
+    // that doesn't fit in what's left of the budget is interpreted an instruction at a time, and
+    // nothing is compiled while there are breakpoints to stop at
+    pub fn run_cycles(&mut self, cpu: &mut CPU, budget: u32) -> CycleReport {
+        if cpu.quirks() != self.quirks || cpu.memory().len() != self.slots.len() {
+            self.flush(cpu);
+        }
+
+        let mut report = CycleReport {
+            ran: 0,
+            waiting_for_key: false,
+            stopped: None,
+        };
+        while report.ran < budget {
+            if cpu.breakpoints().is_empty() {
+                if let Some(length) = self.run_block(cpu, budget - report.ran) {
+                    report.ran += length;
+                    continue;
+                }
+            }
+
+            if let Err(error) = cpu.tick() {
+                report.stopped = Some(error);
+                break;
+            }
+            report.ran += 1;
+            if cpu.waiting_for_key() {
+                report.waiting_for_key = true;
+                break;
+            }
+        }
+        report
+    }
+
+    // runs the block at the program counter if there is one and it fits in `budget`, returning
+    // how many instructions it ran
+    fn run_block(&mut self, cpu: &mut CPU, budget: u32) -> Option<u32> {
+        let pc = cpu.pc();
+        let start = pc as usize;
+        if matches!(self.slots.get(start)?, Slot::Unseen) {
+            self.slots[start] = match self.compile(cpu, pc) {
+                Ok(Some(block)) => Slot::Compiled(block),
+                Ok(None) => Slot::Interpreted,
+                Err(message) => {
+                    tracing::warn!("unable to compile the block at #{:03X}: {}", pc, message);
+                    Slot::Interpreted
+                }
+            };
+        }
+
+        let Slot::Compiled(block) = &self.slots[start] else {
+            return None;
+        };
+        if cpu.memory()[start..start + block.bytes.len()] != block.bytes[..] {
+            self.slots[start] = Slot::Interpreted;
+            return None;
+        }
+        if block.length > budget {
+            return None;
+        }
+
+        let mut registers = Registers {
+            index: cpu.index_register(),
+            pc,
+            ..Registers::default()
+        };
+        registers.v.copy_from_slice(cpu.v_registers());
+        (block.function)(&mut registers);
+        for (index, &value) in registers.v.iter().enumerate() {
+            cpu.set_v_register(index, value);
+        }
+        cpu.set_index_register(registers.index);
+        cpu.set_pc(registers.pc);
+        Some(block.length)
+    }
+
+    fn compile(&mut self, cpu: &CPU, start: u16) -> Result<Option<Block>, String> {
+        let quirks = cpu.quirks();
+        let memory = cpu.memory();
+
+        // the instructions in the block, the last may end it
+        let mut instructions = Vec::new();
+        let mut address = start as usize;
+        while address + 1 < memory.len() {
+            let op = (memory[address] as u16) << 8 | memory[address + 1] as u16;
+            let instruction = Instruction::decode(op);
+            let ends_block = match instruction {
+                // a hires CHIP-8 program's first jump sets up the display
+                Instruction::Jump(_)
+                    if quirks.two_page && op == 0x1260 && address == START_ADDRESS as usize =>
+                {
+                    break
+                }
+                // how far an XO-CHIP skip goes depends on the instruction after it
+                Instruction::SkipEqImm { .. }
+                | Instruction::SkipNeImm { .. }
+                | Instruction::SkipEqReg { .. }
+                | Instruction::SkipNeReg { .. }
+                    if quirks.xochip =>
+                {
+                    break
+                }
+                Instruction::Jump(_)
+                | Instruction::SkipEqImm { .. }
+                | Instruction::SkipNeImm { .. }
+                | Instruction::SkipEqReg { .. }
+                | Instruction::SkipNeReg { .. } => true,
+                Instruction::LoadImm { .. }
+                | Instruction::AddImm { .. }
+                | Instruction::LoadReg { .. }
+                | Instruction::Or { .. }
+                | Instruction::And { .. }
+                | Instruction::Xor { .. }
+                | Instruction::AddReg { .. }
+                | Instruction::SubReg { .. }
+                | Instruction::ShiftRight { .. }
+                | Instruction::SubnReg { .. }
+                | Instruction::ShiftLeft { .. }
+                | Instruction::LoadIndex(_) => false,
+                _ => break,
+            };
+            instructions.push(instruction);
+            address += 2;
+            if ends_block {
+                break;
+            }
+        }
+        if instructions.is_empty() {
+            return Ok(None);
+        }
+
+        let function = self.translate(start, &instructions, quirks)?;
+        Ok(Some(Block {
+            function,
+            bytes: memory[start as usize..address].to_vec(),
+            length: instructions.len() as u32,
+        }))
+    }
+
+    fn translate(
+        &mut self,
+        start: u16,
+        instructions: &[Instruction],
+        quirks: Quirks,
+    ) -> Result<extern "C" fn(*mut Registers), String> {
+        let pointer = self.module.target_config().pointer_type();
+        self.context
+            .func
+            .signature
+            .params
+            .push(AbiParam::new(pointer));
+
+        let mut builder = FunctionBuilder::new(&mut self.context.func, &mut self.builder_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let registers = builder.block_params(entry)[0];
+        let flags = MemFlags::trusted();
+
+        // V0 to VF are variables 0 to 15, I is 16
+        let v = |register: u8| Variable::from_u32(register as u32);
+        let index = Variable::from_u32(16);
+        for register in 0..16 {
+            builder.declare_var(v(register), types::I8);
+            let value = builder
+                .ins()
+                .load(types::I8, flags, registers, register as i32);
+            builder.def_var(v(register), value);
+        }
+        builder.declare_var(index, types::I16);
+        let value = builder
+            .ins()
+            .load(types::I16, flags, registers, INDEX_OFFSET);
+        builder.def_var(index, value);
+
+        // falling off the end of the block goes on to the next instruction
+        let mut pc = builder
+            .ins()
+            .iconst(types::I16, (start + 2 * instructions.len() as u16) as i64);
+        for (offset, &instruction) in instructions.iter().enumerate() {
+            let address = start + 2 * offset as u16;
+            let skip = |builder: &mut FunctionBuilder, condition: Value| {
+                let skipped = builder.ins().iconst(types::I16, (address + 4) as i64);
+                let next = builder.ins().iconst(types::I16, (address + 2) as i64);
+                builder.ins().select(condition, skipped, next)
+            };
+            match instruction {
+                Instruction::Jump(nnn) => pc = builder.ins().iconst(types::I16, nnn as i64),
+                Instruction::SkipEqImm { x, nn } | Instruction::SkipNeImm { x, nn } => {
+                    let condition = match instruction {
+                        Instruction::SkipEqImm { .. } => IntCC::Equal,
+                        _ => IntCC::NotEqual,
+                    };
+                    let vx = builder.use_var(v(x));
+                    let taken = builder.ins().icmp_imm(condition, vx, nn as i64);
+                    pc = skip(&mut builder, taken);
+                }
+                Instruction::SkipEqReg { x, y } | Instruction::SkipNeReg { x, y } => {
+                    let condition = match instruction {
+                        Instruction::SkipEqReg { .. } => IntCC::Equal,
+                        _ => IntCC::NotEqual,
+                    };
+                    let (vx, vy) = (builder.use_var(v(x)), builder.use_var(v(y)));
+                    let taken = builder.ins().icmp(condition, vx, vy);
+                    pc = skip(&mut builder, taken);
+                }
+                Instruction::LoadImm { x, nn } => {
+                    let value = builder.ins().iconst(types::I8, nn as i64);
+                    builder.def_var(v(x), value);
+                }
+                Instruction::AddImm { x, nn } => {
+                    let vx = builder.use_var(v(x));
+                    let value = builder.ins().iadd_imm(vx, nn as i64);
+                    builder.def_var(v(x), value);
+                }
+                Instruction::LoadReg { x, y } => {
+                    let vy = builder.use_var(v(y));
+                    builder.def_var(v(x), vy);
+                }
+                Instruction::Or { x, y }
+                | Instruction::And { x, y }
+                | Instruction::Xor { x, y } => {
+                    let (vx, vy) = (builder.use_var(v(x)), builder.use_var(v(y)));
+                    let value = match instruction {
+                        Instruction::Or { .. } => builder.ins().bor(vx, vy),
+                        Instruction::And { .. } => builder.ins().band(vx, vy),
+                        _ => builder.ins().bxor(vx, vy),
+                    };
+                    builder.def_var(v(x), value);
+                    if quirks.vf_reset {
+                        let zero = builder.ins().iconst(types::I8, 0);
+                        builder.def_var(v(0xF), zero);
+                    }
+                }
+                // the flag is set before the result, so VF as VX ends up with the result
+                Instruction::AddReg { x, y } => {
+                    let (vx, vy) = (builder.use_var(v(x)), builder.use_var(v(y)));
+                    let sum = builder.ins().iadd(vx, vy);
+                    let carry = builder.ins().icmp(IntCC::UnsignedLessThan, sum, vx);
+                    builder.def_var(v(0xF), carry);
+                    builder.def_var(v(x), sum);
+                }
+                Instruction::SubReg { x, y } | Instruction::SubnReg { x, y } => {
+                    let (vx, vy) = (builder.use_var(v(x)), builder.use_var(v(y)));
+                    let (minuend, subtrahend) = match instruction {
+                        Instruction::SubReg { .. } => (vx, vy),
+                        _ => (vy, vx),
+                    };
+                    let difference = builder.ins().isub(minuend, subtrahend);
+                    let no_borrow =
+                        builder
+                            .ins()
+                            .icmp(IntCC::UnsignedGreaterThanOrEqual, minuend, subtrahend);
+                    builder.def_var(v(0xF), no_borrow);
+                    builder.def_var(v(x), difference);
+                }
+                Instruction::ShiftRight { x, y } | Instruction::ShiftLeft { x, y } => {
+                    let source = if quirks.shift_uses_vy { v(y) } else { v(x) };
+                    let value = builder.use_var(source);
+                    let (shifted, bit) = match instruction {
+                        Instruction::ShiftRight { .. } => (
+                            builder.ins().ushr_imm(value, 1),
+                            builder.ins().band_imm(value, 1),
+                        ),
+                        _ => (
+                            builder.ins().ishl_imm(value, 1),
+                            builder.ins().ushr_imm(value, 7),
+                        ),
+                    };
+                    builder.def_var(v(x), shifted);
+                    builder.def_var(v(0xF), bit);
+                }
+                Instruction::LoadIndex(nnn) => {
+                    let value = builder.ins().iconst(types::I16, nnn as i64);
+                    builder.def_var(index, value);
+                }
+                _ => unreachable!("{:?} isn't compiled", instruction),
+            }
+        }
+
+        for register in 0..16 {
+            let value = builder.use_var(v(register));
+            builder
+                .ins()
+                .store(flags, value, registers, register as i32);
+        }
+        let value = builder.use_var(index);
+        builder.ins().store(flags, value, registers, INDEX_OFFSET);
+        builder.ins().store(flags, pc, registers, PC_OFFSET);
+        builder.ins().return_(&[]);
+        builder.finalize();
+
+        let id = self
+            .module
+            .declare_anonymous_function(&self.context.func.signature)
+            .map_err(|error| error.to_string())?;
+        self.module
+            .define_function(id, &mut self.context)
+            .map_err(|error| error.to_string())?;
+        self.module.clear_context(&mut self.context);
+        self.module
+            .finalize_definitions()
+            .map_err(|error| error.to_string())?;
+
+        let code = self.module.get_finalized_function(id);
+        // the function was built with this signature just above
+        Ok(unsafe { mem::transmute::<*const u8, extern "C" fn(*mut Registers)>(code) })
+    }
+
+    // throws away every block, for when the quirks they were compiled with change or it's given
+    // a machine with a different amount of memory
+    fn flush(&mut self, cpu: &CPU) {
+        self.slots.clear();
+        self.slots.resize_with(cpu.memory().len(), || Slot::Unseen);
+        self.quirks = cpu.quirks();
+    }
+}
+
+fn module() -> Result<JITModule, String> {
+    let mut flags = settings::builder();
+    flags
+        .set("use_colocated_libcalls", "false")
+        .map_err(|error| error.to_string())?;
+    flags
+        .set("is_pic", "false")
+        .map_err(|error| error.to_string())?;
+    flags
+        .set("opt_level", "speed")
+        .map_err(|error| error.to_string())?;
+    let isa = cranelift_native::builder()
+        .map_err(|message| message.to_string())?
+        .finish(settings::Flags::new(flags))
+        .map_err(|error| error.to_string())?;
+    Ok(JITModule::new(JITBuilder::with_isa(
+        isa,
+        default_libcall_names(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // the same program run both ways must leave the same registers, gives the JIT to look at
+    fn assert_equivalent(rom: &[u8], quirks: Quirks, cycles: u32) -> Jit {
+        let mut interpreted = CPU::builder().quirks(quirks).seed(0).build().unwrap();
+        interpreted.load(rom);
+        let mut compiled = interpreted.clone();
+
+        let expected = interpreted.run_cycles(cycles);
+        let mut jit = Jit::new().unwrap();
+        let report = jit.run_cycles(&mut compiled, cycles);
+        assert_eq!(report, expected);
+        assert_eq!(compiled.pc(), interpreted.pc());
+        assert_eq!(compiled.v_registers(), interpreted.v_registers());
+        assert_eq!(compiled.index_register(), interpreted.index_register());
+        assert_eq!(compiled.memory(), interpreted.memory());
+        jit
+    }
+
+    #[test]
+    fn test_loop() {
+        // counts V0 up by 3 until it wraps past FF, adding the carries up in V2
+        let rom = [
+            0x61, 0x03, // 200: LD V1, #03
+            0x80, 0x14, // 202: ADD V0, V1
+            0x72, 0x00, // 204: ADD V2, #00
+            0x82, 0xF4, // 206: ADD V2, VF
+            0x30, 0x00, // 208: SE V0, #00
+            0x12, 0x02, // 20A: JP #202
+            0xA3, 0x00, // 20C: LD I, #300
+            0x12, 0x0E, // 20E: JP #20E
+        ];
+        assert_equivalent(&rom, Quirks::default(), 1000);
+        let jit = assert_equivalent(&rom, Quirks::vip(), 1000);
+        assert!(matches!(&jit.slots[0x202], Slot::Compiled(block) if block.length == 4));
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // the block at 20A stores V0 and V1 over its own first instruction, making it LD V1, #05
+        // the second time round
+        let rom = [
+            0x60, 0x61, // 200: LD V0, #61
+            0x61, 0x05, // 202: LD V1, #05
+            0x12, 0x0A, // 204: JP #20A
+            0x00, 0x00, // 206
+            0x00, 0x00, // 208
+            0x62, 0x01, // 20A: LD V2, #01
+            0xA2, 0x0A, // 20C: LD I, #20A
+            0xF1, 0x55, // 20E: LD [I], V1
+            0x12, 0x0A, // 210: JP #20A
+        ];
+        let jit = assert_equivalent(&rom, Quirks::default(), 50);
+        assert!(matches!(jit.slots[0x20A], Slot::Interpreted));
+    }
+
+    #[test]
+    fn test_budget() {
+        // a block longer than the budget is left to the interpreter
+        let rom = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x12, 0x00];
+        assert_equivalent(&rom, Quirks::default(), 3);
+        assert_equivalent(&rom, Quirks::default(), 9);
+    }
+
+    // an instruction the JIT compiles, or occasionally a jump or skip inside the program
+    fn register_op() -> impl Strategy<Value = u16> {
+        prop_oneof![
+            (0x6000..=0x7FFFu16),
+            (0u16..0x100).prop_flat_map(|xy| {
+                prop::sample::select(vec![0, 1, 2, 3, 4, 5, 6, 7, 0xE])
+                    .prop_map(move |n| 0x8000 | xy << 4 | n)
+            }),
+            (0xA000..=0xAFFFu16),
+            (0x3000..=0x4FFFu16),
+            (0u16..0x100).prop_map(|xy| 0x5000 | xy << 4),
+            (0u16..0x100).prop_map(|xy| 0x9000 | xy << 4),
+            (0u16..16).prop_map(|offset| 0x1200 | (offset * 2)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_equivalence(
+            ops in prop::collection::vec(register_op(), 1..16),
+            vip in any::<bool>(),
+            cycles in 1u32..200,
+        ) {
+            let rom: Vec<u8> = ops.iter().flat_map(|op| op.to_be_bytes()).collect();
+            let quirks = if vip { Quirks::vip() } else { Quirks::default() };
+            assert_equivalent(&rom, quirks, cycles);
+        }
+    }
+}
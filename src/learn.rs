@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+use sdl2::{keyboard::Keycode, render::Canvas, video::Window};
+
+use crate::overlay::{draw_box, Corner};
+
+// explanations kept on screen, the newest at the bottom
+const VISIBLE_STEPS: usize = 4;
+
+// runs the game one instruction per press of Space and says what each one did, for people
+// learning how CHIP-8 programs work
+pub struct LearnMode {
+    // steps asked for since the last frame
+    pending: u32,
+    explanations: VecDeque<String>,
+}
+
+impl LearnMode {
+    pub fn new() -> LearnMode {
+        LearnMode {
+            pending: 0,
+            explanations: VecDeque::new(),
+        }
+    }
+
+    // returns true if the key was a step, holding Space keeps stepping
+    pub fn handle_key(&mut self, key: Keycode) -> bool {
+        if key != Keycode::Space {
+            return false;
+        }
+        self.pending += 1;
+        true
+    }
+
+    // instructions to run this frame
+    pub fn take_steps(&mut self) -> u32 {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn explained(&mut self, explanation: String) {
+        println!("{}", explanation);
+        if self.explanations.len() == VISIBLE_STEPS {
+            self.explanations.pop_front();
+        }
+        self.explanations.push_back(explanation);
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        let lines = self.explanations.iter().map(String::as_str);
+        draw_box(
+            canvas,
+            lines.chain(["SPACE runs the next instruction"]),
+            Corner::BottomLeft,
+        );
+    }
+}
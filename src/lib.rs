@@ -0,0 +1,93 @@
+// the emulator core, kept free of SDL so tests and other frontends can drive it headlessly.
+// without the std feature only the CPU and what it needs are built, as no_std with alloc
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analyzer;
+#[cfg(feature = "std")]
+pub mod cheats;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod compliance;
+#[cfg(feature = "std")]
+pub mod coverage;
+pub mod cpu;
+#[cfg(feature = "std")]
+pub mod crash;
+#[cfg(feature = "std")]
+pub mod crowd;
+#[cfg(feature = "std")]
+pub mod demos;
+#[cfg(feature = "std")]
+pub mod dumps;
+#[cfg(feature = "embedded")]
+pub mod embedded;
+pub mod error;
+pub mod events;
+#[cfg(feature = "std")]
+pub mod explain;
+#[cfg(feature = "fetch")]
+pub mod fetch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod flowgraph;
+pub mod framebuffer;
+#[cfg(feature = "std")]
+pub mod headless;
+#[cfg(feature = "std")]
+pub mod input;
+pub mod instruction;
+#[cfg(feature = "jit")]
+pub mod jit;
+#[cfg(feature = "std")]
+pub mod library;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+#[cfg(feature = "std")]
+pub mod limiter;
+#[cfg(feature = "json")]
+pub mod metadata;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod netplay;
+#[cfg(feature = "std")]
+pub mod options;
+#[cfg(feature = "std")]
+pub mod pacing;
+pub mod patch;
+#[cfg(feature = "std")]
+pub mod platform;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+#[cfg(feature = "std")]
+pub mod profiler;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quirks;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "std")]
+pub mod rewind;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "std")]
+pub mod selftest;
+#[cfg(feature = "std")]
+pub mod settings;
+#[cfg(feature = "json")]
+pub mod state;
+#[cfg(feature = "std")]
+pub mod tas;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod watchdog;
+#[cfg(feature = "std")]
+pub mod wav;
+#[cfg(feature = "web")]
+pub mod web;
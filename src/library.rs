@@ -0,0 +1,204 @@
+// a directory of ROMs with a preview of each, taken by playing it without a window for a couple
+// of seconds. previews are kept in the user's cache directory under a checksum of the ROM, so a
+// big library is only slow the first time. a checkout of the CHIP-8 community archive can be given
+// in place of the directory, its titles and descriptions come with the json feature
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::headless::{self, screen_text};
+#[cfg(feature = "json")]
+use crate::metadata::Metadata;
+use crate::patch::crc32;
+use crate::quirks::Quirks;
+
+// long enough for most games to get past a blank screen
+pub const THUMBNAIL_FRAMES: u32 = 120;
+
+// CXNN is seeded so a preview is the same every time
+const SEED: u64 = 0xC8;
+
+// the archive's index of its games, by the name of each one's ROM without the .ch8. the ROMs are
+// in roms beside it
+pub const ARCHIVE_PROGRAMS: &str = "programs.json";
+
+pub struct Entry {
+    pub path: PathBuf,
+    // the screen as headless::screen_text gives it, None if the program stopped with an error
+    pub thumbnail: Option<String>,
+    // from the ROM's metadata, the title with its authors
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Entry {
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// every .ch8 file in the directory with its thumbnail
+pub fn scan(directory: &Path) -> io::Result<Vec<Entry>> {
+    let cache = cache_dir().map(|dir| dir.join("thumbnails"));
+    scan_cached(directory, cache.as_deref())
+}
+
+fn scan_cached(directory: &Path, cache: Option<&Path>) -> io::Result<Vec<Entry>> {
+    let directory = rom_directory(directory);
+    let paths = roms(&directory)?;
+    let about = about(&directory, &paths)?;
+    paths
+        .into_iter()
+        .zip(about)
+        .map(|(path, (title, description))| {
+            let rom = fs::read(&path)?;
+            let thumbnail = thumbnail_cached(&rom, cache);
+            Ok(Entry {
+                path,
+                thumbnail,
+                title,
+                description,
+            })
+        })
+        .collect()
+}
+
+// where the ROMs are when `path` is a checkout of the archive, otherwise `path` itself
+pub fn rom_directory(path: &Path) -> PathBuf {
+    match path.join(ARCHIVE_PROGRAMS).is_file() {
+        true => path.join("roms"),
+        false => path.to_path_buf(),
+    }
+}
+
+// each ROM's title with its authors and its description, where its metadata has them
+#[cfg(feature = "json")]
+pub fn about(
+    directory: &Path,
+    roms: &[PathBuf],
+) -> io::Result<Vec<(Option<String>, Option<String>)>> {
+    let metadata = Metadata::load_all(directory, roms)?;
+    Ok(metadata
+        .into_iter()
+        .map(|metadata| {
+            metadata.map_or((None, None), |metadata| {
+                (metadata.byline(), metadata.description)
+            })
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "json"))]
+pub fn about(_: &Path, roms: &[PathBuf]) -> io::Result<Vec<(Option<String>, Option<String>)>> {
+    Ok(vec![(None, None); roms.len()])
+}
+
+// the paths of every .ch8 file in the directory, by name
+pub fn roms(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<_> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("ch8"))
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+pub fn thumbnail(rom: &[u8]) -> Option<String> {
+    headless::run(rom, Quirks::default(), SEED, THUMBNAIL_FRAMES, &[])
+        .ok()
+        .map(|cpu| screen_text(&cpu))
+}
+
+// an empty file records a program that stopped, so it isn't run again either
+fn thumbnail_cached(rom: &[u8], cache: Option<&Path>) -> Option<String> {
+    let path = cache.map(|dir| dir.join(format!("{:08x}.txt", crc32(rom))));
+    if let Some(text) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+        return (!text.is_empty()).then_some(text);
+    }
+
+    let thumbnail = thumbnail(rom);
+    // a cache that can't be written is only slower, not an error
+    if let Some(path) = path {
+        let text = thumbnail.as_deref().unwrap_or_default();
+        let _ = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(path, text));
+    }
+    thumbnail
+}
+
+// a thumbnail at half height, two rows of pixels to each line of half blocks
+pub fn render(thumbnail: &str) -> String {
+    let rows: Vec<Vec<bool>> = thumbnail
+        .lines()
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect();
+
+    rows.chunks(2)
+        .map(|pair| {
+            let empty = Vec::new();
+            let (top, bottom) = (&pair[0], pair.get(1).unwrap_or(&empty));
+            let mut line: String = (0..top.len())
+                .map(|x| match (top[x], bottom.get(x) == Some(&true)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("rusty_chip8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render("#.#.\n##..\n.#..\n"), "█▄▀ \n ▀  \n");
+    }
+
+    #[test]
+    fn test_thumbnail_cached() {
+        let cache = env::temp_dir().join(format!("rusty_chip8-library-{}", std::process::id()));
+        // draws the 0 glyph at the top left and waits
+        let rom = [0xD0, 0x05, 0x12, 0x02];
+        let thumbnail = thumbnail_cached(&rom, Some(&cache)).unwrap();
+        let lines: Vec<_> = thumbnail.lines().collect();
+        assert!(lines[0].starts_with("####...."));
+        assert!(lines[1].starts_with("#..#...."));
+        assert_eq!(thumbnail_cached(&rom, Some(&cache)).unwrap(), thumbnail);
+
+        // 5001 isn't an instruction, so there's nothing to show
+        assert_eq!(thumbnail_cached(&[0x50, 0x01], Some(&cache)), None);
+        assert_eq!(thumbnail_cached(&[0x50, 0x01], Some(&cache)), None);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+        fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn test_scan() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+        let entries = scan_cached(&directory, None).unwrap();
+        let names: Vec<_> = entries.iter().map(Entry::name).collect();
+        assert!(names.contains(&"Airplane".to_string()));
+        assert!(entries.iter().all(|entry| entry.thumbnail.is_some()));
+    }
+}
@@ -0,0 +1,428 @@
+// the emulator as a libretro core, so it can be loaded into RetroArch. build it with
+//   cargo build --release --no-default-features --features libretro
+// and load target/release/libchip8.so (chip8.dll, libchip8.dylib) as the core
+//
+// the frontend calls these from one thread, the mutexes only make the statics safe to share
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    ffi::{c_char, c_uint, c_void},
+    ptr,
+    sync::Mutex,
+};
+
+use crate::cpu::{CPU, MEMORY_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, START_ADDRESS};
+
+const API_VERSION: c_uint = 1;
+
+const ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const DEVICE_JOYPAD: c_uint = 1;
+const DEVICE_KEYBOARD: c_uint = 3;
+const MEMORY_SYSTEM_RAM: c_uint = 2;
+const REGION_NTSC: c_uint = 0;
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: f64 = 44_100.0;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / FPS) as usize;
+const BEEP_HZ: usize = 440;
+const BEEP_VOLUME: i16 = 0x1000;
+
+const PIXEL_ON: u32 = 0x00FF_FFFF;
+const PIXEL_OFF: u32 = 0x0000_0000;
+
+// libretro keyboard codes are lowercase ASCII, in the same layout as the SDL frontend
+const KEYBOARD: [(c_uint, usize); 16] = [
+    (b'1' as c_uint, 0x1),
+    (b'2' as c_uint, 0x2),
+    (b'3' as c_uint, 0x3),
+    (b'4' as c_uint, 0xC),
+    (b'q' as c_uint, 0x4),
+    (b'w' as c_uint, 0x5),
+    (b'e' as c_uint, 0x6),
+    (b'r' as c_uint, 0xD),
+    (b'a' as c_uint, 0x7),
+    (b's' as c_uint, 0x8),
+    (b'd' as c_uint, 0x9),
+    (b'f' as c_uint, 0xE),
+    (b'z' as c_uint, 0xA),
+    (b'x' as c_uint, 0x0),
+    (b'c' as c_uint, 0xB),
+    (b'v' as c_uint, 0xF),
+];
+
+// the d-pad is W A S D on the keypad, the face buttons are the keys around them
+const JOYPAD: [(c_uint, usize); 8] = [
+    (4, 0x5), // up
+    (5, 0x8), // down
+    (6, 0x7), // left
+    (7, 0x9), // right
+    (8, 0x6), // A
+    (0, 0x4), // B
+    (9, 0xD), // X
+    (1, 0xE), // Y
+];
+
+#[repr(C)]
+pub struct SystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    geometry: GameGeometry,
+    timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+type EnvironmentFn = unsafe extern "C" fn(command: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = unsafe extern "C" fn();
+type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    cpu: CPU,
+    rom: Vec<u8>,
+    framebuffer: [u32; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // where the square wave is, so it doesn't click between frames
+    sample: usize,
+    // the program hit an error, the last frame stays on screen
+    stopped: bool,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    API_VERSION
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    *info = SystemInfo {
+        library_name: c"rusty_chip8".as_ptr(),
+        library_version: c"0.1.0".as_ptr(),
+        valid_extensions: c"ch8|c8".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    *info = SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: SCREEN_WIDTH as c_uint,
+            max_height: SCREEN_HEIGHT as c_uint,
+            aspect_ratio: SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32,
+        },
+        timing: SystemTiming {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+// every frame's audio goes out in one batch
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_: c_uint, _: c_uint) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+
+    let rom = std::slice::from_raw_parts((*game).data as *const u8, (*game).size).to_vec();
+    if rom.len() > MEMORY_SIZE - START_ADDRESS as usize {
+        return false;
+    }
+
+    if let Some(environment) = CALLBACKS.lock().unwrap().environment {
+        let mut format = PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut _ as *mut c_void,
+        ) {
+            return false;
+        }
+    }
+
+    // the frontend shows the game as failed to load
+    let mut cpu = CPU::new();
+    if cpu.try_load(&rom).is_err() {
+        return false;
+    }
+    *CORE.lock().unwrap() = Some(Core {
+        cpu,
+        rom,
+        framebuffer: [PIXEL_OFF; SCREEN_WIDTH * SCREEN_HEIGHT],
+        sample: 0,
+        stopped: false,
+    });
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_: c_uint, _: *const GameInfo, _: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.cpu.reset();
+        core.cpu.load(&core.rom);
+        core.stopped = false;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_run() {
+    let callbacks = CALLBACKS.lock().unwrap();
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+
+    if let (Some(poll), Some(state)) = (callbacks.input_poll, callbacks.input_state) {
+        poll();
+
+        let mut keys = [false; 16];
+        for &(id, key) in KEYBOARD.iter() {
+            keys[key] |= state(0, DEVICE_KEYBOARD, 0, id) != 0;
+        }
+        for &(id, key) in JOYPAD.iter() {
+            keys[key] |= state(0, DEVICE_JOYPAD, 0, id) != 0;
+        }
+        core.cpu.set_keys(&keys);
+    }
+
+    // cheats may have written to memory through retro_get_memory_data's pointer
+    core.cpu.memory_changed();
+    if !core.stopped && core.cpu.run_frame().is_err() {
+        core.stopped = true;
+    }
+
+    for (pixel, on) in core.framebuffer.iter_mut().zip(core.cpu.pixels()) {
+        *pixel = if on { PIXEL_ON } else { PIXEL_OFF };
+    }
+    if let Some(video_refresh) = callbacks.video_refresh {
+        video_refresh(
+            core.framebuffer.as_ptr() as *const c_void,
+            SCREEN_WIDTH as c_uint,
+            SCREEN_HEIGHT as c_uint,
+            SCREEN_WIDTH * 4,
+        );
+    }
+
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+        let samples = audio_frame(core);
+        audio_sample_batch(samples.as_ptr(), SAMPLES_PER_FRAME);
+    }
+}
+
+// a square wave while the sound timer runs, stereo samples interleaved
+fn audio_frame(core: &mut Core) -> Vec<i16> {
+    let beeping = core.cpu.sound_timer() > 0;
+    let half_period = SAMPLE_RATE as usize / BEEP_HZ / 2;
+
+    let mut samples = Vec::with_capacity(SAMPLES_PER_FRAME * 2);
+    for _ in 0..SAMPLES_PER_FRAME {
+        let value = match beeping {
+            true if (core.sample / half_period) & 1 != 0 => BEEP_VOLUME,
+            true => -BEEP_VOLUME,
+            false => 0,
+        };
+        samples.extend_from_slice(&[value, value]);
+        core.sample = core.sample.wrapping_add(1);
+    }
+    samples
+}
+
+// save states aren't supported yet
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize(_: *mut c_void, _: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unserialize(_: *const c_void, _: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_: c_uint, _: bool, _: *const c_char) {}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    REGION_NTSC
+}
+
+// lets RetroArch's cheat search and achievements see the 4K of RAM
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match (id, CORE.lock().unwrap().as_mut()) {
+        (MEMORY_SYSTEM_RAM, Some(core)) => core.cpu.memory_mut().as_mut_ptr() as *mut c_void,
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match (id, CORE.lock().unwrap().as_ref()) {
+        (MEMORY_SYSTEM_RAM, Some(_)) => MEMORY_SIZE,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FRAME: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    static AUDIO_FRAMES: Mutex<usize> = Mutex::new(0);
+
+    unsafe extern "C" fn video_refresh(
+        data: *const c_void,
+        width: c_uint,
+        height: c_uint,
+        _: usize,
+    ) {
+        let pixels = std::slice::from_raw_parts(data as *const u32, (width * height) as usize);
+        *FRAME.lock().unwrap() = pixels.to_vec();
+    }
+
+    unsafe extern "C" fn audio_sample_batch(_: *const i16, frames: usize) -> usize {
+        *AUDIO_FRAMES.lock().unwrap() += frames;
+        frames
+    }
+
+    #[test]
+    fn test_run() {
+        // draw the 0 glyph at 0,0 then loop
+        let rom: [u8; 4] = [0xD0, 0x05, 0x12, 0x02];
+        let game = GameInfo {
+            path: ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: ptr::null(),
+        };
+
+        unsafe {
+            retro_set_video_refresh(video_refresh);
+            retro_set_audio_sample_batch(audio_sample_batch);
+            assert!(retro_load_game(&game));
+            retro_run();
+        }
+
+        let frame = FRAME.lock().unwrap();
+        assert_eq!(frame.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert_eq!(
+            frame[..5],
+            [PIXEL_ON, PIXEL_ON, PIXEL_ON, PIXEL_ON, PIXEL_OFF]
+        );
+        assert_eq!(*AUDIO_FRAMES.lock().unwrap(), SAMPLES_PER_FRAME);
+        assert_eq!(retro_get_memory_size(MEMORY_SYSTEM_RAM), MEMORY_SIZE);
+
+        retro_unload_game();
+        assert!(retro_get_memory_data(MEMORY_SYSTEM_RAM).is_null());
+    }
+}
@@ -0,0 +1,114 @@
+// keeps the frame loop to a frame rate when vsync isn't doing it, rather than running flat out at
+// 100% CPU. most of the wait is a sleep, the last of it a spin, since a sleep can wake a
+// millisecond or more late. how much is left to spin follows how late the sleeps have actually
+// woken, so it costs next to nothing on a system with a precise timer
+use std::{
+    hint, thread,
+    time::{Duration, Instant},
+};
+
+// what's left for spinning to begin with, and the most it can grow to
+const INITIAL_MARGIN: Duration = Duration::from_millis(2);
+const MAX_MARGIN: Duration = Duration::from_millis(4);
+
+pub struct FrameLimiter {
+    frame: Duration,
+    // when the frame being run should end
+    deadline: Instant,
+    margin: Duration,
+}
+
+impl FrameLimiter {
+    pub fn new(fps: u32, now: Instant) -> FrameLimiter {
+        let frame = Duration::from_secs(1) / fps.max(1);
+        FrameLimiter {
+            frame,
+            deadline: now + frame,
+            margin: INITIAL_MARGIN,
+        }
+    }
+
+    // call once the frame is presented, returns when the next one should start. a frame that
+    // ran long isn't made up by rushing the ones after it
+    pub fn wait(&mut self) {
+        loop {
+            let now = Instant::now();
+            let Some(remaining) = self.deadline.checked_duration_since(now) else {
+                break;
+            };
+            if remaining > self.margin {
+                let sleep = remaining - self.margin;
+                thread::sleep(sleep);
+                let late = now.elapsed().saturating_sub(sleep);
+                self.margin = adapt(self.margin, late);
+            } else if remaining.is_zero() {
+                break;
+            } else {
+                hint::spin_loop();
+            }
+        }
+
+        let now = Instant::now();
+        self.deadline += self.frame;
+        if self.deadline < now {
+            self.deadline = now + self.frame;
+        }
+    }
+
+    // starts the next frame from `now`, for when something else held the last one up
+    pub fn restart(&mut self, now: Instant) {
+        self.deadline = now + self.frame;
+    }
+}
+
+// the margin jumps to a sleep that woke later than it allows for, and otherwise creeps back down
+// towards how late they wake, so one slow wake-up doesn't mean spinning for good
+fn adapt(margin: Duration, late: Duration) -> Duration {
+    let margin = if late > margin {
+        late
+    } else {
+        margin - (margin - late) / 16
+    };
+    margin.min(MAX_MARGIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adapt() {
+        let margin = Duration::from_millis(2);
+        assert_eq!(
+            adapt(margin, Duration::from_millis(3)),
+            Duration::from_millis(3)
+        );
+        assert_eq!(adapt(margin, Duration::from_secs(1)), MAX_MARGIN);
+        assert_eq!(
+            adapt(margin, Duration::ZERO),
+            margin - Duration::from_micros(125)
+        );
+    }
+
+    #[test]
+    fn test_wait() {
+        let start = Instant::now();
+        let mut limiter = FrameLimiter::new(200, start);
+        for _ in 0..10 {
+            limiter.wait();
+        }
+        // ten frames of 5ms can't go faster, and shouldn't be far off
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_restart() {
+        let start = Instant::now();
+        let mut limiter = FrameLimiter::new(200, start - Duration::from_secs(1));
+        limiter.restart(start);
+        limiter.wait();
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+}
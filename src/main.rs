@@ -1,99 +1,1622 @@
 use sdl2::{
-    event::Event, keyboard::Keycode, pixels::Color, rect::Rect, render::Canvas, video::Window,
+    event::Event,
+    keyboard::{Keycode, Mod},
+    pixels::Color,
+    rect::Rect,
+    render::Canvas,
+    sys::SDL_RendererFlags,
+    video::{FullscreenType, Window, WindowPos},
+    VideoSubsystem,
 };
-use std::{env, fs::File, io::Read};
+use std::{
+    cell::Cell,
+    collections::BTreeSet,
+    env,
+    fmt::Write as _,
+    fs,
+    fs::File,
+    io::{self, BufWriter, Read},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, error, info, trace_span, warn, Level};
 
-use cpu::{CPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+use bezel::Bezel;
+use cheat_menu::CheatMenu;
+use chip8::cheats::Cheats;
+use chip8::clock::Clock;
+use chip8::coverage::Coverage;
+use chip8::cpu::{CpuBuilder, CPU, SCREEN_HEIGHT, SCREEN_PIXELS, SCREEN_WIDTH, START_ADDRESS};
+use chip8::crash::History;
+use chip8::crowd::Crowd;
+#[cfg(feature = "json")]
+use chip8::error::Error;
+use chip8::error::RomError;
+use chip8::events::{self, EventBus};
+use chip8::explain::explain;
+#[cfg(feature = "fetch")]
+use chip8::fetch;
+use chip8::flowgraph::{DynamicEdges, FlowGraph};
+use chip8::framebuffer::Framebuffer;
+use chip8::input::{self, KeyQueue, StickyKeys};
+use chip8::limiter::FrameLimiter;
+#[cfg(feature = "json")]
+use chip8::metadata::Metadata;
+use chip8::metrics::Metrics;
+use chip8::netplay::Netplay;
+use chip8::options::{self, Command, Options, SecondKeys};
+use chip8::pacing::SpeedDetector;
+use chip8::platform::Platform;
+#[cfg(feature = "plugins")]
+use chip8::plugin::Plugins;
+use chip8::profiler::Profiler;
+use chip8::quirks::Quirks;
+#[cfg(feature = "remote")]
+use chip8::remote::RemoteServer;
+use chip8::rewind::{Rewind, REWIND_FRAMES};
+#[cfg(feature = "rpc")]
+use chip8::rpc::RpcServer;
+use chip8::settings::{Palette, Settings};
+#[cfg(feature = "json")]
+use chip8::state;
+use chip8::tas::{Movie, Recording};
+use chip8::trace::{self, Tracer};
+use chip8::watchdog::{self, Watchdog};
+use chip8::wav::WavWriter;
+use chip8::{analyzer, compliance, dumps, headless, library, patch, selftest};
+#[cfg(feature = "clipboard")]
+use clipboard::ScreenClipboard;
+use command_palette::{Action, CommandPalette};
+use debugger::Debugger;
+use heatmap::Heatmap;
+use learn::LearnMode;
+use memory_viewer::MemoryViewer;
+use overlay::{draw_notice, KeypadDisplay, RegisterHud};
+use pause_menu::{PauseAction, PauseMenu};
+use ram_search::RamSearch;
+#[cfg(feature = "lua")]
+use script::Script;
+use scrubber::Scrubber;
+use settings_menu::SettingsMenu;
+use speedrun::SpeedrunTimer;
+use sprite_viewer::SpriteViewer;
+#[cfg(feature = "json")]
+use state_picker::{SlotAction, StatePicker};
+use tas_editor::TasEditor;
 
-mod cpu;
+mod bezel;
+mod cheat_menu;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod command_palette;
+mod debugger;
+mod heatmap;
+mod learn;
+mod memory_viewer;
+mod overlay;
+mod pause_menu;
+mod ram_search;
+#[cfg(feature = "lua")]
+mod script;
+mod scrubber;
+mod settings_menu;
+mod speedrun;
+mod split;
+mod sprite_viewer;
+#[cfg(feature = "json")]
+mod state_picker;
+mod tas_editor;
+#[cfg(feature = "terminal")]
+mod terminal;
+mod text;
 
 const SCALE: u32 = 15;
 const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
 const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
-const TICKS_PER_FRAME: u32 = 10;
+const BENCH_DURATION: Duration = Duration::from_secs(5);
+const WINDOW_TITLE: &str = "Rusty Chip8";
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: cargo run /path/to/game");
+    let args: Vec<_> = env::args().skip(1).collect();
+    let options = match options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}\n\n{}", message, options::USAGE);
+            return;
+        }
+    };
+    init_logging(options.log_level, options.log_path.as_deref());
+
+    #[cfg(not(feature = "lua"))]
+    if options.script_path.is_some() {
+        println!("--script needs Lua support, rebuild with --features lua");
+        return;
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    if !options.plugin_paths.is_empty() {
+        println!("--plugin needs plugin support, rebuild with --features plugins");
+        return;
+    }
+
+    #[cfg(not(feature = "json"))]
+    if options.load_state_path.is_some() || options.save_state_path.is_some() {
+        println!("--load-state and --save-state need JSON support, rebuild with --features json");
+        return;
+    }
+
+    #[cfg(not(feature = "rpc"))]
+    if options.rpc_address.is_some() {
+        println!("--rpc needs RPC support, rebuild with --features rpc");
+        return;
+    }
+
+    #[cfg(not(feature = "remote"))]
+    if options.serve_address.is_some() {
+        println!("--serve needs remote support, rebuild with --features remote");
+        return;
+    }
+
+    if options.self_test {
+        match selftest::run() {
+            Ok(checks) => println!("self-test passed, {} checks", checks),
+            Err(message) => {
+                println!("self-test failed, {}", message);
+                process::exit(1);
+            }
+        }
         return;
     }
 
+    // the path is a directory of test ROMs rather than a game
+    if options.command == Command::Compliance {
+        print!(
+            "{}",
+            compliance::report(Path::new(&options.rom_path), compliance::SUITE)
+        );
+        return;
+    }
+
+    if options.command == Command::Library {
+        let entries =
+            library::scan(Path::new(&options.rom_path)).expect("unable to read ROM directory");
+        for entry in entries {
+            println!("{}", entry.title.clone().unwrap_or_else(|| entry.name()));
+            if let Some(description) = &entry.description {
+                println!("{}", description);
+            }
+            match &entry.thumbnail {
+                Some(thumbnail) => println!("{}", library::render(thumbnail)),
+                None => println!("(stopped with an error)\n"),
+            }
+        }
+        return;
+    }
+
+    let mut settings = Settings::load_default().expect("unable to read settings");
+
+    let mut rom = match options.demo {
+        Some(demo) => demo.rom.to_vec(),
+        None => read_rom(&options.rom_path),
+    };
+    if let Some(path) = &options.patch_path {
+        let patch = fs::read(path).expect("unable to read patch file");
+        rom = match patch::apply(&rom, &patch) {
+            Ok(patched) => patched,
+            Err(message) => {
+                println!("unable to apply {}: {}", path, message);
+                process::exit(1);
+            }
+        };
+    }
+
+    if let Some(message) = dumps::check(&rom) {
+        warn!("{}", message);
+    }
+    let rom_path = options.demo.is_none().then_some(options.rom_path.as_str());
+    // the platform's speed is where the game's own settings start from
+    let platform_quirks = options.platform.as_ref().map(|platform| {
+        settings.speed = platform.speed;
+        platform.quirks
+    });
+    let (window_title, game_quirks) = read_game_settings(&mut settings, &rom, rom_path);
+    let quirks = options.quirks.or(platform_quirks).unwrap_or(game_quirks);
+
+    match options.command {
+        Command::Run | Command::Compliance | Command::Library => (),
+        Command::Cfg => {
+            match control_flow_graph(&rom, &BTreeSet::new()) {
+                Ok(graph) => print!("{}", graph.to_dot()),
+                Err(error) => {
+                    error!("unable to load the ROM: {}", error);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::Check => {
+            let warnings = analyzer::check(&rom);
+            for warning in warnings.iter() {
+                println!("warning: {}", warning);
+            }
+            println!("{} warning(s)", warnings.len());
+
+            if !warnings.is_empty() {
+                process::exit(1);
+            }
+            return;
+        }
+        Command::Term => {
+            #[cfg(feature = "terminal")]
+            if let Err(error) =
+                terminal::run(&rom, cpu_builder(&options).quirks(quirks), settings.speed)
+            {
+                println!("{}", error);
+                process::exit(1);
+            }
+            #[cfg(not(feature = "terminal"))]
+            println!("term needs terminal support, rebuild with --features terminal");
+            return;
+        }
+    }
+
+    if let Some(path) = &options.compare_path {
+        let reference = fs::read_to_string(path).expect("unable to read reference trace");
+        match trace::compare(&rom, quirks, &reference) {
+            Ok(count) => println!("all {} instructions match", count),
+            Err(message) => {
+                println!("{}", message);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if options.bench {
+        let report = match headless::benchmark(&rom, quirks, BENCH_DURATION) {
+            Ok(report) => report,
+            Err(error) => {
+                error!("unable to load the ROM: {}", error);
+                process::exit(1);
+            }
+        };
+        if let Some(error) = report.stopped {
+            println!("program stopped: {}", error);
+        }
+        println!(
+            "ran {} instructions in {:.2}s: {:.0} instructions/s, {:.0} frames/s ({:.0}x real time)",
+            report.instructions,
+            report.elapsed.as_secs_f64(),
+            report.instructions_per_second(),
+            report.frames_per_second(),
+            report.frames_per_second() / 60.0
+        );
+        return;
+    }
+
+    if let Some(path) = &options.split_path {
+        let other_rom = read_rom(path);
+        if let Err(error) = split::run(
+            [&rom, &other_rom],
+            [quirks, options.split_quirks],
+            settings.speed,
+        ) {
+            error!("unable to load the ROMs: {}", error);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // a bezel beside the game is picked up without asking, like its cheats
+    let bezel_path = options.bezel_path.clone().or_else(|| {
+        let path = format!("{}.bezel.bmp", options.rom_path);
+        (options.demo.is_none() && Path::new(&path).is_file()).then_some(path)
+    });
+    let bezel_image = bezel_path.map(|path| match bezel::load(&path) {
+        Ok(image) => image,
+        Err(message) => {
+            error!("{}", message);
+            process::exit(1);
+        }
+    });
+    let (window_width, window_height) = bezel_image
+        .as_ref()
+        .map_or((WINDOW_WIDTH, WINDOW_HEIGHT), |image| image.size());
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = video_subsystem
-        .window("Rusty Chip8", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .window(&window_title, window_width, window_height)
         .position_centered()
         .opengl()
         .build()
         .unwrap();
 
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut canvas = match options.vsync {
+        true => window.into_canvas().present_vsync(),
+        false => window.into_canvas(),
+    }
+    .build()
+    .unwrap();
+    // without vsync, asked for or not, the loop would spin as fast as it can
+    let vsync = canvas.info().flags & SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32 != 0;
+    if options.vsync && !vsync {
+        info!(
+            "vsync isn't available, keeping to {} frames a second",
+            options.fps
+        );
+    }
+    // with vsync it still paces the frames that aren't presented, which present wouldn't hold up
+    let mut limiter = FrameLimiter::new(options.fps, Instant::now());
+    // everything is drawn at the window's first size and scaled up to fill it when fullscreen
+    canvas
+        .set_logical_size(window_width, window_height)
+        .unwrap();
     canvas.clear();
     canvas.present();
+    let texture_creator = canvas.texture_creator();
+    let bezel = bezel_image
+        .map(|image| Bezel::new(&image, &texture_creator).expect("unable to show bezel"));
 
     let mut event_pump = sdl_context.event_pump().unwrap();
-    let mut cpu = CPU::new();
+    let timer = sdl_context.timer().unwrap();
+    let mut cpu = cpu_builder(&options)
+        .build()
+        .expect("unable to set up the machine");
+    let mut debugger = Debugger::new();
+    let mut register_hud = RegisterHud::new();
+    let mut keypad_display = KeypadDisplay::new();
+    let mut memory_viewer = MemoryViewer::new(cpu.memory().len());
+    let mut heatmap = Heatmap::new(cpu.memory().len());
+    let mut sprite_viewer = SpriteViewer::new();
+    let mut cheat_menu = CheatMenu::new();
+    let displays = video_subsystem
+        .num_video_displays()
+        .map_or(1, |displays| displays as u32);
+    let mut settings_menu = SettingsMenu::new(displays);
+    let mut fullscreen = false;
+    let mut command_palette = CommandPalette::new();
+    #[cfg(feature = "clipboard")]
+    let mut clipboard = ScreenClipboard::new();
+    // Load ROM looks beside the game, or in the working directory for one that isn't a file
+    let rom_directory = Path::new(&options.rom_path)
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty() && !options.rom_path.contains("://"))
+        .unwrap_or(Path::new("."));
+    let mut pause_menu = PauseMenu::new(rom_directory);
+    let mut speedrun_timer = SpeedrunTimer::new();
+    let mut tas_editor = TasEditor::new();
+    let mut learn_mode = options.learn.then(LearnMode::new);
+    // the machine before the instruction learn mode explains, copied into rather than cloned
+    let mut learn_before = learn_mode.is_some().then(CPU::new);
+    let mut recording = options.tas.then(Recording::new);
+    let movie = options.play_movie_path.as_ref().map(|path| {
+        let text = fs::read_to_string(path).expect("unable to read movie file");
+        Movie::parse(&text).unwrap_or_else(|message| {
+            error!("unable to load {}: {}", path, message);
+            process::exit(1);
+        })
+    });
+    // the movie's frame the next frame plays
+    let mut movie_frame = 0;
+    let mut rewind = Rewind::new(REWIND_FRAMES);
+    let mut scrubber = Scrubber::new();
+    // a demo's cheats go in the working directory, under its name
+    let cheats_path = match options.demo {
+        Some(demo) => format!("{}.cheats", demo.name),
+        None => format!("{}.cheats", options.rom_path),
+    };
+    let mut cheats = Cheats::load(&cheats_path).expect("unable to read cheats");
+    #[cfg(feature = "json")]
+    let mut state_picker = StatePicker::new(match options.demo {
+        Some(demo) => demo.name,
+        None => &options.rom_path,
+    });
 
-    let mut rom = File::open(&args[1]).expect("unable to open ROM file");
-    let mut buffer = Vec::new();
-    rom.read_to_end(&mut buffer);
-    cpu.load(&buffer);
+    let mut tracer = options.trace_path.as_ref().map(|path| {
+        Tracer::create(path, options.trace_range.clone()).expect("unable to create trace file")
+    });
+    // the machine's events, listened for rather than looked for in its registers
+    let mut event_bus = EventBus::new();
+    event_bus.subscribe(|event| debug!("{:?}", event));
+    let buzzing = Rc::new(Cell::new(false));
+    let breakpoint = Rc::new(Cell::new(None));
+    // set when the screen changed since it was last presented, so frames that leave it as it was
+    // aren't drawn again
+    let screen_dirty = Rc::new(Cell::new(true));
+    {
+        let (buzzing, breakpoint, screen_dirty) =
+            (buzzing.clone(), breakpoint.clone(), screen_dirty.clone());
+        event_bus.subscribe(move |event| match *event {
+            events::Event::SoundStarted => buzzing.set(true),
+            events::Event::SoundStopped => buzzing.set(false),
+            events::Event::Breakpoint(address) => breakpoint.set(Some(address)),
+            events::Event::DisplayCleared
+            | events::Event::DisplayChanged
+            | events::Event::RomLoaded(_) => screen_dirty.set(true),
+            _ => (),
+        });
+    }
+    let mut audio = options.audio_path.as_ref().map(|path| {
+        let file = File::create(path).expect("unable to create audio file");
+        WavWriter::new(BufWriter::new(file)).expect("unable to write audio file")
+    });
+    let mut profiler = options.profile.then(|| Profiler::new(cpu.memory().len()));
+    let mut coverage = options
+        .coverage_path
+        .is_some()
+        .then(|| Coverage::new(cpu.memory().len()));
+    let mut dynamic_edges = options.cfg_path.is_some().then(DynamicEdges::new);
+    let mut watchdog = options
+        .watchdog
+        .then(|| Watchdog::new(watchdog::DEFAULT_LIMIT));
+    let mut speed_detector = options.auto_speed.then(SpeedDetector::new);
+    let mut history = History::new();
+    // set when the watchdog paused the game, P carries on
+    let mut stalled = false;
+    // set when the program reached a breakpoint, P carries on from there too
+    let mut at_breakpoint = None;
+    let mut breakpoint_notice = String::new();
+    let mut frame: u64 = 0;
+    // the screen as the last frame left it and which display it was, for anti-flicker
+    let mut last_screen = (cpu.screen, cpu.display_mode());
+    // anti-flicker blends with the frame before, so the frame after a change is drawn too
+    let mut flicker_owed = false;
+    // the lit pixels of a frame, kept from frame to frame so drawing doesn't allocate
+    let mut rects = Vec::with_capacity(SCREEN_PIXELS);
+
+    cpu.set_quirks(quirks);
+    if let Err(error) = cpu.try_load(&rom) {
+        error!("unable to load the ROM: {}", error);
+        process::exit(1);
+    }
+    #[cfg(feature = "json")]
+    if let Some(path) = &options.load_state_path {
+        let text = fs::read_to_string(path).expect("unable to read state file");
+        cpu = match state::from_json(&text) {
+            Ok(loaded) => loaded,
+            Err(message) => {
+                error!("unable to load {}: {}", path, message);
+                process::exit(1);
+            }
+        };
+    }
+    // a state isn't saved with it, it's how this run was asked to be checked
+    cpu.set_strict(options.strict);
+    let mut ram_search = RamSearch::new(cpu.memory());
+
+    #[cfg(feature = "plugins")]
+    let plugins = Plugins::load(&options.plugin_paths).unwrap_or_else(|message| {
+        error!("{}", message);
+        process::exit(1);
+    });
+    #[cfg(feature = "plugins")]
+    plugins.install(&mut cpu);
+
+    #[cfg(feature = "lua")]
+    let mut script = options.script_path.as_ref().map(|path| {
+        let source = fs::read_to_string(path).expect("unable to read script file");
+        Script::new(&source, path, &mut cpu).expect("unable to load script")
+    });
+
+    #[cfg(feature = "remote")]
+    let mut remote = options.serve_address.as_ref().map(|address| {
+        let server = RemoteServer::start(address).expect("unable to start remote server");
+        info!("serving on ws://{}", server.address());
+        server
+    });
+
+    #[cfg(feature = "rpc")]
+    let mut rpc = options.rpc_address.as_ref().map(|address| {
+        let server = RpcServer::start(address).expect("unable to start RPC server");
+        info!("RPC listening on {}", server.address());
+        server
+    });
+
+    let metrics = options.metrics_address.as_ref().map(|address| {
+        let metrics = Metrics::start(address).expect("unable to start metrics server");
+        info!("metrics on http://{}/metrics", metrics.address());
+        metrics
+    });
+
+    let mut netplay = match (&options.host_address, &options.join_address) {
+        (Some(address), _) => {
+            info!("waiting for the other player on {}", address);
+            Some(Netplay::host(address, &rom, rand::random()).expect("unable to host netplay"))
+        }
+        (None, Some(address)) => {
+            Some(Netplay::join(address, &rom).expect("unable to join netplay"))
+        }
+        (None, None) => None,
+    };
+    if let Some(netplay) = netplay.as_ref() {
+        cpu.seed(netplay.seed());
+    }
+    // CXNN has to give the same numbers when the recording is played back
+    if let Some(seed) = recording
+        .as_ref()
+        .map(Recording::seed)
+        .or(movie.as_ref().map(Movie::seed))
+    {
+        cpu.seed(seed);
+    }
+    // keys held on this machine, netplay decides what the game actually sees
+    let mut local_keys = [false; 16];
+
+    let mut crowd = options.crowd.as_ref().map(|(server, channel)| {
+        let crowd =
+            Crowd::connect(server, channel, options.vote_window).expect("unable to join chat");
+        info!(
+            "taking votes from #{} on {}",
+            channel.trim_start_matches('#'),
+            server
+        );
+        crowd
+    });
+
+    let mut clock = options.hz.map(|hz| Clock::new(hz, Instant::now()));
+    let mut timer_clock = Clock::new(headless::TIMER_HZ, Instant::now());
+    // key events are timed on SDL's clock
+    let mut key_queue = KeyQueue::new(timer.ticks());
+    let mut sticky_keys = options.sticky_frames.map(StickyKeys::new);
 
     'gameloop: loop {
+        let _frame = trace_span!("frame", frame).entered();
+        // where and how to go fullscreen
+        let display = (
+            options.monitor.unwrap_or(settings.monitor),
+            settings.borderless,
+        );
+        // anything from the window may have changed what's drawn over or around the screen
+        let mut window_events = false;
         for event in event_pump.poll_iter() {
+            window_events = true;
             match event {
                 Event::Quit { .. } => {
                     break 'gameloop;
                 }
                 Event::KeyDown {
-                    keycode: Some(key), ..
+                    keycode: Some(Keycode::P),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    command_palette.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    repeat: false,
+                    ..
+                } if stalled || at_breakpoint.is_some() => {
+                    stalled = false;
+                    at_breakpoint = None;
+                }
+                // Ctrl+D rather than a function key, they're all taken
+                Event::KeyDown {
+                    keycode: Some(Keycode::D),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => match dump_memory(&cpu) {
+                    Ok(path) => info!("memory written to {}", path.display()),
+                    Err(error) => error!("unable to dump memory: {}", error),
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    #[cfg(feature = "json")]
+                    state_picker.toggle(true);
+                    #[cfg(not(feature = "json"))]
+                    warn!("save states need JSON support, rebuild with --features json");
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    #[cfg(feature = "json")]
+                    state_picker.toggle(false);
+                    #[cfg(not(feature = "json"))]
+                    warn!("save states need JSON support, rebuild with --features json");
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::C),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    #[cfg(feature = "clipboard")]
+                    copy_screenshot(&mut clipboard, &cpu, settings.palette());
+                    #[cfg(not(feature = "clipboard"))]
+                    warn!("copying needs clipboard support, rebuild with --features clipboard");
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::T),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    text_screenshot(&cpu);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Comma),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    settings_menu.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    fullscreen = !fullscreen;
+                    set_fullscreen(&mut canvas, &video_subsystem, fullscreen, display);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    repeat: false,
+                    ..
+                } => {
+                    pause_menu.escape();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F1),
+                    repeat: false,
+                    ..
+                } => {
+                    debugger.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => {
+                    register_hud.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => {
+                    memory_viewer.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    repeat: false,
+                    ..
+                } => {
+                    sprite_viewer.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => {
+                    cheat_menu.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F6),
+                    repeat: false,
+                    ..
+                } => {
+                    ram_search.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    repeat: false,
+                    ..
+                } => {
+                    speedrun_timer.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F8),
+                    repeat: false,
+                    ..
+                } => {
+                    speedrun_timer.split(Instant::now());
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => {
+                    speedrun_timer.reset();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F10),
+                    repeat: false,
+                    ..
+                } => {
+                    keypad_display.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
                 } => {
-                    if let Some(k) = convert_key_to_button(key) {
-                        cpu.keypress(k, true);
+                    if let Some(recording) = recording.as_ref() {
+                        tas_editor.toggle(recording);
                     }
                 }
-                Event::KeyUp {
-                    keycode: Some(key), ..
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => {
+                    heatmap.toggle();
+                }
+                Event::KeyDown {
+                    keycode: Some(key),
+                    timestamp,
+                    ..
                 } => {
-                    if key == Keycode::Escape {
-                        break 'gameloop;
+                    #[cfg(feature = "json")]
+                    if state_picker.handle_key(key) {
+                        continue;
+                    }
+                    if pause_menu.handle_key(key)
+                        || command_palette.handle_key(key)
+                        || recording
+                            .as_mut()
+                            .is_some_and(|recording| tas_editor.handle_key(key, recording, &mut cpu))
+                        || debugger.handle_key(key, &mut cpu)
+                        || memory_viewer.handle_key(key, &mut cpu)
+                        || sprite_viewer.handle_key(key, &cpu)
+                        || cheat_menu.handle_key(key, &mut cheats)
+                        || settings_menu.handle_key(key, &mut settings, &mut cpu)
+                        || ram_search.handle_key(key, &cpu, &mut cheats)
+                        // taking play back would leave the other player behind
+                        || netplay.is_none()
+                            && scrubber.handle_key(key, &mut rewind, recording.as_mut(), &mut cpu)
+                        || learn_mode
+                            .as_mut()
+                            .is_some_and(|learn_mode| learn_mode.handle_key(key))
+                    {
+                        continue;
                     }
 
-                    if let Some(k) = convert_key_to_button(key) {
-                        cpu.keypress(k, false);
+                    if let Some(k) = convert_key_to_button(key)
+                        .or_else(|| convert_second_key(options.second_keys?, key))
+                    {
+                        speedrun_timer.key_pressed(Instant::now());
+                        local_keys[k] = true;
+                        key_queue.push(timestamp, k, true);
+                        if let Some(sticky_keys) = sticky_keys.as_mut() {
+                            sticky_keys.press(k, frame);
+                        }
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(key),
+                    timestamp,
+                    ..
+                } => {
+                    if let Some(k) = convert_key_to_button(key)
+                        .or_else(|| convert_second_key(options.second_keys?, key))
+                    {
+                        if sticky_keys
+                            .as_mut()
+                            .is_none_or(|sticky_keys| sticky_keys.release(k, frame))
+                        {
+                            local_keys[k] = false;
+                            key_queue.push(timestamp, k, false);
+                        }
                     }
                 }
                 _ => (),
             }
         }
 
-        for _ in 0..TICKS_PER_FRAME {
-            cpu.tick();
+        let action = command_palette.take_action();
+        match action {
+            Some(Action::Debugger) => debugger.toggle(),
+            Some(Action::Registers) => register_hud.toggle(),
+            Some(Action::MemoryViewer) => memory_viewer.toggle(),
+            Some(Action::SpriteViewer) => sprite_viewer.toggle(),
+            Some(Action::Cheats) => cheat_menu.toggle(),
+            Some(Action::RamSearch) => ram_search.toggle(),
+            Some(Action::SpeedrunTimer) => speedrun_timer.toggle(),
+            Some(Action::SpeedrunSplit) => speedrun_timer.split(Instant::now()),
+            Some(Action::SpeedrunReset) => speedrun_timer.reset(),
+            Some(Action::Keypad) => keypad_display.toggle(),
+            Some(Action::TasEditor) => {
+                if let Some(recording) = recording.as_ref() {
+                    tas_editor.toggle(recording);
+                }
+            }
+            Some(Action::Heatmap) => heatmap.toggle(),
+            // the pause menu's ROM picker, which loads it the same way
+            Some(Action::OpenRom) => pause_menu.open_roms(),
+            Some(Action::Settings) => settings_menu.toggle(),
+            Some(Action::DumpMemory) => match dump_memory(&cpu) {
+                Ok(path) => info!("memory written to {}", path.display()),
+                Err(error) => error!("unable to dump memory: {}", error),
+            },
+            Some(Action::TextScreenshot) => text_screenshot(&cpu),
+            Some(Action::CopyScreenshot) => {
+                #[cfg(feature = "clipboard")]
+                copy_screenshot(&mut clipboard, &cpu, settings.palette());
+                #[cfg(not(feature = "clipboard"))]
+                warn!("copying needs clipboard support, rebuild with --features clipboard");
+            }
+            Some(Action::SaveState | Action::LoadState) => {
+                #[cfg(feature = "json")]
+                state_picker.toggle(action == Some(Action::SaveState));
+                #[cfg(not(feature = "json"))]
+                warn!("save states need JSON support, rebuild with --features json");
+            }
+            Some(Action::Faster) => {
+                settings.step_speed(true);
+                save_settings(&settings);
+                stop_detecting_speed(&mut speed_detector);
+            }
+            Some(Action::Slower) => {
+                settings.step_speed(false);
+                save_settings(&settings);
+                stop_detecting_speed(&mut speed_detector);
+            }
+            Some(Action::NextPalette) => {
+                settings.cycle_palette(true);
+                save_settings(&settings);
+            }
+            Some(Action::NextQuirks) => {
+                settings.cycle_preset(true);
+                cpu.set_quirks(settings.quirks());
+                save_settings(&settings);
+            }
+            Some(Action::AntiFlicker) => {
+                settings.anti_flicker = !settings.anti_flicker;
+                save_settings(&settings);
+            }
+            Some(Action::Fullscreen) => {
+                fullscreen = !fullscreen;
+                set_fullscreen(&mut canvas, &video_subsystem, fullscreen, display);
+            }
+            Some(Action::Quit) => break 'gameloop,
+            None => (),
+        }
+        match pause_menu.take_action() {
+            // starting over would leave the other player behind
+            Some(PauseAction::Reset | PauseAction::LoadRom(_)) if netplay.is_some() => {
+                warn!("can't reset or change games during netplay");
+            }
+            Some(PauseAction::Reset) => {
+                cpu.reset();
+                if let Err(error) = cpu.try_load(&rom) {
+                    error!("unable to reload the ROM: {}", error);
+                }
+                if let Some(speed_detector) = speed_detector.as_mut() {
+                    *speed_detector = SpeedDetector::new();
+                }
+            }
+            Some(PauseAction::LoadRom(path)) => match fs::read(&path) {
+                Ok(new_rom) if cpu.check_rom(&new_rom).is_ok() => {
+                    rom = new_rom;
+                    if let Some(message) = dumps::check(&rom) {
+                        warn!("{}", message);
+                    }
+                    if let Some(platform) = &options.platform {
+                        settings.speed = platform.speed;
+                    }
+                    let (window_title, game_quirks) =
+                        read_game_settings(&mut settings, &rom, Some(&path.to_string_lossy()));
+                    if let Err(error) = canvas.window_mut().set_title(&window_title) {
+                        error!("unable to set the window title: {}", error);
+                    }
+                    cpu.reset();
+                    cpu.set_quirks(options.quirks.or(platform_quirks).unwrap_or(game_quirks));
+                    if let Err(error) = cpu.try_load(&rom) {
+                        error!("unable to load {}: {}", path.display(), error);
+                    }
+                    let cheats_path = format!("{}.cheats", path.display());
+                    cheats = Cheats::load(&cheats_path).unwrap_or_else(|error| {
+                        error!("unable to read cheats, playing without them: {}", error);
+                        Cheats::empty(&cheats_path)
+                    });
+                    #[cfg(feature = "json")]
+                    state_picker.set_rom(&path.to_string_lossy());
+                    rewind = Rewind::new(REWIND_FRAMES);
+                    if let Some(speed_detector) = speed_detector.as_mut() {
+                        *speed_detector = SpeedDetector::new();
+                    }
+                }
+                // the game being played carries on
+                Ok(new_rom) => {
+                    if let Err(error) = cpu.check_rom(&new_rom) {
+                        error!("unable to load {}: {}", path.display(), error);
+                    }
+                }
+                Err(error) => error!("unable to read {}: {}", path.display(), error),
+            },
+            Some(PauseAction::Settings) => settings_menu.open(),
+            Some(PauseAction::Quit) => break 'gameloop,
+            None => (),
+        }
+        #[cfg(feature = "json")]
+        match state_picker.take_action() {
+            Some(SlotAction::Save(slot)) => {
+                let saved_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+                let text = state::to_json_with_preview(&cpu, &state_picker.rom_name(), saved_at);
+                let path = state_picker.slot_path(slot);
+                match fs::write(&path, text) {
+                    Ok(()) => info!("state saved to {}", path),
+                    Err(error) => error!("unable to write {}: {}", path, error),
+                }
+            }
+            // going back in time would leave the other player behind
+            Some(SlotAction::Load(_)) if netplay.is_some() => {
+                warn!("can't load states during netplay");
+            }
+            Some(SlotAction::Load(slot)) => {
+                let path = state_picker.slot_path(slot);
+                match fs::read_to_string(&path)
+                    .map_err(Error::from)
+                    .and_then(|text| state::from_json(&text).map_err(Error::from))
+                {
+                    Ok(loaded) => {
+                        cpu = loaded;
+                        cpu.set_strict(options.strict);
+                        #[cfg(feature = "plugins")]
+                        plugins.install(&mut cpu);
+                    }
+                    Err(message) => error!("unable to load {}: {}", path, message),
+                }
+            }
+            None => (),
+        }
+
+        // a display or kind of fullscreen picked in the settings menu takes over straight away
+        let changed = (
+            options.monitor.unwrap_or(settings.monitor),
+            settings.borderless,
+        );
+        if fullscreen && changed != display {
+            set_fullscreen(&mut canvas, &video_subsystem, true, changed);
+        }
+
+        if let Some(sticky_keys) = sticky_keys.as_mut() {
+            for k in sticky_keys.due(frame) {
+                local_keys[k] = false;
+                key_queue.push(timer.ticks(), k, false);
+            }
+        }
+
+        #[cfg(feature = "remote")]
+        if let Some(remote) = remote.as_ref() {
+            for (key, pressed) in remote.key_events() {
+                input::press(&mut cpu, key, pressed);
+            }
+        }
+
+        if let Some(crowd) = crowd.as_mut() {
+            for (key, pressed) in crowd.update() {
+                input::press(&mut cpu, key, pressed);
+            }
+        }
+
+        // requests are answered even while paused, they're how the pause ends
+        #[cfg(feature = "rpc")]
+        let paused = rpc.as_mut().is_some_and(|rpc| {
+            rpc.handle(&mut cpu);
+            rpc.paused()
+        });
+        #[cfg(not(feature = "rpc"))]
+        let paused = false;
+        // in learn mode the game only runs the steps asked for
+        let steps = learn_mode.as_mut().map(LearnMode::take_steps);
+        let paused = paused
+            || stalled
+            || at_breakpoint.is_some()
+            || pause_menu.is_open()
+            || tas_editor.is_open()
+            || scrubber.is_open()
+            || steps == Some(0);
+        #[cfg(feature = "json")]
+        let paused = paused || state_picker.is_open();
+
+        // input that's recorded or shared can only change between frames
+        if netplay.is_some() || recording.is_some() || movie.is_some() {
+            key_queue.flush(&mut cpu);
+        }
+        if let Some(netplay) = netplay.as_mut() {
+            match netplay.exchange(&local_keys) {
+                Ok(keys) => cpu.set_keys(&keys),
+                Err(error) => {
+                    error!("lost connection to the other player: {}", error);
+                    break 'gameloop;
+                }
+            }
+        }
+
+        // a movie holds the keys until it runs out, then the player takes over
+        let playing = movie
+            .as_ref()
+            .filter(|movie| !paused && movie_frame < movie.len());
+        if let Some(movie) = playing {
+            cpu.set_keys(movie.keys(movie_frame).unwrap());
+        }
+
+        #[cfg(feature = "plugins")]
+        plugins.filter_input(&mut cpu);
+        cheats.apply(&mut cpu);
+        if let Some(recording) = recording.as_mut().filter(|_| !paused) {
+            recording.record(&cpu);
+        }
+        if let Some(movie) = playing {
+            if let Err(message) = movie.check(movie_frame, &cpu) {
+                error!(
+                    "{}, the game no longer matches the movie. it may have been recorded with \
+                     another version of the game, speed, quirks or cheats",
+                    message
+                );
+                break 'gameloop;
+            }
+            movie_frame += 1;
+            if movie_frame == movie.len() {
+                info!("the movie is over, the keyboard has the controls");
+            }
+        }
+
+        // the clock keeps time while paused too, so nothing is owed when play carries on
+        let budget = clock
+            .as_mut()
+            .map_or(settings.speed, |clock| clock.frame(Instant::now()));
+        let ticks = if paused { 0 } else { steps.unwrap_or(budget) };
+        // the delay and sound timers keep to real time however fast the program runs, except
+        // when every frame has to play out the same again, where they count down once a frame
+        let timer_ticks = match timer_clock.frame(Instant::now()) {
+            _ if netplay.is_some() || recording.is_some() || movie.is_some() => 1,
+            timer_ticks => timer_ticks,
+        };
+        key_queue.start_frame(timer.ticks(), ticks);
+        for tick in 0..ticks {
+            key_queue.apply(tick, &mut cpu);
+            if let Some(tracer) = tracer.as_mut() {
+                tracer.before(&cpu);
+            }
+            if let Some(profiler) = profiler.as_mut() {
+                profiler.record(&cpu);
+            }
+            if let Some(dynamic_edges) = dynamic_edges.as_mut() {
+                dynamic_edges.before(&cpu);
+            }
+            #[cfg(feature = "lua")]
+            if let Some(Err(error)) = script
+                .as_mut()
+                .map(|script| script.before_instruction(&mut cpu))
+            {
+                error!("script stopped: {}", error);
+                script = None;
+            }
+            #[cfg(feature = "plugins")]
+            plugins.before_instruction(&mut cpu);
+
+            let pc = cpu.pc();
+            if watchdog
+                .as_mut()
+                .is_some_and(|watchdog| watchdog.check(cpu.opcode_at(pc)))
+            {
+                warn!(
+                    "{} instructions without drawing, reading keys or using the timers at #{:03X}, \
+                     the game may be stuck or need different quirks. paused, press P to carry on",
+                    watchdog::DEFAULT_LIMIT, pc
+                );
+                stalled = true;
+                break;
+            }
+            if let Some(speed_detector) = speed_detector.as_mut() {
+                speed_detector.observe(cpu.opcode_at(pc), cpu.delay_timer());
+            }
+            if let Some(before) = learn_before.as_mut() {
+                before.clone_from(&cpu);
+            }
+            history.record(&cpu);
+            let result = cpu.tick();
+            event_bus.dispatch(&mut cpu);
+            if let Err(error) = result {
+                error!("program stopped at #{:03X}: {}", pc, error);
+                match history.write_report(&rom, &cpu, &error) {
+                    Ok(path) => info!(
+                        "the machine's state and last instructions are in {}",
+                        path.display()
+                    ),
+                    Err(error) => error!("unable to write a crash report: {}", error),
+                }
+                if let Some(metrics) = metrics.as_ref() {
+                    metrics.record_error(&error);
+                }
+                break 'gameloop;
+            }
+
+            if let (Some(learn_mode), Some(before)) = (learn_mode.as_mut(), learn_before.as_ref()) {
+                learn_mode.explained(explain(before.opcode_at(pc), before, &cpu));
+            }
+            if let Some(Err(error)) = tracer.as_mut().map(|tracer| tracer.after(frame, &cpu)) {
+                error!(
+                    "unable to write to the trace file, tracing stopped: {}",
+                    error
+                );
+                tracer = None;
+            }
+            if let Some(dynamic_edges) = dynamic_edges.as_mut() {
+                dynamic_edges.after(&cpu);
+            }
+            memory_viewer.record(cpu.memory_accesses());
+            heatmap.record(cpu.memory_accesses());
+            if let Some(coverage) = coverage.as_mut() {
+                coverage.record(cpu.memory_accesses());
+            }
+            if let Some(address) = breakpoint.take() {
+                info!("breakpoint at #{:03X}, P to carry on", address);
+                at_breakpoint = Some(address);
+                break;
+            }
+        }
+        memory_viewer.update();
+        if !paused {
+            let pattern = cpu
+                .audio_pattern()
+                .map(|pattern| (pattern, cpu.playback_rate()));
+            if let Some(Err(error)) = audio
+                .as_mut()
+                .map(|audio| audio.frame(buzzing.get(), pattern))
+            {
+                error!(
+                    "unable to write the audio file, recording stopped: {}",
+                    error
+                );
+                audio = None;
+            }
+            for _ in 0..timer_ticks {
+                cpu.tick_timers();
+            }
+            event_bus.dispatch(&mut cpu);
+            speedrun_timer.frame();
+            rewind.push(&cpu);
+            if let Some(speed) = speed_detector
+                .as_mut()
+                .and_then(|speed_detector| speed_detector.end_frame(settings.speed))
+            {
+                info!(
+                    "the game seems to want {} instructions a frame rather than {}",
+                    speed, settings.speed
+                );
+                settings.speed = speed;
+                save_settings(&settings);
+            }
+        }
+        #[cfg(feature = "lua")]
+        if let Some(Err(error)) = script
+            .as_mut()
+            .map(|script| script.end_frame(frame, &mut cpu))
+        {
+            error!("script stopped: {}", error);
+            script = None;
+        }
+        #[cfg(feature = "plugins")]
+        plugins.end_frame(frame, &mut cpu);
+        #[cfg(feature = "remote")]
+        if let Some(remote) = remote.as_mut() {
+            remote.update(&cpu);
+        }
+
+        let screen_changed = screen_dirty.take();
+        let redraw = screen_changed
+            || flicker_owed
+            || window_events
+            || stalled
+            || at_breakpoint.is_some()
+            || learn_mode.is_some()
+            || register_hud.is_open()
+            || debugger.is_open()
+            || memory_viewer.is_open()
+            || heatmap.is_open()
+            || sprite_viewer.is_open()
+            || cheat_menu.is_open()
+            || settings_menu.is_open()
+            || ram_search.is_open()
+            || speedrun_timer.is_open()
+            || keypad_display.is_open()
+            || scrubber.is_open()
+            || tas_editor.is_open()
+            || command_palette.is_open()
+            || pause_menu.is_open();
+        // the debug protocol can load states and poke memory, which don't go through the events
+        #[cfg(feature = "rpc")]
+        let redraw = redraw || rpc.is_some();
+        #[cfg(feature = "json")]
+        let redraw = redraw || state_picker.is_open();
+        #[cfg(feature = "lua")]
+        let redraw = redraw || script.is_some();
+        flicker_owed = screen_changed && settings.anti_flicker;
+        if redraw {
+            let previous = (settings.anti_flicker && last_screen.1 == cpu.display_mode())
+                .then_some(&last_screen.0);
+            draw_screen(
+                &cpu,
+                previous,
+                settings.palette(),
+                bezel.as_ref(),
+                &mut rects,
+                &mut canvas,
+            );
+            last_screen = (cpu.screen, cpu.display_mode());
+            register_hud.draw(&cpu, &mut canvas);
+            debugger.draw(&cpu, &mut canvas);
+            memory_viewer.draw(&cpu, &mut canvas);
+            heatmap.draw(&mut canvas);
+            sprite_viewer.draw(&cpu, &mut canvas);
+            cheat_menu.draw(&cheats, &mut canvas);
+            settings_menu.draw(&settings, &mut canvas);
+            ram_search.draw(&cpu, &mut canvas);
+            speedrun_timer.draw(&mut canvas);
+            keypad_display.draw(&cpu, &mut canvas);
+            if let Some(learn_mode) = learn_mode.as_ref() {
+                learn_mode.draw(&mut canvas);
+            }
+            scrubber.draw(&rewind, &mut canvas);
+            if let Some(recording) = recording.as_ref() {
+                tas_editor.draw(recording, &mut canvas);
+            }
+            command_palette.draw(&mut canvas);
+            pause_menu.draw(&mut canvas);
+            #[cfg(feature = "json")]
+            state_picker.draw(&mut canvas);
+            if stalled {
+                draw_notice(
+                    &mut canvas,
+                    &[
+                        "the game looks stuck, it may need other quirks",
+                        "P to carry on",
+                    ],
+                );
+            }
+            if let Some(address) = at_breakpoint {
+                breakpoint_notice.clear();
+                write!(breakpoint_notice, "breakpoint at #{:03X}", address).unwrap();
+                draw_notice(&mut canvas, &[&breakpoint_notice, "P to carry on"]);
+            }
+            #[cfg(feature = "lua")]
+            if let Some(script) = script.as_ref() {
+                script.draw(&mut canvas);
+            }
+            canvas.present();
+        }
+        if vsync && redraw {
+            // present waited for the display, the next frame skipped should wait a whole one
+            limiter.restart(Instant::now());
+        } else {
+            limiter.wait();
+        }
+        if let Some(metrics) = metrics.as_ref() {
+            metrics.record_frame(ticks as u64);
         }
+        frame += 1;
+    }
+
+    if let Some(profiler) = profiler {
+        print!("{}", profiler.report(&cpu, frame));
+    }
 
-        draw_screen(&cpu, &mut canvas);
+    if let (Some(coverage), Some(path)) = (coverage, options.coverage_path) {
+        fs::write(path, coverage.map()).expect("unable to write coverage map");
+    }
+
+    if let (Some(dynamic_edges), Some(path)) = (dynamic_edges, options.cfg_path) {
+        // the ROM was loaded to get this far, so it fits
+        if let Ok(graph) = control_flow_graph(&rom, &dynamic_edges.edges) {
+            fs::write(path, graph.to_dot()).expect("unable to write control-flow graph");
+        }
+    }
+
+    if let Some(audio) = audio {
+        audio.finish().expect("unable to write audio file");
+    }
+
+    if let (Some(recording), Some(path)) = (recording, options.save_movie_path) {
+        fs::write(path, recording.to_movie()).expect("unable to write movie file");
+    }
+
+    #[cfg(feature = "json")]
+    if let Some(path) = options.save_state_path {
+        fs::write(path, state::to_json(&cpu)).expect("unable to write state file");
     }
 }
 
-fn draw_screen(cpu: &CPU, canvas: &mut Canvas<Window>) {
-    canvas.set_draw_color(Color::BLACK);
-    canvas.clear();
+// a URL is downloaded first, and - reads the program from stdin, e.g. piped from an assembler
+fn read_rom(path: &str) -> Vec<u8> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return download_rom(path);
+    }
 
-    let screen_buffer = cpu.screen;
-    canvas.set_draw_color(Color::WHITE);
+    let mut buffer = Vec::new();
+    if path == "-" {
+        io::stdin()
+            .read_to_end(&mut buffer)
+            .expect("unable to read ROM from stdin");
+        return buffer;
+    }
 
-    for (i, pixel) in screen_buffer.iter().enumerate() {
-        if *pixel {
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
+    let mut rom = File::open(path).expect("unable to open ROM file");
+    rom.read_to_end(&mut buffer)
+        .expect("unable to read ROM file");
+    buffer
+}
 
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
-            canvas.fill_rect(rect);
+#[cfg(feature = "fetch")]
+fn download_rom(url: &str) -> Vec<u8> {
+    match fetch::fetch(url) {
+        Ok(rom) => rom,
+        Err(message) => {
+            error!("unable to download {}: {}", url, message);
+            process::exit(1);
         }
     }
+}
 
-    canvas.present();
+#[cfg(not(feature = "fetch"))]
+fn download_rom(_: &str) -> Vec<u8> {
+    error!("ROMs from URLs need fetch support, rebuild with --features fetch");
+    process::exit(1);
+}
+
+// the window's title and the quirks to play the ROM with, after putting its settings into
+// `settings`. metadata says how a game should be played, unless it's been played differently
+// since. a game's quirks from its metadata may not be any preset's
+fn read_game_settings(settings: &mut Settings, rom: &[u8], path: Option<&str>) -> (String, Quirks) {
+    let (title, game_quirks) = match path {
+        Some(path) => read_metadata(path, settings),
+        None => (WINDOW_TITLE.to_string(), None),
+    };
+    let remembered = settings.use_rom(rom).unwrap_or_else(|error| {
+        error!("unable to read the game's settings: {}", error);
+        false
+    });
+    let quirks = game_quirks
+        .filter(|_| !remembered)
+        .unwrap_or_else(|| settings.quirks());
+    (title, quirks)
+}
+
+// the window's title and quirks for the ROM at `path`, from its sidecar or the archive it's in,
+// after putting the speed and quirks into the settings
+#[cfg(feature = "json")]
+fn read_metadata(path: &str, settings: &mut Settings) -> (String, Option<Quirks>) {
+    let metadata = match Metadata::load(path) {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return (WINDOW_TITLE.to_string(), None),
+        Err(error) => {
+            error!("unable to read the game's metadata: {}", error);
+            return (WINDOW_TITLE.to_string(), None);
+        }
+    };
+    metadata.apply(settings);
+    if !metadata.keys.is_empty() {
+        info!("keys: {}", metadata.key_hints());
+    }
+
+    let title = match metadata.byline() {
+        Some(byline) => format!("{} - {}", byline, WINDOW_TITLE),
+        None => WINDOW_TITLE.to_string(),
+    };
+    (title, metadata.quirks)
+}
+
+#[cfg(not(feature = "json"))]
+fn read_metadata(_: &str, _: &mut Settings) -> (String, Option<Quirks>) {
+    (WINDOW_TITLE.to_string(), None)
+}
+
+// events go to the terminal, or a file for a long run at the trace level, which logs every
+// instruction
+fn init_logging(level: Level, path: Option<&str>) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_target(false)
+        .without_time();
+    match path {
+        Some(path) => {
+            let file = File::create(path).expect("unable to create log file");
+            builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .init();
+        }
+        None => builder.with_writer(io::stdout).init(),
+    }
+}
+
+// the raw RAM, for looking at in a hex editor. named by the time so dumps don't overwrite each other
+fn dump_memory(cpu: &CPU) -> io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let path = PathBuf::from(format!("chip8-memory-{}.bin", millis));
+    fs::write(&path, cpu.memory())?;
+    Ok(path)
+}
+
+// the screen in block characters, printed and written to a file named by the time
+fn text_screenshot(cpu: &CPU) {
+    let text = headless::screen_blocks(cpu);
+    print!("{}", text);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let path = format!("chip8-screen-{}.txt", millis);
+    match fs::write(&path, text) {
+        Ok(()) => info!("screen written to {}", path),
+        Err(error) => error!("unable to write {}: {}", path, error),
+    }
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_screenshot(clipboard: &mut ScreenClipboard, cpu: &CPU, palette: &Palette) {
+    match clipboard.copy(cpu, palette) {
+        Ok(()) => info!("screenshot copied to the clipboard"),
+        Err(error) => error!("unable to copy the screenshot: {}", error),
+    }
+}
+
+// fullscreen on the display numbered from 1, or 0 for whichever the window is on now. borderless
+// fullscreen covers the desktop with the window, exclusive switches the display to the window's size
+fn set_fullscreen(
+    canvas: &mut Canvas<Window>,
+    video: &VideoSubsystem,
+    fullscreen: bool,
+    (monitor, borderless): (u32, bool),
+) {
+    let window = canvas.window_mut();
+    // SDL goes fullscreen on the display the window is on, so it's moved there first
+    if fullscreen && monitor > 0 {
+        match video.display_bounds(monitor as i32 - 1) {
+            Ok(bounds) => window.set_position(
+                WindowPos::Positioned(bounds.x()),
+                WindowPos::Positioned(bounds.y()),
+            ),
+            Err(error) => error!("unable to use display {}: {}", monitor, error),
+        }
+    }
+
+    let mode = match (fullscreen, borderless) {
+        (true, true) => FullscreenType::Desktop,
+        (true, false) => FullscreenType::True,
+        (false, _) => FullscreenType::Off,
+    };
+    if let Err(error) = window.set_fullscreen(mode) {
+        error!("unable to change fullscreen: {}", error);
+    }
+}
+
+// a speed chosen by hand takes over from the one being worked out
+fn stop_detecting_speed(speed_detector: &mut Option<SpeedDetector>) {
+    if speed_detector.take().is_some() {
+        info!("speed changed by hand, no longer working it out");
+    }
+}
+
+// a setting that can't be saved still applies until the emulator closes
+fn save_settings(settings: &Settings) {
+    if let Err(error) = settings.save() {
+        error!("unable to save settings: {}", error);
+    }
+}
+
+// the machine the platform and --stack-size ask for
+fn cpu_builder(options: &Options) -> CpuBuilder {
+    let builder = options
+        .platform
+        .as_ref()
+        .map_or_else(CPU::builder, Platform::builder);
+    match options.stack_size {
+        Some(size) => builder.stack_size(size),
+        None => builder,
+    }
+}
+
+// analyzes the ROM as loaded rather than the live memory, which the program may have modified
+fn control_flow_graph(
+    rom: &[u8],
+    dynamic_edges: &BTreeSet<(u16, u16)>,
+) -> Result<FlowGraph, RomError> {
+    let mut cpu = CPU::new();
+    cpu.try_load(rom)?;
+    Ok(FlowGraph::analyze(
+        cpu.memory(),
+        START_ADDRESS,
+        dynamic_edges,
+    ))
+}
+
+fn draw_screen(
+    cpu: &CPU,
+    previous: Option<&Framebuffer>,
+    palette: &Palette,
+    bezel: Option<&Bezel>,
+    rects: &mut Vec<Rect>,
+    canvas: &mut Canvas<Window>,
+) {
+    let (red, green, blue) = palette.background;
+    canvas.set_draw_color(Color::RGB(red, green, blue));
+    let area = match bezel {
+        Some(bezel) => {
+            bezel.draw(canvas);
+            canvas.fill_rect(bezel.screen()).unwrap();
+            bezel.screen()
+        }
+        None => {
+            canvas.clear();
+            Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT)
+        }
+    };
+    // anti-flicker only ever keeps the first plane's pixels
+    let first = |y| cpu.screen.row(y) | previous.map_or(0, |previous| previous.row(y));
+    let second = |y| cpu.second_plane.row(y);
+    let layers: [(&dyn Fn(usize) -> u128, _); 3] = [
+        (&|y| first(y) & !second(y), palette.foreground),
+        (&|y| second(y) & !first(y), palette.second),
+        (&|y| first(y) & second(y), palette.both),
+    ];
+    let resolution = cpu.resolution();
+    for (lit, (red, green, blue)) in layers {
+        draw_pixels(
+            resolution,
+            lit,
+            area,
+            Color::RGB(red, green, blue),
+            rects,
+            canvas,
+        );
+    }
+}
+
+// the pixels `lit` gives for each row stretched over `area` at whatever resolution the program is
+// using right now, so switching display mid-game still fills it. the two-page display's pixels come
+// out half as tall as they are wide, as they did on the VIP. each run of lit pixels in a row is one
+// rectangle, and they all go to the renderer at once in `rects`, which is reused so a frame doesn't
+// allocate
+fn draw_pixels(
+    (width, height): (usize, usize),
+    lit: &dyn Fn(usize) -> u128,
+    area: Rect,
+    color: Color,
+    rects: &mut Vec<Rect>,
+    canvas: &mut Canvas<Window>,
+) {
+    let column = |x: usize| area.x() + (x as u32 * area.width() / width as u32) as i32;
+    let row = |y: usize| area.y() + (y as u32 * area.height() / height as u32) as i32;
+
+    rects.clear();
+    for y in 0..height {
+        let lit = lit(y);
+        let mut x = 0;
+        while x < width && lit << x != 0 {
+            let start = x + (lit << x).leading_zeros() as usize;
+            let end = start + (lit << start).leading_ones() as usize;
+            let (left, top) = (column(start), row(y));
+            rects.push(Rect::new(
+                left,
+                top,
+                (column(end) - left) as u32,
+                (row(y + 1) - top) as u32,
+            ));
+            x = end;
+        }
+    }
+    canvas.set_draw_color(color);
+    canvas.fill_rects(rects).unwrap();
 }
 
 fn convert_key_to_button(key: Keycode) -> Option<usize> {
@@ -117,3 +1640,29 @@ fn convert_key_to_button(key: Keycode) -> Option<usize> {
         _ => None,
     }
 }
+
+// the numpad is laid out like the original keypad, so it reads the same as the main keys
+fn convert_second_key(cluster: SecondKeys, key: Keycode) -> Option<usize> {
+    match cluster {
+        SecondKeys::Right => split::convert_right_key(key),
+        SecondKeys::Numpad => match key {
+            Keycode::Kp7 => Some(0x1),
+            Keycode::Kp8 => Some(0x2),
+            Keycode::Kp9 => Some(0x3),
+            Keycode::KpDivide => Some(0xC),
+            Keycode::Kp4 => Some(0x4),
+            Keycode::Kp5 => Some(0x5),
+            Keycode::Kp6 => Some(0x6),
+            Keycode::KpMultiply => Some(0xD),
+            Keycode::Kp1 => Some(0x7),
+            Keycode::Kp2 => Some(0x8),
+            Keycode::Kp3 => Some(0x9),
+            Keycode::KpMinus => Some(0xE),
+            Keycode::Kp0 => Some(0xA),
+            Keycode::KpPeriod => Some(0x0),
+            Keycode::KpEnter => Some(0xB),
+            Keycode::KpPlus => Some(0xF),
+            _ => None,
+        },
+    }
+}
@@ -0,0 +1,217 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, CHAR_WIDTH, LINE_HEIGHT};
+use chip8::cpu::{AccessKind, MemoryAccess, CPU};
+
+const ROW_BYTES: usize = 16;
+const VISIBLE_ROWS: usize = 30;
+const VIEWER_PADDING: i32 = 6;
+
+// how many frames a byte stays highlighted after it was touched
+const HIGHLIGHT_FRAMES: u8 = 30;
+
+const VIEWER_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const READ_COLOR: Color = Color::RGB(80, 160, 255);
+const WRITE_COLOR: Color = Color::RGB(255, 80, 80);
+const CURSOR_BACKGROUND: Color = Color::RGB(90, 90, 90);
+
+pub struct MemoryViewer {
+    visible: bool,
+    cursor: usize,
+    top_row: usize,
+    // the high nibble typed so far, a byte is written once both nibbles are in
+    pending_nibble: Option<u8>,
+    recent_reads: Vec<u8>,
+    recent_writes: Vec<u8>,
+}
+
+impl MemoryViewer {
+    pub fn new(memory_size: usize) -> MemoryViewer {
+        MemoryViewer {
+            visible: false,
+            cursor: 0,
+            top_row: 0,
+            pending_nibble: None,
+            recent_reads: vec![0; memory_size],
+            recent_writes: vec![0; memory_size],
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.pending_nibble = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // called after every tick so accesses between frames aren't lost
+    pub fn record(&mut self, accesses: &[MemoryAccess]) {
+        for access in accesses {
+            let address = access.address as usize;
+            match access.kind {
+                AccessKind::Read => self.recent_reads[address] = HIGHLIGHT_FRAMES,
+                AccessKind::Write => self.recent_writes[address] = HIGHLIGHT_FRAMES,
+                AccessKind::Execute => (),
+            }
+        }
+    }
+
+    // fades the highlights, called once per frame
+    pub fn update(&mut self) {
+        for age in self
+            .recent_reads
+            .iter_mut()
+            .chain(self.recent_writes.iter_mut())
+        {
+            *age = age.saturating_sub(1);
+        }
+    }
+
+    // returns true if the viewer consumed the key
+    pub fn handle_key(&mut self, key: Keycode, cpu: &mut CPU) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let last = cpu.memory().len() - 1;
+        let page = ROW_BYTES * VISIBLE_ROWS;
+
+        match key {
+            Keycode::Left => self.move_cursor(self.cursor.saturating_sub(1)),
+            Keycode::Right => self.move_cursor((self.cursor + 1).min(last)),
+            Keycode::Up => self.move_cursor(self.cursor.saturating_sub(ROW_BYTES)),
+            Keycode::Down => self.move_cursor((self.cursor + ROW_BYTES).min(last)),
+            Keycode::PageUp => self.move_cursor(self.cursor.saturating_sub(page)),
+            Keycode::PageDown => self.move_cursor((self.cursor + page).min(last)),
+            Keycode::Home => self.move_cursor(0),
+            Keycode::End => self.move_cursor(last),
+            _ => {
+                if let Some(nibble) = convert_key_to_nibble(key) {
+                    self.type_nibble(nibble, cpu);
+                }
+            }
+        }
+
+        true
+    }
+
+    fn move_cursor(&mut self, address: usize) {
+        self.cursor = address;
+        self.pending_nibble = None;
+
+        let row = address / ROW_BYTES;
+        if row < self.top_row {
+            self.top_row = row;
+        } else if row >= self.top_row + VISIBLE_ROWS {
+            self.top_row = row + 1 - VISIBLE_ROWS;
+        }
+    }
+
+    fn type_nibble(&mut self, nibble: u8, cpu: &mut CPU) {
+        match self.pending_nibble.take() {
+            None => self.pending_nibble = Some(nibble),
+            Some(high) => {
+                cpu.poke(self.cursor as u16, (high << 4) | nibble);
+                let last = cpu.memory().len() - 1;
+                self.move_cursor((self.cursor + 1).min(last));
+            }
+        }
+    }
+
+    pub fn draw(&self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let memory = cpu.memory();
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(VIEWER_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = VIEWER_PADDING;
+        let mut y = VIEWER_PADDING;
+        let title = format!(
+            "MEMORY  #{:04X} = #{:02X}  ARROWS/PGUP/PGDN MOVE, 0-F EDIT",
+            self.cursor, memory[self.cursor]
+        );
+        draw_text(canvas, &title, x, y, TITLE_COLOR);
+
+        let first = self.top_row * ROW_BYTES;
+        let last = (first + ROW_BYTES * VISIBLE_ROWS).min(memory.len());
+
+        for row_start in (first..last).step_by(ROW_BYTES) {
+            y += LINE_HEIGHT;
+            draw_text(canvas, &format!("{:04X}", row_start), x, y, TITLE_COLOR);
+
+            let row_end = (row_start + ROW_BYTES).min(memory.len());
+            for (column, byte) in memory[row_start..row_end].iter().enumerate() {
+                let address = row_start + column;
+                let column = column as i32;
+                let byte_x = x + (5 + column * 3) * CHAR_WIDTH;
+
+                if address == self.cursor {
+                    canvas.set_draw_color(CURSOR_BACKGROUND);
+                    canvas
+                        .fill_rect(Rect::new(
+                            byte_x - 2,
+                            y - 2,
+                            (CHAR_WIDTH * 2 + 2) as u32,
+                            LINE_HEIGHT as u32,
+                        ))
+                        .unwrap();
+                }
+
+                let color = if self.recent_writes[address] > 0 {
+                    WRITE_COLOR
+                } else if self.recent_reads[address] > 0 {
+                    READ_COLOR
+                } else {
+                    TEXT_COLOR
+                };
+
+                let text = match self.pending_nibble {
+                    Some(high) if address == self.cursor => format!("{:X}_", high),
+                    _ => format!("{:02X}", byte),
+                };
+                draw_text(canvas, &text, byte_x, y, color);
+            }
+        }
+    }
+}
+
+pub fn convert_key_to_nibble(key: Keycode) -> Option<u8> {
+    match key {
+        Keycode::Num0 | Keycode::Kp0 => Some(0x0),
+        Keycode::Num1 | Keycode::Kp1 => Some(0x1),
+        Keycode::Num2 | Keycode::Kp2 => Some(0x2),
+        Keycode::Num3 | Keycode::Kp3 => Some(0x3),
+        Keycode::Num4 | Keycode::Kp4 => Some(0x4),
+        Keycode::Num5 | Keycode::Kp5 => Some(0x5),
+        Keycode::Num6 | Keycode::Kp6 => Some(0x6),
+        Keycode::Num7 | Keycode::Kp7 => Some(0x7),
+        Keycode::Num8 | Keycode::Kp8 => Some(0x8),
+        Keycode::Num9 | Keycode::Kp9 => Some(0x9),
+        Keycode::A => Some(0xA),
+        Keycode::B => Some(0xB),
+        Keycode::C => Some(0xC),
+        Keycode::D => Some(0xD),
+        Keycode::E => Some(0xE),
+        Keycode::F => Some(0xF),
+        _ => None,
+    }
+}
@@ -0,0 +1,390 @@
+// what a <rom>.json beside a ROM says about it, in the shape of a game's entry in the CHIP-8
+// community archive's programs.json. only the title, authors, description, platform, tickrate,
+// quirks and key hints are used, everything else an entry can have (release date, colours) is
+// ignored. a ROM in a checkout of the archive, in its roms directory, has its entry there instead
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::compliance::presets;
+use crate::error::MetadataError;
+use crate::library::ARCHIVE_PROGRAMS;
+use crate::quirks::Quirks;
+use crate::settings::Settings;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    // the archive's name for the interpreter it was written for, chip8, schip or xochip
+    pub platform: Option<String>,
+    // instructions a frame
+    pub tickrate: Option<u32>,
+    // the platform's quirks with any the options set, None when neither says
+    pub quirks: Option<Quirks>,
+    // what the game uses keys for, like ("left", 0x7), by name
+    pub keys: Vec<(String, usize)>,
+}
+
+impl Metadata {
+    // the sidecar next to the ROM at `rom_path`, or its entry in the archive it's part of. None
+    // when there's neither
+    pub fn load(rom_path: &str) -> io::Result<Option<Metadata>> {
+        let rom_path = Path::new(rom_path);
+        if let Some(metadata) = read_sidecar(rom_path)? {
+            return Ok(Some(metadata));
+        }
+        let directory = rom_path.parent().unwrap_or(Path::new(""));
+        Ok(Archive::find(directory)?.and_then(|archive| archive.get(rom_path).cloned()))
+    }
+
+    // the same for each of the ROMs in a directory, reading the archive once
+    pub fn load_all(directory: &Path, roms: &[PathBuf]) -> io::Result<Vec<Option<Metadata>>> {
+        let archive = Archive::find(directory)?;
+        roms.iter()
+            .map(|rom| match read_sidecar(rom)? {
+                Some(metadata) => Ok(Some(metadata)),
+                None => Ok(archive
+                    .as_ref()
+                    .and_then(|archive| archive.get(rom))
+                    .cloned()),
+            })
+            .collect()
+    }
+
+    // the quirks preset for the platform, by its name in compliance::presets(). a platform the
+    // emulator can't run, like megachip, has none
+    pub fn preset(&self) -> Option<&'static str> {
+        match self.platform.as_deref()? {
+            "chip8" => Some("vip"),
+            "schip" => Some("schip"),
+            platform => presets()
+                .into_iter()
+                .find(|preset| preset.name == platform)
+                .map(|preset| preset.name),
+        }
+    }
+
+    // the speed and quirks the game is meant to run with, over what the settings had. the
+    // settings only hold a preset, the one with the same quirks or else the platform's
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(tickrate) = self.tickrate {
+            settings.speed = tickrate;
+        }
+        let presets = presets();
+        let preset = presets
+            .iter()
+            .position(|preset| Some(preset.quirks) == self.quirks)
+            .or_else(|| {
+                let name = self.preset()?;
+                presets.iter().position(|preset| preset.name == name)
+            });
+        if let Some(preset) = preset {
+            settings.preset = preset;
+        }
+    }
+
+    // the title with its authors, like "Br8kout by SharpenedSpoon"
+    pub fn byline(&self) -> Option<String> {
+        let title = self.title.clone()?;
+        match self.authors.is_empty() {
+            true => Some(title),
+            false => Some(format!("{} by {}", title, self.authors.join(", "))),
+        }
+    }
+
+    // like "left 7, right 9", for showing alongside the title
+    pub fn key_hints(&self) -> String {
+        self.keys
+            .iter()
+            .map(|(name, key)| format!("{} {:X}", name, key))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// a checkout's programs.json
+pub struct Archive {
+    programs: BTreeMap<String, Metadata>,
+}
+
+impl Archive {
+    // the archive a directory of ROMs belongs to, looking in the directory and the one above it.
+    // None if it isn't part of one
+    pub fn find(directory: &Path) -> io::Result<Option<Archive>> {
+        let checkout = directory
+            .ancestors()
+            .take(2)
+            .find(|checkout| checkout.join(ARCHIVE_PROGRAMS).is_file());
+        let Some(checkout) = checkout else {
+            return Ok(None);
+        };
+
+        let path = checkout.join(ARCHIVE_PROGRAMS);
+        let text = fs::read_to_string(&path)?;
+        Archive::parse(&text).map(Some).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), error),
+            )
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Archive, MetadataError> {
+        let programs = parse_json(text)?;
+        let programs = programs
+            .as_object()
+            .ok_or_else(|| MetadataError::Invalid("programs must be a JSON object".to_string()))?;
+
+        programs
+            .iter()
+            .map(|(name, entry)| {
+                read(entry)
+                    .map(|metadata| (name.clone(), metadata))
+                    .map_err(|message| MetadataError::Invalid(format!("{}: {}", name, message)))
+            })
+            .collect::<Result<_, _>>()
+            .map(|programs| Archive { programs })
+    }
+
+    // the entry for a ROM, by its file name
+    pub fn get(&self, rom_path: &Path) -> Option<&Metadata> {
+        let name = rom_path.file_stem()?.to_str()?;
+        self.programs.get(name)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Metadata, MetadataError> {
+    read(&parse_json(text)?).map_err(MetadataError::Invalid)
+}
+
+// <rom>.json, None if there isn't one
+fn read_sidecar(rom_path: &Path) -> io::Result<Option<Metadata>> {
+    let path = format!("{}.json", rom_path.display());
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    parse(&text)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, error)))
+}
+
+fn parse_json(text: &str) -> Result<Value, MetadataError> {
+    serde_json::from_str(text).map_err(|error| MetadataError::Syntax(error.to_string()))
+}
+
+fn read(value: &Value) -> Result<Metadata, String> {
+    let entry = value.as_object().ok_or("metadata must be a JSON object")?;
+
+    let mut metadata = Metadata::default();
+    if let Some(title) = entry.get("title") {
+        metadata.title = Some(string(title, "title")?);
+    }
+    if let Some(authors) = entry.get("authors") {
+        metadata.authors = authors
+            .as_array()
+            .ok_or("authors must be an array")?
+            .iter()
+            .map(|author| string(author, "authors"))
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(description) = entry.get("desc") {
+        metadata.description = Some(string(description, "desc")?);
+    }
+    if let Some(platform) = entry.get("platform") {
+        metadata.platform = Some(string(platform, "platform")?);
+    }
+    metadata.quirks = metadata
+        .preset()
+        .and_then(|name| presets().into_iter().find(|preset| preset.name == name))
+        .map(|preset| preset.quirks);
+
+    if let Some(options) = entry.get("options") {
+        let options = options.as_object().ok_or("options must be an object")?;
+        for (name, value) in options {
+            match name.as_str() {
+                "tickrate" => {
+                    metadata.tickrate = Some(
+                        value
+                            .as_u64()
+                            .and_then(|tickrate| u32::try_from(tickrate).ok())
+                            .filter(|&tickrate| tickrate > 0)
+                            .ok_or("tickrate must be a number above 0")?,
+                    )
+                }
+                // Octo's names for the quirks this has, the other options are colours and such
+                // or quirks it doesn't have
+                "shiftQuirks" | "loadStoreQuirks" | "jumpQuirks" | "clipQuirks" | "logicQuirks" => {
+                    let enabled = value
+                        .as_bool()
+                        .ok_or_else(|| format!("{} must be true or false", name))?;
+                    let quirks = metadata.quirks.get_or_insert_with(Quirks::default);
+                    match name.as_str() {
+                        "shiftQuirks" => quirks.shift_uses_vy = !enabled,
+                        "loadStoreQuirks" => quirks.memory_increment = !enabled,
+                        "jumpQuirks" => quirks.jump_uses_vx = enabled,
+                        "clipQuirks" => quirks.clipping = enabled,
+                        _ => quirks.vf_reset = enabled,
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    if let Some(keys) = entry.get("keys") {
+        let keys = keys.as_object().ok_or("keys must be an object")?;
+        for (name, key) in keys {
+            metadata.keys.push((name.clone(), key_number(key, name)?));
+        }
+    }
+    Ok(metadata)
+}
+
+fn string(value: &Value, name: &str) -> Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} must be a string", name))
+}
+
+// a key of the keypad, as a number or a hex digit in a string the way the archive writes them
+fn key_number(value: &Value, name: &str) -> Result<usize, String> {
+    match value {
+        Value::Number(number) => number.as_u64().map(|key| key as usize),
+        Value::String(digit) => usize::from_str_radix(digit, 16).ok(),
+        _ => None,
+    }
+    .filter(|&key| key < 0x10)
+    .ok_or_else(|| format!("key {} must be a keypad key, 0 to F", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_parse() {
+        let metadata = parse(
+            r##"{
+                "title": "Br8kout",
+                "authors": ["SharpenedSpoon"],
+                "desc": "Breakout, for Octojam",
+                "platform": "chip8",
+                "options": { "tickrate": 7, "fillColor": "#FFFFFF" },
+                "keys": { "left": "7", "right": 9 }
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.byline().as_deref(),
+            Some("Br8kout by SharpenedSpoon")
+        );
+        assert_eq!(metadata.tickrate, Some(7));
+        assert_eq!(metadata.preset(), Some("vip"));
+        assert_eq!(metadata.key_hints(), "left 7, right 9");
+
+        let mut settings = Settings::new();
+        metadata.apply(&mut settings);
+        assert_eq!(settings.speed, 7);
+        assert_eq!(presets()[settings.preset].name, "vip");
+
+        // a platform that can't be run leaves the quirks alone
+        let metadata = parse(r#"{ "platform": "megachip" }"#).unwrap();
+        assert_eq!(metadata.preset(), None);
+        let metadata = parse(r#"{ "platform": "xochip" }"#).unwrap();
+        assert_eq!(metadata.preset(), Some("xochip"));
+        assert!(matches!(parse("{"), Err(MetadataError::Syntax(_))));
+        assert_eq!(
+            parse(r#"{ "keys": { "fire": "G" } }"#),
+            Err(MetadataError::Invalid(
+                "key fire must be a keypad key, 0 to F".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_archive() {
+        let archive = Archive::parse(
+            r#"{
+                "octojam1title": {
+                    "title": "Octojam 1 Title",
+                    "authors": ["John Earnest"],
+                    "desc": "the title screen",
+                    "platform": "chip8",
+                    "options": {
+                        "tickrate": 15,
+                        "shiftQuirks": false,
+                        "loadStoreQuirks": true,
+                        "vBlankQuirks": false,
+                        "clipQuirks": true,
+                        "jumpQuirks": false,
+                        "logicQuirks": true
+                    }
+                },
+                "sweetcopter": { "title": "Sweet Copter", "platform": "schip" }
+            }"#,
+        )
+        .unwrap();
+
+        let title = archive.get(Path::new("roms/octojam1title.ch8")).unwrap();
+        assert_eq!(title.description.as_deref(), Some("the title screen"));
+        let quirks = title.quirks.unwrap();
+        assert!(quirks.shift_uses_vy && !quirks.memory_increment && quirks.vf_reset);
+
+        // quirks no preset has are put in the settings as the platform's
+        let mut settings = Settings::new();
+        title.apply(&mut settings);
+        assert_eq!((settings.speed, settings.preset().name), (15, "vip"));
+        let copter = archive.get(Path::new("sweetcopter.ch8")).unwrap();
+        copter.apply(&mut settings);
+        assert_eq!(settings.preset().name, "schip");
+        assert!(archive.get(Path::new("pong.ch8")).is_none());
+
+        assert_eq!(
+            Archive::parse(r#"{ "pong": { "title": 1 } }"#).err(),
+            Some(MetadataError::Invalid(
+                "pong: title must be a string".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_load_from_archive() {
+        let checkout = env::temp_dir().join(format!("rusty_chip8-archive-{}", std::process::id()));
+        fs::create_dir_all(checkout.join("roms")).unwrap();
+        fs::write(
+            checkout.join(ARCHIVE_PROGRAMS),
+            r#"{ "pong": { "title": "Pong" }, "maze": { "title": "Maze" } }"#,
+        )
+        .unwrap();
+        let pong = checkout.join("roms").join("pong.ch8");
+        let maze = checkout.join("roms").join("maze.ch8");
+        fs::write(
+            format!("{}.json", maze.display()),
+            r#"{ "title": "Maze!" }"#,
+        )
+        .unwrap();
+
+        let roms = [pong.clone(), maze.clone()];
+        let all = Metadata::load_all(&checkout.join("roms"), &roms).unwrap();
+        assert_eq!(all[0].as_ref().unwrap().title.as_deref(), Some("Pong"));
+        let title = |rom: &Path| {
+            Metadata::load(&rom.to_string_lossy())
+                .unwrap()
+                .and_then(|metadata| metadata.title)
+        };
+        assert_eq!(title(&pong).as_deref(), Some("Pong"));
+        // a sidecar comes first
+        assert_eq!(title(&maze).as_deref(), Some("Maze!"));
+        assert_eq!(title(&env::temp_dir().join("pong.ch8")), None);
+        fs::remove_dir_all(&checkout).unwrap();
+    }
+}
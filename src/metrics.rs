@@ -0,0 +1,149 @@
+// Prometheus counters on a tiny HTTP server, for keeping an eye on emulators left running in
+// kiosks and installations. scrape http://<address>/metrics
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::cpu::CpuError;
+
+#[derive(Default)]
+struct Counters {
+    instructions: AtomicU64,
+    frames: AtomicU64,
+    unknown_opcodes: AtomicU64,
+}
+
+pub struct Metrics {
+    address: SocketAddr,
+    counters: Arc<Counters>,
+}
+
+impl Metrics {
+    pub fn start(address: &str) -> io::Result<Metrics> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let counters = Arc::new(Counters::default());
+
+        let served = counters.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // a scrape is one short request, not worth a thread each, but a client that
+                // never finishes its request mustn't hold up the next one
+                let _ = stream
+                    .set_read_timeout(Some(Duration::from_secs(5)))
+                    .and_then(|_| serve_client(stream, &served));
+            }
+        });
+
+        Ok(Metrics { address, counters })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // call once per frame with the number of instructions it ran
+    pub fn record_frame(&self, instructions: u64) {
+        self.counters
+            .instructions
+            .fetch_add(instructions, Ordering::Relaxed);
+        self.counters.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, error: &CpuError) {
+        if let CpuError::UnknownOpcode(_) = error {
+            self.counters
+                .unknown_opcodes
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn serve_client(stream: TcpStream, counters: &Counters) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers don't matter, but they have to be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = match request.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", render(counters)),
+        _ => ("404 Not Found", "not found, try /metrics\n".to_string()),
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn render(counters: &Counters) -> String {
+    let mut text = String::new();
+    for (name, help, counter) in [
+        (
+            "chip8_instructions_total",
+            "Instructions executed.",
+            &counters.instructions,
+        ),
+        ("chip8_frames_total", "Frames rendered.", &counters.frames),
+        (
+            "chip8_unknown_opcodes_total",
+            "Unknown opcodes encountered.",
+            &counters.unknown_opcodes,
+        ),
+    ] {
+        text += &format!(
+            "# HELP {} {}\n# TYPE {} counter\n{} {}\n",
+            name,
+            help,
+            name,
+            name,
+            counter.load(Ordering::Relaxed)
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::start("127.0.0.1:0").unwrap();
+        metrics.record_frame(10);
+        metrics.record_frame(7);
+        metrics.record_error(&CpuError::StackUnderflow);
+        metrics.record_error(&CpuError::UnknownOpcode(0xFFFF));
+
+        let response = get(metrics.address(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\nchip8_instructions_total 17\n"));
+        assert!(response.contains("\nchip8_frames_total 2\n"));
+        assert!(response.contains("\nchip8_unknown_opcodes_total 1\n"));
+        assert!(response.contains("# TYPE chip8_frames_total counter\n"));
+
+        assert!(get(metrics.address(), "/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,202 @@
+// two players on two machines sharing one keypad. each side owns half of it, split down the
+// middle so the usual two-player layouts (1/4 against C/D in Pong) work:
+//   host   1 2 . .    guest  . . 3 C
+//          4 5 . .           . . 6 D
+//          7 8 . .           . . 9 E
+//          A 0 . .           . . B F
+// both machines run the same program in lockstep. every frame each side sends its half of the
+// keys for DELAY frames ahead and waits for the other side's keys for the frame it's about to
+// run, so a little latency is hidden and both see exactly the same input on the same frame
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::patch::crc32;
+
+const MAGIC: &[u8; 4] = b"C8NP";
+const VERSION: u8 = 1;
+// frames between pressing a key and the game seeing it, about 50ms at 60 frames a second
+pub const DELAY: usize = 3;
+
+const HOST_KEYS: u16 = key_mask(&[0x1, 0x2, 0x4, 0x5, 0x7, 0x8, 0xA, 0x0]);
+const GUEST_KEYS: u16 = !HOST_KEYS;
+
+const fn key_mask(keys: &[usize]) -> u16 {
+    let mut mask = 0;
+    let mut index = 0;
+    while index < keys.len() {
+        mask |= 1 << keys[index];
+        index += 1;
+    }
+    mask
+}
+
+pub struct Netplay {
+    stream: TcpStream,
+    // the keys this side may press
+    own_keys: u16,
+    seed: u64,
+    // our keys already sent but not yet played
+    local: VecDeque<u16>,
+    frame: usize,
+}
+
+impl Netplay {
+    // waits for the other player to connect, the seed makes CXNN the same on both sides
+    pub fn host(address: &str, rom: &[u8], seed: u64) -> io::Result<Netplay> {
+        Netplay::accept(TcpListener::bind(address)?, rom, seed)
+    }
+
+    fn accept(listener: TcpListener, rom: &[u8], seed: u64) -> io::Result<Netplay> {
+        let (mut stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+
+        let mut handshake = MAGIC.to_vec();
+        handshake.push(VERSION);
+        handshake.extend_from_slice(&crc32(rom).to_le_bytes());
+        handshake.extend_from_slice(&seed.to_le_bytes());
+        stream.write_all(&handshake)?;
+
+        // the guest answers with one byte once it has checked the ROM
+        let mut answer = [0];
+        stream.read_exact(&mut answer)?;
+        if answer[0] == 0 {
+            return Err(invalid("the other player has a different ROM"));
+        }
+
+        Ok(Netplay::new(stream, HOST_KEYS, seed))
+    }
+
+    pub fn join(address: &str, rom: &[u8]) -> io::Result<Netplay> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        let mut handshake = [0; 17];
+        stream.read_exact(&mut handshake)?;
+        if &handshake[..4] != MAGIC || handshake[4] != VERSION {
+            return Err(invalid(
+                "not a rusty_chip8 netplay host, or a different version",
+            ));
+        }
+
+        let same_rom = handshake[5..9] == crc32(rom).to_le_bytes();
+        stream.write_all(&[same_rom as u8])?;
+        if !same_rom {
+            return Err(invalid("the other player has a different ROM"));
+        }
+
+        let seed = u64::from_le_bytes(handshake[9..17].try_into().unwrap());
+        Ok(Netplay::new(stream, GUEST_KEYS, seed))
+    }
+
+    fn new(stream: TcpStream, own_keys: u16, seed: u64) -> Netplay {
+        Netplay {
+            stream,
+            own_keys,
+            seed,
+            local: VecDeque::from(vec![0; DELAY]),
+            frame: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // call once per frame with the keys held on this machine, returns the whole keypad for the
+    // frame about to run. blocks if the other side has fallen more than DELAY frames behind
+    pub fn exchange(&mut self, keys: &[bool]) -> io::Result<[bool; 16]> {
+        let mask = keys.iter().enumerate().fold(0u16, |mask, (key, &pressed)| {
+            mask | ((pressed as u16) << key)
+        }) & self.own_keys;
+        self.stream.write_all(&mask.to_le_bytes())?;
+        self.local.push_back(mask);
+
+        // nothing was sent for the first few frames, so there is nothing to wait for
+        let remote = if self.frame < DELAY {
+            0
+        } else {
+            let mut bytes = [0; 2];
+            self.stream.read_exact(&mut bytes)?;
+            u16::from_le_bytes(bytes) & !self.own_keys
+        };
+        self.frame += 1;
+
+        let combined = self.local.pop_front().unwrap() | remote;
+        Ok(std::array::from_fn(|key| combined & (1 << key) != 0))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn pressed(keys: &[usize]) -> [bool; 16] {
+        std::array::from_fn(|key| keys.contains(&key))
+    }
+
+    // hosts on a free port, returning its address and the host once someone has joined
+    fn host<T: Send + 'static>(
+        rom: &'static [u8],
+        seed: u64,
+        then: impl FnOnce(io::Result<Netplay>) -> T + Send + 'static,
+    ) -> (String, thread::JoinHandle<T>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let host = thread::spawn(move || then(Netplay::accept(listener, rom, seed)));
+        (address, host)
+    }
+
+    #[test]
+    fn test_lockstep() {
+        let (address, host) = host(b"ROM", 42, |netplay| {
+            let mut netplay = netplay.unwrap();
+            // the host presses 1 and, outside its half, C on the first frame
+            (0..DELAY + 1)
+                .map(|frame| {
+                    let keys = if frame == 0 {
+                        pressed(&[0x1, 0xC])
+                    } else {
+                        pressed(&[])
+                    };
+                    netplay.exchange(&keys).unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut guest = Netplay::join(&address, b"ROM").unwrap();
+        assert_eq!(guest.seed(), 42);
+        let guest_frames: Vec<_> = (0..DELAY + 1)
+            .map(|frame| {
+                let keys = if frame == 0 {
+                    pressed(&[0xD])
+                } else {
+                    pressed(&[])
+                };
+                guest.exchange(&keys).unwrap()
+            })
+            .collect();
+        let host_frames = host.join().unwrap();
+
+        // both sides see the same keys, DELAY frames late, and the host's C is dropped
+        assert_eq!(host_frames, guest_frames);
+        assert_eq!(host_frames[0], pressed(&[]));
+        assert_eq!(host_frames[DELAY], pressed(&[0x1, 0xD]));
+    }
+
+    #[test]
+    fn test_different_rom() {
+        let (address, host) = host(b"ROM", 0, |netplay| netplay.is_err());
+
+        let guest = Netplay::join(&address, b"OTHER");
+        assert_eq!(guest.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        assert!(host.join().unwrap());
+    }
+}
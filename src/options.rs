@@ -0,0 +1,709 @@
+use std::{ops::RangeInclusive, str::FromStr};
+
+use tracing::Level;
+
+use crate::compliance;
+use crate::cpu;
+use crate::crowd::DEFAULT_WINDOW;
+use crate::demos::{self, Demo};
+use crate::error::ConfigError;
+use crate::platform::{self, Platform};
+use crate::quirks::Quirks;
+
+// the frame rate kept to without vsync, the CHIP-8's own 60Hz
+const DEFAULT_FPS: u32 = 60;
+
+pub const USAGE: &str = "Usage: cargo run [command] [options] /path/to/game
+The game can also be an http(s) URL (needs the fetch feature), or - to read it from stdin.
+
+Commands:
+    run                     play the game (the default)
+    cfg                     print the game's control-flow graph in Graphviz DOT format
+    check                   look for problems in the ROM without running it
+    term                    play the game in the terminal (needs the terminal feature)
+    compliance <dir>        run the test suite ROMs in <dir> under each quirk preset
+    library <dir>           show every ROM in <dir> with a preview of its screen after two seconds,
+                            <dir> can be a checkout of the chip8Archive
+
+Options:
+    --demo <name>           play a built-in program instead of a game file: maze, particles or
+                            airplane
+    --quirks <preset>       run the game the way another interpreter would: default, vip, schip,
+                            schip-legacy, chip8e, hires or xochip
+    --platform <name>       run the game on the machine it was written for, setting the quirks,
+                            font, memory and speed at once: vip, schip, xochip or octo. --quirks
+                            still picks the quirks
+    --trace <file>          write every executed instruction to <file>
+    --trace-range <a>-<b>   only trace instructions between hex addresses a and b
+    --compare <file>        run the game without a window against a full trace from --trace, here
+                            or in another emulator, and report the first instruction that differs
+    --stack-size <depth>    how many calls can be nested before a stack overflow (default 16), e.g.
+                            64 for Octo programs that recurse
+    --hz <speed>            run this many instructions a second, e.g. 700, rather than 10 each
+                            frame, which goes faster on displays that refresh more often
+    --auto-speed            work out the speed the game was written for from how it waits on the
+                            delay timer, and remember it for the game
+    --sticky <frames>       keep every key press held for at least this many frames, for
+                            keyboards that miss quick taps
+    --profile               print an execution profile on exit
+    --no-watchdog           don't pause games that seem stuck in a loop
+    --strict                stop the game with an error when it writes below #200, over the
+                            interpreter and font
+    --tas                   record every frame's input so F11 can edit it and replay the rest
+    --save-movie <file>     write the --tas recording to <file> on exit
+    --play-movie <file>     play a movie saved with --save-movie, stopping if the game stops
+                            matching it
+    --record-audio <file>   write the buzzer to a WAV file as the game plays
+    --learn                 run one instruction per press of Space and explain what each one did
+    --bench                 run the game uncapped for a few seconds without a window and report its speed
+    --self-test             run the built-in test ROM without a window to check this build works,
+                            no game needed
+    --coverage <file>       write a map of executed, read and untouched memory on exit
+    --cfg <file>            write the control-flow graph on exit, including jumps seen at runtime
+    --patch <file>          apply an IPS or BPS patch to the ROM before running it
+    --load-state <file>     start from a machine state saved as JSON (needs the json feature)
+    --save-state <file>     write the machine state as JSON on exit (needs the json feature)
+    --script <file>         run a Lua script alongside the game (needs the lua feature)
+    --plugin <file>         load a plugin from a shared library, can be given more than once
+                            (needs the plugins feature)
+    --serve <address>       stream the screen and take key presses over WebSocket, e.g. 0.0.0.0:8080
+                            (needs the remote feature)
+    --rpc <address>         accept JSON-RPC requests on a local socket, e.g. 127.0.0.1:7878
+                            (needs the rpc feature)
+    --host <address>        wait for a second player to join over the network, e.g. 0.0.0.0:7070
+    --join <address>        play against the player hosting at <address>
+    --crowd <server>/<channel>
+                            let an IRC or Twitch chat vote on keys, e.g. irc.chat.twitch.tv:6667/name
+    --vote-window <frames>  frames each crowd vote lasts (default 30)
+    --metrics <address>     serve Prometheus counters over HTTP, e.g. 0.0.0.0:9100
+    --split <file>          run a second game beside the first, played with 7890/UIOP/JKL;/M,./
+    --split-quirks <preset> quirks for the second game, as for --quirks
+    --monitor <number>      the display to go fullscreen on with Alt+Return, counting from 1,
+                            rather than the one the window is on
+    --no-vsync              don't wait for the display to refresh between frames, keeping to
+                            --fps by sleeping instead
+    --fps <rate>            frames a second when vsync is off or the display can't do it (default
+                            60)
+    --bezel <file>          draw a BMP image around the screen, which sits in the middle of it. a
+                            <game>.bezel.bmp beside the game is used without this
+    --log-level <level>     how much to log: error, warn, info (the default), debug, or trace for
+                            every instruction run
+    --log-file <file>       write the log to <file> rather than the terminal
+    --second-keys <cluster> let a second player share the keypad from the keyboard's numpad, laid
+                            out like the keypad, or from 7890/UIOP/JKL;/M,./ (numpad or right)";
+
+// where a second player's keys are on a shared keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecondKeys {
+    Numpad,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    Cfg,
+    Check,
+    Term,
+    Compliance,
+    Library,
+}
+
+pub struct Options {
+    pub command: Command,
+    pub rom_path: String,
+    pub demo: Option<&'static Demo>,
+    // None leaves it to the saved settings
+    pub quirks: Option<Quirks>,
+    pub platform: Option<Platform>,
+    pub trace_path: Option<String>,
+    pub trace_range: Option<RangeInclusive<u16>>,
+    pub compare_path: Option<String>,
+    pub hz: Option<u32>,
+    // None leaves it to the platform
+    pub stack_size: Option<usize>,
+    pub auto_speed: bool,
+    pub sticky_frames: Option<u64>,
+    pub profile: bool,
+    pub watchdog: bool,
+    pub strict: bool,
+    pub tas: bool,
+    pub save_movie_path: Option<String>,
+    pub play_movie_path: Option<String>,
+    pub audio_path: Option<String>,
+    pub learn: bool,
+    pub bench: bool,
+    pub self_test: bool,
+    pub coverage_path: Option<String>,
+    pub cfg_path: Option<String>,
+    pub script_path: Option<String>,
+    pub plugin_paths: Vec<String>,
+    pub patch_path: Option<String>,
+    pub load_state_path: Option<String>,
+    pub save_state_path: Option<String>,
+    pub serve_address: Option<String>,
+    pub rpc_address: Option<String>,
+    pub host_address: Option<String>,
+    pub join_address: Option<String>,
+    // chat server and channel
+    pub crowd: Option<(String, String)>,
+    pub vote_window: u32,
+    pub metrics_address: Option<String>,
+    pub split_path: Option<String>,
+    pub split_quirks: Quirks,
+    pub bezel_path: Option<String>,
+    // None leaves it to the saved settings
+    pub monitor: Option<u32>,
+    pub vsync: bool,
+    pub fps: u32,
+    pub second_keys: Option<SecondKeys>,
+    pub log_level: Level,
+    pub log_path: Option<String>,
+}
+
+pub fn parse(args: &[String]) -> Result<Options, ConfigError> {
+    let (command, args) = match args.first().map(String::as_str) {
+        Some("run") => (Command::Run, &args[1..]),
+        Some("cfg") => (Command::Cfg, &args[1..]),
+        Some("check") => (Command::Check, &args[1..]),
+        Some("term") => (Command::Term, &args[1..]),
+        Some("compliance") => (Command::Compliance, &args[1..]),
+        Some("library") => (Command::Library, &args[1..]),
+        _ => (Command::Run, args),
+    };
+
+    let mut rom_path = None;
+    let mut demo = None;
+    let mut quirks = None;
+    let mut platform = None;
+    let mut trace_path = None;
+    let mut trace_range = None;
+    let mut compare_path = None;
+    let mut hz = None;
+    let mut stack_size = None;
+    let mut auto_speed = false;
+    let mut sticky_frames = None;
+    let mut profile = false;
+    let mut watchdog = true;
+    let mut strict = false;
+    let mut tas = false;
+    let mut save_movie_path = None;
+    let mut play_movie_path = None;
+    let mut audio_path = None;
+    let mut learn = false;
+    let mut bench = false;
+    let mut self_test = false;
+    let mut coverage_path = None;
+    let mut cfg_path = None;
+    let mut script_path = None;
+    let mut plugin_paths = Vec::new();
+    let mut patch_path = None;
+    let mut load_state_path = None;
+    let mut save_state_path = None;
+    let mut serve_address = None;
+    let mut rpc_address = None;
+    let mut host_address = None;
+    let mut join_address = None;
+    let mut crowd = None;
+    let mut vote_window = DEFAULT_WINDOW;
+    let mut metrics_address = None;
+    let mut split_path = None;
+    let mut split_quirks = Quirks::default();
+    let mut bezel_path = None;
+    let mut monitor = None;
+    let mut vsync = true;
+    let mut fps = DEFAULT_FPS;
+    let mut second_keys = None;
+    let mut log_level = Level::INFO;
+    let mut log_path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--demo" => demo = Some(demos::find(value(&mut args, arg)?)?),
+            "--quirks" => quirks = Some(parse_preset(value(&mut args, arg)?)?),
+            "--platform" => platform = Some(platform::find(value(&mut args, arg)?)?),
+            "--trace" => trace_path = Some(value(&mut args, arg)?.clone()),
+            "--trace-range" => trace_range = Some(parse_range(value(&mut args, arg)?)?),
+            "--compare" => compare_path = Some(value(&mut args, arg)?.clone()),
+            "--auto-speed" => auto_speed = true,
+            "--profile" => profile = true,
+            "--no-watchdog" => watchdog = false,
+            "--strict" => strict = true,
+            "--tas" => tas = true,
+            "--save-movie" => save_movie_path = Some(value(&mut args, arg)?.clone()),
+            "--play-movie" => play_movie_path = Some(value(&mut args, arg)?.clone()),
+            "--record-audio" => audio_path = Some(value(&mut args, arg)?.clone()),
+            "--learn" => learn = true,
+            "--no-vsync" => vsync = false,
+            "--bench" => bench = true,
+            "--self-test" => self_test = true,
+            "--coverage" => coverage_path = Some(value(&mut args, arg)?.clone()),
+            "--cfg" => cfg_path = Some(value(&mut args, arg)?.clone()),
+            "--script" => script_path = Some(value(&mut args, arg)?.clone()),
+            "--plugin" => plugin_paths.push(value(&mut args, arg)?.clone()),
+            "--patch" => patch_path = Some(value(&mut args, arg)?.clone()),
+            "--load-state" => load_state_path = Some(value(&mut args, arg)?.clone()),
+            "--save-state" => save_state_path = Some(value(&mut args, arg)?.clone()),
+            "--serve" => serve_address = Some(value(&mut args, arg)?.clone()),
+            "--rpc" => rpc_address = Some(value(&mut args, arg)?.clone()),
+            "--host" => host_address = Some(value(&mut args, arg)?.clone()),
+            "--join" => join_address = Some(value(&mut args, arg)?.clone()),
+            "--split" => split_path = Some(value(&mut args, arg)?.clone()),
+            "--bezel" => bezel_path = Some(value(&mut args, arg)?.clone()),
+            "--split-quirks" => split_quirks = parse_preset(value(&mut args, arg)?)?,
+            "--second-keys" => {
+                second_keys = Some(match value(&mut args, arg)?.as_str() {
+                    "numpad" => SecondKeys::Numpad,
+                    "right" => SecondKeys::Right,
+                    other => {
+                        return Err(ConfigError::Invalid(format!(
+                            "unknown key cluster {}, expected numpad or right",
+                            other
+                        )))
+                    }
+                })
+            }
+            "--log-level" => {
+                let text = value(&mut args, arg)?;
+                log_level = Level::from_str(text)
+                    .map_err(|_| ConfigError::Invalid(format!("unknown log level {}", text)))?;
+            }
+            "--log-file" => log_path = Some(value(&mut args, arg)?.clone()),
+            "--metrics" => metrics_address = Some(value(&mut args, arg)?.clone()),
+            "--crowd" => crowd = Some(parse_crowd(value(&mut args, arg)?)?),
+            "--monitor" => {
+                let text = value(&mut args, arg)?;
+                monitor = Some(
+                    text.parse()
+                        .ok()
+                        .filter(|&monitor| monitor > 0)
+                        .ok_or_else(|| {
+                            ConfigError::Invalid(format!(
+                                "invalid monitor {}, displays count from 1",
+                                text
+                            ))
+                        })?,
+                );
+            }
+            "--fps" => {
+                let text = value(&mut args, arg)?;
+                fps =
+                    text.parse().ok().filter(|&fps| fps > 0).ok_or_else(|| {
+                        ConfigError::Invalid(format!("invalid frame rate {}", text))
+                    })?;
+            }
+            "--stack-size" => {
+                let text = value(&mut args, arg)?;
+                let size = text
+                    .parse()
+                    .map_err(|_| ConfigError::Invalid(format!("invalid stack size {}", text)))?;
+                cpu::check_stack_size(size)?;
+                stack_size = Some(size);
+            }
+            "--hz" => {
+                let text = value(&mut args, arg)?;
+                hz = Some(text.parse().ok().filter(|&hz| hz > 0).ok_or_else(|| {
+                    ConfigError::Invalid(format!("invalid clock speed {}", text))
+                })?);
+            }
+            "--sticky" => {
+                let text = value(&mut args, arg)?;
+                sticky_frames = Some(text.parse().ok().filter(|&frames| frames > 0).ok_or_else(
+                    || ConfigError::Invalid(format!("invalid number of frames {}", text)),
+                )?);
+            }
+            "--vote-window" => {
+                let text = value(&mut args, arg)?;
+                vote_window = text
+                    .parse()
+                    .ok()
+                    .filter(|&frames| frames > 0)
+                    .ok_or_else(|| ConfigError::Invalid(format!("invalid vote window {}", text)))?;
+            }
+            _ if arg.starts_with("--") => return Err(ConfigError::UnknownOption(arg.clone())),
+            _ if rom_path.is_none() => rom_path = Some(arg.clone()),
+            _ => return Err(ConfigError::UnexpectedArgument(arg.clone())),
+        }
+    }
+
+    // a recording is replayed a fixed number of instructions per frame
+    if hz.is_some() && tas {
+        return Err(ConfigError::Invalid(
+            "--hz and --tas can't be used together".to_string(),
+        ));
+    }
+
+    // the speed can't change under a recording or the other player
+    if auto_speed
+        && (hz.is_some()
+            || tas
+            || play_movie_path.is_some()
+            || host_address.is_some()
+            || join_address.is_some())
+    {
+        return Err(ConfigError::Invalid(
+            "--auto-speed can't be used with --hz, --tas, --play-movie or netplay".to_string(),
+        ));
+    }
+
+    if save_movie_path.is_some() && !tas {
+        return Err(ConfigError::Invalid(
+            "--save-movie needs --tas to record the movie".to_string(),
+        ));
+    }
+
+    // a movie is played a fixed number of instructions a frame, with nobody else's keys
+    if play_movie_path.is_some()
+        && (tas || hz.is_some() || host_address.is_some() || join_address.is_some())
+    {
+        return Err(ConfigError::Invalid(
+            "--play-movie can't be used with --tas, --hz or netplay".to_string(),
+        ));
+    }
+
+    if host_address.is_some() && join_address.is_some() {
+        return Err(ConfigError::Invalid(
+            "--host and --join can't be used together".to_string(),
+        ));
+    }
+
+    if demo.is_some() && rom_path.is_some() {
+        return Err(ConfigError::Invalid(
+            "--demo can't be used with a ROM".to_string(),
+        ));
+    }
+
+    // the self-test and demos bring their own ROM
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None if self_test || demo.is_some() => String::new(),
+        None => return Err(ConfigError::NoRom),
+    };
+
+    Ok(Options {
+        command,
+        rom_path,
+        demo,
+        quirks,
+        platform,
+        trace_path,
+        trace_range,
+        compare_path,
+        hz,
+        stack_size,
+        auto_speed,
+        sticky_frames,
+        profile,
+        watchdog,
+        strict,
+        tas,
+        save_movie_path,
+        play_movie_path,
+        audio_path,
+        learn,
+        bench,
+        self_test,
+        coverage_path,
+        cfg_path,
+        script_path,
+        plugin_paths,
+        patch_path,
+        load_state_path,
+        save_state_path,
+        serve_address,
+        rpc_address,
+        host_address,
+        join_address,
+        crowd,
+        vote_window,
+        metrics_address,
+        split_path,
+        split_quirks,
+        bezel_path,
+        monitor,
+        vsync,
+        fps,
+        second_keys,
+        log_level,
+        log_path,
+    })
+}
+
+fn value<'a>(
+    args: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> Result<&'a String, ConfigError> {
+    args.next()
+        .ok_or_else(|| ConfigError::MissingValue(option.to_string()))
+}
+
+pub fn parse_address(text: &str) -> Result<u16, ConfigError> {
+    let digits = text.trim_start_matches("0x").trim_start_matches('#');
+    u16::from_str_radix(digits, 16)
+        .map_err(|_| ConfigError::Invalid(format!("invalid address {}", text)))
+}
+
+fn parse_range(text: &str) -> Result<RangeInclusive<u16>, ConfigError> {
+    let (start, end) = text
+        .split_once('-')
+        .ok_or_else(|| ConfigError::Invalid(format!("invalid address range {}", text)))?;
+
+    Ok(parse_address(start)?..=parse_address(end)?)
+}
+
+fn parse_preset(name: &str) -> Result<Quirks, ConfigError> {
+    compliance::presets()
+        .into_iter()
+        .find(|preset| preset.name == name)
+        .map(|preset| preset.quirks)
+        .ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "unknown quirk preset {}, expected default, vip, schip, schip-legacy, chip8e, hires or xochip",
+                name
+            ))
+        })
+}
+
+fn parse_crowd(text: &str) -> Result<(String, String), ConfigError> {
+    match text.split_once('/') {
+        Some((server, channel)) if !server.is_empty() && !channel.is_empty() => {
+            Ok((server.to_string(), channel.to_string()))
+        }
+        _ => Err(ConfigError::Invalid(format!(
+            "expected <server>/<channel>, got {}",
+            text
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_rom_only() {
+        let options = parse(&args("game.ch8")).unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(options.rom_path, "game.ch8");
+        assert!(options.trace_path.is_none());
+        assert!(!options.bench);
+    }
+
+    #[test]
+    fn test_demo() {
+        let options = parse(&args("--demo maze")).unwrap();
+        assert_eq!(options.demo.map(|demo| demo.name), Some("maze"));
+        assert!(parse(&args("game.ch8")).unwrap().demo.is_none());
+        assert!(parse(&args("--demo pong")).is_err());
+        assert!(parse(&args("--demo maze game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_self_test() {
+        let options = parse(&args("--self-test")).unwrap();
+        assert!(options.self_test);
+        assert!(!parse(&args("game.ch8")).unwrap().self_test);
+        assert!(parse(&args("--bench")).is_err());
+    }
+
+    #[test]
+    fn test_trace() {
+        let options = parse(&args("--trace out.log --trace-range 200-2FF game.ch8")).unwrap();
+        assert_eq!(options.trace_path.as_deref(), Some("out.log"));
+        assert_eq!(options.trace_range, Some(0x200..=0x2FF));
+    }
+
+    #[test]
+    fn test_crowd() {
+        let options = parse(&args("--crowd irc.chat.twitch.tv:6667/name game.ch8")).unwrap();
+        assert_eq!(
+            options.crowd,
+            Some(("irc.chat.twitch.tv:6667".to_string(), "name".to_string()))
+        );
+        assert_eq!(options.vote_window, DEFAULT_WINDOW);
+    }
+
+    #[test]
+    fn test_stdin() {
+        let options = parse(&args("--profile -")).unwrap();
+        assert_eq!(options.rom_path, "-");
+        assert!(options.profile);
+    }
+
+    #[test]
+    fn test_split() {
+        let options = parse(&args("--split other.ch8 --split-quirks vip game.ch8")).unwrap();
+        assert_eq!(options.split_path.as_deref(), Some("other.ch8"));
+        assert_eq!(options.split_quirks, Quirks::vip());
+        assert!(parse(&args("--split-quirks bogus game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_monitor() {
+        assert_eq!(
+            parse(&args("--monitor 2 game.ch8")).unwrap().monitor,
+            Some(2)
+        );
+        assert_eq!(parse(&args("game.ch8")).unwrap().monitor, None);
+        assert!(parse(&args("--monitor 0 game.ch8")).is_err());
+        assert!(parse(&args("--monitor left game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_vsync() {
+        let options = parse(&args("game.ch8")).unwrap();
+        assert!(options.vsync);
+        assert_eq!(options.fps, DEFAULT_FPS);
+
+        let options = parse(&args("--no-vsync --fps 30 game.ch8")).unwrap();
+        assert!(!options.vsync);
+        assert_eq!(options.fps, 30);
+        assert!(parse(&args("--fps 0 game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_auto_speed() {
+        assert!(parse(&args("--auto-speed game.ch8")).unwrap().auto_speed);
+        assert!(!parse(&args("game.ch8")).unwrap().auto_speed);
+        assert!(parse(&args("--auto-speed --hz 700 game.ch8")).is_err());
+        assert!(parse(&args("--auto-speed --tas game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_platform() {
+        let options = parse(&args("--platform schip game.ch8")).unwrap();
+        assert_eq!(options.platform.unwrap().quirks, Quirks::schip());
+        assert!(parse(&args("game.ch8")).unwrap().platform.is_none());
+        assert!(parse(&args("--platform chip48 game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_strict() {
+        assert!(parse(&args("--strict game.ch8")).unwrap().strict);
+        assert!(!parse(&args("game.ch8")).unwrap().strict);
+    }
+
+    #[test]
+    fn test_stack_size() {
+        let options = parse(&args("--stack-size 64 game.ch8")).unwrap();
+        assert_eq!(options.stack_size, Some(64));
+        assert_eq!(parse(&args("game.ch8")).unwrap().stack_size, None);
+        assert!(parse(&args("--stack-size 0 game.ch8")).is_err());
+        assert!(parse(&args("--stack-size deep game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_bezel() {
+        let options = parse(&args("--bezel cabinet.bmp game.ch8")).unwrap();
+        assert_eq!(options.bezel_path.as_deref(), Some("cabinet.bmp"));
+        assert!(parse(&args("game.ch8")).unwrap().bezel_path.is_none());
+    }
+
+    #[test]
+    fn test_quirks() {
+        let options = parse(&args("--quirks chip8e game.ch8")).unwrap();
+        assert_eq!(options.quirks, Some(Quirks::chip8e()));
+        assert_eq!(parse(&args("game.ch8")).unwrap().quirks, None);
+        assert!(parse(&args("--quirks chip8 game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_hz() {
+        assert_eq!(parse(&args("--hz 700 game.ch8")).unwrap().hz, Some(700));
+        assert!(parse(&args("game.ch8")).unwrap().hz.is_none());
+        assert!(parse(&args("--hz 0 game.ch8")).is_err());
+        assert!(parse(&args("--hz 700 --tas game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_movies() {
+        let options = parse(&args("--tas --save-movie run.movie game.ch8")).unwrap();
+        assert_eq!(options.save_movie_path.as_deref(), Some("run.movie"));
+        let options = parse(&args("--play-movie run.movie game.ch8")).unwrap();
+        assert_eq!(options.play_movie_path.as_deref(), Some("run.movie"));
+        assert!(parse(&args("--save-movie run.movie game.ch8")).is_err());
+        assert!(parse(&args("--play-movie run.movie --tas game.ch8")).is_err());
+        assert!(parse(&args("--play-movie run.movie --hz 700 game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_record_audio() {
+        let options = parse(&args("--record-audio song.wav game.ch8")).unwrap();
+        assert_eq!(options.audio_path.as_deref(), Some("song.wav"));
+        assert!(parse(&args("game.ch8")).unwrap().audio_path.is_none());
+    }
+
+    #[test]
+    fn test_sticky() {
+        assert_eq!(
+            parse(&args("--sticky 4 game.ch8")).unwrap().sticky_frames,
+            Some(4)
+        );
+        assert!(parse(&args("--sticky 0 game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_second_keys() {
+        let options = parse(&args("--second-keys numpad game.ch8")).unwrap();
+        assert_eq!(options.second_keys, Some(SecondKeys::Numpad));
+        assert!(parse(&args("game.ch8")).unwrap().second_keys.is_none());
+        assert!(parse(&args("--second-keys arrows game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_plugins() {
+        let options = parse(&args("--plugin a.so --plugin b.so game.ch8")).unwrap();
+        assert_eq!(options.plugin_paths, ["a.so", "b.so"]);
+        assert!(parse(&args("game.ch8")).unwrap().plugin_paths.is_empty());
+    }
+
+    #[test]
+    fn test_logging() {
+        let options = parse(&args("--log-level trace --log-file run.log game.ch8")).unwrap();
+        assert_eq!(options.log_level, Level::TRACE);
+        assert_eq!(options.log_path.as_deref(), Some("run.log"));
+        assert_eq!(parse(&args("game.ch8")).unwrap().log_level, Level::INFO);
+        assert!(parse(&args("--log-level loud game.ch8")).is_err());
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(parse(&args("run game.ch8")).unwrap().command, Command::Run);
+        assert_eq!(parse(&args("cfg game.ch8")).unwrap().command, Command::Cfg);
+        assert_eq!(
+            parse(&args("check game.ch8")).unwrap().command,
+            Command::Check
+        );
+        assert_eq!(
+            parse(&args("term game.ch8")).unwrap().command,
+            Command::Term
+        );
+        assert_eq!(
+            parse(&args("compliance tests/timendus")).unwrap().command,
+            Command::Compliance
+        );
+        assert_eq!(
+            parse(&args("library roms")).unwrap().command,
+            Command::Library
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(parse(&args("")), Err(ConfigError::NoRom)));
+        assert!(parse(&args("a.ch8 b.ch8")).is_err());
+        assert!(parse(&args("--trace")).is_err());
+        assert!(parse(&args("--trace-range 200 game.ch8")).is_err());
+        assert!(matches!(
+            parse(&args("--bogus game.ch8")),
+            Err(ConfigError::UnknownOption(_))
+        ));
+        assert!(parse(&args("--crowd irc.example.com game.ch8")).is_err());
+        assert!(parse(&args("--vote-window 0 game.ch8")).is_err());
+    }
+}
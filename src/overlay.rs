@@ -0,0 +1,199 @@
+use std::fmt::Write;
+
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, text_width, LINE_HEIGHT};
+use chip8::cpu::CPU;
+
+const OVERLAY_PADDING: i32 = 4;
+const OVERLAY_BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const OVERLAY_TEXT_COLOR: Color = Color::RGB(0, 255, 128);
+const PRESSED_TEXT_COLOR: Color = Color::BLACK;
+
+// the keypad as it's laid out on the COSMAC VIP
+const KEYPAD_LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+const KEY_SIZE: i32 = 28;
+const KEY_GAP: i32 = 2;
+
+pub enum Corner {
+    BottomLeft,
+    TopRight,
+}
+
+// draws lines of text in a translucent box anchored to a corner of the window
+pub fn draw_box<'a, I>(canvas: &mut Canvas<Window>, lines: I, corner: Corner)
+where
+    I: IntoIterator<Item = &'a str>,
+    I::IntoIter: Clone,
+{
+    let lines = lines.into_iter();
+    let (window_width, window_height) = canvas.logical_size();
+    let width = lines.clone().map(text_width).max().unwrap_or(0);
+    let height = lines.clone().count() as i32 * LINE_HEIGHT;
+    let (x, y) = match corner {
+        Corner::BottomLeft => (0, window_height as i32 - height - 2 * OVERLAY_PADDING),
+        Corner::TopRight => (window_width as i32 - width - 2 * OVERLAY_PADDING, 0),
+    };
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(OVERLAY_BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(
+            x,
+            y,
+            (width + 2 * OVERLAY_PADDING) as u32,
+            (height + 2 * OVERLAY_PADDING) as u32,
+        ))
+        .unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    for (i, line) in lines.enumerate() {
+        let line_y = y + OVERLAY_PADDING + i as i32 * LINE_HEIGHT;
+        draw_text(
+            canvas,
+            line,
+            x + OVERLAY_PADDING,
+            line_y,
+            OVERLAY_TEXT_COLOR,
+        );
+    }
+}
+
+// a message for the player, e.g. why the game paused
+pub fn draw_notice(canvas: &mut Canvas<Window>, lines: &[&str]) {
+    draw_box(canvas, lines.iter().copied(), Corner::BottomLeft);
+}
+
+// `count` empty lines to write an overlay's text into, the strings from the last frame are
+// kept so drawing doesn't allocate once they're long enough
+pub fn reuse_lines(lines: &mut Vec<String>, count: usize) -> &mut [String] {
+    lines.resize_with(count, String::new);
+    lines.iter_mut().for_each(String::clear);
+    lines
+}
+
+// a quick look at the registers without opening the full debugger
+pub struct RegisterHud {
+    visible: bool,
+    lines: Vec<String>,
+}
+
+impl RegisterHud {
+    pub fn new() -> RegisterHud {
+        RegisterHud {
+            visible: false,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    pub fn draw(&mut self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (first, rows) = reuse_lines(&mut self.lines, 3).split_first_mut().unwrap();
+        write!(
+            first,
+            "PC {:03X} I {:03X} DT {:02X} ST {:02X}",
+            cpu.pc(),
+            cpu.index_register(),
+            cpu.delay_timer(),
+            cpu.sound_timer()
+        )
+        .unwrap();
+
+        for ((index, values), line) in cpu.v_registers().chunks(8).enumerate().zip(rows) {
+            write!(line, "V{:X}", index * 8).unwrap();
+            for value in values {
+                write!(line, " {:02X}", value).unwrap();
+            }
+        }
+
+        draw_box(
+            canvas,
+            self.lines.iter().map(String::as_str),
+            Corner::BottomLeft,
+        );
+    }
+}
+
+// the keypad in the bottom right corner with the held keys lit, for streams and for checking
+// that a key press actually reaches the game
+pub struct KeypadDisplay {
+    visible: bool,
+}
+
+impl KeypadDisplay {
+    pub fn new() -> KeypadDisplay {
+        KeypadDisplay { visible: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    pub fn draw(&self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+        let size = 4 * KEY_SIZE + 3 * KEY_GAP;
+        let left = window_width as i32 - size - OVERLAY_PADDING;
+        let top = window_height as i32 - size - OVERLAY_PADDING;
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (column, &key) in keys.iter().enumerate() {
+                let x = left + column as i32 * (KEY_SIZE + KEY_GAP);
+                let y = top + row as i32 * (KEY_SIZE + KEY_GAP);
+                let pressed = cpu.keys()[key];
+
+                canvas.set_draw_color(if pressed {
+                    OVERLAY_TEXT_COLOR
+                } else {
+                    OVERLAY_BACKGROUND
+                });
+                canvas
+                    .fill_rect(Rect::new(x, y, KEY_SIZE as u32, KEY_SIZE as u32))
+                    .unwrap();
+
+                let label = format!("{:X}", key);
+                draw_text(
+                    canvas,
+                    &label,
+                    x + (KEY_SIZE - text_width(&label)) / 2,
+                    y + (KEY_SIZE - LINE_HEIGHT) / 2,
+                    if pressed {
+                        PRESSED_TEXT_COLOR
+                    } else {
+                        OVERLAY_TEXT_COLOR
+                    },
+                );
+            }
+        }
+        canvas.set_blend_mode(BlendMode::None);
+    }
+}
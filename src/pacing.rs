@@ -0,0 +1,151 @@
+// works out how fast a game was meant to run from how it waits on the delay timer. a game that
+// keeps time sets the timer, does its work, then reads the timer over and over until it runs out,
+// so the instructions between one wait and the next are the work and the frames they took are
+// how long its author gave it. enough speed for the most work any of those stretches asked of a
+// frame, with some to spare, is the pace it was written for, whatever it's being run at now.
+// a game that's run too slow to ever wait gets no guess, it may not keep time at all
+use std::mem;
+
+use crate::instruction::Instruction;
+use crate::settings::SPEEDS;
+
+// frames each guess is made over
+const WINDOW: u32 = 120;
+// the most instructions between two reads of the timer for them to be a loop waiting on it
+const POLL_LOOP: u32 = 4;
+
+#[derive(Default)]
+pub struct SpeedDetector {
+    // instructions since the timer last read zero, less those spent waiting, and the frames since
+    work: u32,
+    frames: u32,
+    // whether it's read zero yet, the work before that is just the game starting up
+    timing: bool,
+    // instructions since the timer was last read, and whether it wasn't zero then
+    since_read: u32,
+    polling: bool,
+    // whether the game waited in this stretch, and in any this window
+    waited: bool,
+    window_waited: bool,
+    // the most instructions a frame any stretch this window needed
+    needed: u32,
+    window: u32,
+}
+
+impl SpeedDetector {
+    pub fn new() -> SpeedDetector {
+        SpeedDetector::default()
+    }
+
+    // call before each instruction with the delay timer as it is then
+    pub fn observe(&mut self, op: u16, delay_timer: u8) {
+        match Instruction::decode(op) {
+            Instruction::LoadDelay { .. } => {
+                // the instructions since the last read went round a loop back to reading it
+                if self.polling && self.since_read <= POLL_LOOP {
+                    self.work -= self.since_read.min(self.work);
+                    self.waited = true;
+                }
+                if delay_timer == 0 {
+                    self.stretch_over();
+                }
+                self.polling = delay_timer != 0;
+                self.since_read = 0;
+            }
+            // waiting on a key isn't work either
+            Instruction::WaitKey { .. } => (),
+            _ => {
+                self.work += 1;
+                self.since_read += 1;
+            }
+        }
+    }
+
+    // call after each frame the game ran in, with the instructions a frame it ran at. the speed
+    // the game seems to want once a window's gone by, if that's another one
+    pub fn end_frame(&mut self, speed: u32) -> Option<u32> {
+        self.frames += 1;
+        self.window += 1;
+        if self.window < WINDOW {
+            return None;
+        }
+        self.window = 0;
+
+        let needed = mem::take(&mut self.needed);
+        if !mem::take(&mut self.window_waited) || needed == 0 {
+            return None;
+        }
+        let wanted = needed + needed.div_ceil(4);
+        let guess = SPEEDS
+            .iter()
+            .copied()
+            .find(|&speed| speed >= wanted)
+            .unwrap_or(SPEEDS[SPEEDS.len() - 1]);
+        (guess != speed).then_some(guess)
+    }
+
+    fn stretch_over(&mut self) {
+        if self.timing {
+            // reading it again in the same frame, the stretch carries on
+            if self.frames == 0 {
+                return;
+            }
+            self.needed = self.needed.max(self.work.div_ceil(self.frames));
+            self.window_waited |= self.waited;
+        }
+        self.timing = true;
+        self.work = 0;
+        self.frames = 0;
+        self.waited = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a game that does `work` instructions a frame then waits out the rest, run at `speed`
+    fn run(detector: &mut SpeedDetector, work: u32, speed: u32) -> Option<u32> {
+        let mut guess = None;
+        for _ in 0..WINDOW {
+            detector.observe(0xF007, 0);
+            detector.observe(0xF015, 0);
+            let mut ticks = 2;
+            for _ in 0..work.min(speed - 2) {
+                detector.observe(0x7001, 1);
+                ticks += 1;
+            }
+            while ticks < speed {
+                detector.observe([0xF007, 0x3000, 0x1200][(ticks % 3) as usize], 1);
+                ticks += 1;
+            }
+            guess = detector.end_frame(speed);
+        }
+        guess
+    }
+
+    #[test]
+    fn test_guess() {
+        // 20 instructions of work wants 25 with the room to spare, the next speed up being 30
+        let mut detector = SpeedDetector::new();
+        assert_eq!(run(&mut detector, 20, 100), Some(30));
+        assert_eq!(run(&mut detector, 20, 30), None);
+
+        // too slow to wait at all, there's no telling
+        let mut detector = SpeedDetector::new();
+        assert_eq!(run(&mut detector, 20, 10), None);
+    }
+
+    #[test]
+    fn test_busy_game() {
+        // reading the timer now and then without waiting on it says nothing about the pace
+        let mut detector = SpeedDetector::new();
+        for frame in 0..WINDOW {
+            for tick in 0..10 {
+                let op = if tick == 0 { 0xF007 } else { 0x7001 };
+                detector.observe(op, (frame % 2) as u8);
+            }
+            assert_eq!(detector.end_frame(10), None);
+        }
+    }
+}
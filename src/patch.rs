@@ -0,0 +1,366 @@
+// applies IPS and BPS patches to a ROM, the format is picked from the patch header
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::cpu::MAX_MEMORY_SIZE;
+
+const IPS_HEADER: &[u8] = b"PATCH";
+const IPS_FOOTER: &[u8] = b"EOF";
+const BPS_HEADER: &[u8] = b"BPS1";
+// three CRC32s close every BPS patch: source, target and the patch itself
+const BPS_FOOTER_SIZE: usize = 12;
+
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.starts_with(IPS_HEADER) {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(BPS_HEADER) {
+        apply_bps(rom, patch)
+    } else {
+        Err("not an IPS or BPS patch".to_string())
+    }
+}
+
+// a reader over the patch bytes that reports running off the end as an error
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(count)
+            .ok_or("patch is truncated")?;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or("patch is truncated")?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn big_endian(&mut self, count: usize) -> Result<usize, String> {
+        Ok(self
+            .bytes(count)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as usize))
+    }
+
+    // BPS numbers are variable length, 7 bits at a time with the top bit ending the number
+    fn number(&mut self) -> Result<usize, String> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.bytes(1)?[0] as usize;
+            value = (byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or("patch is corrupt")?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(0x80).ok_or("patch is corrupt")?;
+            value = value.checked_add(shift).ok_or("patch is corrupt")?;
+        }
+    }
+}
+
+// records are a 3 byte offset and 2 byte size followed by the data,
+// a size of zero means a run of one repeated byte
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = rom.to_vec();
+    let mut reader = Reader {
+        data: patch,
+        position: IPS_HEADER.len(),
+    };
+
+    loop {
+        if reader.data[reader.position..].starts_with(IPS_FOOTER) {
+            reader.position += IPS_FOOTER.len();
+            break;
+        }
+
+        let offset = reader.big_endian(3)?;
+        let size = reader.big_endian(2)?;
+        let data = match size {
+            0 => {
+                let count = reader.big_endian(2)?;
+                vec![reader.bytes(1)?[0]; count]
+            }
+            _ => reader.bytes(size)?.to_vec(),
+        };
+
+        let end = offset + data.len();
+        if end > MAX_MEMORY_SIZE {
+            return Err("patched ROM is too big".to_string());
+        }
+        if output.len() < end {
+            output.resize(end, 0);
+        }
+        output[offset..end].copy_from_slice(&data);
+    }
+
+    // some tools add the final size after the footer so the ROM can shrink
+    if let Ok(size) = reader.big_endian(3) {
+        output.truncate(size);
+    }
+
+    Ok(output)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.len() < BPS_HEADER.len() + BPS_FOOTER_SIZE {
+        return Err("patch is truncated".to_string());
+    }
+
+    let footer_start = patch.len() - BPS_FOOTER_SIZE;
+    let checksum = |index: usize| {
+        let start = footer_start + index * 4;
+        u32::from_le_bytes(patch[start..start + 4].try_into().unwrap())
+    };
+    if crc32(&patch[..patch.len() - 4]) != checksum(2) {
+        return Err("patch is corrupt".to_string());
+    }
+    if crc32(rom) != checksum(0) {
+        return Err("patch is for a different ROM".to_string());
+    }
+
+    let mut reader = Reader {
+        data: &patch[..footer_start],
+        position: BPS_HEADER.len(),
+    };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err("patch is for a different ROM".to_string());
+    }
+    // no machine has room for more, and the size is only a number in the patch until it's checked
+    if target_size > MAX_MEMORY_SIZE {
+        return Err("patched ROM is too big".to_string());
+    }
+
+    let mut output = Vec::with_capacity(target_size);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+
+    while reader.position < reader.data.len() {
+        let command = reader.number()?;
+        let length = (command >> 2) + 1;
+        if length > target_size - output.len() {
+            return Err("patch is corrupt".to_string());
+        }
+
+        match command & 3 {
+            // source read, copy from the same offset in the ROM
+            0 => {
+                let start = output.len();
+                let bytes = rom.get(start..start + length).ok_or("patch is corrupt")?;
+                output.extend_from_slice(bytes);
+            }
+            // target read, the bytes are in the patch
+            1 => output.extend_from_slice(reader.bytes(length)?),
+            // source copy, from anywhere in the ROM
+            2 => {
+                source_offset = relative_offset(source_offset, reader.number()?)?;
+                let end = source_offset
+                    .checked_add(length)
+                    .ok_or("patch is corrupt")?;
+                let bytes = rom.get(source_offset..end).ok_or("patch is corrupt")?;
+                output.extend_from_slice(bytes);
+                source_offset = end;
+            }
+            // target copy, from earlier in the output, byte by byte since the ranges may overlap
+            _ => {
+                target_offset = relative_offset(target_offset, reader.number()?)?;
+                for _ in 0..length {
+                    let byte = *output.get(target_offset).ok_or("patch is corrupt")?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size || crc32(&output) != checksum(1) {
+        return Err("patched ROM doesn't match the patch's checksum".to_string());
+    }
+
+    Ok(output)
+}
+
+// the lowest bit is the sign, the rest is the distance
+fn relative_offset(offset: usize, data: usize) -> Result<usize, String> {
+    let distance = data >> 1;
+    let offset = if data & 1 != 0 {
+        offset.checked_sub(distance)
+    } else {
+        offset.checked_add(distance)
+    };
+    offset.ok_or_else(|| "patch is corrupt".to_string())
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ips() {
+        let rom = [0x00, 0x11, 0x22, 0x33];
+        let mut patch = b"PATCH".to_vec();
+        // two bytes at 1
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // a run of three 0xFF past the end
+        patch.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0xFF]);
+        patch.extend_from_slice(b"EOF");
+
+        assert_eq!(
+            apply(&rom, &patch).unwrap(),
+            [0x00, 0xAA, 0xBB, 0x33, 0xFF, 0xFF, 0xFF]
+        );
+
+        patch.extend_from_slice(&[0x00, 0x00, 0x02]);
+        assert_eq!(apply(&rom, &patch).unwrap(), [0x00, 0xAA]);
+
+        assert!(apply(&rom, b"PATCH\x00\x00").is_err());
+        assert!(apply(&rom, b"NOT A PATCH").is_err());
+    }
+
+    fn encode_number(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte | 0x80);
+                return;
+            }
+            out.push(byte);
+            value -= 1;
+        }
+    }
+
+    #[test]
+    fn test_bps() {
+        let rom = b"ABCDEF";
+        let target = b"ABXDEFDEF";
+
+        let mut patch = b"BPS1".to_vec();
+        encode_number(rom.len(), &mut patch);
+        encode_number(target.len(), &mut patch);
+        encode_number(0, &mut patch);
+        // source read "AB"
+        encode_number((2 - 1) << 2, &mut patch);
+        // target read "X"
+        encode_number(1, &mut patch);
+        patch.push(b'X');
+        // source read "DEF"
+        encode_number((3 - 1) << 2, &mut patch);
+        // source copy "DEF" again, 3 bytes forward from the start of the ROM
+        encode_number((3 - 1) << 2 | 2, &mut patch);
+        encode_number(3 << 1, &mut patch);
+        patch.extend_from_slice(&crc32(rom).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let checksum = crc32(&patch);
+        patch.extend_from_slice(&checksum.to_le_bytes());
+
+        assert_eq!(apply(rom, &patch).unwrap(), target);
+        assert!(apply(b"ABCDEG", &patch).is_err());
+
+        let last = patch.len() - 1;
+        patch[last] ^= 1;
+        assert!(apply(rom, &patch).is_err());
+    }
+
+    // a BPS patch for rom with the given body, checksummed as if it made target
+    fn bps(rom: &[u8], target: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.extend_from_slice(body);
+        patch.extend_from_slice(&crc32(rom).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let checksum = crc32(&patch);
+        patch.extend_from_slice(&checksum.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn test_bps_malformed() {
+        let rom = b"ABCDEF";
+        let mut sizes = Vec::new();
+        encode_number(rom.len(), &mut sizes);
+
+        // a number that never ends overflows instead of wrapping around
+        let mut body = sizes.clone();
+        body.extend_from_slice(&[0x7F; 16]);
+        body.push(0x80);
+        assert_eq!(
+            apply(rom, &bps(rom, rom, &body)).unwrap_err(),
+            "patch is corrupt"
+        );
+
+        // a target bigger than any machine's memory isn't allocated
+        let mut body = sizes.clone();
+        encode_number(usize::MAX >> 8, &mut body);
+        encode_number(0, &mut body);
+        assert_eq!(
+            apply(rom, &bps(rom, rom, &body)).unwrap_err(),
+            "patched ROM is too big"
+        );
+
+        // metadata running past the end of the patch
+        let mut body = sizes.clone();
+        encode_number(rom.len(), &mut body);
+        encode_number(usize::MAX >> 8, &mut body);
+        assert_eq!(
+            apply(rom, &bps(rom, rom, &body)).unwrap_err(),
+            "patch is truncated"
+        );
+
+        // a target copy longer than the target
+        let mut body = sizes.clone();
+        encode_number(rom.len(), &mut body);
+        encode_number(0, &mut body);
+        // target read "A", then copy a huge run of it
+        encode_number(1, &mut body);
+        body.push(b'A');
+        encode_number(1 << 40 | 3, &mut body);
+        encode_number(0, &mut body);
+        assert_eq!(
+            apply(rom, &bps(rom, rom, &body)).unwrap_err(),
+            "patch is corrupt"
+        );
+
+        // a source copy from past the end of the ROM
+        let mut body = sizes.clone();
+        encode_number(rom.len(), &mut body);
+        encode_number(0, &mut body);
+        encode_number(2, &mut body);
+        encode_number(usize::MAX & !1, &mut body);
+        assert_eq!(
+            apply(rom, &bps(rom, rom, &body)).unwrap_err(),
+            "patch is corrupt"
+        );
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
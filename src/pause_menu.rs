@@ -0,0 +1,293 @@
+use std::path::{Path, PathBuf};
+
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+use tracing::error;
+
+use crate::text::{draw_text, text_width, CHAR_WIDTH, LINE_HEIGHT};
+use chip8::library;
+
+const MENU_PADDING: i32 = 6;
+// ROMs shown at once when picking one, the list scrolls to keep the selection in view
+const VISIBLE_ROMS: usize = 16;
+
+const MENU_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PauseAction {
+    Reset,
+    LoadRom(PathBuf),
+    Settings,
+    Quit,
+}
+
+const ITEMS: &[&str] = &["resume", "reset", "load rom", "settings", "quit"];
+
+// a ROM that can be loaded, by its title when its metadata has one
+struct RomItem {
+    path: PathBuf,
+    name: String,
+    description: Option<String>,
+}
+
+pub struct PauseMenu {
+    visible: bool,
+    selected: usize,
+    // where Load ROM looks, the current game's directory
+    directory: PathBuf,
+    // the ROMs there while one is being picked
+    roms: Option<Vec<RomItem>>,
+    // picked with return, taken by the main loop
+    chosen: Option<PauseAction>,
+}
+
+impl PauseMenu {
+    pub fn new(directory: &Path) -> PauseMenu {
+        PauseMenu {
+            visible: false,
+            selected: 0,
+            directory: directory.to_path_buf(),
+            roms: None,
+            chosen: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // escape opens the menu, and closes it or backs out of picking a ROM
+    pub fn escape(&mut self) {
+        match self.roms {
+            Some(_) => {
+                self.roms = None;
+                self.selected = ITEMS.iter().position(|&item| item == "load rom").unwrap();
+            }
+            None => {
+                self.visible = !self.visible;
+                self.selected = 0;
+            }
+        }
+    }
+
+    // straight to picking a ROM, for the command palette
+    pub fn open_roms(&mut self) {
+        self.visible = true;
+        self.selected = ITEMS.iter().position(|&item| item == "load rom").unwrap();
+        self.choose();
+    }
+
+    // returns true if the menu consumed the key, the game can't be played under it anyway
+    pub fn handle_key(&mut self, key: Keycode) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let count = self.roms.as_ref().map_or(ITEMS.len(), Vec::len);
+        match key {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            Keycode::Return | Keycode::KpEnter => self.choose(),
+            _ => (),
+        }
+
+        true
+    }
+
+    fn choose(&mut self) {
+        if let Some(roms) = self.roms.take() {
+            if let Some(rom) = roms.into_iter().nth(self.selected) {
+                self.close(PauseAction::LoadRom(rom.path));
+            }
+            return;
+        }
+
+        match ITEMS[self.selected] {
+            "resume" => self.visible = false,
+            "reset" => self.close(PauseAction::Reset),
+            "load rom" => match library::roms(&self.directory) {
+                Ok(roms) => {
+                    self.roms = Some(rom_items(&self.directory, roms));
+                    self.selected = 0;
+                }
+                Err(error) => error!(
+                    "unable to list ROMs in {}: {}",
+                    self.directory.display(),
+                    error
+                ),
+            },
+            "settings" => self.close(PauseAction::Settings),
+            _ => self.close(PauseAction::Quit),
+        }
+    }
+
+    fn close(&mut self, action: PauseAction) {
+        self.chosen = Some(action);
+        self.visible = false;
+        self.roms = None;
+    }
+
+    pub fn take_action(&mut self) -> Option<PauseAction> {
+        self.chosen.take()
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(MENU_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let (title, lines): (&str, Vec<String>) = match &self.roms {
+            Some(roms) => (
+                "LOAD ROM  RETURN LOADS, ESC GOES BACK",
+                roms.iter().map(|rom| rom.name.clone()).collect(),
+            ),
+            None => (
+                "PAUSED",
+                ITEMS.iter().map(|item| item.to_string()).collect(),
+            ),
+        };
+
+        let centre = |text: &str| (window_width as i32 - text_width(text)) / 2;
+        let mut y = MENU_PADDING * 4;
+        draw_text(canvas, title, centre(title), y, TITLE_COLOR);
+
+        y += LINE_HEIGHT;
+        if lines.is_empty() {
+            let text = "NO ROMS HERE";
+            draw_text(canvas, text, centre(text), y + LINE_HEIGHT, TEXT_COLOR);
+        }
+
+        let first = self.selected.saturating_sub(VISIBLE_ROMS - 1);
+        for (index, line) in lines.iter().enumerate().skip(first).take(VISIBLE_ROMS) {
+            y += LINE_HEIGHT;
+
+            if index == self.selected {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y - 2, window_width, LINE_HEIGHT as u32))
+                    .unwrap();
+            }
+            draw_text(canvas, line, centre(line), y, TEXT_COLOR);
+        }
+
+        // the picked ROM's description along the bottom, as much as fits
+        let description = self
+            .roms
+            .as_ref()
+            .and_then(|roms| roms.get(self.selected)?.description.as_deref());
+        if let Some(description) = description {
+            let fits = (window_width as i32 - MENU_PADDING * 2) / CHAR_WIDTH;
+            let line: String = description
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(fits as usize)
+                .collect();
+            let y = window_height as i32 - MENU_PADDING * 4 - LINE_HEIGHT;
+            draw_text(canvas, &line, centre(&line), y, TITLE_COLOR);
+        }
+    }
+}
+
+fn rom_items(directory: &Path, roms: Vec<PathBuf>) -> Vec<RomItem> {
+    let about = library::about(directory, &roms).unwrap_or_else(|error| {
+        error!(
+            "unable to read metadata in {}: {}",
+            directory.display(),
+            error
+        );
+        vec![(None, None); roms.len()]
+    });
+    roms.into_iter()
+        .zip(about)
+        .map(|(path, (title, description))| RomItem {
+            name: title.unwrap_or_else(|| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+            path,
+            description,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roms_directory() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("roms")
+    }
+
+    #[test]
+    fn test_items() {
+        let mut menu = PauseMenu::new(&roms_directory());
+        assert!(!menu.handle_key(Keycode::Return));
+
+        menu.escape();
+        assert!(menu.is_open());
+        menu.handle_key(Keycode::Return);
+        assert!(!menu.is_open());
+        assert_eq!(menu.take_action(), None);
+
+        menu.escape();
+        menu.handle_key(Keycode::Down);
+        menu.handle_key(Keycode::Return);
+        assert_eq!(menu.take_action(), Some(PauseAction::Reset));
+
+        menu.escape();
+        for _ in 0..10 {
+            menu.handle_key(Keycode::Down);
+        }
+        menu.handle_key(Keycode::Return);
+        assert_eq!(menu.take_action(), Some(PauseAction::Quit));
+    }
+
+    #[test]
+    fn test_load_rom() {
+        let mut menu = PauseMenu::new(&roms_directory());
+        menu.escape();
+        menu.handle_key(Keycode::Down);
+        menu.handle_key(Keycode::Down);
+        menu.handle_key(Keycode::Return);
+        assert!(menu.roms.as_ref().is_some_and(|roms| !roms.is_empty()));
+
+        // escape backs out to the menu rather than closing it
+        menu.escape();
+        assert!(menu.is_open());
+        assert!(menu.roms.is_none());
+
+        menu.handle_key(Keycode::Return);
+        menu.handle_key(Keycode::Return);
+        let Some(PauseAction::LoadRom(path)) = menu.take_action() else {
+            panic!("expected a ROM to load");
+        };
+        assert_eq!(path.extension().unwrap(), "ch8");
+        assert!(!menu.is_open());
+
+        menu.open_roms();
+        assert!(menu.is_open());
+        menu.handle_key(Keycode::Return);
+        assert!(matches!(menu.take_action(), Some(PauseAction::LoadRom(_))));
+    }
+}
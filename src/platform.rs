@@ -0,0 +1,127 @@
+// whole machines a game can be run as, for when it's easier to know which one a game was written
+// for than which of its quirks it needs. each brings the quirks, font, memory and speed of the
+// interpreter it's named for
+use crate::cpu::{
+    CpuBuilder, CPU, DEFAULT_STACK_SIZE, FONTSET, FONTSET_SIZE, MAX_MEMORY_SIZE, MEMORY_SIZE,
+};
+use crate::error::ConfigError;
+use crate::quirks::Quirks;
+
+// the COSMAC VIP's own digits, which differ from the later ones in 1, 4, 7, B and D
+const VIP_FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Platform {
+    pub name: &'static str,
+    pub quirks: Quirks,
+    pub fontset: [u8; FONTSET_SIZE],
+    pub memory_size: usize,
+    // calls that can be nested
+    pub stack_size: usize,
+    // instructions a frame, unless the game's settings say otherwise
+    pub speed: u32,
+}
+
+impl Platform {
+    // a machine set up as this platform, to add anything else to
+    pub fn builder(&self) -> CpuBuilder {
+        CPU::builder()
+            .quirks(self.quirks)
+            .fontset(self.fontset)
+            .memory_size(self.memory_size)
+            .stack_size(self.stack_size)
+    }
+}
+
+pub fn platforms() -> Vec<Platform> {
+    vec![
+        Platform {
+            name: "vip",
+            quirks: Quirks::vip(),
+            fontset: VIP_FONTSET,
+            memory_size: MEMORY_SIZE,
+            stack_size: DEFAULT_STACK_SIZE,
+            speed: 15,
+        },
+        Platform {
+            name: "schip",
+            quirks: Quirks::schip(),
+            fontset: FONTSET,
+            memory_size: MEMORY_SIZE,
+            stack_size: DEFAULT_STACK_SIZE,
+            speed: 30,
+        },
+        Platform {
+            name: "octo",
+            quirks: Quirks::octo(),
+            fontset: FONTSET,
+            memory_size: MEMORY_SIZE,
+            // Octo's structured code recurses more than games written for the older machines
+            stack_size: 64,
+            speed: 20,
+        },
+        Platform {
+            name: "xochip",
+            quirks: Quirks::xochip(),
+            fontset: FONTSET,
+            // all 64K, with F000 NNNN to point I past the first 4K
+            memory_size: MAX_MEMORY_SIZE,
+            stack_size: 64,
+            // XO-CHIP games are written for Octo's faster settings
+            speed: 1000,
+        },
+    ]
+}
+
+pub fn find(name: &str) -> Result<Platform, ConfigError> {
+    platforms()
+        .into_iter()
+        .find(|platform| platform.name == name)
+        .ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "unknown platform {}, expected vip, schip, xochip or octo",
+                name
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platforms() {
+        for platform in platforms() {
+            let cpu = platform.builder().build().unwrap();
+            assert_eq!(cpu.quirks(), platform.quirks);
+            assert_eq!(cpu.memory().len(), platform.memory_size);
+            assert_eq!(cpu.stack_size(), platform.stack_size);
+            assert_eq!(cpu.memory()[..FONTSET_SIZE], platform.fontset);
+        }
+
+        let vip = find("vip").unwrap();
+        assert_eq!(vip.quirks, Quirks::vip());
+        assert_eq!(vip.fontset[5..10], [0x60, 0x20, 0x20, 0x20, 0x70]);
+        assert!(find("chip48").is_err());
+        let xochip = find("xochip").unwrap();
+        assert!(xochip.quirks.xochip);
+        assert_eq!(xochip.memory_size, 0x10000);
+    }
+}
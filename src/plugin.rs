@@ -0,0 +1,305 @@
+// shared libraries loaded at startup that hook into the emulator, so things like achievements
+// live outside this crate. a plugin exports any of these C functions, each is optional:
+//
+//   void chip8_plugin_frame(Chip8Machine *machine, uint64_t frame);   after every frame
+//   void chip8_plugin_instruction(Chip8Machine *machine, uint16_t pc); before every instruction
+//   void chip8_plugin_input(bool keys[16]);       may change the keys the game is about to see
+//   bool chip8_plugin_opcode(Chip8Machine *machine, uint16_t op);
+//                                   runs an opcode the CPU doesn't know, true if it did
+//
+// Chip8Machine is the Machine struct below, a handle and functions to look at and change the
+// machine with, the same things a Lua script can
+use std::{ffi::c_void, path::Path, sync::Arc};
+
+use libloading::Library;
+
+use crate::cpu::{CPU, NUM_KEYS};
+
+#[repr(C)]
+pub struct Machine {
+    cpu: *mut c_void,
+    read: extern "C" fn(*mut c_void, u16) -> u8,
+    write: extern "C" fn(*mut c_void, u16, u8),
+    reg: extern "C" fn(*mut c_void, u8) -> u8,
+    set_reg: extern "C" fn(*mut c_void, u8, u8),
+    index: extern "C" fn(*mut c_void) -> u16,
+    set_index: extern "C" fn(*mut c_void, u16),
+    pc: extern "C" fn(*mut c_void) -> u16,
+    set_pc: extern "C" fn(*mut c_void, u16),
+}
+
+type FrameHook = unsafe extern "C" fn(*mut Machine, u64);
+type InstructionHook = unsafe extern "C" fn(*mut Machine, u16);
+type InputHook = unsafe extern "C" fn(*mut bool);
+type OpcodeHook = unsafe extern "C" fn(*mut Machine, u16) -> bool;
+
+#[derive(Clone, Copy, Default)]
+struct Hooks {
+    frame: Option<FrameHook>,
+    instruction: Option<InstructionHook>,
+    input: Option<InputHook>,
+    opcode: Option<OpcodeHook>,
+}
+
+struct Plugin {
+    hooks: Hooks,
+    // keeps the hooks' code loaded, None for hooks built into this program
+    _library: Option<Library>,
+}
+
+#[derive(Clone, Default)]
+pub struct Plugins {
+    plugins: Arc<Vec<Plugin>>,
+}
+
+impl Plugins {
+    pub fn load(paths: &[String]) -> Result<Plugins, String> {
+        let plugins = paths
+            .iter()
+            .map(|path| load_plugin(path))
+            .collect::<Result<_, _>>()?;
+        Ok(Plugins {
+            plugins: Arc::new(plugins),
+        })
+    }
+
+    // hooks built into this program rather than loaded, for testing
+    #[cfg(test)]
+    fn from_hooks(hooks: Vec<Hooks>) -> Plugins {
+        let plugins = hooks
+            .into_iter()
+            .map(|hooks| Plugin {
+                hooks,
+                _library: None,
+            })
+            .collect();
+        Plugins {
+            plugins: Arc::new(plugins),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    // lets the plugins' opcodes run, the first plugin to take one runs it
+    pub fn install(&self, cpu: &mut CPU) {
+        if self
+            .plugins
+            .iter()
+            .all(|plugin| plugin.hooks.opcode.is_none())
+        {
+            return;
+        }
+        let plugins = self.clone();
+        cpu.set_opcode_extension(Some(Arc::new(move |cpu: &mut CPU, op: u16| {
+            plugins.plugins.iter().any(|plugin| {
+                plugin
+                    .hooks
+                    .opcode
+                    .is_some_and(|hook| with_machine(cpu, |machine| unsafe { hook(machine, op) }))
+            })
+        })));
+    }
+
+    // call right before the CPU ticks
+    pub fn before_instruction(&self, cpu: &mut CPU) {
+        let pc = cpu.pc();
+        for hook in self
+            .plugins
+            .iter()
+            .filter_map(|plugin| plugin.hooks.instruction)
+        {
+            with_machine(cpu, |machine| unsafe { hook(machine, pc) });
+        }
+    }
+
+    pub fn end_frame(&self, frame: u64, cpu: &mut CPU) {
+        for hook in self.plugins.iter().filter_map(|plugin| plugin.hooks.frame) {
+            with_machine(cpu, |machine| unsafe { hook(machine, frame) });
+        }
+    }
+
+    // the keys the game sees this frame, after each plugin has had its say in turn
+    pub fn filter_input(&self, cpu: &mut CPU) {
+        let mut keys = [false; NUM_KEYS];
+        keys.copy_from_slice(cpu.keys());
+        for hook in self.plugins.iter().filter_map(|plugin| plugin.hooks.input) {
+            unsafe { hook(keys.as_mut_ptr()) };
+        }
+        cpu.set_keys(&keys);
+    }
+}
+
+fn load_plugin(path: &str) -> Result<Plugin, String> {
+    let error = |message: String| format!("unable to load plugin {}: {}", path, message);
+    // a bare file name would be searched for on the library path rather than taken from here
+    let path = Path::new(path)
+        .canonicalize()
+        .map_err(|io_error| error(io_error.to_string()))?;
+
+    // loading runs the library's initialisers, it's trusted as much as this program is
+    let library =
+        unsafe { Library::new(&path) }.map_err(|load_error| error(load_error.to_string()))?;
+    let hooks = unsafe {
+        Hooks {
+            frame: symbol(&library, b"chip8_plugin_frame\0"),
+            instruction: symbol(&library, b"chip8_plugin_instruction\0"),
+            input: symbol(&library, b"chip8_plugin_input\0"),
+            opcode: symbol(&library, b"chip8_plugin_opcode\0"),
+        }
+    };
+    if hooks.frame.is_none()
+        && hooks.instruction.is_none()
+        && hooks.input.is_none()
+        && hooks.opcode.is_none()
+    {
+        return Err(error(
+            "it exports none of the chip8_plugin_ functions".to_string(),
+        ));
+    }
+
+    Ok(Plugin {
+        hooks,
+        _library: Some(library),
+    })
+}
+
+// the function the library exports under `name`, if it does. the caller vouches for its type
+unsafe fn symbol<T: Copy>(library: &Library, name: &[u8]) -> Option<T> {
+    library.get::<T>(name).ok().map(|symbol| *symbol)
+}
+
+// hands a hook the machine, which it can only use until the hook returns
+fn with_machine<T>(cpu: &mut CPU, hook: impl FnOnce(*mut Machine) -> T) -> T {
+    let mut machine = Machine {
+        cpu: cpu as *mut CPU as *mut c_void,
+        read,
+        write,
+        reg,
+        set_reg,
+        index,
+        set_index,
+        pc,
+        set_pc,
+    };
+    hook(&mut machine)
+}
+
+fn machine<'a>(cpu: *mut c_void) -> &'a mut CPU {
+    // only ever called with the handle made by with_machine, during the hook
+    unsafe { &mut *(cpu as *mut CPU) }
+}
+
+// addresses and registers past the end read as 0 and ignore writes, a plugin can't go outside
+extern "C" fn read(cpu: *mut c_void, address: u16) -> u8 {
+    machine(cpu)
+        .memory()
+        .get(address as usize)
+        .copied()
+        .unwrap_or(0)
+}
+
+extern "C" fn write(cpu: *mut c_void, address: u16, value: u8) {
+    let cpu = machine(cpu);
+    if (address as usize) < cpu.memory().len() {
+        cpu.poke(address, value);
+    }
+}
+
+extern "C" fn reg(cpu: *mut c_void, x: u8) -> u8 {
+    machine(cpu)
+        .v_registers()
+        .get(x as usize)
+        .copied()
+        .unwrap_or(0)
+}
+
+extern "C" fn set_reg(cpu: *mut c_void, x: u8, value: u8) {
+    let cpu = machine(cpu);
+    if (x as usize) < cpu.v_registers().len() {
+        cpu.set_v_register(x as usize, value);
+    }
+}
+
+extern "C" fn index(cpu: *mut c_void) -> u16 {
+    machine(cpu).index_register()
+}
+
+extern "C" fn set_index(cpu: *mut c_void, value: u16) {
+    machine(cpu).set_index_register(value);
+}
+
+extern "C" fn pc(cpu: *mut c_void) -> u16 {
+    machine(cpu).pc()
+}
+
+extern "C" fn set_pc(cpu: *mut c_void, value: u16) {
+    machine(cpu).set_pc(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // hooks as a plugin would write them, through the machine's functions only
+    unsafe extern "C" fn count_frames(machine: *mut Machine, frame: u64) {
+        let machine = &*machine;
+        (machine.write)(machine.cpu, 0xF00, frame as u8);
+    }
+
+    unsafe extern "C" fn hold_key_5(keys: *mut bool) {
+        *keys.add(5) = true;
+    }
+
+    // 5XY1 sets VX to VY + 1
+    unsafe extern "C" fn increment(machine: *mut Machine, op: u16) -> bool {
+        let machine = &*machine;
+        if op & 0xF00F != 0x5001 {
+            return false;
+        }
+        let (x, y) = ((op >> 8 & 0xF) as u8, (op >> 4 & 0xF) as u8);
+        let value = (machine.reg)(machine.cpu, y).wrapping_add(1);
+        (machine.set_reg)(machine.cpu, x, value);
+        true
+    }
+
+    #[test]
+    fn test_hooks() {
+        let plugins = Plugins::from_hooks(vec![
+            Hooks {
+                frame: Some(count_frames),
+                ..Hooks::default()
+            },
+            Hooks {
+                input: Some(hold_key_5),
+                opcode: Some(increment),
+                ..Hooks::default()
+            },
+        ]);
+        let mut cpu = CPU::new();
+        cpu.load(&[0x61, 0x07, 0x50, 0x11]);
+        plugins.install(&mut cpu);
+
+        plugins.filter_input(&mut cpu);
+        assert!(cpu.keys()[5]);
+        cpu.tick().unwrap();
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v_registers()[0], 8);
+        plugins.end_frame(42, &mut cpu);
+        assert_eq!(cpu.memory()[0xF00], 42);
+
+        // the machine's edges
+        assert_eq!(read(&mut cpu as *mut CPU as *mut c_void, 0xFFFF), 0);
+        set_reg(&mut cpu as *mut CPU as *mut c_void, 16, 1);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert!(Plugins::load(&["no-such-plugin.so".to_string()]).is_err());
+        // a file that isn't a library
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml").to_string();
+        assert!(Plugins::load(&[path]).is_err());
+        assert!(Plugins::load(&[]).unwrap().is_empty());
+    }
+}
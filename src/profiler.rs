@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::cpu::CPU;
+use crate::instruction::Instruction;
+
+const REPORT_ENTRIES: usize = 10;
+
+// counts what gets executed so ROM authors can see where the time goes
+pub struct Profiler {
+    instructions: u64,
+    // how long the same instructions would have taken on the COSMAC VIP
+    vip_micros: u64,
+    address_counts: Vec<u64>,
+    family_counts: HashMap<&'static str, u64>,
+    // backward jumps, keyed by (loop start, jump address)
+    loop_counts: HashMap<(u16, u16), u64>,
+}
+
+impl Profiler {
+    pub fn new(memory_size: usize) -> Profiler {
+        Profiler {
+            instructions: 0,
+            vip_micros: 0,
+            address_counts: vec![0; memory_size],
+            family_counts: HashMap::new(),
+            loop_counts: HashMap::new(),
+        }
+    }
+
+    // call right before the CPU ticks
+    pub fn record(&mut self, cpu: &CPU) {
+        let pc = cpu.pc();
+        let instruction = Instruction::decode(cpu.opcode_at(pc));
+
+        self.instructions += 1;
+        self.vip_micros += instruction.vip_micros() as u64;
+        if let Some(count) = self.address_counts.get_mut(pc as usize) {
+            *count += 1;
+        }
+        *self.family_counts.entry(instruction.pattern()).or_insert(0) += 1;
+
+        if let Instruction::Jump(target) = instruction {
+            if target <= pc {
+                *self.loop_counts.entry((target, pc)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub fn report(&self, cpu: &CPU, frames: u64) -> String {
+        let mut report = String::new();
+        let percent = |count: u64| count as f64 * 100.0 / self.instructions.max(1) as f64;
+
+        writeln!(
+            report,
+            "Profile: {} instructions over {} frames",
+            self.instructions, frames
+        )
+        .unwrap();
+        writeln!(
+            report,
+            "About {:.3}s on a COSMAC VIP, {:.1} frames at 60 a second",
+            self.vip_micros as f64 / 1_000_000.0,
+            self.vip_micros as f64 * 60.0 / 1_000_000.0
+        )
+        .unwrap();
+
+        writeln!(report, "\nHottest addresses:").unwrap();
+        let mut addresses: Vec<_> = self
+            .address_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .collect();
+        addresses.sort_by(|a, b| b.1.cmp(a.1));
+        for (address, count) in addresses.into_iter().take(REPORT_ENTRIES) {
+            let instruction = Instruction::decode(cpu.opcode_at(address as u16));
+            writeln!(
+                report,
+                "  #{:03X}  {:<16} {:>10} {:>6.2}%",
+                address,
+                instruction.to_string(),
+                count,
+                percent(*count)
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\nHottest loops:").unwrap();
+        let mut loops: Vec<_> = self.loop_counts.iter().collect();
+        loops.sort_by(|a, b| b.1.cmp(a.1));
+        for ((start, end), count) in loops.into_iter().take(REPORT_ENTRIES) {
+            writeln!(
+                report,
+                "  #{:03X}-#{:03X}  {:>10} iterations",
+                start, end, count
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\nOpcode histogram:").unwrap();
+        let mut families: Vec<_> = self.family_counts.iter().collect();
+        families.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (family, count) in families {
+            writeln!(
+                report,
+                "  {}  {:>10} {:>6.2}%",
+                family,
+                count,
+                percent(*count)
+            )
+            .unwrap();
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let mut cpu = CPU::new();
+        let mut profiler = Profiler::new(cpu.memory().len());
+
+        // V0 += 1, jump back
+        cpu.load(&[0x70, 0x01, 0x12, 0x00]);
+        for _ in 0..6 {
+            profiler.record(&cpu);
+            cpu.tick().unwrap();
+        }
+
+        assert_eq!(profiler.instructions, 6);
+        assert_eq!(profiler.vip_micros, 3 * 45 + 3 * 105);
+        assert_eq!(profiler.address_counts[0x200], 3);
+        assert_eq!(profiler.family_counts["7XNN"], 3);
+        assert_eq!(profiler.loop_counts[&(0x200, 0x202)], 3);
+
+        let report = profiler.report(&cpu, 1);
+        assert!(report.contains("About 0.000s on a COSMAC VIP, 0.0 frames at 60 a second"));
+        assert!(report.contains("#200-#202           3 iterations"));
+        assert!(report.contains("1NNN           3  50.00%"));
+    }
+}
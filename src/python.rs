@@ -0,0 +1,146 @@
+// the core as a Python module, for driving the emulator from notebooks and experiments. build and
+// install it into the current virtualenv with maturin, which reads pyproject.toml:
+//   maturin develop --release
+// then
+//   import chip8
+//   machine = chip8.Chip8(open("pong.ch8", "rb").read())
+//   machine.keypress(0x5, True)
+//   machine.frame()
+//   pixels = machine.framebuffer()
+//
+// pyo3's generated wrappers convert each PyResult's error into itself, which clippy flags
+#![allow(clippy::useless_conversion)]
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+
+use crate::cpu::CPU;
+use crate::headless::TICKS_PER_FRAME;
+
+#[pyclass(name = "Chip8")]
+pub struct Chip8 {
+    cpu: CPU,
+}
+
+#[pymethods]
+impl Chip8 {
+    #[new]
+    #[pyo3(signature = (rom=None))]
+    fn new(rom: Option<&[u8]>) -> PyResult<Chip8> {
+        let mut machine = Chip8 { cpu: CPU::new() };
+        if let Some(rom) = rom {
+            machine.load(rom)?;
+        }
+        Ok(machine)
+    }
+
+    // loads over whatever is at the start address, the rest of the machine is left as it is
+    fn load(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.cpu
+            .try_load(rom)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    fn seed(&mut self, seed: u64) {
+        self.cpu.seed(seed);
+    }
+
+    // runs one instruction, raises RuntimeError if the program stops
+    fn tick(&mut self) -> PyResult<()> {
+        let pc = self.cpu.pc();
+        self.cpu.tick().map_err(|error| {
+            PyRuntimeError::new_err(format!("program stopped at #{:03X}: {}", pc, error))
+        })
+    }
+
+    // a 60Hz frame, the instructions in it then the timers
+    fn frame(&mut self) -> PyResult<()> {
+        for _ in 0..TICKS_PER_FRAME {
+            self.tick()?;
+        }
+        self.cpu.tick_timers();
+        Ok(())
+    }
+
+    fn keypress(&mut self, key: usize, pressed: bool) -> PyResult<()> {
+        self.cpu
+            .keypress(key, pressed)
+            .map_err(|_| PyValueError::new_err(format!("no key {:X}", key)))
+    }
+
+    // the screen's width and height, which change when a program switches to hires
+    fn resolution(&self) -> (usize, usize) {
+        self.cpu.resolution()
+    }
+
+    // one byte per pixel a row at a time, 1 for lit, ready for numpy.frombuffer
+    fn framebuffer(&self) -> Vec<u8> {
+        self.cpu.pixels().map(|on| on as u8).collect()
+    }
+
+    fn beeping(&self) -> bool {
+        self.cpu.sound_timer() > 0
+    }
+
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.cpu.pc()
+    }
+
+    #[getter]
+    fn index(&self) -> u16 {
+        self.cpu.index_register()
+    }
+
+    #[getter]
+    fn registers(&self) -> Vec<u8> {
+        self.cpu.v_registers().to_vec()
+    }
+
+    #[getter]
+    fn memory(&self) -> Vec<u8> {
+        self.cpu.memory().to_vec()
+    }
+}
+
+#[pymodule]
+fn chip8(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Chip8>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{MEMORY_SIZE, NUM_KEYS};
+
+    #[test]
+    fn test_machine() {
+        // set the sound timer, then draw the 0 glyph at 0,0
+        let mut machine =
+            Chip8::new(Some(&[0x60, 0x20, 0xF0, 0x18, 0xD1, 0x15, 0x12, 0x06])).unwrap();
+        machine.keypress(0xF, true).unwrap();
+        machine.frame().unwrap();
+
+        let (width, height) = machine.resolution();
+        let framebuffer = machine.framebuffer();
+        assert_eq!(framebuffer.len(), width * height);
+        assert_eq!(framebuffer[..5], [1, 1, 1, 1, 0]);
+        assert!(machine.beeping());
+        assert_eq!(machine.pc(), 0x206);
+        assert_eq!(machine.registers()[0], 0x20);
+
+        // jumping off the end of memory stops the program
+        let mut machine = Chip8::new(Some(&[0x1F, 0xFF])).unwrap();
+        machine.tick().unwrap();
+        assert!(machine.tick().is_err());
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        let mut machine = Chip8::new(None).unwrap();
+        assert!(machine.keypress(NUM_KEYS, true).is_err());
+        assert!(machine.load(&vec![0; MEMORY_SIZE]).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+// the places where CHIP-8 interpreters disagree, every ROM was written against one of them.
+// the default has every quirk off, which is how this emulator has always behaved
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY1, 8XY2 and 8XY3 clear VF
+    pub vf_reset: bool,
+    // FX55 and FX65 leave I pointing past the last register
+    pub memory_increment: bool,
+    // 8XY6 and 8XYE shift VY into VX instead of shifting VX in place
+    pub shift_uses_vy: bool,
+    // BNNN jumps to XNN + VX instead of NNN + V0
+    pub jump_uses_vx: bool,
+    // sprites are cut off at the screen edges instead of wrapping around
+    pub clipping: bool,
+    // FX55 and FX65 leave I pointing at the last register instead, when memory_increment is off
+    pub increment_by_x: bool,
+    // DXY0 draws an 8x16 sprite from 16 bytes at I instead of nothing, or instead of a 16x16 one
+    // on the standard display with schip_hires
+    pub tall_sprites: bool,
+    // the CHIP-8E instructions from the VIPER newsletter: 00ED, 0151, 0188, 5XY1, 5XY2, 5XY3,
+    // BBNN, BFNN, FX03, FX1B, FX4F, FXE3 and FXE7
+    pub chip8e: bool,
+    // a program starting with 1260 switches to the hires CHIP-8 interpreter's 64x64 display and
+    // starts at 2C0, where 0230 clears the screen
+    pub two_page: bool,
+    // 00FF and 00FE switch to SUPER-CHIP's 128x64 display and back. DXY0 draws a 16x16 sprite,
+    // and in 128x64 VF counts the sprite rows that collided or were cut off at the bottom. 00CN
+    // scrolls the display down N rows, 00FB and 00FC 4 pixels right and left
+    pub schip_hires: bool,
+    // XO-CHIP: F000 NNNN loads I with the 16-bit address after it, and skips step over all four
+    // bytes. FN01 picks which of two display planes 00E0, scrolling and DXYN work on, a sprite
+    // drawn to both taking its rows for the second after those for the first. 00DN scrolls up N
+    // rows, 5XY2 and 5XY3 save and load VX to VY at I, F002 loads a 16 byte audio pattern from I
+    // and FX3A sets the pitch it plays at
+    pub xochip: bool,
+}
+
+impl Quirks {
+    // the original COSMAC VIP interpreter
+    pub fn vip() -> Quirks {
+        Quirks {
+            vf_reset: true,
+            memory_increment: true,
+            shift_uses_vy: true,
+            jump_uses_vx: false,
+            clipping: true,
+            increment_by_x: false,
+            tall_sprites: false,
+            chip8e: false,
+            two_page: false,
+            schip_hires: false,
+            xochip: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP48
+    pub fn schip() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            memory_increment: false,
+            shift_uses_vy: false,
+            jump_uses_vx: true,
+            clipping: true,
+            increment_by_x: false,
+            tall_sprites: false,
+            chip8e: false,
+            two_page: false,
+            schip_hires: true,
+            xochip: false,
+        }
+    }
+
+    // SUPER-CHIP 1.0, which a few HP48 games were written for before 1.1 changed these
+    pub fn schip_legacy() -> Quirks {
+        Quirks {
+            increment_by_x: true,
+            tall_sprites: true,
+            ..Quirks::schip()
+        }
+    }
+
+    // CHIP-8E, Gilles Detillieux's extension of the VIP interpreter
+    pub fn chip8e() -> Quirks {
+        Quirks {
+            chip8e: true,
+            ..Quirks::vip()
+        }
+    }
+
+    // Octo, which XO-CHIP follows: the VIP's shifts and FX55/FX65 with SUPER-CHIP's display,
+    // sprites wrapping and logic leaving VF alone
+    pub fn octo() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            memory_increment: true,
+            shift_uses_vy: true,
+            jump_uses_vx: false,
+            clipping: false,
+            increment_by_x: false,
+            tall_sprites: false,
+            chip8e: false,
+            two_page: false,
+            schip_hires: true,
+            xochip: false,
+        }
+    }
+
+    // XO-CHIP, Octo's extension of SUPER-CHIP
+    pub fn xochip() -> Quirks {
+        Quirks {
+            xochip: true,
+            ..Quirks::octo()
+        }
+    }
+
+    // the hires CHIP-8 interpreter, the VIP's with a two-page display
+    pub fn two_page() -> Quirks {
+        Quirks {
+            two_page: true,
+            ..Quirks::vip()
+        }
+    }
+}
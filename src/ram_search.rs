@@ -0,0 +1,238 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+use tracing::error;
+
+use crate::memory_viewer::convert_key_to_nibble;
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::cheats::{Cheat, CheatKind, Cheats};
+use chip8::cpu::CPU;
+
+const SEARCH_PADDING: i32 = 6;
+const VISIBLE_CANDIDATES: usize = 24;
+
+const SEARCH_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+
+// how a byte must relate to the last snapshot to stay a candidate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal(u8),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+impl Comparison {
+    fn matches(&self, old: u8, new: u8) -> bool {
+        match *self {
+            Comparison::Equal(value) => new == value,
+            Comparison::Changed => new != old,
+            Comparison::Unchanged => new == old,
+            Comparison::Increased => new > old,
+            Comparison::Decreased => new < old,
+        }
+    }
+}
+
+// narrows memory down to the few bytes that behave like a lives counter or a score,
+// each refinement compares against the memory as it was at the previous one
+pub struct RamSearch {
+    visible: bool,
+    candidates: Vec<u16>,
+    snapshot: Vec<u8>,
+    selected: usize,
+    // the high nibble of a value being typed
+    pending_nibble: Option<u8>,
+    typed_value: Option<u8>,
+}
+
+impl RamSearch {
+    pub fn new(memory: &[u8]) -> RamSearch {
+        let mut search = RamSearch {
+            visible: false,
+            candidates: Vec::new(),
+            snapshot: Vec::new(),
+            selected: 0,
+            pending_nibble: None,
+            typed_value: None,
+        };
+        search.restart(memory);
+        search
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    pub fn restart(&mut self, memory: &[u8]) {
+        self.candidates = (0..memory.len() as u16).collect();
+        self.snapshot = memory.to_vec();
+        self.selected = 0;
+    }
+
+    pub fn refine(&mut self, memory: &[u8], comparison: Comparison) {
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|address| {
+            let address = *address as usize;
+            comparison.matches(snapshot[address], memory[address])
+        });
+        self.snapshot = memory.to_vec();
+        self.selected = 0;
+    }
+
+    // returns true if the search consumed the key
+    pub fn handle_key(&mut self, key: Keycode, cpu: &CPU, cheats: &mut Cheats) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let memory = cpu.memory();
+        let comparison = match key {
+            Keycode::Return | Keycode::KpEnter => self.typed_value.take().map(Comparison::Equal),
+            Keycode::N => Some(Comparison::Changed),
+            Keycode::S => Some(Comparison::Unchanged),
+            Keycode::G => Some(Comparison::Increased),
+            Keycode::L => Some(Comparison::Decreased),
+            _ => None,
+        };
+        if let Some(comparison) = comparison {
+            self.refine(memory, comparison);
+            return true;
+        }
+
+        match key {
+            Keycode::R => self.restart(memory),
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => {
+                self.selected = (self.selected + 1).min(self.candidates.len().saturating_sub(1))
+            }
+            // freeze the selected byte at its current value
+            Keycode::Tab => {
+                if let Some(&address) = self.candidates.get(self.selected) {
+                    let value = memory[address as usize];
+                    cheats.add(Cheat::new(address, value, CheatKind::Freeze));
+                    if let Err(error) = cheats.save() {
+                        error!("unable to save cheats: {}", error);
+                    }
+                }
+            }
+            _ => {
+                if let Some(nibble) = convert_key_to_nibble(key) {
+                    match self.pending_nibble.take() {
+                        None => self.pending_nibble = Some(nibble),
+                        Some(high) => self.typed_value = Some((high << 4) | nibble),
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn draw(&self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let memory = cpu.memory();
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(SEARCH_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = SEARCH_PADDING;
+        let mut y = SEARCH_PADDING;
+        draw_text(
+            canvas,
+            "RAM SEARCH  N CHANGED, S SAME, G GREATER, L LESS, R RESTART, TAB FREEZE",
+            x,
+            y,
+            TITLE_COLOR,
+        );
+
+        y += LINE_HEIGHT;
+        let value = match (self.pending_nibble, self.typed_value) {
+            (Some(high), _) => format!("{:X}_", high),
+            (None, Some(value)) => format!("{:02X}", value),
+            (None, None) => "__".to_string(),
+        };
+        let status = format!(
+            "VALUE #{}  RETURN SEARCHES FOR IT   {} CANDIDATES",
+            value,
+            self.candidates.len()
+        );
+        draw_text(canvas, &status, x, y, TITLE_COLOR);
+
+        y += LINE_HEIGHT;
+        // keep the selection on screen
+        let first = self.selected.saturating_sub(VISIBLE_CANDIDATES - 1);
+        let shown = self.candidates.iter().skip(first).take(VISIBLE_CANDIDATES);
+
+        for (index, &address) in shown.enumerate() {
+            y += LINE_HEIGHT;
+
+            if first + index == self.selected {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y - 2, window_width, LINE_HEIGHT as u32))
+                    .unwrap();
+            }
+
+            let address = address as usize;
+            let line = format!(
+                "#{:03X}  WAS #{:02X}  NOW #{:02X}",
+                address, self.snapshot[address], memory[address]
+            );
+            draw_text(canvas, &line, x, y, TEXT_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refine() {
+        let mut memory = vec![0; 8];
+        memory[2] = 3;
+        memory[5] = 3;
+        let mut search = RamSearch::new(&memory);
+
+        search.refine(&memory, Comparison::Equal(3));
+        assert_eq!(search.candidates, [2, 5]);
+
+        // one life lost
+        memory[2] = 2;
+        memory[5] = 4;
+        search.refine(&memory, Comparison::Decreased);
+        assert_eq!(search.candidates, [2]);
+
+        search.refine(&memory, Comparison::Unchanged);
+        assert_eq!(search.candidates, [2]);
+
+        search.refine(&memory, Comparison::Changed);
+        assert!(search.candidates.is_empty());
+
+        search.restart(&memory);
+        search.refine(&memory, Comparison::Increased);
+        assert!(search.candidates.is_empty());
+        assert_eq!(search.snapshot, memory);
+    }
+}
@@ -0,0 +1,247 @@
+// streams the screen to WebSocket clients and takes key presses back, for watching or playing
+// from another machine. every message is a JSON object with a type:
+//   server to client  {"type": "frame", "width": 64, "height": 32, "pixels": "<hex>"}
+//                     pixels are packed 8 to a byte, most significant bit first, row by row.
+//                     the size changes when the program switches display
+//                     {"type": "sound", "on": true}
+//   client to server  {"type": "key", "key": 5, "pressed": true}
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tungstenite::{Error, Message};
+
+use crate::cpu::{CPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::framebuffer::Framebuffer;
+
+// how long a client waits for key presses before checking for frames to send
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub type KeyEvent = (usize, bool);
+
+pub struct RemoteServer {
+    address: SocketAddr,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    // set when someone connects, so they get a frame even if the screen doesn't change
+    new_client: Arc<AtomicBool>,
+    keys: Receiver<KeyEvent>,
+    // both planes, XO-CHIP may only change the second
+    last_screen: Option<(Framebuffer, Framebuffer)>,
+    last_resolution: (usize, usize),
+    last_sound: bool,
+}
+
+impl RemoteServer {
+    pub fn start(address: &str) -> io::Result<RemoteServer> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let new_client = Arc::new(AtomicBool::new(false));
+        let (key_sender, keys) = mpsc::channel();
+
+        let accepted_clients = clients.clone();
+        let accepted_flag = new_client.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (frame_sender, frames) = mpsc::channel();
+                accepted_clients.lock().unwrap().push(frame_sender);
+                accepted_flag.store(true, Ordering::Relaxed);
+
+                let key_sender = key_sender.clone();
+                thread::spawn(move || serve_client(stream, frames, key_sender));
+            }
+        });
+
+        Ok(RemoteServer {
+            address,
+            clients,
+            new_client,
+            keys,
+            last_screen: None,
+            last_resolution: (SCREEN_WIDTH, SCREEN_HEIGHT),
+            last_sound: false,
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // key presses from every client since the last call
+    pub fn key_events(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.keys.try_iter()
+    }
+
+    // call once per frame, sends whatever changed to every client
+    pub fn update(&mut self, cpu: &CPU) {
+        let resend = self.new_client.swap(false, Ordering::Relaxed);
+
+        let resized = self.last_resolution != cpu.resolution();
+        if resend || resized || self.last_screen != Some((cpu.screen, cpu.second_plane)) {
+            self.broadcast(frame_message(cpu));
+            self.last_screen = Some((cpu.screen, cpu.second_plane));
+            self.last_resolution = cpu.resolution();
+        }
+
+        let sound = cpu.sound_timer() > 0;
+        if resend || sound != self.last_sound {
+            self.broadcast(json!({"type": "sound", "on": sound}).to_string());
+            self.last_sound = sound;
+        }
+    }
+
+    // clients that have gone away are dropped here
+    fn broadcast(&self, message: String) {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| client.send(message.clone()).is_ok());
+    }
+}
+
+fn serve_client(stream: TcpStream, frames: Receiver<String>, keys: Sender<KeyEvent>) {
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        for frame in frames.try_iter() {
+            if socket.send(Message::Text(frame)).is_err() {
+                return;
+            }
+        }
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Some(event) = parse_key_event(&text) {
+                    if keys.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => return,
+            Ok(_) => (),
+            Err(Error::Io(error))
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+// the screen as it is, its size can change when the program switches display
+fn frame_message(cpu: &CPU) -> String {
+    let (width, height) = cpu.resolution();
+    let pixels: Vec<bool> = cpu.pixels().collect();
+    let pixels: String = pixels
+        .chunks(8)
+        .map(|bits| {
+            let byte = bits.iter().fold(0u8, |byte, &on| (byte << 1) | on as u8);
+            format!("{:02x}", byte)
+        })
+        .collect();
+
+    json!({
+        "type": "frame",
+        "width": width,
+        "height": height,
+        "pixels": pixels,
+    })
+    .to_string()
+}
+
+// anything that isn't a well formed key event is ignored
+fn parse_key_event(text: &str) -> Option<KeyEvent> {
+    let message: Value = serde_json::from_str(text).ok()?;
+    if message["type"] != "key" {
+        return None;
+    }
+
+    let key = message["key"].as_u64().filter(|&key| key <= 0xF)?;
+    let pressed = message["pressed"].as_bool()?;
+    Some((key as usize, pressed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::DisplayMode;
+
+    #[test]
+    fn test_messages() {
+        let mut cpu = CPU::new();
+        cpu.screen.set(0, true);
+        cpu.screen.set(9, true);
+        let frame: Value = serde_json::from_str(&frame_message(&cpu)).unwrap();
+        assert_eq!(frame["width"], 64);
+        assert_eq!(frame["pixels"].as_str().unwrap().len(), 64 * 32 / 4);
+        assert!(frame["pixels"].as_str().unwrap().starts_with("804000"));
+
+        cpu.set_display_mode(DisplayMode::Hires);
+        let frame: Value = serde_json::from_str(&frame_message(&cpu)).unwrap();
+        assert_eq!(
+            (&frame["width"], &frame["height"]),
+            (&json!(128), &json!(64))
+        );
+        assert_eq!(frame["pixels"].as_str().unwrap().len(), 128 * 64 / 4);
+
+        assert_eq!(
+            parse_key_event(r#"{"type": "key", "key": 10, "pressed": true}"#),
+            Some((0xA, true))
+        );
+        assert_eq!(
+            parse_key_event(r#"{"type": "key", "key": 16, "pressed": true}"#),
+            None
+        );
+        assert_eq!(parse_key_event(r#"{"type": "key", "key": 1}"#), None);
+        assert_eq!(parse_key_event("not json"), None);
+    }
+
+    #[test]
+    fn test_server() {
+        let mut server = RemoteServer::start("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", server.address());
+        let (mut client, _) = tungstenite::connect(url).unwrap();
+
+        // the frame goes out once the server has seen the new client
+        let cpu = CPU::new();
+        while server.clients.lock().unwrap().is_empty() {
+            thread::yield_now();
+        }
+        server.update(&cpu);
+
+        let Message::Text(frame) = client.read().unwrap() else {
+            panic!("expected a text message");
+        };
+        assert!(frame.contains(r#""type":"frame""#));
+
+        client
+            .send(Message::Text(
+                r#"{"type": "key", "key": 5, "pressed": true}"#.to_string(),
+            ))
+            .unwrap();
+        let event = loop {
+            if let Some(event) = server.key_events().next() {
+                break event;
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        assert_eq!(event, (5, true));
+    }
+}
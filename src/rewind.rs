@@ -0,0 +1,85 @@
+// the machine as it was at the end of each recent frame, oldest first, so play can be taken back
+// to any of them. the frontend's timeline scrubber moves through these
+use std::collections::VecDeque;
+
+use crate::cpu::CPU;
+
+// a minute at 60 frames a second, a few megabytes of savestates
+pub const REWIND_FRAMES: usize = 3600;
+
+pub struct Rewind {
+    states: VecDeque<CPU>,
+    capacity: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            states: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    // call at the end of every frame that ran, the oldest frame drops off once it's full
+    pub fn push(&mut self, cpu: &CPU) {
+        if self.states.len() == self.capacity {
+            // the oldest state becomes the newest, rather than allocating one every frame
+            if let Some(mut oldest) = self.states.pop_front() {
+                oldest.clone_from(cpu);
+                self.states.push_back(oldest);
+                return;
+            }
+        }
+        self.states.push_back(cpu.clone());
+    }
+
+    // the machine `back` frames before the latest one
+    pub fn get(&self, back: usize) -> Option<&CPU> {
+        let index = self.states.len().checked_sub(back + 1)?;
+        self.states.get(index)
+    }
+
+    // forgets the `back` newest frames, so play carries on from the one before them
+    pub fn discard_newest(&mut self, back: usize) {
+        self.states.truncate(self.states.len().saturating_sub(back));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(v0: u8) -> CPU {
+        let mut cpu = CPU::new();
+        cpu.set_v_register(0, v0);
+        cpu
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut rewind = Rewind::new(3);
+        for v0 in 0..5 {
+            rewind.push(&state(v0));
+        }
+
+        // only the last three are kept
+        assert_eq!(rewind.len(), 3);
+        assert_eq!(rewind.get(0).unwrap().v_registers()[0], 4);
+        assert_eq!(rewind.get(2).unwrap().v_registers()[0], 2);
+        assert!(rewind.get(3).is_none());
+
+        rewind.discard_newest(1);
+        assert_eq!(rewind.get(0).unwrap().v_registers()[0], 3);
+        rewind.push(&state(9));
+        assert_eq!(rewind.get(0).unwrap().v_registers()[0], 9);
+        assert_eq!(rewind.get(1).unwrap().v_registers()[0], 3);
+    }
+}
@@ -0,0 +1,444 @@
+// a JSON-RPC 2.0 API on a local TCP socket, one request per line, for tools and test scripts
+// that drive the emulator. requests are answered between frames. methods:
+//   load_rom {path} or {data: "<hex>"}       resets the machine with a new program
+//   pause, resume                            stop or restart the clock
+//   step {count = 1}                         run up to a million instructions, returns the pc
+//   registers                                pc, index, v, delay and sound timers, stack
+//   read_memory {address, length}            bytes as an array of numbers
+//   write_memory {address, bytes}
+//   press_key {key, pressed = true}
+//   screenshot                               {width, height, rows} with # for lit pixels
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use crate::cpu::CPU;
+use crate::headless;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// the program itself failed, e.g. while stepping
+const CPU_ERROR: i64 = -32000;
+
+// the most instructions one step runs, the emulator is stuck answering until they're done
+const MAX_STEPS: u64 = 1_000_000;
+
+type Call = (String, Sender<String>);
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn params(message: impl Into<String>) -> RpcError {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+}
+
+pub struct RpcServer {
+    address: SocketAddr,
+    calls: Receiver<Call>,
+    paused: bool,
+}
+
+impl RpcServer {
+    pub fn start(address: &str) -> io::Result<RpcServer> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let (sender, calls) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || serve_client(stream, sender));
+            }
+        });
+
+        Ok(RpcServer {
+            address,
+            calls,
+            paused: false,
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // call once per frame, answers everything that came in since the last one
+    pub fn handle(&mut self, cpu: &mut CPU) {
+        while let Ok((request, reply)) = self.calls.try_recv() {
+            let response = self.respond(cpu, &request);
+            // the client may have hung up while waiting, that's fine
+            let _ = reply.send(response);
+        }
+    }
+
+    fn respond(&mut self, cpu: &mut CPU, request: &str) -> String {
+        let (id, result) = match serde_json::from_str::<Value>(request) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                (id, self.call(cpu, &request))
+            }
+            Err(error) => (
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, error.to_string())),
+            ),
+        };
+
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(error) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": error.code, "message": error.message},
+            }),
+        };
+        response.to_string()
+    }
+
+    fn call(&mut self, cpu: &mut CPU, request: &Value) -> Result<Value, RpcError> {
+        let method = request["method"]
+            .as_str()
+            .ok_or_else(|| RpcError::new(INVALID_REQUEST, "method must be a string"))?;
+        let params = &request["params"];
+
+        match method {
+            "load_rom" => {
+                let rom = match (params["path"].as_str(), params["data"].as_str()) {
+                    (Some(path), _) => {
+                        fs::read(path).map_err(|error| RpcError::params(error.to_string()))?
+                    }
+                    (None, Some(data)) => {
+                        parse_hex(data).ok_or_else(|| RpcError::params("data must be hex"))?
+                    }
+                    (None, None) => return Err(RpcError::params("expected path or data")),
+                };
+                cpu.check_rom(&rom)
+                    .map_err(|error| RpcError::params(error.to_string()))?;
+                cpu.reset();
+                cpu.try_load(&rom)
+                    .map_err(|error| RpcError::params(error.to_string()))?;
+                Ok(Value::Null)
+            }
+            "pause" => {
+                self.paused = true;
+                Ok(Value::Null)
+            }
+            "resume" => {
+                self.paused = false;
+                Ok(Value::Null)
+            }
+            "step" => {
+                let count = optional_number(params, "count", 1)?;
+                if count > MAX_STEPS {
+                    return Err(RpcError::params(format!(
+                        "count must be at most {}",
+                        MAX_STEPS
+                    )));
+                }
+                for _ in 0..count {
+                    let pc = cpu.pc();
+                    cpu.tick().map_err(|error| {
+                        RpcError::new(
+                            CPU_ERROR,
+                            format!("program stopped at #{:03X}: {}", pc, error),
+                        )
+                    })?;
+                }
+                Ok(json!(cpu.pc()))
+            }
+            "registers" => Ok(json!({
+                "pc": cpu.pc(),
+                "index": cpu.index_register(),
+                "v": cpu.v_registers(),
+                "delay_timer": cpu.delay_timer(),
+                "sound_timer": cpu.sound_timer(),
+                "stack": cpu.stack(),
+            })),
+            "read_memory" => {
+                let address = number(params, "address")? as usize;
+                let length = number(params, "length")? as usize;
+                let bytes = cpu
+                    .memory()
+                    .get(address..address.saturating_add(length))
+                    .ok_or_else(|| RpcError::params("range is outside memory"))?;
+                Ok(json!(bytes))
+            }
+            "write_memory" => {
+                let address = u16::try_from(number(params, "address")?)
+                    .map_err(|_| RpcError::params("range is outside memory"))?;
+                let bytes: Vec<u8> = serde_json::from_value(params["bytes"].clone())
+                    .map_err(|_| RpcError::params("bytes must be an array of bytes"))?;
+                let end = (address as usize).checked_add(bytes.len());
+                if end.is_none_or(|end| end > cpu.memory().len()) {
+                    return Err(RpcError::params("range is outside memory"));
+                }
+                for (offset, byte) in bytes.into_iter().enumerate() {
+                    cpu.poke(address + offset as u16, byte);
+                }
+                Ok(Value::Null)
+            }
+            "press_key" => {
+                let key = usize::try_from(number(params, "key")?).unwrap_or(usize::MAX);
+                let pressed = params
+                    .get("pressed")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true);
+                cpu.keypress(key, pressed)
+                    .map_err(|_| RpcError::params("key must be 0 to F"))?;
+                Ok(Value::Null)
+            }
+            "screenshot" => Ok(json!({
+                "width": cpu.resolution().0,
+                "height": cpu.resolution().1,
+                "rows": headless::screen_text(cpu).lines().collect::<Vec<_>>(),
+            })),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {}", method),
+            )),
+        }
+    }
+}
+
+// each line is a request, answered on the same connection once the emulator gets to it
+fn serve_client(stream: TcpStream, calls: Sender<Call>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (reply, response) = mpsc::channel();
+        if calls.send((line, reply)).is_err() {
+            return;
+        }
+        let Ok(response) = response.recv() else {
+            return;
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+fn number(params: &Value, name: &str) -> Result<u64, RpcError> {
+    params[name]
+        .as_u64()
+        .ok_or_else(|| RpcError::params(format!("{} must be a number", name)))
+}
+
+fn optional_number(params: &Value, name: &str, default: u64) -> Result<u64, RpcError> {
+    match params.get(name) {
+        None => Ok(default),
+        Some(_) => number(params, name),
+    }
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> RpcServer {
+        RpcServer::start("127.0.0.1:0").unwrap()
+    }
+
+    fn call(server: &mut RpcServer, cpu: &mut CPU, request: &str) -> Value {
+        serde_json::from_str(&server.respond(cpu, request)).unwrap()
+    }
+
+    #[test]
+    fn test_methods() {
+        let mut server = server();
+        let mut cpu = CPU::new();
+
+        let response = call(
+            &mut server,
+            &mut cpu,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "load_rom", "params": {"data": "6005A000D1151206"}}"#,
+        );
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], Value::Null);
+
+        let response = call(
+            &mut server,
+            &mut cpu,
+            r#"{"id": 2, "method": "step", "params": {"count": 3}}"#,
+        );
+        assert_eq!(response["result"], 0x206);
+
+        let response = call(&mut server, &mut cpu, r#"{"id": 3, "method": "registers"}"#);
+        assert_eq!(response["result"]["v"][0], 5);
+
+        call(
+            &mut server,
+            &mut cpu,
+            r#"{"id": 4, "method": "write_memory", "params": {"address": 768, "bytes": [1, 2]}}"#,
+        );
+        let response = call(
+            &mut server,
+            &mut cpu,
+            r#"{"id": 5, "method": "read_memory", "params": {"address": 768, "length": 3}}"#,
+        );
+        assert_eq!(response["result"], json!([1, 2, 0]));
+
+        call(&mut server, &mut cpu, r#"{"id": 6, "method": "step"}"#);
+        let response = call(
+            &mut server,
+            &mut cpu,
+            r#"{"id": 7, "method": "screenshot"}"#,
+        );
+        assert!(response["result"]["rows"][0]
+            .as_str()
+            .unwrap()
+            .starts_with("####."));
+
+        call(&mut server, &mut cpu, r#"{"id": 8, "method": "pause"}"#);
+        assert!(server.paused());
+        call(&mut server, &mut cpu, r#"{"id": 9, "method": "resume"}"#);
+        assert!(!server.paused());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut server = server();
+        let mut cpu = CPU::new();
+        let code = |server: &mut RpcServer, cpu: &mut CPU, request: &str| {
+            call(server, cpu, request)["error"]["code"]
+                .as_i64()
+                .unwrap()
+        };
+
+        assert_eq!(code(&mut server, &mut cpu, "{"), PARSE_ERROR);
+        assert_eq!(code(&mut server, &mut cpu, r#"{"id": 1}"#), INVALID_REQUEST);
+        assert_eq!(
+            code(&mut server, &mut cpu, r#"{"id": 1, "method": "bogus"}"#),
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            code(
+                &mut server,
+                &mut cpu,
+                r#"{"id": 1, "method": "read_memory", "params": {"address": 4095, "length": 2}}"#
+            ),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(
+                &mut server,
+                &mut cpu,
+                r#"{"id": 1, "method": "write_memory", "params": {"address": 4095, "bytes": [1, 2]}}"#
+            ),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(
+                &mut server,
+                &mut cpu,
+                r#"{"id": 1, "method": "write_memory", "params": {"address": 65536, "bytes": []}}"#
+            ),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(
+                &mut server,
+                &mut cpu,
+                r#"{"id": 1, "method": "press_key", "params": {"key": 16}}"#
+            ),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(
+                &mut server,
+                &mut cpu,
+                r#"{"id": 1, "method": "step", "params": {"count": 18446744073709551615}}"#
+            ),
+            INVALID_PARAMS
+        );
+        let rom = "00".repeat(4096 - 0x200 + 1);
+        assert_eq!(
+            code(
+                &mut server,
+                &mut cpu,
+                &format!(
+                    r#"{{"id": 1, "method": "load_rom", "params": {{"data": "{}"}}}}"#,
+                    rom
+                )
+            ),
+            INVALID_PARAMS
+        );
+        // returning with an empty stack
+        call(
+            &mut server,
+            &mut cpu,
+            r#"{"id": 1, "method": "load_rom", "params": {"data": "00EE"}}"#,
+        );
+        assert_eq!(
+            code(&mut server, &mut cpu, r#"{"id": 1, "method": "step"}"#),
+            CPU_ERROR
+        );
+    }
+
+    #[test]
+    fn test_socket() {
+        let mut server = server();
+        let mut cpu = CPU::new();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        writeln!(
+            stream,
+            r#"{{"jsonrpc": "2.0", "id": 1, "method": "press_key", "params": {{"key": 3}}}}"#
+        )
+        .unwrap();
+
+        let client = thread::spawn(move || {
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).unwrap();
+            line
+        });
+        while !client.is_finished() {
+            server.handle(&mut cpu);
+            thread::yield_now();
+        }
+
+        let response: Value = serde_json::from_str(&client.join().unwrap()).unwrap();
+        assert_eq!(response["id"], 1);
+        assert!(response.get("error").is_none());
+    }
+}
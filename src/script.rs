@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+
+use mlua::{Function, Lua, Table};
+use sdl2::{pixels::Color, render::Canvas, video::Window};
+
+use crate::text::draw_text;
+use chip8::cpu::CPU;
+
+// callbacks live in the Lua registry so scripts can't clobber them by accident
+const FRAME_CALLBACKS: &str = "frame_callbacks";
+const INSTRUCTION_CALLBACKS: &str = "instruction_callbacks";
+
+const HUD_COLOR: Color = Color::RGB(255, 255, 0);
+
+// a line of text a script asked to draw this frame
+pub struct HudText {
+    pub x: i32,
+    pub y: i32,
+    pub text: String,
+}
+
+// runs a Lua script alongside the game, everything it can do goes through the global chip8 table:
+//   chip8.read(address), chip8.write(address, value)
+//   chip8.reg(x), chip8.set_reg(x, value), chip8.index(), chip8.set_index(value), chip8.pc()
+//   chip8.press(key, [pressed]), chip8.text(x, y, text), chip8.frame()
+//   chip8.on_frame(function(frame)), chip8.on_instruction(function(pc))
+pub struct Script {
+    lua: Lua,
+    frame: u64,
+    hud: Vec<HudText>,
+}
+
+impl Script {
+    pub fn new(source: &str, name: &str, cpu: &mut CPU) -> mlua::Result<Script> {
+        let lua = Lua::new();
+        lua.set_named_registry_value(FRAME_CALLBACKS, lua.create_table()?)?;
+        lua.set_named_registry_value(INSTRUCTION_CALLBACKS, lua.create_table()?)?;
+
+        let api = lua.create_table()?;
+        api.set("on_frame", register_callback(&lua, FRAME_CALLBACKS)?)?;
+        api.set(
+            "on_instruction",
+            register_callback(&lua, INSTRUCTION_CALLBACKS)?,
+        )?;
+        lua.globals().set("chip8", api)?;
+
+        let mut script = Script {
+            lua,
+            frame: 0,
+            hud: Vec::new(),
+        };
+
+        // the top level of the script can already poke at the machine, e.g. to patch the ROM
+        with_api(&script.lua, &mut script.hud, 0, cpu, |lua| {
+            lua.load(source).set_name(name).exec()
+        })?;
+        Ok(script)
+    }
+
+    // call right before the CPU ticks
+    pub fn before_instruction(&mut self, cpu: &mut CPU) -> mlua::Result<()> {
+        let pc = cpu.pc();
+        self.run_callbacks(INSTRUCTION_CALLBACKS, cpu, pc as u64)
+    }
+
+    // call once per frame after the CPU has run, before anything is drawn
+    pub fn end_frame(&mut self, frame: u64, cpu: &mut CPU) -> mlua::Result<()> {
+        self.frame = frame;
+        self.hud.clear();
+        self.run_callbacks(FRAME_CALLBACKS, cpu, frame)
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        for line in self.hud.iter() {
+            draw_text(canvas, &line.text, line.x, line.y, HUD_COLOR);
+        }
+    }
+
+    fn run_callbacks(&mut self, name: &str, cpu: &mut CPU, argument: u64) -> mlua::Result<()> {
+        let callbacks: Table = self.lua.named_registry_value(name)?;
+        if callbacks.raw_len() == 0 {
+            return Ok(());
+        }
+
+        with_api(&self.lua, &mut self.hud, self.frame, cpu, |_| {
+            for callback in callbacks.sequence_values::<Function>() {
+                callback?.call::<_, ()>(argument)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+// the functions that touch the machine only exist while the CPU is lent to the script
+fn with_api(
+    lua: &Lua,
+    hud: &mut Vec<HudText>,
+    frame: u64,
+    cpu: &mut CPU,
+    run: impl FnOnce(&Lua) -> mlua::Result<()>,
+) -> mlua::Result<()> {
+    let cpu = RefCell::new(cpu);
+    let hud = RefCell::new(hud);
+
+    lua.scope(|scope| {
+        let api: Table = lua.globals().get("chip8")?;
+
+        api.set(
+            "read",
+            scope.create_function(|_, address: usize| {
+                let cpu = cpu.borrow();
+                let byte = cpu.memory().get(address).copied();
+                byte.ok_or_else(|| out_of_range("address", address))
+            })?,
+        )?;
+        api.set(
+            "write",
+            scope.create_function(|_, (address, value): (usize, u8)| {
+                let mut cpu = cpu.borrow_mut();
+                if address >= cpu.memory().len() {
+                    return Err(out_of_range("address", address));
+                }
+                cpu.poke(address as u16, value);
+                Ok(())
+            })?,
+        )?;
+        api.set(
+            "reg",
+            scope.create_function(|_, x: usize| {
+                let cpu = cpu.borrow();
+                let value = cpu.v_registers().get(x).copied();
+                value.ok_or_else(|| out_of_range("register", x))
+            })?,
+        )?;
+        api.set(
+            "set_reg",
+            scope.create_function(|_, (x, value): (usize, u8)| {
+                let mut cpu = cpu.borrow_mut();
+                if x >= cpu.v_registers().len() {
+                    return Err(out_of_range("register", x));
+                }
+                cpu.set_v_register(x, value);
+                Ok(())
+            })?,
+        )?;
+        api.set(
+            "index",
+            scope.create_function(|_, ()| Ok(cpu.borrow().index_register()))?,
+        )?;
+        api.set(
+            "set_index",
+            scope.create_function(|_, value: u16| {
+                cpu.borrow_mut().set_index_register(value);
+                Ok(())
+            })?,
+        )?;
+        api.set("pc", scope.create_function(|_, ()| Ok(cpu.borrow().pc()))?)?;
+        api.set(
+            "press",
+            scope.create_function(|_, (key, pressed): (usize, Option<bool>)| {
+                cpu.borrow_mut()
+                    .keypress(key, pressed.unwrap_or(true))
+                    .map_err(|_| out_of_range("key", key))
+            })?,
+        )?;
+        api.set(
+            "text",
+            scope.create_function(|_, (x, y, text): (i32, i32, String)| {
+                hud.borrow_mut().push(HudText { x, y, text });
+                Ok(())
+            })?,
+        )?;
+        api.set("frame", scope.create_function(move |_, ()| Ok(frame))?)?;
+
+        run(lua)
+    })
+}
+
+fn register_callback<'lua>(lua: &'lua Lua, name: &'static str) -> mlua::Result<Function<'lua>> {
+    lua.create_function(move |lua, callback: Function| {
+        let callbacks: Table = lua.named_registry_value(name)?;
+        callbacks.raw_push(callback)
+    })
+}
+
+fn out_of_range(what: &str, value: usize) -> mlua::Error {
+    mlua::Error::RuntimeError(format!("{} {:X} is out of range", what, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callbacks() {
+        let mut cpu = CPU::new();
+        cpu.load(&[0x60, 0x05, 0x12, 0x02]);
+
+        let source = r#"
+            chip8.write(0x300, 0x42)
+            chip8.on_instruction(function(pc)
+                chip8.set_reg(1, chip8.reg(1) + 1)
+            end)
+            chip8.on_frame(function(frame)
+                chip8.set_index(chip8.read(0x300))
+                chip8.text(0, 0, "frame " .. frame)
+            end)
+        "#;
+        let mut script = Script::new(source, "test", &mut cpu).unwrap();
+        assert_eq!(cpu.memory()[0x300], 0x42);
+
+        for _ in 0..3 {
+            script.before_instruction(&mut cpu).unwrap();
+            cpu.tick().unwrap();
+        }
+        script.end_frame(7, &mut cpu).unwrap();
+
+        assert_eq!(cpu.v_registers()[0], 0x05);
+        assert_eq!(cpu.v_registers()[1], 3);
+        assert_eq!(cpu.index_register(), 0x42);
+        assert_eq!(script.hud[0].text, "frame 7");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut cpu = CPU::new();
+        assert!(Script::new("chip8.reg(16)", "test", &mut cpu).is_err());
+        assert!(Script::new("chip8.write(0x1000, 0)", "test", &mut cpu).is_err());
+        assert!(Script::new("chip8.press(", "test", &mut cpu).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::cpu::CPU;
+use chip8::rewind::Rewind;
+use chip8::tas::Recording;
+
+const BAR_PADDING: i32 = 6;
+const TRACK_HEIGHT: i32 = 8;
+const HANDLE_WIDTH: i32 = 4;
+// frames Page Up and Page Down move by, a second
+const PAGE: usize = 60;
+const FRAMES_PER_SECOND: f64 = 60.0;
+
+const BAR_BACKGROUND: Color = Color::RGBA(0, 0, 0, 200);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TRACK_COLOR: Color = Color::RGB(60, 60, 120);
+const HANDLE_COLOR: Color = Color::RGB(0, 255, 128);
+
+// a slider along the bottom of the window over the last minute of play. Backspace opens it and
+// steps back, the game stays paused on whichever frame is picked until Return plays on from there
+pub struct Scrubber {
+    // frames behind the latest one while it's open
+    back: Option<usize>,
+}
+
+impl Scrubber {
+    pub fn new() -> Scrubber {
+        Scrubber { back: None }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.back.is_some()
+    }
+
+    // returns true if the scrubber consumed the key. playing on from an earlier frame forgets
+    // everything after it, including those frames of a TAS recording
+    pub fn handle_key(
+        &mut self,
+        key: Keycode,
+        rewind: &mut Rewind,
+        recording: Option<&mut Recording>,
+        cpu: &mut CPU,
+    ) -> bool {
+        let Some(back) = self.back else {
+            if key == Keycode::Backspace && !rewind.is_empty() {
+                self.back = Some(0);
+                return true;
+            }
+            return false;
+        };
+
+        let oldest = rewind.len() - 1;
+        let back = match key {
+            Keycode::Backspace | Keycode::Left => (back + 1).min(oldest),
+            Keycode::Right => back.saturating_sub(1),
+            Keycode::PageUp => (back + PAGE).min(oldest),
+            Keycode::PageDown => back.saturating_sub(PAGE),
+            Keycode::Home => oldest,
+            Keycode::End => 0,
+            Keycode::Return | Keycode::KpEnter => {
+                rewind.discard_newest(back);
+                if let Some(recording) = recording {
+                    recording.truncate(recording.len().saturating_sub(back));
+                }
+                self.back = None;
+                return true;
+            }
+            _ => return true,
+        };
+
+        self.back = Some(back);
+        *cpu = rewind.get(back).expect("within the rewind buffer").clone();
+        true
+    }
+
+    pub fn draw(&self, rewind: &Rewind, canvas: &mut Canvas<Window>) {
+        let Some(back) = self.back else {
+            return;
+        };
+
+        let (window_width, window_height) = canvas.logical_size();
+        let height = LINE_HEIGHT + TRACK_HEIGHT + 3 * BAR_PADDING;
+        let top = window_height as i32 - height;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(BAR_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, top, window_width, height as u32))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        draw_text(
+            canvas,
+            &format!(
+                "{:.2}S BACK  LEFT RIGHT PGUP PGDN HOME END, RETURN PLAYS ON FROM HERE",
+                back as f64 / FRAMES_PER_SECOND
+            ),
+            BAR_PADDING,
+            top + BAR_PADDING,
+            TITLE_COLOR,
+        );
+
+        // the oldest frame on the left, the latest on the right
+        let track_y = top + 2 * BAR_PADDING + LINE_HEIGHT;
+        let track_width = window_width as i32 - 2 * BAR_PADDING;
+        canvas.set_draw_color(TRACK_COLOR);
+        canvas
+            .fill_rect(Rect::new(
+                BAR_PADDING,
+                track_y,
+                track_width as u32,
+                TRACK_HEIGHT as u32,
+            ))
+            .unwrap();
+
+        let oldest = rewind.len().saturating_sub(1).max(1);
+        let position =
+            (oldest - back.min(oldest)) as i32 * (track_width - HANDLE_WIDTH) / oldest as i32;
+        canvas.set_draw_color(HANDLE_COLOR);
+        canvas
+            .fill_rect(Rect::new(
+                BAR_PADDING + position,
+                track_y,
+                HANDLE_WIDTH as u32,
+                TRACK_HEIGHT as u32,
+            ))
+            .unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+// a small test ROM built into the emulator, so a build can be checked on a new platform without
+// finding ROMs first. the program puts the number of the check it's on in VE and ends by jumping
+// to itself, at PASS_ADDRESS if everything held or FAIL_ADDRESS as soon as something didn't
+use crate::cpu::CPU;
+use crate::headless::TICKS_PER_FRAME;
+
+const ROM: &[u8] = include_bytes!("../roms/self-test.ch8");
+
+const PASS_ADDRESS: u16 = 0x314;
+const FAIL_ADDRESS: u16 = 0x316;
+
+// the last check waits on the delay timer, everything is over well before this
+const MAX_FRAMES: u32 = 60;
+
+// what each check covers, in the order the ROM runs them
+pub const CHECKS: &[&str] = &[
+    "6XNN and 3XNN",
+    "4XNN",
+    "7XNN wraps and leaves VF alone",
+    "8XY4 sets the carry",
+    "8XY5 clears VF on a borrow",
+    "8XY7",
+    "8XY1, 8XY2 and 8XY3",
+    "8XY6 and 8XYE shift out into VF",
+    "5XY0 and 9XY0",
+    "FX55 and FX65",
+    "FX33",
+    "FX1E",
+    "2NNN and 00EE",
+    "DXYN sets VF on a collision",
+    "BNNN",
+    "CXNN with a mask of 0",
+    "FX15, FX07 and the delay timer counting down",
+];
+
+// runs the ROM to the end, giving how many checks passed or what went wrong
+pub fn run() -> Result<usize, String> {
+    let mut cpu = CPU::new();
+    cpu.load(ROM);
+
+    for _ in 0..MAX_FRAMES {
+        for _ in 0..TICKS_PER_FRAME {
+            let pc = cpu.pc();
+            cpu.tick()
+                .map_err(|error| format!("program stopped at #{:03X}: {}", pc, error))?;
+        }
+        cpu.tick_timers();
+
+        match cpu.pc() {
+            PASS_ADDRESS => return Ok(CHECKS.len()),
+            FAIL_ADDRESS => {
+                let check = cpu.v_registers()[0xE] as usize;
+                let name = CHECKS
+                    .get(check.wrapping_sub(1))
+                    .unwrap_or(&"an unknown check");
+                return Err(format!("check {} failed: {}", check, name));
+            }
+            _ => (),
+        }
+    }
+
+    Err(format!("still running after {} frames", MAX_FRAMES))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(run(), Ok(CHECKS.len()));
+    }
+
+    #[test]
+    fn test_rom_layout() {
+        // the addresses above have to stay in step with the ROM
+        let mut cpu = CPU::new();
+        cpu.load(ROM);
+        assert_eq!(cpu.opcode_at(PASS_ADDRESS), 0x1000 | PASS_ADDRESS);
+        assert_eq!(cpu.opcode_at(FAIL_ADDRESS), 0x1000 | FAIL_ADDRESS);
+        assert_eq!(cpu.opcode_at(0x200), 0x6E01);
+    }
+}
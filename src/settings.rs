@@ -0,0 +1,426 @@
+// preferences changed from the settings menu, kept in the user's config directory. the file is
+// a few `name = "value"` lines, a small part of TOML, so it can still be edited by hand. the
+// palette, speed and quirks are also remembered for each game, in roms/<CRC-32>.toml beside it
+use std::{
+    env,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::compliance::{self, Preset};
+use crate::error::ConfigError;
+use crate::patch::crc32;
+use crate::quirks::Quirks;
+
+pub struct Palette {
+    pub name: &'static str,
+    pub foreground: (u8, u8, u8),
+    pub background: (u8, u8, u8),
+    // XO-CHIP's pixels lit on only its second plane, and on both
+    pub second: (u8, u8, u8),
+    pub both: (u8, u8, u8),
+}
+
+pub const PALETTES: &[Palette] = &[
+    Palette {
+        name: "white",
+        foreground: (255, 255, 255),
+        background: (0, 0, 0),
+        second: (170, 170, 170),
+        both: (85, 85, 85),
+    },
+    Palette {
+        name: "amber",
+        foreground: (255, 176, 0),
+        background: (24, 12, 0),
+        second: (170, 110, 0),
+        both: (100, 60, 0),
+    },
+    Palette {
+        name: "green",
+        foreground: (51, 255, 102),
+        background: (0, 20, 8),
+        second: (34, 170, 68),
+        both: (17, 90, 34),
+    },
+    Palette {
+        name: "lcd",
+        foreground: (15, 56, 15),
+        background: (155, 188, 15),
+        second: (48, 98, 48),
+        both: (139, 172, 15),
+    },
+];
+
+// instructions a frame the menu steps through, the file can hold any other
+pub const SPEEDS: &[u32] = &[5, 10, 15, 20, 30, 50, 100];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    path: Option<PathBuf>,
+    // where the game being played has its own settings, once there is one
+    rom_path: Option<PathBuf>,
+    // indexes into PALETTES and compliance::presets()
+    pub palette: usize,
+    pub speed: u32,
+    pub preset: usize,
+    // show each pixel lit in either of the last two frames, hiding the flicker of sprites
+    // erased and drawn again a frame apart
+    pub anti_flicker: bool,
+    // the display to go fullscreen on counting from 1, 0 for whichever the window is on
+    pub monitor: u32,
+    // fullscreen as a window covering the desktop, rather than switching the display's mode
+    pub borderless: bool,
+}
+
+impl Settings {
+    // settings that are never saved, for when there's nowhere to put them
+    pub fn new() -> Settings {
+        Settings {
+            path: None,
+            rom_path: None,
+            palette: 0,
+            speed: 10,
+            preset: 0,
+            anti_flicker: false,
+            monitor: 0,
+            borderless: false,
+        }
+    }
+
+    // the user's settings, or the defaults if they haven't changed any
+    pub fn load_default() -> io::Result<Settings> {
+        match config_dir() {
+            Some(dir) => Settings::load(&dir.join("settings.toml")),
+            None => Ok(Settings::new()),
+        }
+    }
+
+    // a missing file just means nothing has been changed yet
+    pub fn load(path: &Path) -> io::Result<Settings> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        let mut settings = parse(&text).map_err(|message| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), message),
+            )
+        })?;
+        settings.path = Some(path.to_path_buf());
+        Ok(settings)
+    }
+
+    // the palette, speed and quirks last used with this game take over from the ones it starts
+    // with, which are whatever was used last with any game. changes from here on are
+    // remembered for it too. true if it had any
+    pub fn use_rom(&mut self, rom: &[u8]) -> io::Result<bool> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        let rom_path = path
+            .with_file_name("roms")
+            .join(format!("{:08X}.toml", crc32(rom)));
+
+        let remembered = match fs::read_to_string(&rom_path) {
+            Ok(text) => {
+                let saved = parse_onto(&text, self.clone()).map_err(|message| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: {}", rom_path.display(), message),
+                    )
+                })?;
+                self.palette = saved.palette;
+                self.speed = saved.speed;
+                self.preset = saved.preset;
+                true
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => false,
+            Err(error) => return Err(error),
+        };
+        self.rom_path = Some(rom_path);
+        Ok(remembered)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, format(self))?;
+
+        if let Some(rom_path) = &self.rom_path {
+            if let Some(dir) = rom_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(rom_path, format_rom(self))?;
+        }
+        Ok(())
+    }
+
+    pub fn palette(&self) -> &'static Palette {
+        &PALETTES[self.palette]
+    }
+
+    pub fn preset(&self) -> Preset {
+        compliance::presets().swap_remove(self.preset)
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.preset().quirks
+    }
+
+    // steps forward or back through the choices, wrapping around
+    pub fn cycle_palette(&mut self, forward: bool) {
+        self.palette = cycle(self.palette, PALETTES.len(), forward);
+    }
+
+    pub fn cycle_preset(&mut self, forward: bool) {
+        self.preset = cycle(self.preset, compliance::presets().len(), forward);
+    }
+
+    // steps through the displays there are and the window's own
+    pub fn cycle_monitor(&mut self, displays: u32, forward: bool) {
+        let monitor = self.monitor.min(displays);
+        self.monitor = cycle(monitor as usize, displays as usize + 1, forward) as u32;
+    }
+
+    // the next speed in SPEEDS up or down, stopping at either end
+    pub fn step_speed(&mut self, faster: bool) {
+        let next = match faster {
+            true => SPEEDS.iter().find(|&&speed| speed > self.speed),
+            false => SPEEDS.iter().rev().find(|&&speed| speed < self.speed),
+        };
+        if let Some(&speed) = next {
+            self.speed = speed;
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
+
+fn cycle(index: usize, count: usize, forward: bool) -> usize {
+    match forward {
+        true => (index + 1) % count,
+        false => (index + count - 1) % count,
+    }
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("rusty_chip8"))
+}
+
+fn parse(text: &str) -> Result<Settings, ConfigError> {
+    parse_onto(text, Settings::new())
+}
+
+// `settings` with the ones in the text changed
+fn parse_onto(text: &str, mut settings: Settings) -> Result<Settings, ConfigError> {
+    let presets = compliance::presets();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| ConfigError::Line {
+            line: number + 1,
+            message: message.to_string(),
+        };
+
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected name = value"))?;
+        let value = value.trim();
+        let text = value.trim_matches('"');
+
+        match name.trim() {
+            "palette" => {
+                settings.palette = PALETTES
+                    .iter()
+                    .position(|palette| palette.name == text)
+                    .ok_or_else(|| error(&format!("unknown palette {}", value)))?;
+            }
+            "speed" => {
+                settings.speed = value
+                    .parse()
+                    .ok()
+                    .filter(|&speed| speed > 0)
+                    .ok_or_else(|| error(&format!("invalid speed {}", value)))?;
+            }
+            "quirks" => {
+                settings.preset = presets
+                    .iter()
+                    .position(|preset| preset.name == text)
+                    .ok_or_else(|| error(&format!("unknown quirk preset {}", value)))?;
+            }
+            "anti_flicker" => {
+                settings.anti_flicker = value
+                    .parse()
+                    .map_err(|_| error(&format!("expected true or false, not {}", value)))?;
+            }
+            "monitor" => {
+                settings.monitor = value
+                    .parse()
+                    .map_err(|_| error(&format!("invalid monitor {}", value)))?;
+            }
+            "borderless" => {
+                settings.borderless = value
+                    .parse()
+                    .map_err(|_| error(&format!("expected true or false, not {}", value)))?;
+            }
+            name => return Err(error(&format!("unknown setting {}", name))),
+        }
+    }
+
+    Ok(settings)
+}
+
+fn format(settings: &Settings) -> String {
+    let mut text = format_rom(settings);
+    writeln!(text, "anti_flicker = {}", settings.anti_flicker).unwrap();
+    writeln!(text, "monitor = {}", settings.monitor).unwrap();
+    writeln!(text, "borderless = {}", settings.borderless).unwrap();
+    text
+}
+
+// the settings kept for each game
+fn format_rom(settings: &Settings) -> String {
+    let mut text = String::new();
+    writeln!(text, "palette = \"{}\"", settings.palette().name).unwrap();
+    writeln!(text, "speed = {}", settings.speed).unwrap();
+    writeln!(text, "quirks = \"{}\"", settings.preset().name).unwrap();
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let text = "\
+# written by hand
+palette = \"amber\"
+speed = 15
+quirks = \"schip\" # for the SUPER-CHIP games
+anti_flicker = true
+monitor = 2
+borderless = true
+";
+        let settings = parse(text).unwrap();
+        assert_eq!(settings.palette().name, "amber");
+        assert_eq!(settings.speed, 15);
+        assert_eq!(settings.quirks(), Quirks::schip());
+        assert!(settings.anti_flicker);
+        assert_eq!(settings.monitor, 2);
+        assert!(settings.borderless);
+        assert_eq!(
+            format(&settings),
+            "palette = \"amber\"\nspeed = 15\nquirks = \"schip\"\nanti_flicker = true\nmonitor = 2\nborderless = true\n"
+        );
+
+        assert_eq!(parse("").unwrap(), Settings::new());
+        assert!(parse("palette = \"blue\"").is_err());
+        assert!(parse("speed = 0").is_err());
+        assert!(parse("volume = 3").is_err());
+        assert!(parse("speed").is_err());
+        assert!(parse("anti_flicker = yes").is_err());
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut settings = Settings::new();
+        settings.cycle_palette(false);
+        assert_eq!(settings.palette().name, "lcd");
+        settings.cycle_palette(true);
+        assert_eq!(settings.palette().name, "white");
+
+        settings.step_speed(true);
+        assert_eq!(settings.speed, 15);
+        settings.speed = 12;
+        settings.step_speed(false);
+        assert_eq!(settings.speed, 10);
+        settings.speed = 100;
+        settings.step_speed(true);
+        assert_eq!(settings.speed, 100);
+
+        settings.cycle_preset(true);
+        assert_eq!(settings.preset().name, "vip");
+
+        settings.cycle_monitor(2, false);
+        assert_eq!(settings.monitor, 2);
+        settings.cycle_monitor(2, true);
+        assert_eq!(settings.monitor, 0);
+        // a display that's since been unplugged
+        settings.monitor = 5;
+        settings.cycle_monitor(2, true);
+        assert_eq!(settings.monitor, 0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = env::temp_dir()
+            .join(format!("rusty_chip8-settings-{}", std::process::id()))
+            .join("settings.toml");
+        let mut settings = Settings::load(&path).unwrap();
+        assert_eq!(settings.speed, 10);
+
+        settings.speed = 30;
+        settings.save().unwrap();
+        assert_eq!(Settings::load(&path).unwrap().speed, 30);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_rom_settings() {
+        let path = env::temp_dir()
+            .join(format!("rusty_chip8-rom-settings-{}", std::process::id()))
+            .join("settings.toml");
+        let (pong, maze) = ([0x12, 0x00], [0x13, 0x00]);
+
+        // a new game starts with what was used last
+        let mut settings = Settings::load(&path).unwrap();
+        settings.speed = 20;
+        assert!(!settings.use_rom(&pong).unwrap());
+        assert_eq!(settings.speed, 20);
+        settings.cycle_palette(true);
+        settings.speed = 50;
+        settings.save().unwrap();
+
+        let mut settings = Settings::load(&path).unwrap();
+        settings.use_rom(&maze).unwrap();
+        settings.speed = 5;
+        settings.anti_flicker = true;
+        settings.save().unwrap();
+
+        // and one played before with what it had
+        let mut settings = Settings::load(&path).unwrap();
+        assert!(settings.use_rom(&pong).unwrap());
+        assert_eq!(settings.palette().name, "amber");
+        assert_eq!(settings.speed, 50);
+        assert!(settings.anti_flicker);
+        settings.use_rom(&maze).unwrap();
+        assert_eq!(settings.speed, 5);
+
+        // nothing is kept for settings that aren't saved
+        let mut settings = Settings::new();
+        settings.use_rom(&pong).unwrap();
+        assert_eq!(settings.speed, 10);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::save_settings;
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::cpu::CPU;
+use chip8::settings::Settings;
+
+const MENU_PADDING: i32 = 6;
+const ROWS: usize = 6;
+
+const MENU_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+
+pub struct SettingsMenu {
+    visible: bool,
+    selected: usize,
+    // how many displays there are to go fullscreen on
+    displays: u32,
+}
+
+impl SettingsMenu {
+    pub fn new(displays: u32) -> SettingsMenu {
+        SettingsMenu {
+            visible: false,
+            selected: 0,
+            displays,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+    }
+
+    // returns true if the menu consumed the key. changes take effect straight away and are
+    // saved as they're made
+    pub fn handle_key(&mut self, key: Keycode, settings: &mut Settings, cpu: &mut CPU) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let forward = match key {
+            Keycode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                return true;
+            }
+            Keycode::Down => {
+                self.selected = (self.selected + 1).min(ROWS - 1);
+                return true;
+            }
+            Keycode::Right | Keycode::Return | Keycode::KpEnter => true,
+            Keycode::Left => false,
+            _ => return true,
+        };
+
+        match self.selected {
+            0 => settings.cycle_palette(forward),
+            1 => settings.step_speed(forward),
+            2 => {
+                settings.cycle_preset(forward);
+                cpu.set_quirks(settings.quirks());
+            }
+            3 => settings.anti_flicker = !settings.anti_flicker,
+            4 => settings.cycle_monitor(self.displays, forward),
+            _ => settings.borderless = !settings.borderless,
+        }
+        save_settings(settings);
+
+        true
+    }
+
+    pub fn draw(&self, settings: &Settings, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(MENU_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = MENU_PADDING;
+        let mut y = MENU_PADDING;
+        draw_text(
+            canvas,
+            "SETTINGS  UP/DOWN SELECT, LEFT/RIGHT CHANGE, CTRL+, CLOSES",
+            x,
+            y,
+            TITLE_COLOR,
+        );
+
+        y += LINE_HEIGHT;
+        let rows = [
+            format!("PALETTE  < {} >", settings.palette().name),
+            format!("SPEED    < {} INSTRUCTIONS A FRAME >", settings.speed),
+            format!("QUIRKS   < {} >", settings.preset().name),
+            format!(
+                "ANTI-FLICKER  < {} >",
+                if settings.anti_flicker { "ON" } else { "OFF" }
+            ),
+            match settings.monitor {
+                0 => "FULLSCREEN ON  < THE WINDOW'S DISPLAY >".to_string(),
+                monitor => format!("FULLSCREEN ON  < DISPLAY {} >", monitor),
+            },
+            format!(
+                "FULLSCREEN IS  < {} >",
+                if settings.borderless {
+                    "BORDERLESS"
+                } else {
+                    "EXCLUSIVE"
+                }
+            ),
+        ];
+        for (index, row) in rows.iter().enumerate() {
+            y += LINE_HEIGHT;
+
+            if index == self.selected {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y - 2, window_width, LINE_HEIGHT as u32))
+                    .unwrap();
+            }
+            draw_text(canvas, row, x, y, TEXT_COLOR);
+        }
+    }
+}
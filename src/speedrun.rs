@@ -0,0 +1,155 @@
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use sdl2::{render::Canvas, video::Window};
+
+use crate::overlay::{draw_box, reuse_lines, Corner};
+
+// splits shown under the timer, the older ones scroll away
+const VISIBLE_SPLITS: usize = 5;
+
+// a real-time timer for speedruns that starts with the first key press, next to a count of the
+// emulated frames so runs can also be compared without the host's timing getting in the way
+pub struct SpeedrunTimer {
+    visible: bool,
+    start: Option<Instant>,
+    frames: u64,
+    splits: Vec<(Duration, u64)>,
+    // what's drawn, written over each frame
+    lines: Vec<String>,
+}
+
+impl SpeedrunTimer {
+    pub fn new() -> SpeedrunTimer {
+        SpeedrunTimer {
+            visible: false,
+            start: None,
+            frames: 0,
+            splits: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // call for every key press on the keypad, only the first one of a run matters
+    pub fn key_pressed(&mut self, now: Instant) {
+        if self.start.is_none() {
+            self.start = Some(now);
+        }
+    }
+
+    // call once for every frame the CPU ran
+    pub fn frame(&mut self) {
+        if self.start.is_some() {
+            self.frames += 1;
+        }
+    }
+
+    pub fn split(&mut self, now: Instant) {
+        if let Some(start) = self.start {
+            self.splits.push((now - start, self.frames));
+        }
+    }
+
+    // ready for the next attempt, the timer starts again with the next key press
+    pub fn reset(&mut self) {
+        self.start = None;
+        self.frames = 0;
+        self.splits.clear();
+    }
+
+    fn lines(&mut self, now: Instant) -> &[String] {
+        let elapsed = self.start.map_or(Duration::ZERO, |start| now - start);
+        let first = self.splits.len().saturating_sub(VISIBLE_SPLITS);
+        let (total, lines) = reuse_lines(&mut self.lines, 1 + self.splits.len() - first)
+            .split_first_mut()
+            .unwrap();
+        write_time(total, elapsed);
+        write!(total, " {:>6} frames", self.frames).unwrap();
+
+        let splits = self.splits.iter().enumerate().skip(first);
+        for ((index, (time, frames)), line) in splits.zip(lines) {
+            write!(line, "{:>2} ", index + 1).unwrap();
+            write_time(line, *time);
+            write!(line, " {:>6}", frames).unwrap();
+        }
+        &self.lines
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>) {
+        if self.visible {
+            let lines = self.lines(Instant::now());
+            draw_box(canvas, lines.iter().map(String::as_str), Corner::TopRight);
+        }
+    }
+}
+
+// m:ss.mmm, with hours in front once a run gets that long
+fn write_time(text: &mut String, time: Duration) {
+    let millis = time.as_millis();
+    let (hours, minutes) = (millis / 3_600_000, millis / 60_000 % 60);
+    let (seconds, millis) = (millis / 1000 % 60, millis % 1000);
+    if hours > 0 {
+        write!(
+            text,
+            "{}:{:02}:{:02}.{:03}",
+            hours, minutes, seconds, millis
+        )
+    } else {
+        write!(text, "{}:{:02}.{:03}", minutes, seconds, millis)
+    }
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_time() {
+        let format_time = |time| {
+            let mut text = String::new();
+            write_time(&mut text, time);
+            text
+        };
+        assert_eq!(format_time(Duration::ZERO), "0:00.000");
+        assert_eq!(format_time(Duration::from_millis(83_456)), "1:23.456");
+        assert_eq!(format_time(Duration::from_secs(3_723)), "1:02:03.000");
+    }
+
+    #[test]
+    fn test_timer() {
+        let start = Instant::now();
+        let mut timer = SpeedrunTimer::new();
+
+        // nothing counts before the first key press
+        timer.frame();
+        timer.split(start);
+        assert_eq!(timer.lines(start), ["0:00.000      0 frames"]);
+
+        timer.key_pressed(start);
+        timer.frame();
+        timer.frame();
+        timer.key_pressed(start + Duration::from_secs(1));
+        timer.split(start + Duration::from_millis(1500));
+        assert_eq!(
+            timer.lines(start + Duration::from_secs(2)),
+            ["0:02.000      2 frames", " 1 0:01.500      2"]
+        );
+
+        timer.reset();
+        assert_eq!(
+            timer.lines(start + Duration::from_secs(3)),
+            ["0:00.000      0 frames"]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+// two machines side by side in one window, each with its own ROM, quirks and half of the
+// keyboard: the usual 1234/QWER/ASDF/ZXCV on the left and 7890/UIOP/JKL;/M,./ on the right.
+// when both run the same ROM the left keys drive both, so quirk settings can be compared
+use sdl2::{
+    event::Event, keyboard::Keycode, pixels::Color, rect::Rect, render::Canvas, video::Window,
+};
+use tracing::error;
+
+use chip8::cpu::{CPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::error::RomError;
+use chip8::input;
+use chip8::quirks::Quirks;
+
+use crate::{convert_key_to_button, draw_pixels, SCALE};
+
+// the bar between the two screens
+const DIVIDER: u32 = 4;
+const DIVIDER_COLOR: Color = Color::RGB(80, 80, 80);
+const SCREEN_PIXELS: u32 = SCREEN_WIDTH as u32 * SCALE;
+
+struct Side {
+    name: &'static str,
+    cpu: CPU,
+    // a side whose program stopped stays on screen while the other keeps going
+    stopped: bool,
+}
+
+pub fn run(roms: [&[u8]; 2], quirks: [Quirks; 2], ticks_per_frame: u32) -> Result<(), RomError> {
+    let mirror_keys = roms[0] == roms[1];
+    let [left, right] =
+        [("left", roms[0], quirks[0]), ("right", roms[1], quirks[1])].map(|(name, rom, quirks)| {
+            let mut cpu = CPU::new();
+            cpu.set_quirks(quirks);
+            cpu.try_load(rom)?;
+            Ok(Side {
+                name,
+                cpu,
+                stopped: false,
+            })
+        });
+    let mut sides = [left?, right?];
+
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    let window = video_subsystem
+        .window(
+            "Rusty Chip8",
+            SCREEN_PIXELS * 2 + DIVIDER,
+            SCREEN_HEIGHT as u32 * SCALE,
+        )
+        .position_centered()
+        .opengl()
+        .build()
+        .unwrap();
+    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut rects = Vec::new();
+
+    'gameloop: loop {
+        for event in event_pump.poll_iter() {
+            let (key, pressed) = match event {
+                Event::Quit { .. }
+                | Event::KeyUp {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'gameloop,
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => (key, true),
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => (key, false),
+                _ => continue,
+            };
+
+            if let Some(k) = convert_key_to_button(key) {
+                input::press(&mut sides[0].cpu, k, pressed);
+                if mirror_keys {
+                    input::press(&mut sides[1].cpu, k, pressed);
+                }
+            } else if let Some(k) = convert_right_key(key) {
+                input::press(&mut sides[1].cpu, k, pressed);
+            }
+        }
+
+        for side in sides.iter_mut().filter(|side| !side.stopped) {
+            for _ in 0..ticks_per_frame {
+                let pc = side.cpu.pc();
+                if let Err(error) = side.cpu.tick() {
+                    error!("{} program stopped at #{:03X}: {}", side.name, pc, error);
+                    side.stopped = true;
+                    break;
+                }
+            }
+            side.cpu.tick_timers();
+        }
+
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        draw_screen(&sides[0].cpu, 0, &mut rects, &mut canvas);
+        draw_screen(
+            &sides[1].cpu,
+            SCREEN_PIXELS + DIVIDER,
+            &mut rects,
+            &mut canvas,
+        );
+        canvas.set_draw_color(DIVIDER_COLOR);
+        canvas
+            .fill_rect(Rect::new(
+                SCREEN_PIXELS as i32,
+                0,
+                DIVIDER,
+                SCREEN_HEIGHT as u32 * SCALE,
+            ))
+            .unwrap();
+        canvas.present();
+    }
+    Ok(())
+}
+
+fn draw_screen(cpu: &CPU, left: u32, rects: &mut Vec<Rect>, canvas: &mut Canvas<Window>) {
+    let area = Rect::new(left as i32, 0, SCREEN_PIXELS, SCREEN_HEIGHT as u32 * SCALE);
+    // lit on either plane, there's only the one colour here
+    let lit = |y| cpu.screen.row(y) | cpu.second_plane.row(y);
+    draw_pixels(cpu.resolution(), &lit, area, Color::WHITE, rects, canvas);
+}
+
+// the same layout as the left hand keys, six columns over
+pub fn convert_right_key(key: Keycode) -> Option<usize> {
+    match key {
+        Keycode::Num7 => Some(0x1),
+        Keycode::Num8 => Some(0x2),
+        Keycode::Num9 => Some(0x3),
+        Keycode::Num0 => Some(0xC),
+        Keycode::U => Some(0x4),
+        Keycode::I => Some(0x5),
+        Keycode::O => Some(0x6),
+        Keycode::P => Some(0xD),
+        Keycode::J => Some(0x7),
+        Keycode::K => Some(0x8),
+        Keycode::L => Some(0x9),
+        Keycode::Semicolon => Some(0xE),
+        Keycode::M => Some(0xA),
+        Keycode::Comma => Some(0x0),
+        Keycode::Period => Some(0xB),
+        Keycode::Slash => Some(0xF),
+        _ => None,
+    }
+}
@@ -0,0 +1,145 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::cpu::CPU;
+
+const VIEWER_PADDING: i32 = 6;
+const PIXEL_SIZE: i32 = 5;
+const SPRITE_GAP: i32 = 12;
+const GRID_COLUMNS: usize = 12;
+const GRID_ROWS: usize = 3;
+// DXYN can draw at most 15 rows
+const MAX_SPRITE_ROWS: usize = 15;
+
+const VIEWER_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const PIXEL_COLOR: Color = Color::WHITE;
+const CELL_COLOR: Color = Color::RGB(40, 40, 40);
+
+// shows memory as a grid of consecutive sprites, handy for finding graphics in a ROM
+pub struct SpriteViewer {
+    visible: bool,
+    address: usize,
+    rows: usize,
+}
+
+impl SpriteViewer {
+    pub fn new() -> SpriteViewer {
+        SpriteViewer {
+            visible: false,
+            address: 0x200,
+            rows: 5,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // returns true if the viewer consumed the key
+    pub fn handle_key(&mut self, key: Keycode, cpu: &CPU) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let last = cpu.memory().len() - 1;
+        let page = self.rows * GRID_COLUMNS * GRID_ROWS;
+
+        self.address = match key {
+            Keycode::Left => self.address.saturating_sub(1),
+            Keycode::Right => self.address + 1,
+            Keycode::Up => self.address.saturating_sub(self.rows),
+            Keycode::Down => self.address + self.rows,
+            Keycode::PageUp => self.address.saturating_sub(page),
+            Keycode::PageDown => self.address + page,
+            // jump to wherever the program is pointing I
+            Keycode::I => cpu.index_register() as usize,
+            _ => self.address,
+        }
+        .min(last);
+
+        self.rows = match key {
+            Keycode::Minus | Keycode::KpMinus => self.rows.saturating_sub(1).max(1),
+            Keycode::Equals | Keycode::Plus | Keycode::KpPlus => {
+                (self.rows + 1).min(MAX_SPRITE_ROWS)
+            }
+            _ => self.rows,
+        };
+
+        true
+    }
+
+    pub fn draw(&self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let memory = cpu.memory();
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(VIEWER_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let title = format!(
+            "SPRITES #{:03X}  {} ROWS  ARROWS/PGUP/PGDN MOVE, +/- ROWS, I JUMP TO I",
+            self.address, self.rows
+        );
+        draw_text(canvas, &title, VIEWER_PADDING, VIEWER_PADDING, TITLE_COLOR);
+
+        let cell_width = 8 * PIXEL_SIZE;
+        let cell_height = self.rows as i32 * PIXEL_SIZE;
+
+        for index in 0..GRID_COLUMNS * GRID_ROWS {
+            let start = self.address + index * self.rows;
+            if start >= memory.len() {
+                break;
+            }
+
+            let column = (index % GRID_COLUMNS) as i32;
+            let row = (index / GRID_COLUMNS) as i32;
+            let x = VIEWER_PADDING + column * (cell_width + SPRITE_GAP);
+            let y =
+                VIEWER_PADDING + 2 * LINE_HEIGHT + row * (cell_height + LINE_HEIGHT + SPRITE_GAP);
+
+            canvas.set_draw_color(CELL_COLOR);
+            canvas
+                .fill_rect(Rect::new(x, y, cell_width as u32, cell_height as u32))
+                .unwrap();
+
+            let end = (start + self.rows).min(memory.len());
+            let mut pixels = Vec::new();
+            for (sprite_y, byte) in memory[start..end].iter().enumerate() {
+                for sprite_x in 0..8 {
+                    if byte & (0b1000_0000 >> sprite_x) != 0 {
+                        pixels.push(Rect::new(
+                            x + sprite_x * PIXEL_SIZE,
+                            y + sprite_y as i32 * PIXEL_SIZE,
+                            PIXEL_SIZE as u32,
+                            PIXEL_SIZE as u32,
+                        ));
+                    }
+                }
+            }
+            canvas.set_draw_color(PIXEL_COLOR);
+            canvas.fill_rects(&pixels).unwrap();
+
+            let label = format!("{:03X}", start);
+            draw_text(canvas, &label, x, y + cell_height + 4, TEXT_COLOR);
+        }
+    }
+}
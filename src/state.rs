@@ -0,0 +1,451 @@
+// the whole machine as JSON, to look at, to edit by hand when setting up a test, or to diff.
+// memory goes in rows of hex and the screen as rows of # and ., its size saying which display
+// is in use. a state being loaded can leave out anything that should stay as it is on a freshly
+// reset machine. the random number generator isn't part of it, a loaded machine gets a new one.
+// a state saved to a slot also names its game and when it was saved, for telling slots apart.
+// XO-CHIP's second plane and a memory size other than 4K are only there when a machine has them
+use serde_json::{json, Map, Value};
+
+use crate::cpu::{DisplayMode, CPU, MEMORY_SIZE, NUM_KEYS};
+use crate::error::StateError;
+use crate::framebuffer::Framebuffer;
+
+const MEMORY_ROW_BYTES: usize = 32;
+
+// what a slot shows before it's loaded, the screen doubling as its thumbnail. states saved
+// without a game or time have them empty
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preview {
+    pub rom: String,
+    // seconds since the unix epoch
+    pub saved_at: u64,
+    pub screen: Vec<String>,
+}
+
+pub fn to_json(cpu: &CPU) -> String {
+    serde_json::to_string_pretty(&state(cpu)).unwrap()
+}
+
+pub fn to_json_with_preview(cpu: &CPU, rom: &str, saved_at: u64) -> String {
+    let mut state = state(cpu);
+    state["rom"] = json!(rom);
+    state["saved_at"] = json!(saved_at);
+    serde_json::to_string_pretty(&state).unwrap()
+}
+
+// the preview without loading the rest of the machine
+pub fn preview(text: &str) -> Result<Preview, StateError> {
+    read_preview(&parse(text)?).map_err(StateError::Invalid)
+}
+
+pub fn from_json(text: &str) -> Result<CPU, StateError> {
+    machine(&parse(text)?).map_err(StateError::Invalid)
+}
+
+fn parse(text: &str) -> Result<Value, StateError> {
+    serde_json::from_str(text).map_err(|error| StateError::Syntax(error.to_string()))
+}
+
+fn read_preview(state: &Value) -> Result<Preview, String> {
+    let state = state.as_object().ok_or("a state must be a JSON object")?;
+
+    let mut preview = Preview {
+        rom: String::new(),
+        saved_at: 0,
+        screen: Vec::new(),
+    };
+    if let Some(rom) = state.get("rom") {
+        preview.rom = rom.as_str().ok_or("rom must be a string")?.to_string();
+    }
+    if let Some(saved_at) = state.get("saved_at") {
+        preview.saved_at = number(saved_at, "saved_at")?;
+    }
+    if let Some(screen) = state.get("screen") {
+        preview.screen = array(screen, "screen")?
+            .iter()
+            .map(|row| row.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or("screen rows must be strings")?;
+    }
+    Ok(preview)
+}
+
+fn state(cpu: &CPU) -> Value {
+    let quirks = cpu.quirks();
+    let memory: Map<String, Value> = cpu
+        .memory()
+        .chunks(MEMORY_ROW_BYTES)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            (
+                format!("{:03X}", row * MEMORY_ROW_BYTES),
+                Value::String(hex),
+            )
+        })
+        .collect();
+
+    let mut state = json!({
+        "pc": cpu.pc(),
+        "index": cpu.index_register(),
+        "v": cpu.v_registers(),
+        "delay_timer": cpu.delay_timer(),
+        "sound_timer": cpu.sound_timer(),
+        "stack": cpu.stack(),
+        "stack_size": cpu.stack_size(),
+        "keys": cpu.keys(),
+        "quirks": {
+            "vf_reset": quirks.vf_reset,
+            "memory_increment": quirks.memory_increment,
+            "shift_uses_vy": quirks.shift_uses_vy,
+            "jump_uses_vx": quirks.jump_uses_vx,
+            "clipping": quirks.clipping,
+            "increment_by_x": quirks.increment_by_x,
+            "tall_sprites": quirks.tall_sprites,
+            "chip8e": quirks.chip8e,
+            "two_page": quirks.two_page,
+            "schip_hires": quirks.schip_hires,
+            "xochip": quirks.xochip,
+        },
+        "screen": plane_rows(cpu, &cpu.screen),
+        "memory": memory,
+    });
+    if !cpu.second_plane.is_blank() {
+        state["second_plane"] = json!(plane_rows(cpu, &cpu.second_plane));
+    }
+    if quirks.xochip {
+        state["planes"] = json!(cpu.planes());
+        state["pitch"] = json!(cpu.pitch());
+        if let Some(pattern) = cpu.audio_pattern() {
+            let hex: String = pattern.iter().map(|byte| format!("{:02X}", byte)).collect();
+            state["audio_pattern"] = json!(hex);
+        }
+    }
+    if cpu.memory().len() != MEMORY_SIZE {
+        state["memory_size"] = json!(cpu.memory().len());
+    }
+    state
+}
+
+// the plane's pixels on the display in use as rows of # and .
+fn plane_rows(cpu: &CPU, plane: &Framebuffer) -> Vec<String> {
+    let (width, height) = cpu.resolution();
+    let pixels: Vec<bool> = plane.pixels(height).collect();
+    pixels
+        .chunks(width)
+        .map(|row| row.iter().map(|&on| if on { '#' } else { '.' }).collect())
+        .collect()
+}
+
+// rows of # and . written into the plane, which the display in use must fit
+fn read_plane(rows: &[Value], cpu: &CPU, plane: &mut Framebuffer) -> Result<(), String> {
+    let (width, height) = cpu.resolution();
+    if rows.len() > height {
+        return Err(format!(
+            "the display has {} rows, not {}",
+            height,
+            rows.len()
+        ));
+    }
+    for (y, row) in rows.iter().enumerate() {
+        let row = row.as_str().ok_or("screen rows must be strings")?;
+        if row.chars().count() != width {
+            return Err(format!("screen rows must be {} pixels wide", width));
+        }
+        for (x, pixel) in row.chars().enumerate() {
+            let on = match pixel {
+                '#' => true,
+                '.' => false,
+                _ => return Err(format!("screen pixels are # or ., not {}", pixel)),
+            };
+            plane.set(y * width + x, on);
+        }
+    }
+    Ok(())
+}
+
+fn machine(state: &Value) -> Result<CPU, String> {
+    let state = state.as_object().ok_or("a state must be a JSON object")?;
+
+    let mut cpu = match state.get("memory_size") {
+        Some(size) => CPU::builder()
+            .memory_size(number(size, "memory_size")?)
+            .build()
+            .map_err(|error| error.to_string())?,
+        None => CPU::new(),
+    };
+    // before the stack, which may be deeper than a fresh machine's
+    if let Some(size) = state.get("stack_size") {
+        cpu.set_stack_size(number(size, "stack_size")?)
+            .map_err(|error| error.to_string())?;
+    }
+    for (name, value) in state {
+        match name.as_str() {
+            "pc" => cpu.set_pc(number(value, name)?),
+            "index" => cpu.set_index_register(number(value, name)?),
+            "delay_timer" => cpu.set_delay_timer(number(value, name)?),
+            "planes" => cpu.set_planes(number(value, name)?),
+            "pitch" => cpu.set_pitch(number(value, name)?),
+            "audio_pattern" => {
+                let pattern = value
+                    .as_str()
+                    .and_then(parse_hex)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or("audio_pattern must be 16 bytes of hex")?;
+                cpu.set_audio_pattern(Some(pattern));
+            }
+            "sound_timer" => cpu.set_sound_timer(number(value, name)?),
+            "v" => {
+                let v = array(value, name)?;
+                if v.len() != cpu.v_registers().len() {
+                    return Err(format!("v must have {} registers", cpu.v_registers().len()));
+                }
+                for (index, value) in v.iter().enumerate() {
+                    cpu.set_v_register(index, number(value, name)?);
+                }
+            }
+            "stack" => {
+                let stack = array(value, name)?
+                    .iter()
+                    .map(|value| number(value, name))
+                    .collect::<Result<Vec<u16>, _>>()?;
+                cpu.set_stack(&stack)
+                    .map_err(|_| "the stack has too many return addresses".to_string())?;
+            }
+            "keys" => {
+                let keys = array(value, name)?;
+                if keys.len() != NUM_KEYS {
+                    return Err(format!("keys must have {} entries", NUM_KEYS));
+                }
+                for (key, pressed) in keys.iter().enumerate() {
+                    let pressed = pressed.as_bool().ok_or("keys must be true or false")?;
+                    cpu.keypress(key, pressed)
+                        .map_err(|error| error.to_string())?;
+                }
+            }
+            "quirks" => {
+                let mut quirks = cpu.quirks();
+                let fields = value.as_object().ok_or("quirks must be an object")?;
+                for (quirk, enabled) in fields {
+                    let enabled = enabled
+                        .as_bool()
+                        .ok_or_else(|| format!("quirk {} must be true or false", quirk))?;
+                    match quirk.as_str() {
+                        "vf_reset" => quirks.vf_reset = enabled,
+                        "memory_increment" => quirks.memory_increment = enabled,
+                        "shift_uses_vy" => quirks.shift_uses_vy = enabled,
+                        "jump_uses_vx" => quirks.jump_uses_vx = enabled,
+                        "clipping" => quirks.clipping = enabled,
+                        "increment_by_x" => quirks.increment_by_x = enabled,
+                        "tall_sprites" => quirks.tall_sprites = enabled,
+                        "chip8e" => quirks.chip8e = enabled,
+                        "two_page" => quirks.two_page = enabled,
+                        "schip_hires" => quirks.schip_hires = enabled,
+                        "xochip" => quirks.xochip = enabled,
+                        _ => return Err(format!("unknown quirk {}", quirk)),
+                    }
+                }
+                cpu.set_quirks(quirks);
+            }
+            "screen" => {
+                let rows = array(value, name)?;
+                // the smallest display the rows fit, a screen of 40 rows is the two-page one
+                let width = rows
+                    .first()
+                    .and_then(|row| row.as_str())
+                    .map_or(0, |row| row.chars().count());
+                let mode = [
+                    DisplayMode::Standard,
+                    DisplayMode::TwoPage,
+                    DisplayMode::Hires,
+                ]
+                .into_iter()
+                .find(|mode| {
+                    let size = mode.size();
+                    (width == 0 || width == size.0) && rows.len() <= size.1
+                })
+                .ok_or_else(|| format!("no display has {} rows of {} pixels", rows.len(), width))?;
+                cpu.set_display_mode(mode);
+                let mut plane = cpu.screen;
+                read_plane(rows, &cpu, &mut plane)?;
+                cpu.screen = plane;
+            }
+            "memory" => {
+                let rows = value.as_object().ok_or("memory must be an object")?;
+                for (address, bytes) in rows {
+                    let start = u16::from_str_radix(address, 16)
+                        .map_err(|_| format!("invalid memory address {}", address))?;
+                    let bytes = bytes
+                        .as_str()
+                        .and_then(parse_hex)
+                        .ok_or_else(|| format!("memory at {} must be a string of hex", address))?;
+                    if start as usize + bytes.len() > cpu.memory().len() {
+                        return Err(format!("memory at {} runs past the end", address));
+                    }
+                    for (offset, byte) in bytes.into_iter().enumerate() {
+                        cpu.poke(start + offset as u16, byte);
+                    }
+                }
+            }
+            // read before and after everything else
+            "stack_size" | "memory_size" | "second_plane" => (),
+            // they describe the state, the machine doesn't keep them
+            "rom" | "saved_at" => (),
+            _ => return Err(format!("unknown field {}", name)),
+        }
+    }
+
+    // once the screen has picked the display it's on
+    if let Some(rows) = state.get("second_plane") {
+        let rows = array(rows, "second_plane")?;
+        let mut plane = cpu.second_plane;
+        read_plane(rows, &cpu, &mut plane)?;
+        cpu.second_plane = plane;
+    }
+
+    Ok(cpu)
+}
+
+fn number<T: TryFrom<u64>>(value: &Value, name: &str) -> Result<T, String> {
+    value
+        .as_u64()
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or_else(|| format!("{} is out of range or not a number", name))
+}
+
+fn array<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("{} must be an array", name))
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::MAX_MEMORY_SIZE;
+    use crate::quirks::Quirks;
+
+    #[test]
+    fn test_round_trip() {
+        let mut cpu = CPU::new();
+        cpu.set_quirks(Quirks::vip());
+        // call a subroutine that sets V3 and draws the font's 0, where I starts, at 1,2
+        cpu.load(&[
+            0x22, 0x04, 0x00, 0x00, 0x63, 0x07, 0x61, 0x01, 0x62, 0x02, 0xD1, 0x25,
+        ]);
+        for _ in 0..5 {
+            cpu.tick().unwrap();
+        }
+        cpu.keypress(0xA, true).unwrap();
+
+        let text = to_json(&cpu);
+        let loaded = from_json(&text).unwrap();
+        assert_eq!(to_json(&loaded), text);
+        assert_eq!(loaded.pc(), cpu.pc());
+        assert_eq!(loaded.stack(), [0x202]);
+        assert_eq!(loaded.v_registers(), cpu.v_registers());
+        assert_eq!(loaded.quirks(), Quirks::vip());
+        assert_eq!(loaded.screen, cpu.screen);
+        assert_eq!(loaded.memory(), cpu.memory());
+        assert!(loaded.keys()[0xA]);
+    }
+
+    #[test]
+    fn test_xochip() {
+        let mut cpu = CPU::builder()
+            .quirks(Quirks::xochip())
+            .memory_size(MAX_MEMORY_SIZE)
+            .build()
+            .unwrap();
+        // both planes, a pattern from the font and a higher pitch, then the font's 0 on the second plane
+        cpu.load(&[
+            0xF3, 0x01, 0xF0, 0x02, 0x60, 0x70, 0xF0, 0x3A, 0xF2, 0x01, 0xD1, 0x15,
+        ]);
+        cpu.poke(0xFFFF, 0xAB);
+        for _ in 0..6 {
+            cpu.tick().unwrap();
+        }
+
+        let text = to_json(&cpu);
+        let loaded = from_json(&text).unwrap();
+        assert_eq!(to_json(&loaded), text);
+        assert_eq!(loaded.memory().len(), MAX_MEMORY_SIZE);
+        assert_eq!(loaded.memory()[0xFFFF], 0xAB);
+        assert_eq!((loaded.planes(), loaded.pitch()), (2, 0x70));
+        assert_eq!(loaded.audio_pattern(), cpu.audio_pattern());
+        assert_eq!(loaded.second_plane, cpu.second_plane);
+        assert!(!loaded.second_plane.is_blank() && loaded.screen.is_blank());
+        assert!(!to_json(&CPU::new()).contains("second_plane"));
+    }
+
+    #[test]
+    fn test_hand_written() {
+        let cpu = from_json(
+            r#"{"pc": 768, "v": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
+                "quirks": {"clipping": true}, "memory": {"300": "6005"}}"#,
+        )
+        .unwrap();
+        assert_eq!(cpu.pc(), 0x300);
+        assert_eq!(cpu.v_registers()[0xF], 16);
+        assert!(cpu.quirks().clipping && !cpu.quirks().vf_reset);
+        assert_eq!(cpu.opcode_at(0x300), 0x6005);
+        // everything else is as on a fresh machine, font included
+        assert_eq!(cpu.memory()[..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+        assert!(from_json(r#"{"memory": {"FFF": "0102"}}"#).is_err());
+        assert!(from_json(r#"{"v": [1]}"#).is_err());
+        assert!(from_json(r#"{"pc": 70000}"#).is_err());
+        assert!(from_json(r#"{"colour": 1}"#).is_err());
+        let deep = from_json(r#"{"stack": [512, 512, 512], "stack_size": 3}"#).unwrap();
+        assert_eq!((deep.stack().len(), deep.stack_size()), (3, 3));
+        assert!(from_json(r#"{"stack": [512, 512, 512], "stack_size": 2}"#).is_err());
+        assert!(matches!(from_json("{"), Err(StateError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_display_modes() {
+        let mut cpu = CPU::new();
+        cpu.set_display_mode(DisplayMode::TwoPage);
+        cpu.screen.set(63 * 64, true);
+
+        let loaded = from_json(&to_json(&cpu)).unwrap();
+        assert_eq!(loaded.display_mode(), DisplayMode::TwoPage);
+        assert!(loaded.screen[63 * 64]);
+        assert_eq!(from_json("{}").unwrap().resolution(), (64, 32));
+
+        cpu.set_display_mode(DisplayMode::Hires);
+        cpu.screen.set(63 * 128 + 127, true);
+        let loaded = from_json(&to_json(&cpu)).unwrap();
+        assert_eq!(loaded.display_mode(), DisplayMode::Hires);
+        assert!(loaded.screen[63 * 128 + 127]);
+        assert!(from_json(&format!(r#"{{"screen": ["{}"]}}"#, ".".repeat(100))).is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut cpu = CPU::new();
+        cpu.screen.set(1, true);
+
+        let text = to_json_with_preview(&cpu, "pong.ch8", 1_700_000_000);
+        let preview = preview(&text).unwrap();
+        assert_eq!(preview.rom, "pong.ch8");
+        assert_eq!(preview.saved_at, 1_700_000_000);
+        assert_eq!(preview.screen.len(), 32);
+        assert!(preview.screen[0].starts_with(".#."));
+        assert_eq!(to_json(&from_json(&text).unwrap()), to_json(&cpu));
+
+        let bare = super::preview(&to_json(&cpu)).unwrap();
+        assert_eq!((bare.rom.as_str(), bare.saved_at), ("", 0));
+        assert!(super::preview(r#"{"rom": 3}"#).is_err());
+    }
+}
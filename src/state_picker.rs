@@ -0,0 +1,304 @@
+// save slots for the game being played, kept beside it as <rom>.slot<n>.json. the picker shows
+// each slot's screen, game and time so they can be told apart before one is loaded or overwritten
+use std::{fs, path::Path};
+
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, LINE_HEIGHT};
+use chip8::state::{self, Preview};
+
+const SLOTS: usize = 4;
+const MENU_PADDING: i32 = 6;
+// thumbnails are this wide whatever the display, hires ones at a pixel a pixel
+const THUMBNAIL_WIDTH: u32 = 128;
+
+const MENU_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const THUMBNAIL_BACKGROUND: Color = Color::RGB(30, 30, 30);
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotAction {
+    Save(usize),
+    Load(usize),
+}
+
+pub struct StatePicker {
+    visible: bool,
+    saving: bool,
+    selected: usize,
+    // the ROM's path, or a demo's name, that slot files are named after
+    rom: String,
+    // read when the picker opens, None for an empty slot or one that can't be read
+    previews: Vec<Option<Preview>>,
+    // picked with return, taken by the main loop
+    chosen: Option<SlotAction>,
+}
+
+impl StatePicker {
+    pub fn new(rom: &str) -> StatePicker {
+        StatePicker {
+            visible: false,
+            saving: false,
+            selected: 0,
+            rom: rom.to_string(),
+            previews: Vec::new(),
+            chosen: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // after another game is loaded, its slots are the ones shown
+    pub fn set_rom(&mut self, rom: &str) {
+        self.rom = rom.to_string();
+    }
+
+    // the game's file name, stored in the states saved
+    pub fn rom_name(&self) -> String {
+        Path::new(&self.rom).file_name().map_or_else(
+            || self.rom.clone(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    pub fn slot_path(&self, slot: usize) -> String {
+        format!("{}.slot{}.json", self.rom, slot + 1)
+    }
+
+    // opening it for the other of saving and loading switches over rather than closing it
+    pub fn toggle(&mut self, saving: bool) {
+        if self.visible && self.saving == saving {
+            self.visible = false;
+            return;
+        }
+
+        self.visible = true;
+        self.saving = saving;
+        self.previews = (0..SLOTS)
+            .map(|slot| {
+                let text = fs::read_to_string(self.slot_path(slot)).ok()?;
+                state::preview(&text).ok()
+            })
+            .collect();
+    }
+
+    // returns true if the picker consumed the key
+    pub fn handle_key(&mut self, key: Keycode) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        match key {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => self.selected = (self.selected + 1).min(SLOTS - 1),
+            // an empty slot has nothing to load
+            Keycode::Return | Keycode::KpEnter
+                if self.saving || self.previews[self.selected].is_some() =>
+            {
+                self.chosen = Some(match self.saving {
+                    true => SlotAction::Save(self.selected),
+                    false => SlotAction::Load(self.selected),
+                });
+                self.visible = false;
+            }
+            _ => (),
+        }
+
+        true
+    }
+
+    pub fn take_action(&mut self) -> Option<SlotAction> {
+        self.chosen.take()
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(MENU_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = MENU_PADDING;
+        let mut y = MENU_PADDING;
+        let title = match self.saving {
+            true => "SAVE STATE  UP/DOWN SELECT, RETURN SAVES, CTRL+S CLOSES",
+            false => "LOAD STATE  UP/DOWN SELECT, RETURN LOADS, CTRL+L CLOSES",
+        };
+        draw_text(canvas, title, x, y, TITLE_COLOR);
+
+        let row_height = THUMBNAIL_WIDTH as i32 / 2 + MENU_PADDING * 2;
+        y += LINE_HEIGHT;
+        for (slot, preview) in self.previews.iter().enumerate() {
+            if slot == self.selected {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y, window_width, row_height as u32))
+                    .unwrap();
+            }
+
+            let thumbnail_y = y + MENU_PADDING;
+            draw_thumbnail(canvas, preview.as_ref(), x, thumbnail_y);
+
+            let text_x = x + THUMBNAIL_WIDTH as i32 + MENU_PADDING * 2;
+            draw_text(
+                canvas,
+                &format!("SLOT {}", slot + 1),
+                text_x,
+                thumbnail_y,
+                TITLE_COLOR,
+            );
+            let (rom, saved_at) = match preview {
+                Some(preview) => (
+                    match preview.rom.as_str() {
+                        "" => "UNKNOWN GAME",
+                        rom => rom,
+                    },
+                    match preview.saved_at {
+                        0 => "UNKNOWN TIME".to_string(),
+                        seconds => format_time(seconds),
+                    },
+                ),
+                None => ("EMPTY", String::new()),
+            };
+            draw_text(canvas, rom, text_x, thumbnail_y + LINE_HEIGHT, TEXT_COLOR);
+            draw_text(
+                canvas,
+                &saved_at,
+                text_x,
+                thumbnail_y + LINE_HEIGHT * 2,
+                TEXT_COLOR,
+            );
+
+            y += row_height;
+        }
+    }
+}
+
+// the slot's screen scaled to THUMBNAIL_WIDTH, or a blank one for an empty slot
+fn draw_thumbnail(canvas: &mut Canvas<Window>, preview: Option<&Preview>, x: i32, y: i32) {
+    canvas.set_draw_color(THUMBNAIL_BACKGROUND);
+    canvas
+        .fill_rect(Rect::new(x, y, THUMBNAIL_WIDTH, THUMBNAIL_WIDTH / 2))
+        .unwrap();
+
+    let Some(preview) = preview else {
+        return;
+    };
+    let width = preview.screen.first().map_or(0, |row| row.chars().count()) as u32;
+    if width == 0 {
+        return;
+    }
+    let scale = (THUMBNAIL_WIDTH / width).max(1);
+
+    canvas.set_draw_color(TEXT_COLOR);
+    for (row, line) in preview.screen.iter().enumerate() {
+        for (column, pixel) in line.chars().enumerate() {
+            if pixel == '#' {
+                canvas
+                    .fill_rect(Rect::new(
+                        x + (column as u32 * scale) as i32,
+                        y + (row as u32 * scale) as i32,
+                        scale,
+                        scale,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+}
+
+// seconds since the unix epoch as a UTC date and time
+fn format_time(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let minutes = seconds % 86400 / 60;
+
+    // days to a civil date, counting in 400-year eras from 0000-03-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    use chip8::cpu::CPU;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00 UTC");
+        assert_eq!(
+            format_time(951_782_400 + 3600 * 13 + 60 * 7),
+            "2000-02-29 13:07 UTC"
+        );
+        assert_eq!(format_time(1_700_000_000), "2023-11-14 22:13 UTC");
+    }
+
+    #[test]
+    fn test_slots() {
+        let rom = env::temp_dir()
+            .join(format!("rusty_chip8-slots-{}.ch8", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut picker = StatePicker::new(&rom);
+        assert!(picker.rom_name().starts_with("rusty_chip8-slots-"));
+        assert!(!picker.handle_key(Keycode::Return));
+
+        // nothing to load from an empty slot
+        picker.toggle(false);
+        picker.handle_key(Keycode::Return);
+        assert_eq!(picker.take_action(), None);
+
+        picker.toggle(true);
+        picker.handle_key(Keycode::Down);
+        picker.handle_key(Keycode::Return);
+        assert_eq!(picker.take_action(), Some(SlotAction::Save(1)));
+        let text = state::to_json_with_preview(&CPU::new(), &picker.rom_name(), 1);
+        fs::write(picker.slot_path(1), text).unwrap();
+
+        picker.toggle(false);
+        assert!(picker.previews[0].is_none());
+        assert_eq!(picker.previews[1].as_ref().unwrap().rom, picker.rom_name());
+        picker.handle_key(Keycode::Return);
+        assert_eq!(picker.take_action(), Some(SlotAction::Load(1)));
+        fs::remove_file(picker.slot_path(1)).unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+// the keys held on every frame of a run, with a copy of the machine every so often, so the input
+// on any earlier frame can be changed and everything after it played out again from the nearest
+// copy. this is the core of tool-assisted play, the frontend draws the editor around it.
+//
+// a recording is saved as a movie: a `seed` line for CXNN, then a line a frame of the keys held
+// as four hex digits, key 0 the lowest bit. before every SAVESTATE_INTERVAL'th frame a `= ` line
+// holds a hash of the machine at that point, so a replay that goes its own way is caught where it
+// happens rather than noticed later
+use std::fmt::Write;
+
+use crate::cpu::{CpuError, CPU, NUM_KEYS};
+use crate::headless::TICKS_PER_FRAME;
+use crate::patch::crc32;
+
+// frames between savestates, a replay never runs more than this before reaching an edit
+pub const SAVESTATE_INTERVAL: usize = 60;
+
+pub type Keys = [bool; NUM_KEYS];
+
+pub struct Recording {
+    // the machine's random numbers are seeded with this, so CXNN replays the same
+    seed: u64,
+    inputs: Vec<Keys>,
+    // (frame, the machine just before that frame ran), in frame order
+    savestates: Vec<(usize, CPU)>,
+}
+
+impl Default for Recording {
+    fn default() -> Recording {
+        Recording::new()
+    }
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording {
+            seed: rand::random(),
+            inputs: Vec::new(),
+            savestates: Vec::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn keys(&self, frame: usize) -> &Keys {
+        &self.inputs[frame]
+    }
+
+    // call right before each frame runs, once its keys are set
+    pub fn record(&mut self, cpu: &CPU) {
+        let frame = self.inputs.len();
+        if frame.is_multiple_of(SAVESTATE_INTERVAL) {
+            self.savestates.push((frame, cpu.clone()));
+        }
+        let mut keys = [false; NUM_KEYS];
+        keys.copy_from_slice(cpu.keys());
+        self.inputs.push(keys);
+    }
+
+    // forgets every frame from `len` on, for when play is taken back to an earlier frame
+    pub fn truncate(&mut self, len: usize) {
+        self.inputs.truncate(len);
+        self.savestates.retain(|(at, _)| *at < len);
+    }
+
+    // flips one key on an earlier frame and replays up to the end of the recording, returning
+    // the machine as it is after the last frame. if the new input makes the program fail the
+    // edit is undone, the frame it failed on comes back with the error
+    pub fn toggle(&mut self, frame: usize, key: usize) -> Result<CPU, (usize, CpuError)> {
+        self.inputs[frame][key] = !self.inputs[frame][key];
+
+        // copies after the edit no longer hold, the replay makes new ones
+        self.savestates.retain(|(at, _)| *at <= frame);
+        let (start, mut cpu) = self
+            .savestates
+            .last()
+            .map(|(at, cpu)| (*at, cpu.clone()))
+            .expect("the first frame always has a savestate");
+
+        for at in start..self.inputs.len() {
+            if at > start && at.is_multiple_of(SAVESTATE_INTERVAL) {
+                self.savestates.push((at, cpu.clone()));
+            }
+            cpu.set_keys(&self.inputs[at]);
+            for _ in 0..TICKS_PER_FRAME {
+                if let Err(error) = cpu.tick() {
+                    self.inputs[frame][key] = !self.inputs[frame][key];
+                    self.savestates.retain(|(saved, _)| *saved <= frame);
+                    return Err((at, error));
+                }
+            }
+        }
+
+        Ok(cpu)
+    }
+
+    pub fn to_movie(&self) -> String {
+        let mut text = format!("seed {}\n", self.seed);
+        let mut savestates = self.savestates.iter().peekable();
+        for (frame, keys) in self.inputs.iter().enumerate() {
+            if let Some((_, cpu)) = savestates.next_if(|(at, _)| *at == frame) {
+                writeln!(text, "= {:08X}", state_hash(cpu)).unwrap();
+            }
+            writeln!(text, "{:04X}", key_mask(keys)).unwrap();
+        }
+        text
+    }
+}
+
+// a recording read back to be played
+pub struct Movie {
+    seed: u64,
+    inputs: Vec<Keys>,
+    // (frame, the hash of the machine just before that frame ran), in frame order
+    hashes: Vec<(usize, u32)>,
+}
+
+impl Movie {
+    pub fn parse(text: &str) -> Result<Movie, String> {
+        let mut seed = None;
+        let mut inputs = Vec::new();
+        let mut hashes = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+
+            if let Some(value) = line.strip_prefix("seed ") {
+                seed = Some(value.parse().map_err(|_| error("invalid seed"))?);
+            } else if let Some(value) = line.strip_prefix("= ") {
+                let hash = u32::from_str_radix(value, 16).map_err(|_| error("invalid hash"))?;
+                hashes.push((inputs.len(), hash));
+            } else if !line.is_empty() {
+                let mask = u16::from_str_radix(line, 16)
+                    .ok()
+                    .filter(|_| line.len() == 4)
+                    .ok_or_else(|| error("expected the keys held as four hex digits"))?;
+                inputs.push(std::array::from_fn(|key| mask & (1 << key) != 0));
+            }
+        }
+
+        Ok(Movie {
+            seed: seed.ok_or("the movie has no seed")?,
+            inputs,
+            hashes,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn keys(&self, frame: usize) -> Option<&Keys> {
+        self.inputs.get(frame)
+    }
+
+    // call right before each frame runs, once its keys are set, as when it was recorded
+    pub fn check(&self, frame: usize, cpu: &CPU) -> Result<(), String> {
+        match self.hashes.binary_search_by_key(&frame, |&(at, _)| at) {
+            Ok(index) if self.hashes[index].1 != state_hash(cpu) => {
+                Err(format!("desynced at frame {}", frame))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// everything a program can see or leave behind, the random number generator aside
+pub fn state_hash(cpu: &CPU) -> u32 {
+    let mut bytes = Vec::with_capacity(cpu.memory().len() + cpu.pixels().len() + 64);
+    bytes.extend_from_slice(&cpu.pc().to_le_bytes());
+    bytes.extend_from_slice(&cpu.index_register().to_le_bytes());
+    bytes.extend_from_slice(cpu.v_registers());
+    bytes.push(cpu.delay_timer());
+    bytes.push(cpu.sound_timer());
+    for address in cpu.stack() {
+        bytes.extend_from_slice(&address.to_le_bytes());
+    }
+    bytes.extend_from_slice(cpu.memory());
+    bytes.extend(cpu.pixels().map(|pixel| pixel as u8));
+    crc32(&bytes)
+}
+
+fn key_mask(keys: &Keys) -> u16 {
+    keys.iter()
+        .enumerate()
+        .filter(|(_, &pressed)| pressed)
+        .fold(0, |mask, (key, _)| mask | 1 << key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // counts frames with key 5 held in V0, forever
+    const ROM: [u8; 8] = [0x65, 0x05, 0xE5, 0xA1, 0x70, 0x01, 0x12, 0x02];
+
+    fn play(frames: usize, held: &[usize]) -> (Recording, CPU) {
+        let mut recording = Recording::new();
+        let mut cpu = CPU::new();
+        cpu.seed(0);
+        cpu.load(&ROM);
+        for frame in 0..frames {
+            cpu.keypress(5, held.contains(&frame)).unwrap();
+            recording.record(&cpu);
+            for _ in 0..TICKS_PER_FRAME {
+                cpu.tick().unwrap();
+            }
+        }
+        (recording, cpu)
+    }
+
+    #[test]
+    fn test_toggle() {
+        let frames = SAVESTATE_INTERVAL * 2 + 10;
+        let (mut recording, _) = play(frames, &[3]);
+        assert_eq!(recording.len(), frames);
+        assert!(recording.keys(3)[5]);
+
+        // the same as playing it with the key held on frame 70 as well
+        let edited = recording.toggle(70, 5).unwrap();
+        let (_, expected) = play(frames, &[3, 70]);
+        assert_eq!(edited.v_registers(), expected.v_registers());
+        assert!(recording.keys(70)[5]);
+
+        // and back again
+        let edited = recording.toggle(3, 5).unwrap();
+        let (_, expected) = play(frames, &[70]);
+        assert_eq!(edited.v_registers(), expected.v_registers());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let (mut recording, _) = play(SAVESTATE_INTERVAL + 10, &[]);
+        recording.truncate(SAVESTATE_INTERVAL);
+        assert_eq!(recording.len(), SAVESTATE_INTERVAL);
+
+        // recording carries on from there with a savestate for the frame it starts on
+        let mut cpu = CPU::new();
+        cpu.load(&ROM);
+        cpu.keypress(5, true).unwrap();
+        recording.record(&cpu);
+        let edited = recording.toggle(SAVESTATE_INTERVAL, 5).unwrap();
+        assert_eq!(edited.v_registers()[0], 0);
+    }
+
+    #[test]
+    fn test_movie() {
+        let frames = SAVESTATE_INTERVAL * 2 + 10;
+        let (recording, _) = play(frames, &[3, 70]);
+        let text = recording.to_movie();
+        assert!(text.starts_with(&format!("seed {}\n= ", recording.seed())));
+        assert_eq!(
+            text.lines().filter(|line| line.starts_with("= ")).count(),
+            3
+        );
+
+        let movie = Movie::parse(&text).unwrap();
+        assert_eq!(movie.len(), frames);
+        assert_eq!(movie.keys(3).map(|keys| keys[5]), Some(true));
+        assert_eq!(movie.keys(4).map(|keys| keys[5]), Some(false));
+
+        // played back the same it stays in sync, with a key missed it doesn't
+        for missed in [None, Some(70)] {
+            let mut cpu = CPU::new();
+            cpu.load(&ROM);
+            let mut result = Ok(());
+            for frame in 0..movie.len() {
+                let keys = movie.keys(frame).unwrap();
+                cpu.keypress(5, keys[5] && Some(frame) != missed).unwrap();
+                result = result.and(movie.check(frame, &cpu));
+                for _ in 0..TICKS_PER_FRAME {
+                    cpu.tick().unwrap();
+                }
+            }
+            match missed {
+                None => assert_eq!(result, Ok(())),
+                Some(_) => assert_eq!(result, Err("desynced at frame 120".to_string())),
+            }
+        }
+
+        assert!(Movie::parse("0000\n").is_err());
+        assert!(Movie::parse("seed 1\n12345\n").is_err());
+        assert!(Movie::parse("seed 1\n= nothex\n").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::text::{draw_text, CHAR_WIDTH, LINE_HEIGHT};
+use chip8::cpu::{CPU, NUM_KEYS};
+use chip8::tas::Recording;
+
+const EDITOR_PADDING: i32 = 6;
+const VISIBLE_FRAMES: usize = 24;
+// frames Page Up and Page Down move by
+const PAGE: usize = 20;
+
+const EDITOR_BACKGROUND: Color = Color::RGBA(0, 0, 0, 220);
+const TITLE_COLOR: Color = Color::RGB(255, 200, 0);
+const TEXT_COLOR: Color = Color::WHITE;
+const HELD_COLOR: Color = Color::RGB(0, 255, 128);
+const SELECTED_BACKGROUND: Color = Color::RGB(60, 60, 120);
+const CURSOR_BACKGROUND: Color = Color::RGB(160, 80, 0);
+
+// a grid of the recorded input, one row per frame and one column per key. the game stays paused
+// while it's open and every edit replays the rest of the recording
+pub struct TasEditor {
+    visible: bool,
+    frame: usize,
+    key: usize,
+    // why the last edit was refused
+    message: Option<String>,
+}
+
+impl TasEditor {
+    pub fn new() -> TasEditor {
+        TasEditor {
+            visible: false,
+            frame: 0,
+            key: 0,
+            message: None,
+        }
+    }
+
+    // opens on the latest frame
+    pub fn toggle(&mut self, recording: &Recording) {
+        self.visible = !self.visible;
+        self.frame = recording.len().saturating_sub(1);
+        self.message = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.visible
+    }
+
+    // returns true if the editor consumed the key
+    pub fn handle_key(&mut self, key: Keycode, recording: &mut Recording, cpu: &mut CPU) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let last = recording.len().saturating_sub(1);
+        match key {
+            Keycode::Up => self.frame = self.frame.saturating_sub(1),
+            Keycode::Down => self.frame = (self.frame + 1).min(last),
+            Keycode::PageUp => self.frame = self.frame.saturating_sub(PAGE),
+            Keycode::PageDown => self.frame = (self.frame + PAGE).min(last),
+            Keycode::Home => self.frame = 0,
+            Keycode::End => self.frame = last,
+            Keycode::Left => self.key = self.key.saturating_sub(1),
+            Keycode::Right => self.key = (self.key + 1).min(NUM_KEYS - 1),
+            Keycode::Space if !recording.is_empty() => {
+                self.message = match recording.toggle(self.frame, self.key) {
+                    Ok(replayed) => {
+                        *cpu = replayed;
+                        None
+                    }
+                    Err((frame, error)) => Some(format!(
+                        "NOT CHANGED, THE PROGRAM WOULD STOP ON FRAME {}: {}",
+                        frame,
+                        error.to_string().to_uppercase()
+                    )),
+                };
+            }
+            _ => (),
+        }
+
+        true
+    }
+
+    pub fn draw(&self, recording: &Recording, canvas: &mut Canvas<Window>) {
+        if !self.visible {
+            return;
+        }
+
+        let (window_width, window_height) = canvas.logical_size();
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(EDITOR_BACKGROUND);
+        canvas
+            .fill_rect(Rect::new(0, 0, window_width, window_height))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let x = EDITOR_PADDING;
+        let mut y = EDITOR_PADDING;
+        draw_text(
+            canvas,
+            "TAS EDITOR  ARROWS MOVE, SPACE TOGGLES, PGUP PGDN HOME END, F11 PLAYS ON",
+            x,
+            y,
+            TITLE_COLOR,
+        );
+
+        y += LINE_HEIGHT;
+        let status = match &self.message {
+            Some(message) => message.clone(),
+            None => format!("FRAME {} OF {}", self.frame, recording.len()),
+        };
+        draw_text(canvas, &status, x, y, TITLE_COLOR);
+
+        y += LINE_HEIGHT * 2;
+        // columns start after the frame number and two spaces
+        let keys_x = x + 8 * CHAR_WIDTH;
+        let header: String = (0..NUM_KEYS).map(|key| format!("{:X}", key)).collect();
+        draw_text(canvas, &header, keys_x, y, TITLE_COLOR);
+
+        // keep the cursor on screen
+        let first = self.frame.saturating_sub(VISIBLE_FRAMES - 1);
+        for frame in (first..recording.len()).take(VISIBLE_FRAMES) {
+            y += LINE_HEIGHT;
+
+            if frame == self.frame {
+                canvas.set_draw_color(SELECTED_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(0, y - 2, window_width, LINE_HEIGHT as u32))
+                    .unwrap();
+                canvas.set_draw_color(CURSOR_BACKGROUND);
+                canvas
+                    .fill_rect(Rect::new(
+                        keys_x + self.key as i32 * CHAR_WIDTH,
+                        y - 2,
+                        CHAR_WIDTH as u32,
+                        LINE_HEIGHT as u32,
+                    ))
+                    .unwrap();
+            }
+
+            draw_text(canvas, &format!("{:06}", frame), x, y, TEXT_COLOR);
+            for (key, &held) in recording.keys(frame).iter().enumerate() {
+                let (mark, color) = if held {
+                    (format!("{:X}", key), HELD_COLOR)
+                } else {
+                    (".".to_string(), TEXT_COLOR)
+                };
+                draw_text(canvas, &mark, keys_x + key as i32 * CHAR_WIDTH, y, color);
+            }
+        }
+    }
+}
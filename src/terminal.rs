@@ -0,0 +1,184 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::Print,
+    terminal,
+};
+
+use chip8::cpu::{CpuBuilder, CPU};
+use chip8::error::Error;
+use chip8::{events, input};
+
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+// most terminals only report presses, so a key is let go once it stops repeating
+const HOLD_FRAMES: u32 = 8;
+
+// plays the game in the terminal, two pixels per character cell using half blocks
+pub fn run(rom: &[u8], builder: CpuBuilder, ticks_per_frame: u32) -> Result<(), Error> {
+    // a machine or ROM that won't load is reported before the terminal is taken over
+    let mut cpu = builder.build()?;
+    cpu.try_load(rom)?;
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    // with key release events the keypad behaves the same as in the SDL frontend
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if releases {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
+
+    let result = game_loop(cpu, ticks_per_frame, releases, &mut stdout);
+
+    if releases {
+        execute!(stdout, PopKeyboardEnhancementFlags)?;
+    }
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    Ok(result?)
+}
+
+fn game_loop(
+    mut cpu: CPU,
+    ticks_per_frame: u32,
+    releases: bool,
+    stdout: &mut impl Write,
+) -> io::Result<()> {
+    // frames left until each key is let go, only used without release events
+    let mut held = [0u32; 16];
+    let mut last_screen = None;
+    let mut last_resolution = None;
+
+    loop {
+        let frame_start = Instant::now();
+
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if is_quit(&key) {
+                return Ok(());
+            }
+
+            if let Some(button) = convert_key_to_button(key.code) {
+                let pressed = key.kind != KeyEventKind::Release;
+                input::press(&mut cpu, button, pressed);
+                if !releases {
+                    held[button] = HOLD_FRAMES;
+                }
+            }
+        }
+
+        if !releases {
+            for (button, frames) in held.iter_mut().enumerate() {
+                if *frames > 0 {
+                    *frames -= 1;
+                    if *frames == 0 {
+                        input::press(&mut cpu, button, false);
+                    }
+                }
+            }
+        }
+
+        for _ in 0..ticks_per_frame {
+            let pc = cpu.pc();
+            if let Err(error) = cpu.tick() {
+                let message = format!("program stopped at #{:03X}: {}", pc, error);
+                return Err(io::Error::other(message));
+            }
+        }
+        cpu.tick_timers();
+
+        // a switch to the smaller display leaves the bigger one's cells behind
+        if last_resolution != Some(cpu.resolution()) {
+            queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+            last_resolution = Some(cpu.resolution());
+            last_screen = None;
+        }
+        if last_screen != Some((cpu.screen, cpu.second_plane)) {
+            draw_screen(&cpu, stdout)?;
+            last_screen = Some((cpu.screen, cpu.second_plane));
+        }
+
+        // the terminal bell is the closest thing to a beeper, ring it when a sound starts, even
+        // one that's over by the end of the frame
+        if cpu
+            .drain_events()
+            .any(|event| event == events::Event::SoundStarted)
+        {
+            queue!(stdout, Print('\x07'))?;
+        }
+        stdout.flush()?;
+
+        if let Some(remaining) = FRAME_TIME.checked_sub(frame_start.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+    }
+}
+
+fn draw_screen(cpu: &CPU, stdout: &mut impl Write) -> io::Result<()> {
+    let (width, height) = cpu.resolution();
+    let pixels: Vec<bool> = cpu.pixels().collect();
+    let pixel = |x: usize, y: usize| pixels[y * width + x];
+
+    for row in 0..height / 2 {
+        let line: String = (0..width)
+            .map(|x| match (pixel(x, row * 2), pixel(x, row * 2 + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            })
+            .collect();
+        queue!(stdout, cursor::MoveTo(0, row as u16), Print(line))?;
+    }
+    Ok(())
+}
+
+// raw mode swallows ctrl-c, so it quits alongside escape
+fn is_quit(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Esc => true,
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+fn convert_key_to_button(key: KeyCode) -> Option<usize> {
+    let KeyCode::Char(key) = key else {
+        return None;
+    };
+
+    match key.to_ascii_lowercase() {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+};
+
+use crate::cpu::CPU;
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+
+#[derive(Clone, Copy)]
+struct Registers {
+    v: [u8; 16],
+    i: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl Registers {
+    fn capture(cpu: &CPU) -> Registers {
+        let mut v = [0; 16];
+        v.copy_from_slice(cpu.v_registers());
+
+        Registers {
+            v,
+            i: cpu.index_register(),
+            delay_timer: cpu.delay_timer(),
+            sound_timer: cpu.sound_timer(),
+        }
+    }
+
+    // takes one NAME=value change from a trace line
+    fn apply(&mut self, name: &str, value: u16) -> Option<()> {
+        match name {
+            "I" => self.i = value,
+            "DT" => self.delay_timer = u8::try_from(value).ok()?,
+            "ST" => self.sound_timer = u8::try_from(value).ok()?,
+            _ => {
+                let index = usize::from_str_radix(name.strip_prefix('V')?, 16).ok()?;
+                *self.v.get_mut(index)? = u8::try_from(value).ok()?;
+            }
+        }
+        Some(())
+    }
+
+    fn differences(&self, expected: &Registers) -> Vec<String> {
+        let mut differences = Vec::new();
+        for (i, (actual, expected)) in self.v.iter().zip(expected.v.iter()).enumerate() {
+            if actual != expected {
+                differences.push(format!(
+                    "V{:X}={:02X}, expected {:02X}",
+                    i, actual, expected
+                ));
+            }
+        }
+        if self.i != expected.i {
+            differences.push(format!("I={:03X}, expected {:03X}", self.i, expected.i));
+        }
+        if self.delay_timer != expected.delay_timer {
+            differences.push(format!(
+                "DT={:02X}, expected {:02X}",
+                self.delay_timer, expected.delay_timer
+            ));
+        }
+        if self.sound_timer != expected.sound_timer {
+            differences.push(format!(
+                "ST={:02X}, expected {:02X}",
+                self.sound_timer, expected.sound_timer
+            ));
+        }
+        differences
+    }
+}
+
+// writes one line per executed instruction:
+// <frame> <pc> <opcode> <mnemonic> | <registers the instruction changed>
+pub struct Tracer {
+    out: BufWriter<File>,
+    range: Option<RangeInclusive<u16>>,
+    pending: Option<(u16, u16, Registers)>,
+}
+
+impl Tracer {
+    pub fn create(path: &str, range: Option<RangeInclusive<u16>>) -> io::Result<Tracer> {
+        Ok(Tracer {
+            out: BufWriter::new(File::create(path)?),
+            range,
+            pending: None,
+        })
+    }
+
+    // call right before the CPU ticks
+    pub fn before(&mut self, cpu: &CPU) {
+        let pc = cpu.pc();
+        self.pending = match &self.range {
+            Some(range) if !range.contains(&pc) => None,
+            _ => Some((pc, cpu.opcode_at(pc), Registers::capture(cpu))),
+        };
+    }
+
+    // call right after the CPU ticks
+    pub fn after(&mut self, frame: u64, cpu: &CPU) -> io::Result<()> {
+        let Some((pc, op, before)) = self.pending.take() else {
+            return Ok(());
+        };
+
+        let line = trace_line(frame, pc, op, &before, &Registers::capture(cpu));
+        writeln!(self.out, "{}", line)
+    }
+}
+
+fn trace_line(frame: u64, pc: u16, op: u16, before: &Registers, after: &Registers) -> String {
+    let mut changes = Vec::new();
+    for (i, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            changes.push(format!("V{:X}={:02X}", i, new));
+        }
+    }
+    if before.i != after.i {
+        changes.push(format!("I={:03X}", after.i));
+    }
+    if before.delay_timer != after.delay_timer {
+        changes.push(format!("DT={:02X}", after.delay_timer));
+    }
+    if before.sound_timer != after.sound_timer {
+        changes.push(format!("ST={:02X}", after.sound_timer));
+    }
+
+    format!(
+        "{:06} {:03X} {:04X} {} | {}",
+        frame,
+        pc,
+        op,
+        Instruction::decode(op),
+        changes.join(" ")
+    )
+}
+
+// runs the ROM alongside a full trace in the format above, from another emulator or an older
+// version of this one, and returns how many instructions matched or where they first differ.
+// registers are compared after every instruction, mnemonics are ignored, and CXNN takes its
+// result from the trace since no two emulators roll the same random numbers. the timers count
+// down once each time the frame number goes up, which is as close as a trace gets to real time
+pub fn compare(rom: &[u8], quirks: Quirks, reference: &str) -> Result<usize, String> {
+    let mut cpu = CPU::new();
+    cpu.set_quirks(quirks);
+    cpu.try_load(rom).map_err(|error| error.to_string())?;
+    let mut expected = Registers::capture(&cpu);
+    let mut count = 0;
+    let mut last_frame = None;
+
+    for (index, text) in reference.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let line = index + 1;
+        let (frame, pc, op, changes) =
+            parse_line(text).ok_or_else(|| format!("line {}: not a trace line", line))?;
+
+        for _ in 0..last_frame.map_or(0, |last| frame.saturating_sub(last)) {
+            cpu.tick_timers();
+            expected.delay_timer = expected.delay_timer.saturating_sub(1);
+            expected.sound_timer = expected.sound_timer.saturating_sub(1);
+        }
+        last_frame = Some(frame);
+
+        let actual_op = cpu.opcode_at(cpu.pc());
+        if (cpu.pc(), actual_op) != (pc, op) {
+            return Err(format!(
+                "line {}: expected {:03X} {:04X} to run next, but the program is at {:03X} {:04X}",
+                line,
+                pc,
+                op,
+                cpu.pc(),
+                actual_op
+            ));
+        }
+
+        for (name, value) in changes {
+            expected.apply(name, value).ok_or_else(|| {
+                format!(
+                    "line {}: invalid register change {}={:X}",
+                    line, name, value
+                )
+            })?;
+        }
+
+        cpu.tick().map_err(|error| {
+            format!(
+                "line {} ({:03X} {:04X}): program stopped: {}",
+                line, pc, op, error
+            )
+        })?;
+        if op & 0xF000 == 0xC000 {
+            let x = (op as usize >> 8) & 0xF;
+            cpu.set_v_register(x, expected.v[x]);
+        }
+
+        let differences = Registers::capture(&cpu).differences(&expected);
+        if !differences.is_empty() {
+            return Err(format!(
+                "line {} ({:03X} {:04X}): {}",
+                line,
+                pc,
+                op,
+                differences.join(", ")
+            ));
+        }
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+// a register name and its new value
+type Change<'a> = (&'a str, u16);
+
+// <frame> <pc> <opcode> <mnemonic> | <changes>, giving the frame, pc, opcode and the changes
+fn parse_line(line: &str) -> Option<(u64, u16, u16, Vec<Change<'_>>)> {
+    let (instruction, changes) = line.split_once('|')?;
+    let mut fields = instruction.split_whitespace();
+    let frame = fields.next()?.parse().ok()?;
+    let pc = u16::from_str_radix(fields.next()?, 16).ok()?;
+    let op = u16::from_str_radix(fields.next()?, 16).ok()?;
+
+    let changes = changes
+        .split_whitespace()
+        .map(|change| {
+            let (name, value) = change.split_once('=')?;
+            Some((name, u16::from_str_radix(value, 16).ok()?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((frame, pc, op, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_line() {
+        let mut cpu = CPU::new();
+        cpu.load(&[0x6A, 0x02, 0xA3, 0x00]);
+
+        let before = Registers::capture(&cpu);
+        cpu.tick().unwrap();
+        let after = Registers::capture(&cpu);
+        assert_eq!(
+            trace_line(7, 0x200, 0x6A02, &before, &after),
+            "000007 200 6A02 LD VA, #02 | VA=02"
+        );
+
+        let before = after;
+        cpu.tick().unwrap();
+        let after = Registers::capture(&cpu);
+        assert_eq!(
+            trace_line(7, 0x202, 0xA300, &before, &after),
+            "000007 202 A300 LD I, #300 | I=300"
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let rom = [0x6A, 0x02, 0xA3, 0x00, 0xC0, 0xFF, 0x12, 0x06];
+        let reference = "\
+000000 200 6A02 LD VA, #02 | VA=02
+000000 202 A300 LD I, #300 | I=300
+000000 204 C0FF RND V0, #FF | V0=2A
+000000 206 1206 JP #206 |
+";
+        assert_eq!(compare(&rom, Quirks::default(), reference), Ok(4));
+
+        let wrong_value = reference.replace("VA=02", "VA=03");
+        assert_eq!(
+            compare(&rom, Quirks::default(), &wrong_value),
+            Err("line 1 (200 6A02): VA=02, expected 03".to_string())
+        );
+
+        let wrong_jump = reference.replace("206 1206", "206 1208");
+        assert!(compare(&rom, Quirks::default(), &wrong_jump)
+            .unwrap_err()
+            .starts_with("line 4: expected 206 1208 to run next"));
+
+        assert!(compare(&rom, Quirks::default(), "garbage").is_err());
+    }
+
+    #[test]
+    fn test_compare_timers() {
+        // DT = 5, then read it back a frame and three frames later
+        let rom = [0x60, 0x05, 0xF0, 0x15, 0xF1, 0x07, 0xF2, 0x07];
+        let reference = "\
+000000 200 6005 LD V0, #05 | V0=05
+000000 202 F015 LD DT, V0 | DT=05
+000001 204 F107 LD V1, DT | V1=04
+000003 206 F207 LD V2, DT | V2=02
+";
+        assert_eq!(compare(&rom, Quirks::default(), reference), Ok(4));
+    }
+}
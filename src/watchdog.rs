@@ -0,0 +1,66 @@
+// notices a program spinning without doing anything a player could see or affect, which is
+// usually a ROM that needs different quirks, so the frontend can say so instead of showing a
+// frozen window. drawing, clearing the screen, checking keys and using the timers all count
+use crate::instruction::Instruction;
+
+// about ten seconds at the usual speed, far longer than any game thinks without drawing
+pub const DEFAULT_LIMIT: u32 = 6000;
+
+pub struct Watchdog {
+    limit: u32,
+    idle: u32,
+}
+
+impl Watchdog {
+    pub fn new(limit: u32) -> Watchdog {
+        Watchdog { limit, idle: 0 }
+    }
+
+    // call before each instruction, true once per stretch of `limit` idle instructions
+    pub fn check(&mut self, op: u16) -> bool {
+        if is_activity(Instruction::decode(op)) {
+            self.idle = 0;
+            return false;
+        }
+
+        self.idle = self.idle.saturating_add(1);
+        self.idle == self.limit
+    }
+}
+
+fn is_activity(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Cls
+            | Instruction::Draw { .. }
+            | Instruction::SkipKey { .. }
+            | Instruction::SkipNotKey { .. }
+            | Instruction::WaitKey { .. }
+            | Instruction::LoadDelay { .. }
+            | Instruction::SetDelay { .. }
+            | Instruction::SetSound { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog() {
+        let mut watchdog = Watchdog::new(3);
+        // a jump to itself
+        assert!(!watchdog.check(0x1200));
+        assert!(!watchdog.check(0x1200));
+        assert!(watchdog.check(0x1200));
+        // only one warning for the same stretch
+        assert!(!watchdog.check(0x1200));
+
+        // waiting on the delay timer is fine
+        let mut watchdog = Watchdog::new(3);
+        for _ in 0..10 {
+            assert!(!watchdog.check(0xF007));
+            assert!(!watchdog.check(0x3000));
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// the buzzer written out as a WAV file, a frame of sound for each frame played. CHIP-8 has one
+// tone, on while the sound timer is above zero, so that's what's heard: a square wave at
+// BUZZER_HZ, or once an XO-CHIP program has loaded one, its 128 bit pattern played at its pitch.
+// the sizes in the header are only known at the end, finish fills them in
+use std::io::{self, Seek, SeekFrom, Write};
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const BUZZER_HZ: u32 = 440;
+// samples in a 60th of a second
+const SAMPLES_PER_FRAME: u32 = SAMPLE_RATE / 60;
+const AMPLITUDE: i16 = 8000;
+const HEADER_SIZE: u32 = 44;
+
+pub struct WavWriter<W: Write + Seek> {
+    output: W,
+    samples: u32,
+    // where the square wave is in its cycle, carried between frames so a long tone doesn't click
+    phase: u32,
+    // the same for a pattern, the bit it's at
+    bit: f64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut output: W) -> io::Result<WavWriter<W>> {
+        output.write_all(&header(0))?;
+        Ok(WavWriter {
+            output,
+            samples: 0,
+            phase: 0,
+            bit: 0.0,
+        })
+    }
+
+    // `pattern` is the XO-CHIP pattern and the bits a second it plays at, if there is one
+    pub fn frame(&mut self, buzzing: bool, pattern: Option<(&[u8; 16], f64)>) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(SAMPLES_PER_FRAME as usize * 2);
+        if !buzzing {
+            self.phase = 0;
+            self.bit = 0.0;
+        }
+        for _ in 0..SAMPLES_PER_FRAME {
+            let sample = match (buzzing, pattern) {
+                (false, _) => 0,
+                // each bit high or low in turn, the first byte's highest bit first
+                (true, Some((pattern, _))) => {
+                    let bit = self.bit as usize;
+                    match pattern[bit / 8] & (0x80 >> (bit % 8)) {
+                        0 => -AMPLITUDE,
+                        _ => AMPLITUDE,
+                    }
+                }
+                // high for the first half of each cycle, low for the second
+                (true, None) if self.phase < SAMPLE_RATE / 2 => AMPLITUDE,
+                (true, None) => -AMPLITUDE,
+            };
+            bytes.extend_from_slice(&sample.to_le_bytes());
+            if buzzing {
+                self.phase = (self.phase + BUZZER_HZ) % SAMPLE_RATE;
+                if let Some((_, rate)) = pattern {
+                    self.bit = (self.bit + rate / SAMPLE_RATE as f64) % 128.0;
+                }
+            }
+        }
+        self.output.write_all(&bytes)?;
+        self.samples += SAMPLES_PER_FRAME;
+        Ok(())
+    }
+
+    // writes the final sizes into the header, the file isn't a valid WAV until this is done
+    pub fn finish(mut self) -> io::Result<W> {
+        self.output.seek(SeekFrom::Start(0))?;
+        self.output.write_all(&header(self.samples * 2))?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+// 16-bit mono PCM with `data_size` bytes of samples to follow
+fn header(data_size: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    // bytes a second, then bytes a sample and bits a sample
+    header.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn sample(bytes: &[u8], index: usize) -> i16 {
+        let at = HEADER_SIZE as usize + index * 2;
+        i16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    #[test]
+    fn test_wav() {
+        let mut writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.frame(false, None).unwrap();
+        writer.frame(true, None).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let data_size = SAMPLES_PER_FRAME * 2 * 2;
+        assert_eq!(bytes.len(), (HEADER_SIZE + data_size) as usize);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(bytes[4..8], (36 + data_size).to_le_bytes());
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(bytes[40..44], data_size.to_le_bytes());
+
+        // silence, then a square wave that flips every half cycle
+        assert_eq!(sample(&bytes, 0), 0);
+        let start = SAMPLES_PER_FRAME as usize;
+        let half_cycle = (SAMPLE_RATE / BUZZER_HZ / 2) as usize;
+        assert_eq!(sample(&bytes, start + 1), AMPLITUDE);
+        assert_eq!(sample(&bytes, start + half_cycle + 5), -AMPLITUDE);
+        assert_eq!(sample(&bytes, start + half_cycle * 2 + 5), AMPLITUDE);
+    }
+
+    #[test]
+    fn test_pattern() {
+        // alternate bytes of ones and zeros, a bit every 10 samples
+        let pattern = [0xFF, 0x00].repeat(8).try_into().unwrap();
+        let mut writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer
+            .frame(true, Some((&pattern, SAMPLE_RATE as f64 / 10.0)))
+            .unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        assert_eq!(sample(&bytes, 0), AMPLITUDE);
+        assert_eq!(sample(&bytes, 79), AMPLITUDE);
+        assert_eq!(sample(&bytes, 81), -AMPLITUDE);
+        assert_eq!(sample(&bytes, 161), AMPLITUDE);
+    }
+}
@@ -0,0 +1,76 @@
+use wasm_bindgen::prelude::*;
+
+use crate::cpu::{CPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::headless::TICKS_PER_FRAME;
+
+// the emulator as seen from JavaScript, web/main.js owns the canvas, audio and keyboard
+#[wasm_bindgen]
+pub struct Emulator {
+    cpu: CPU,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    // throws if the file is empty or too big to load
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: &[u8]) -> Result<Emulator, JsError> {
+        let mut cpu = CPU::new();
+        cpu.try_load(rom)?;
+        Ok(Emulator { cpu })
+    }
+
+    pub fn width() -> usize {
+        SCREEN_WIDTH
+    }
+
+    pub fn height() -> usize {
+        SCREEN_HEIGHT
+    }
+
+    // runs one 60Hz frame, throws if the program stops
+    pub fn frame(&mut self) -> Result<(), JsError> {
+        for _ in 0..TICKS_PER_FRAME {
+            let pc = self.cpu.pc();
+            self.cpu.tick().map_err(|error| {
+                JsError::new(&format!("program stopped at #{:03X}: {}", pc, error))
+            })?;
+        }
+        self.cpu.tick_timers();
+        Ok(())
+    }
+
+    // keys past F are ignored
+    pub fn keypress(&mut self, key: usize, pressed: bool) {
+        let _ = self.cpu.keypress(key, pressed);
+    }
+
+    // one byte per pixel, 1 for lit
+    pub fn screen(&self) -> Vec<u8> {
+        self.cpu.pixels().map(|on| on as u8).collect()
+    }
+
+    pub fn beeping(&self) -> bool {
+        self.cpu.sound_timer() > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        // set the sound timer, then draw the 0 glyph at 0,0
+        let mut emulator =
+            Emulator::new(&[0x60, 0x20, 0xF0, 0x18, 0xD1, 0x15, 0x12, 0x06]).unwrap();
+        emulator.frame().unwrap();
+
+        let screen = emulator.screen();
+        assert_eq!(screen.len(), Emulator::width() * Emulator::height());
+        assert_eq!(screen[..5], [1, 1, 1, 1, 0]);
+        assert!(emulator.beeping());
+
+        // out of range keys are ignored rather than panicking
+        emulator.keypress(0x10, true);
+    }
+}
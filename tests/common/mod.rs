@@ -0,0 +1,56 @@
+// each integration test pulls in the helpers it needs, not all of them
+#![allow(dead_code)]
+
+use std::{env, fs, path::Path, path::PathBuf};
+
+use chip8::cpu::CPU;
+use chip8::headless::{self, screen_text, KeyEvent};
+use chip8::quirks::Quirks;
+
+// CXNN is seeded so runs are repeatable
+pub const SEED: u64 = 0xC8;
+
+// runs a ROM without a window with every quirk off
+pub fn run(rom: &[u8], frames: u32, keys: &[KeyEvent]) -> CPU {
+    headless::run(rom, Quirks::default(), SEED, frames, keys).unwrap()
+}
+
+pub fn test_path(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path)
+}
+
+// compares the screen with the one stored at path. run with BLESS=1 to write the current
+// screen instead, then check the new file before committing it
+pub fn assert_screen(path: &Path, cpu: &CPU) {
+    let actual = screen_text(cpu);
+
+    if env::var_os("BLESS").is_some() {
+        fs::write(path, &actual).expect("unable to write snapshot");
+        return;
+    }
+
+    let Ok(expected) = fs::read_to_string(path) else {
+        panic!(
+            "no snapshot at {}, rerun with BLESS=1 to create it:\n{}",
+            path.display(),
+            actual
+        );
+    };
+    assert!(
+        expected == actual,
+        "screen differs from {}, rerun with BLESS=1 if the change is intended\nexpected:\n{}actual:\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+}
+
+// runs a ROM from roms/ and compares the result with tests/snapshots/<name>.txt
+pub fn assert_snapshot(name: &str, rom: &str, frames: u32, keys: &[KeyEvent]) {
+    let rom_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms").join(rom);
+    let rom = fs::read(&rom_path).expect("unable to read ROM");
+    let cpu = run(&rom, frames, keys);
+    assert_screen(&test_path(&format!("snapshots/{}.txt", name)), &cpu);
+}
@@ -0,0 +1,36 @@
+// properties tying the decoder, the encoder and the CPU together
+use chip8::cpu::{CpuError, CPU};
+use chip8::instruction::Instruction;
+use proptest::prelude::*;
+
+fn is_unknown(op: u16) -> bool {
+    matches!(Instruction::decode(op), Instruction::Unknown(_))
+}
+
+// what the CPU makes of an opcode, run from a fresh start
+fn execute(op: u16) -> Result<(), CpuError> {
+    let mut cpu = CPU::new();
+    cpu.seed(0);
+    cpu.load(&op.to_be_bytes());
+    cpu.tick()
+}
+
+proptest! {
+    #[test]
+    fn test_round_trip(op in any::<u16>().prop_filter("valid opcode", |op| !is_unknown(*op))) {
+        prop_assert_eq!(Instruction::decode(op).encode(), op);
+    }
+
+    #[test]
+    fn test_unknown_round_trip(op in any::<u16>().prop_filter("invalid opcode", |op| is_unknown(*op))) {
+        prop_assert_eq!(Instruction::decode(op), Instruction::Unknown(op));
+        prop_assert_eq!(Instruction::decode(op).encode(), op);
+    }
+
+    // the disassembler and the CPU must agree on which opcodes exist
+    #[test]
+    fn test_cpu_agrees(op in any::<u16>()) {
+        let rejected = execute(op) == Err(CpuError::UnknownOpcode(op));
+        prop_assert_eq!(rejected, is_unknown(op), "{:04X}", op);
+    }
+}
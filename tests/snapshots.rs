@@ -0,0 +1,44 @@
+// regression tests that play the bundled games for a while and compare the screen with a
+// stored snapshot, see common::assert_screen for updating them
+mod common;
+
+#[test]
+fn test_airplane() {
+    common::assert_snapshot("airplane", "Airplane.ch8", 120, &[]);
+}
+
+#[test]
+fn test_airplane_drop() {
+    // 8 drops a package
+    common::assert_snapshot(
+        "airplane_drop",
+        "Airplane.ch8",
+        120,
+        &[(60, 0x8, true), (70, 0x8, false)],
+    );
+}
+
+#[test]
+fn test_maze() {
+    common::assert_snapshot("maze", "Maze (alt) [David Winter, 199x].ch8", 120, &[]);
+}
+
+#[test]
+fn test_particle_demo() {
+    common::assert_snapshot(
+        "particle_demo",
+        "Particle Demo [zeroZshadow, 2008].ch8",
+        120,
+        &[],
+    );
+}
+
+#[test]
+fn test_addition_problems() {
+    common::assert_snapshot(
+        "addition_problems",
+        "Addition Problems [Paul C. Moews].ch8",
+        120,
+        &[],
+    );
+}
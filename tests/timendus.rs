@@ -0,0 +1,61 @@
+// boots Timendus' CHIP-8 test suite (https://github.com/Timendus/chip8-test-suite) and compares
+// the final screen of each test against a known-good one. the ROMs aren't redistributed here:
+// drop them into tests/timendus/ under their usual names, next to the expected <name>.txt screens.
+// with nothing to run it's ignored by default: fetch the ROMs, then `cargo test -- --ignored`.
+// a missing ROM fails the run rather than passing without checking anything.
+use std::fs;
+
+use chip8::headless::KeyEvent;
+
+mod common;
+
+struct SuiteTest {
+    name: &'static str,
+    frames: u32,
+    keys: &'static [KeyEvent],
+}
+
+const SUITE: &[SuiteTest] = &[
+    SuiteTest {
+        name: "1-chip8-logo",
+        frames: 40,
+        keys: &[],
+    },
+    SuiteTest {
+        name: "2-ibm-logo",
+        frames: 40,
+        keys: &[],
+    },
+    SuiteTest {
+        name: "3-corax+",
+        frames: 120,
+        keys: &[],
+    },
+    SuiteTest {
+        name: "4-flags",
+        frames: 120,
+        keys: &[],
+    },
+    // the menu asks which platform to test, 1 picks plain CHIP-8
+    SuiteTest {
+        name: "5-quirks",
+        frames: 600,
+        keys: &[(30, 0x1, true), (40, 0x1, false)],
+    },
+];
+
+#[test]
+#[ignore = "needs Timendus' ROMs in tests/timendus/"]
+fn test_timendus_suite() {
+    let directory = common::test_path("timendus");
+
+    for test in SUITE {
+        let path = directory.join(format!("{}.ch8", test.name));
+        let Ok(rom) = fs::read(&path) else {
+            panic!("no ROM at {}", path.display());
+        };
+
+        let cpu = common::run(&rom, test.frames, test.keys);
+        common::assert_screen(&directory.join(format!("{}.txt", test.name)), &cpu);
+    }
+}
@@ -0,0 +1,138 @@
+// the browser frontend: build the wasm package first with
+//   wasm-pack build --target web --no-default-features --features web --out-dir web/pkg
+// then serve this directory, e.g. python3 -m http.server -d web
+import init, { Emulator } from "./pkg/chip8.js";
+
+const FRAME_MS = 1000 / 60;
+const BEEP_HZ = 440;
+
+// same layout as the SDL frontend
+const KEYS = {
+  Digit1: 0x1, Digit2: 0x2, Digit3: 0x3, Digit4: 0xc,
+  KeyQ: 0x4, KeyW: 0x5, KeyE: 0x6, KeyR: 0xd,
+  KeyA: 0x7, KeyS: 0x8, KeyD: 0x9, KeyF: 0xe,
+  KeyZ: 0xa, KeyX: 0x0, KeyC: 0xb, KeyV: 0xf,
+};
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const status = document.getElementById("status");
+
+let emulator = null;
+let audio = null;
+let oscillator = null;
+
+// browsers only allow audio after the page has been interacted with
+function startAudio() {
+  if (audio) {
+    return;
+  }
+  audio = new AudioContext();
+  oscillator = audio.createOscillator();
+  oscillator.type = "square";
+  oscillator.frequency.value = BEEP_HZ;
+  oscillator.start();
+}
+
+function beep(on) {
+  if (!oscillator) {
+    return;
+  }
+  if (on) {
+    oscillator.connect(audio.destination);
+  } else {
+    oscillator.disconnect();
+  }
+}
+
+function draw() {
+  const width = Emulator.width();
+  const height = Emulator.height();
+  const screen = emulator.screen();
+  const image = context.createImageData(width, height);
+
+  for (let i = 0; i < screen.length; i++) {
+    const value = screen[i] ? 255 : 0;
+    image.data.set([value, value, value, 255], i * 4);
+  }
+  context.putImageData(image, 0, 0);
+}
+
+function stop(message) {
+  emulator = null;
+  beep(false);
+  status.textContent = message;
+}
+
+let last = performance.now();
+let pending = 0;
+
+function loop(now) {
+  pending += now - last;
+  last = now;
+
+  // catch up on missed frames, but not after the tab has been in the background for a while
+  pending = Math.min(pending, FRAME_MS * 4);
+  if (emulator) {
+    while (pending >= FRAME_MS) {
+      try {
+        emulator.frame();
+      } catch (error) {
+        stop(error.message);
+        break;
+      }
+      pending -= FRAME_MS;
+    }
+  }
+
+  if (emulator) {
+    draw();
+    beep(emulator.beeping());
+  }
+  requestAnimationFrame(loop);
+}
+
+function load(rom) {
+  try {
+    emulator = new Emulator(new Uint8Array(rom));
+  } catch (error) {
+    stop(error.message);
+    return;
+  }
+  canvas.width = Emulator.width();
+  canvas.height = Emulator.height();
+  status.textContent = "keys 1234 QWER ASDF ZXCV, escape stops";
+}
+
+document.addEventListener("keydown", (event) => {
+  startAudio();
+  if (emulator && event.code in KEYS) {
+    emulator.keypress(KEYS[event.code], true);
+    event.preventDefault();
+  }
+});
+
+document.addEventListener("keyup", (event) => {
+  if (event.code === "Escape") {
+    stop("stopped");
+  } else if (emulator && event.code in KEYS) {
+    emulator.keypress(KEYS[event.code], false);
+  }
+});
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  startAudio();
+  const file = event.target.files[0];
+  if (file) {
+    load(await file.arrayBuffer());
+  }
+});
+
+await init();
+
+const romUrl = new URLSearchParams(location.search).get("rom");
+if (romUrl) {
+  const response = await fetch(romUrl);
+  load(await response.arrayBuffer());
+}
+requestAnimationFrame(loop);